@@ -0,0 +1,606 @@
+//! Benchmarks comparing alternative implementations within a single day,
+//! typically on synthetic inputs larger than the puzzle input.
+use std::{convert::TryFrom, str::FromStr};
+
+use aoc::{
+    amphipod::{Amphipod, LargeBurrow, SmallBurrow, Strategy as AmphipodStrategy},
+    camera::Manual,
+    cave::CaveSystem,
+    chiton::{ChitonGrid, Strategy, Tiling},
+    decoder::{OpCode, PacketBuilder, StreamingDecoder, Transmission, TransmissionWrapper},
+    dirac::{GameConfig, Games, QuantumGame},
+    fish::{FlatNumber, Homework, Pair},
+    octopus::OctopusGrid,
+    polymer::Polymerizer,
+    probe::{Launcher, Target},
+    reactor::{Cuboid, Instructions, Procedure, Reactor, Strategy as ReactorStrategy},
+    scanner::{Beacon, Mapper, ROTATIONS},
+    trench::{BitImage, Enhancer, Image},
+};
+use aoc_helpers::{generic::prelude::GridLike, Solver};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+
+/// A small, deterministic stand-in for a random number generator, so that the
+/// synthetic inputs are the same for every run
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+fn octopus_grid(size: usize) -> OctopusGrid {
+    let mut rng = Lcg(11);
+    let input = (0..size)
+        .map(|_| {
+            (0..size)
+                .map(|_| std::char::from_digit((rng.next() % 10) as u32, 10).unwrap())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+
+    OctopusGrid::try_from(input).expect("could not make grid")
+}
+
+fn octopus(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_011 engines");
+    group.sample_size(10);
+
+    for size in [100, 1000] {
+        let grid = octopus_grid(size);
+
+        group.bench_with_input(BenchmarkId::new("hashset", size), &grid, |b, grid| {
+            b.iter(|| grid.clone().simulate_hashed(10))
+        });
+
+        group.bench_with_input(BenchmarkId::new("serial", size), &grid, |b, grid| {
+            b.iter(|| grid.clone().simulate(10))
+        });
+
+        group.bench_with_input(BenchmarkId::new("parallel", size), &grid, |b, grid| {
+            b.iter(|| grid.clone().simulate_par(10, 64))
+        });
+    }
+
+    group.finish();
+}
+
+fn cave(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_012 path counting");
+    let caves = CaveSystem::instance();
+
+    for allow_multi_visit in [false, true] {
+        group.bench_with_input(
+            BenchmarkId::new("recursive", allow_multi_visit),
+            &allow_multi_visit,
+            |b, allow| b.iter(|| caves.paths_fast(*allow)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("semi parallel", allow_multi_visit),
+            &allow_multi_visit,
+            |b, allow| b.iter(|| caves.paths_semi_par(*allow)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("memoized", allow_multi_visit),
+            &allow_multi_visit,
+            |b, allow| b.iter(|| caves.paths_memo(*allow)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("parallel", allow_multi_visit),
+            &allow_multi_visit,
+            |b, allow| b.iter(|| caves.paths_par(*allow, 3)),
+        );
+    }
+
+    group.finish();
+}
+
+/// A page the size of the puzzle input with `dots` dots, folded the same way
+fn manual(dots: usize) -> Manual {
+    let folds = [
+        ("x", 655),
+        ("y", 447),
+        ("x", 327),
+        ("y", 223),
+        ("x", 163),
+        ("y", 111),
+        ("x", 81),
+        ("y", 55),
+        ("x", 40),
+        ("y", 27),
+        ("y", 13),
+        ("y", 6),
+    ];
+
+    // like the puzzle input, no dot ever lands on a fold line, which is
+    // easiest to arrange by picking a spot on the folded page and randomly
+    // unfolding it
+    let mut rng = Lcg(13);
+    let mut input = (0..dots)
+        .map(|_| {
+            let mut x = rng.next() % 40;
+            let mut y = rng.next() % 6;
+            for (axis, line) in folds.iter().rev() {
+                if rng.next() % 2 == 0 {
+                    match *axis {
+                        "x" => x = 2 * line - x,
+                        _ => y = 2 * line - y,
+                    }
+                }
+            }
+
+            format!("{},{}", x, y)
+        })
+        .collect::<Vec<_>>();
+
+    input.push(String::new());
+    for (axis, line) in folds.iter() {
+        input.push(format!("fold along {}={}", axis, line));
+    }
+
+    Manual::try_from(input).expect("could not make manual")
+}
+
+fn camera(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_013 folding");
+    let manual = manual(100_000);
+
+    group.bench_function("bitset", |b| b.iter(|| manual.folded().count_visible()));
+    group.bench_function("composed", |b| {
+        b.iter(|| manual.folded_fast().count_visible())
+    });
+    group.bench_function("point reflection", |b| {
+        b.iter(|| manual.count_visible_after(usize::MAX))
+    });
+
+    group.finish();
+}
+
+fn polymer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_014 pair counting");
+    let polymerizer = Polymerizer::instance();
+
+    for num in [40, 100] {
+        group.bench_with_input(BenchmarkId::new("stepping", num), &num, |b, num| {
+            b.iter(|| polymerizer.iterations_wide::<u128>(*num))
+        });
+        group.bench_with_input(BenchmarkId::new("matrix", num), &num, |b, num| {
+            b.iter(|| polymerizer.iterations_matrix::<u128>(*num))
+        });
+    }
+
+    group.finish();
+}
+
+fn chiton(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_015 search");
+    let grid = ChitonGrid::instance();
+    let tiling = Tiling::square(5);
+    let start = grid.top_left();
+    let end = grid.tiled_bottom_right(&tiling);
+
+    for strategy in [
+        Strategy::Dijkstra,
+        Strategy::Bidirectional,
+        Strategy::DeltaStepping { delta: 1 },
+        Strategy::DeltaStepping { delta: 9 },
+    ] {
+        group.bench_function(format!("{:?}", strategy), |b| {
+            b.iter(|| grid.shortest_by(strategy, &tiling, &start, &end))
+        });
+    }
+
+    group.finish();
+}
+
+/// A transmission of `count` top level packets, each a small random tree
+fn transmission(count: usize) -> String {
+    let mut rng = Lcg(16);
+    let codes = [
+        OpCode::Sum,
+        OpCode::Product,
+        OpCode::Minimum,
+        OpCode::Maximum,
+    ];
+
+    let mut packets = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut children = Vec::new();
+        for _ in 0..2 + rng.next() % 6 {
+            let mut operands = Vec::new();
+            for _ in 0..1 + rng.next() % 4 {
+                operands.push(
+                    PacketBuilder::literal((rng.next() % 1000) as usize)
+                        .version((rng.next() % 8) as usize),
+                );
+            }
+
+            let child = PacketBuilder::operator(codes[(rng.next() % 4) as usize], operands);
+            children.push(if rng.next() % 2 == 0 {
+                child.by_bits()
+            } else {
+                child
+            });
+        }
+
+        packets.push(
+            PacketBuilder::operator(OpCode::Sum, children)
+                .build()
+                .expect("could not build packet"),
+        );
+    }
+
+    packets
+        .into_iter()
+        .collect::<Transmission>()
+        .to_hex()
+        .expect("could not encode")
+}
+
+fn decoder(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_016 parsing");
+    let input = TransmissionWrapper::load_input().remove(0);
+
+    group.bench_function("puzzle input", |b| b.iter(|| input.parse::<Transmission>()));
+    group.bench_function("puzzle input expanded", |b| {
+        b.iter(|| Transmission::parse_expanded(&input))
+    });
+
+    let synthetic = transmission(10_000);
+    group.bench_function("synthetic", |b| {
+        b.iter(|| synthetic.parse::<Transmission>())
+    });
+    group.bench_function("synthetic expanded", |b| {
+        b.iter(|| Transmission::parse_expanded(&synthetic))
+    });
+    group.bench_function("synthetic streaming", |b| {
+        b.iter(|| StreamingDecoder::from_chars(synthetic.chars()).count())
+    });
+
+    group.finish();
+}
+
+fn probe(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_017 counting");
+    let input = Target::from_str(&Launcher::load_input()[0]).expect("invalid target");
+
+    for (name, target) in [
+        ("puzzle input", input),
+        ("synthetic", Target::new(2000, 3000, -1000, -500)),
+    ] {
+        let launcher = Launcher::new(target);
+        group.bench_function(BenchmarkId::new("hybrid", name), |b| {
+            b.iter(|| launcher.launch(&target))
+        });
+        group.bench_function(BenchmarkId::new("analytic", name), |b| {
+            b.iter(|| launcher.count_analytic(&target))
+        });
+    }
+
+    group.finish();
+}
+
+fn snailfish(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_018 largest magnitude");
+    let homework = Homework::instance();
+
+    group.bench_function("boxed", |b| {
+        b.iter(|| homework.largest_magnitude_of_pairs_boxed())
+    });
+    group.bench_function("flat", |b| b.iter(|| homework.largest_magnitude_of_pairs()));
+
+    // more numbers than the puzzle input, and all of them nested as deep as a
+    // reduced number can be
+    let mut rng = Lcg(18);
+    let mut next = |n: usize| (rng.next() % n as u64) as usize;
+    let synthetic = Homework::try_from(
+        (0..300)
+            .map(|_| Pair::random_sized(4, 80, &mut next).to_string())
+            .collect::<Vec<_>>(),
+    )
+    .expect("could not make homework");
+
+    group.bench_function("synthetic boxed", |b| {
+        b.iter(|| synthetic.largest_magnitude_of_pairs_boxed())
+    });
+    group.bench_function("synthetic flat", |b| {
+        b.iter(|| synthetic.largest_magnitude_of_pairs())
+    });
+
+    group.finish();
+
+    // the same sums on one thread, to compare just the additions
+    let mut group = c.benchmark_group("day_018 pairwise addition");
+    let mut rng = Lcg(181);
+    let mut next = |n: usize| (rng.next() % n as u64) as usize;
+    let pairs = (0..100)
+        .map(|_| Pair::random_sized(4, 80, &mut next))
+        .collect::<Vec<_>>();
+    let flat = pairs.iter().map(FlatNumber::from).collect::<Vec<_>>();
+
+    group.bench_function("tree", |b| {
+        b.iter(|| {
+            pairs
+                .iter()
+                .cartesian_product(pairs.iter())
+                .map(|(a, b)| (a + b).magnitude())
+                .max()
+        })
+    });
+    group.bench_function("flat", |b| {
+        b.iter(|| {
+            flat.iter()
+                .cartesian_product(flat.iter())
+                .map(|(a, b)| (a + b).magnitude())
+                .max()
+        })
+    });
+    group.bench_function("flat scratch", |b| {
+        b.iter(|| {
+            let mut scratch = FlatNumber::default();
+            flat.iter()
+                .cartesian_product(flat.iter())
+                .map(|(a, b)| {
+                    a.add_into(b, &mut scratch);
+                    scratch.magnitude()
+                })
+                .max()
+        })
+    });
+
+    group.finish();
+}
+
+/// A chain of `count` scanners 1000 apart along x, each reporting the beacons
+/// within 1000 of it on every axis in a random orientation of its own.
+fn scanner_chain(count: usize) -> Mapper {
+    let mut rng = Lcg(19);
+    let mut coord = |lo: i64, hi: i64| lo + (rng.next() % (hi - lo + 1) as u64) as i64;
+
+    let beacons = (0..30 * (count + 1))
+        .map(|_| {
+            Beacon::from([
+                coord(-1000, count as i64 * 1000),
+                coord(-1000, 1000),
+                coord(-1000, 1000),
+            ])
+        })
+        .collect::<Vec<_>>();
+
+    let mut rng = Lcg(91);
+    let mut lines = Vec::new();
+    for idx in 0..count {
+        let position = Beacon::from([idx as i64 * 1000, 0, 0]);
+        // the reference scanner has to be unrotated
+        let rot = if idx == 0 {
+            0
+        } else {
+            (rng.next() % ROTATIONS.len() as u64) as usize
+        };
+
+        if idx > 0 {
+            lines.push(String::new());
+        }
+        lines.push(format!("--- scanner {} ---", idx));
+        for b in &beacons {
+            let rel = b.offset(&position);
+            if rel.x().abs() <= 1000 && rel.y().abs() <= 1000 && rel.z().abs() <= 1000 {
+                // any rotation will do, as long as each scanner has its own
+                lines.push(rel.rotation(rot).to_string().replace(' ', ""));
+            }
+        }
+    }
+
+    Mapper::try_from(lines).expect("could not make scanners")
+}
+
+fn scanner(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_019 correlation");
+
+    for (name, mapper) in [
+        ("puzzle input", Mapper::instance()),
+        ("synthetic", scanner_chain(40)),
+    ] {
+        group.bench_function(BenchmarkId::new("pairwise", name), |b| {
+            b.iter_batched(
+                || mapper.clone(),
+                |mut m| m.correlate_pairwise(&mut FxHashSet::default()),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("fingerprint", name), |b| {
+            b.iter_batched(
+                || mapper.clone(),
+                |mut m| m.correlate(&mut FxHashSet::default()),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn trench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_020 fifty enhancements");
+    group.sample_size(10);
+
+    let input = Enhancer::instance();
+
+    // a larger, noisier starting image enhanced with the puzzle algorithm
+    let mut rng = Lcg(20);
+    let rows = (0..300)
+        .map(|_| {
+            (0..300)
+                .map(|_| if rng.next() % 2 == 0 { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    let synthetic = Enhancer {
+        algorithm: input.algorithm.clone(),
+        image: Image::from(rows.as_ref()),
+    };
+
+    for (name, enhancer) in [("puzzle input", input), ("synthetic", synthetic)] {
+        group.bench_function(BenchmarkId::new("sparse", name), |b| {
+            b.iter(|| enhancer.clone().enhance_times(50).num_lit())
+        });
+        group.bench_function(BenchmarkId::new("dense", name), |b| {
+            b.iter(|| {
+                BitImage::from(&enhancer.image)
+                    .enhance_times(&enhancer.algorithm, 50)
+                    .num_lit()
+            })
+        });
+        group.bench_function(BenchmarkId::new("dense fused", name), |b| {
+            b.iter(|| {
+                let mut image = BitImage::from(&enhancer.image);
+                for _ in 0..25 {
+                    image = image.enhance_twice_fused(&enhancer.algorithm);
+                }
+                image.num_lit()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn dirac(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_021 quantum outcomes");
+
+    let input = QuantumGame::try_from(Games::load_input().as_ref()).expect("invalid input");
+    // one roll of a d6 a turn, to 30 points
+    let d6 = input
+        .clone()
+        .with_config(GameConfig::new(6, 1, 30, 10).expect("invalid config"))
+        .expect("invalid config");
+
+    for (name, game) in [("puzzle input", input), ("d6 to 30", d6)] {
+        group.bench_function(BenchmarkId::new("memoized", name), |b| {
+            b.iter(|| game.outcomes())
+        });
+        group.bench_function(BenchmarkId::new("dense", name), |b| {
+            b.iter(|| game.outcomes_dense())
+        });
+    }
+
+    group.finish();
+}
+
+/// `count` steps switching cuboids up to 20000 on a side, scattered over a
+/// space 200000 on a side, mostly on. Sparse enough that the signed regions
+/// stay in the tens of thousands for 5000 steps
+fn reactor_steps(count: usize) -> Instructions {
+    let mut rng = Lcg(22);
+    let input = (0..count)
+        .map(|_| {
+            let state = if rng.next() % 10 < 7 { "on" } else { "off" };
+            let ranges = ["x", "y", "z"]
+                .iter()
+                .map(|axis| {
+                    let begin = (rng.next() % 200_000) as i64 - 100_000;
+                    let end = begin + (rng.next() % 20_000) as i64;
+                    format!("{}={}..{}", axis, begin, end)
+                })
+                .collect::<Vec<_>>();
+
+            format!("{} {}", state, ranges.join(","))
+        })
+        .collect::<Vec<_>>();
+
+    Instructions::try_from(input).expect("could not make instructions")
+}
+
+fn reactor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_022 volume");
+    let instructions =
+        Instructions::try_from(Procedure::load_input()).expect("could not parse input");
+    let mut reactor = Reactor::default();
+    reactor.reboot(&instructions);
+
+    let limit = Some(Cuboid::new((-50, -50, -50).into(), (50, 50, 50).into()));
+
+    for strategy in [
+        ReactorStrategy::SignedIntersections,
+        ReactorStrategy::Disjoint,
+        ReactorStrategy::Octree,
+        ReactorStrategy::Compressed,
+    ] {
+        let name = format!("{:?}", strategy);
+        group.bench_function(BenchmarkId::new(&name, "initialization"), |b| {
+            b.iter(|| reactor.volume_by(strategy, &limit))
+        });
+
+        // the full input would need hundreds of millions of cells
+        if strategy != ReactorStrategy::Compressed {
+            group.bench_function(BenchmarkId::new(&name, "full"), |b| {
+                b.iter(|| reactor.volume_by(strategy, &None))
+            });
+        }
+    }
+
+    group.finish();
+
+    let mut group = c.benchmark_group("day_022 synthetic volume");
+    group.sample_size(10);
+    let mut reactor = Reactor::default();
+    reactor.reboot(&reactor_steps(5_000));
+
+    for strategy in [
+        ReactorStrategy::SignedIntersections,
+        ReactorStrategy::Disjoint,
+        ReactorStrategy::Octree,
+    ] {
+        group.bench_function(format!("{:?}", strategy), |b| {
+            b.iter(|| reactor.volume_by(strategy, &None))
+        });
+    }
+
+    group.finish();
+}
+
+fn amphipod(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day_023 search");
+    group.sample_size(10);
+    let input = Amphipod::load_input();
+    let small = SmallBurrow::try_from(&input).expect("invalid input");
+    let large = LargeBurrow::try_from(&input).expect("invalid input");
+
+    group.bench_function(BenchmarkId::new("minimize", "small"), |b| {
+        b.iter(|| small.minimize())
+    });
+    group.bench_function(BenchmarkId::new("minimize", "large"), |b| {
+        b.iter(|| large.minimize())
+    });
+    // keeping track of the moves costs a second map
+    group.bench_function(BenchmarkId::new("solve", "small"), |b| {
+        b.iter(|| small.solve())
+    });
+    group.bench_function(BenchmarkId::new("solve", "large"), |b| {
+        b.iter(|| large.solve())
+    });
+    group.bench_function(BenchmarkId::new("ida_star", "small"), |b| {
+        b.iter(|| small.minimize_by(AmphipodStrategy::IdaStar))
+    });
+    group.bench_function(BenchmarkId::new("ida_star", "large"), |b| {
+        b.iter(|| large.minimize_by(AmphipodStrategy::IdaStar))
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches, octopus, cave, camera, polymer, chiton, decoder, probe, snailfish, scanner, trench,
+    dirac, reactor, amphipod
+);
+criterion_main!(benches);
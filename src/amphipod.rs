@@ -8,40 +8,45 @@ use std::{
     // iter::FromIterator,
 };
 
+use crate::visualize::{self, Visualize};
+
+/// The kind of an amphipod, which is also the index of the room it wants to
+/// end up in. The first four are the ones from the puzzle, and every kind
+/// after them takes ten times the energy of the one before.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-pub enum AmphipodType {
-    Amber,
-    Bronze,
-    Copper,
-    Desert,
-}
+pub struct AmphipodType(usize);
 
 impl AmphipodType {
-    pub fn energy_per_step(&self) -> usize {
-        match self {
-            Self::Amber => 1,
-            Self::Bronze => 10,
-            Self::Copper => 100,
-            Self::Desert => 1000,
+    pub const AMBER: Self = Self(0);
+    pub const BRONZE: Self = Self(1);
+    pub const COPPER: Self = Self(2);
+    pub const DESERT: Self = Self(3);
+
+    /// Kinds are lettered from 'A', and have to fit in a nibble of the state
+    /// key alongside an empty cell.
+    pub const MAX_KINDS: usize = 15;
+
+    pub fn new(index: usize) -> Result<Self> {
+        if index >= Self::MAX_KINDS {
+            bail!("there are only {} kinds of amphipod", Self::MAX_KINDS);
         }
+        Ok(Self(index))
+    }
+
+    pub fn letter(&self) -> char {
+        (b'A' + self.0 as u8) as char
+    }
+
+    pub fn energy_per_step(&self) -> usize {
+        10_usize.pow(self.0 as u32)
     }
 
     pub fn desired_room(&self) -> usize {
-        match self {
-            Self::Amber => 0,
-            Self::Bronze => 1,
-            Self::Copper => 2,
-            Self::Desert => 3,
-        }
+        self.0
     }
 
     pub fn desired_room_entrance(&self) -> usize {
-        match self {
-            Self::Amber => 2,
-            Self::Bronze => 4,
-            Self::Copper => 6,
-            Self::Desert => 8,
-        }
+        2 + 2 * self.0
     }
 }
 
@@ -49,30 +54,37 @@ impl TryFrom<char> for AmphipodType {
     type Error = anyhow::Error;
 
     fn try_from(value: char) -> Result<Self> {
-        Ok(match value {
-            'A' => Self::Amber,
-            'B' => Self::Bronze,
-            'C' => Self::Copper,
-            'D' => Self::Desert,
-            _ => bail!("cannot create amphipod type from '{}'", value),
-        })
+        if !value.is_ascii_uppercase() {
+            bail!("cannot create amphipod type from '{}'", value);
+        }
+        Self::new((value as u8 - b'A') as usize)
+            .map_err(|_| anyhow!("cannot create amphipod type from '{}'", value))
     }
 }
 
 impl fmt::Display for AmphipodType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let ch = match self {
-            Self::Amber => 'A',
-            Self::Bronze => 'B',
-            Self::Copper => 'C',
-            Self::Desert => 'D',
-        };
-        write!(f, "{}", ch)
+        write!(f, "{}", self.letter())
     }
 }
 
 pub const EMPTY: char = ' ';
 
+fn nibble(ch: char) -> u128 {
+    if ch == EMPTY {
+        0
+    } else {
+        (ch as u8 - b'A') as u128 + 1
+    }
+}
+
+fn unnibble(packed: u128) -> char {
+    match packed & 0xf {
+        0 => EMPTY,
+        n => (b'A' + n as u8 - 1) as char,
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Room<const N: usize> {
     desired: char,
@@ -138,7 +150,10 @@ impl<const N: usize> Room<N> {
         self.state[self.capacity]
     }
 
-    pub fn valid_hall_moves<'a>(&self, hall: &'a Hall) -> impl Iterator<Item = (char, usize)> + 'a {
+    pub fn valid_hall_moves<'a, const H: usize>(
+        &self,
+        hall: &'a Hall<H>,
+    ) -> impl Iterator<Item = (char, usize)> + 'a {
         let ch = self.peek();
         let empty = self.empty();
         let complete = self.complete();
@@ -147,13 +162,12 @@ impl<const N: usize> Room<N> {
         // incorrect desired values
         let kind = AmphipodType::try_from(self.desired).unwrap();
         let desired_room_entrance = kind.desired_room_entrance();
-        Hall::VALID_WAITING_POSITIONS
-            .iter()
-            .filter(move |p| hall.state[**p] == EMPTY)
+        Hall::<H>::waiting_positions()
+            .filter(move |p| hall.state[*p] == EMPTY)
             .filter_map(move |hall_pos| {
                 if !empty && !complete && !accepting_desired {
-                    hall.can_move_between(desired_room_entrance, *hall_pos)
-                        .then(|| (ch, *hall_pos))
+                    hall.can_move_between(desired_room_entrance, hall_pos)
+                        .then_some((ch, hall_pos))
                 } else {
                     None
                 }
@@ -161,19 +175,29 @@ impl<const N: usize> Room<N> {
     }
 }
 
+/// A hall of `H` cells, with the room entrances on every other cell starting
+/// from the third and two cells spare at either end.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-pub struct Hall {
-    state: [char; 11],
+pub struct Hall<const H: usize> {
+    state: [char; H],
 }
 
-impl Default for Hall {
+impl<const H: usize> Default for Hall<H> {
     fn default() -> Self {
-        Self { state: [EMPTY; 11] }
+        Self { state: [EMPTY; H] }
     }
 }
 
-impl Hall {
-    pub const VALID_WAITING_POSITIONS: [usize; 7] = [0, 1, 3, 5, 7, 9, 10];
+impl<const H: usize> Hall<H> {
+    pub fn is_entrance(pos: usize) -> bool {
+        pos >= 2 && pos + 2 < H && pos & 1 == 0
+    }
+
+    /// The cells an amphipod can stop in, which is all of them except for the
+    /// entrances.
+    pub fn waiting_positions() -> impl Iterator<Item = usize> {
+        (0..H).filter(|pos| !Self::is_entrance(*pos))
+    }
 
     pub fn set(&mut self, pos: usize, val: char) {
         self.state[pos] = val;
@@ -223,38 +247,283 @@ impl Hall {
     }
 }
 
+/// Somewhere an amphipod can stand.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Location {
+    /// A cell of the hall, counting from the left.
+    Hall(usize),
+    /// A slot of a room, counting down from the hall.
+    Room { room: usize, slot: usize },
+}
+
+/// One amphipod moving, as recorded by [`Burrow::solve`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Move {
+    pub kind: AmphipodType,
+    pub from: Location,
+    pub to: Location,
+    /// The energy spent on the move.
+    pub energy: usize,
+}
+
+/// The ways `solve_by` can search for the cheapest way to organize a burrow
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Strategy {
+    /// Dijkstra's algorithm, keeping the lowest cost of every burrow it has
+    /// seen
+    Dijkstra,
+    /// Iterative deepening A*, which only keeps the moves on the current path
+    /// but searches most burrows again on every pass
+    IdaStar,
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Self::Dijkstra
+    }
+}
+
+/// A burrow waiting to be searched, packed down to its key to keep the heap
+/// small.
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Node<const N: usize> {
-    state: Burrow<N>,
+pub struct Node {
+    key: u128,
     cost: usize,
     f: usize,
 }
 
-impl<const N: usize> Node<N> {
-    pub fn new(state: Burrow<N>, cost: usize, f: usize) -> Self {
-        Self { state, cost, f }
+impl Node {
+    pub fn new<const N: usize, const R: usize, const H: usize>(
+        state: Burrow<N, R, H>,
+        cost: usize,
+        f: usize,
+    ) -> Self {
+        Self {
+            key: state.key(),
+            cost,
+            f,
+        }
     }
 }
 
-impl<const N: usize> Ord for Node<N> {
+impl Ord for Node {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         other.f.cmp(&self.f)
     }
 }
 
-impl<const N: usize> PartialOrd for Node<N> {
+impl PartialOrd for Node {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
+/// The open states of a search, with the cheapest known cost of every state
+/// seen so far and, when asked for, the move that reached it.
+struct Search {
+    heap: BinaryHeap<Node>,
+    lowest: FxHashMap<u128, usize>,
+    parents: Option<FxHashMap<u128, (u128, Move)>>,
+}
+
+impl Search {
+    fn new(start: Node, track: bool) -> Self {
+        let mut lowest = FxHashMap::default();
+        lowest.insert(start.key, start.cost);
+        let mut heap = BinaryHeap::new();
+        heap.push(start);
+
+        Self {
+            heap,
+            lowest,
+            parents: track.then(FxHashMap::default),
+        }
+    }
+
+    fn visit(&mut self, from: u128, node: Node, mv: Move) {
+        let lowest = self.lowest.entry(node.key).or_insert(usize::MAX);
+        if node.cost < *lowest {
+            *lowest = node.cost;
+            if let Some(parents) = self.parents.as_mut() {
+                parents.insert(node.key, (from, mv));
+            }
+            self.heap.push(node);
+        }
+    }
+
+    /// The moves leading from `start` to `end`, following the recorded
+    /// parents back.
+    fn moves(&self, start: u128, end: u128) -> Vec<Move> {
+        let mut moves = Vec::new();
+        if let Some(parents) = self.parents.as_ref() {
+            let mut key = end;
+            while key != start {
+                let (parent, mv) = parents[&key];
+                moves.push(mv);
+                key = parent;
+            }
+            moves.reverse();
+        }
+        moves
+    }
+}
+
+/// Problems with a burrow diagram
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BurrowError {
+    /// The cell at `row`, `col` doesn't fit the shape of the burrow, where
+    /// `None` is past the end of the diagram
+    Unexpected {
+        row: usize,
+        col: usize,
+        expected: &'static str,
+        found: Option<char>,
+    },
+    /// The rooms hold the wrong number of one kind of amphipod
+    Count {
+        kind: AmphipodType,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for BurrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unexpected {
+                row,
+                col,
+                expected,
+                found: Some(ch),
+            } => write!(
+                f,
+                "expected {} at row {}, column {}, found '{}'",
+                expected, row, col, ch
+            ),
+            Self::Unexpected {
+                row,
+                col,
+                expected,
+                found: None,
+            } => write!(
+                f,
+                "expected {} at row {}, column {}, found the end of the diagram",
+                expected, row, col
+            ),
+            Self::Count {
+                kind,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {} amphipods of kind {}, found {}",
+                expected, kind, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BurrowError {}
+
+/// `R` rooms, each `N` amphipods deep, off of a hall of `H` cells. Const
+/// generics can't do arithmetic yet, so `H` has to be given as well, and it
+/// has to be `2 * R + 3`.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-pub struct Burrow<const N: usize> {
-    hall: Hall,
-    rooms: [Room<N>; 4],
+pub struct Burrow<const N: usize, const R: usize, const H: usize> {
+    hall: Hall<H>,
+    rooms: [Room<N>; R],
 }
 
-impl<const N: usize> Burrow<N> {
+impl<const N: usize, const R: usize, const H: usize> Burrow<N, R, H> {
+    // checked when a burrow is made, so a bad shape fails to compile
+    const SHAPE: () = assert!(
+        R > 0 && R <= AmphipodType::MAX_KINDS && H == 2 * R + 3 && Self::CELLS <= 32,
+        "a burrow needs a hall 2 * R + 3 long and at most 32 cells to stop in"
+    );
+
+    /// The cells an amphipod can stop in, each of which is a nibble of the key.
+    const CELLS: usize = H - R + R * N;
+
+    /// Reads the rooms from a diagram like the puzzle input, with the hall on
+    /// the second line and a line for each slot of the rooms below it. The
+    /// hall has to start out empty, with exactly `N` of each kind of amphipod
+    /// in the rooms.
+    pub fn parse(lines: &[String]) -> Result<Self> {
+        // so the parsing is dumb
+        let mut burrow = Self::default();
+        let chars = lines
+            .iter()
+            .map(|s| s.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let check =
+            |row: usize, col: usize, expected: &'static str, valid: &dyn Fn(char) -> bool| {
+                let found = chars.get(row).and_then(|r| r.get(col)).copied();
+                match found {
+                    Some(ch) if valid(ch) => Ok(ch),
+                    _ => Err(BurrowError::Unexpected {
+                        row,
+                        col,
+                        expected,
+                        found,
+                    }),
+                }
+            };
+        let wall = |ch: char| ch == '#';
+        let open = |ch: char| ch == '.';
+        let amphipod = |ch: char| {
+            AmphipodType::try_from(ch)
+                .map(|kind| kind.desired_room() < R)
+                .unwrap_or(false)
+        };
+
+        for col in 0..H + 2 {
+            check(0, col, "a wall", &wall)?;
+        }
+
+        check(1, 0, "a wall", &wall)?;
+        for col in 1..=H {
+            check(1, col, "an empty hall", &open)?;
+        }
+        check(1, H + 1, "a wall", &wall)?;
+
+        let c_offset = 1;
+        for row in 2..2 + N {
+            for col in (0..=R).map(|idx| c_offset + 1 + 2 * idx) {
+                check(row, col, "a wall", &wall)?;
+            }
+        }
+
+        for room in burrow.rooms.iter_mut() {
+            let room_kind = AmphipodType::try_from(room.desired).unwrap();
+            let c_idx = c_offset + room_kind.desired_room_entrance();
+            for row in (2..2 + N).rev() {
+                room.push(check(row, c_idx, "an amphipod", &amphipod)?);
+            }
+        }
+
+        for col in 2..=2 * R + 2 {
+            check(2 + N, col, "a wall", &wall)?;
+        }
+
+        let mut counts = [0; R];
+        for ch in burrow.rooms.iter().flat_map(|r| r.state.iter()) {
+            counts[AmphipodType::try_from(*ch)?.desired_room()] += 1;
+        }
+        for (idx, found) in counts.iter().enumerate() {
+            if *found != N {
+                return Err(BurrowError::Count {
+                    kind: AmphipodType(idx),
+                    expected: N,
+                    found: *found,
+                }
+                .into());
+            }
+        }
+
+        Ok(burrow)
+    }
+
     // pub fn key(&self) -> String {
     //     String::from_iter(
     //         self.hall.state.
@@ -265,17 +534,38 @@ impl<const N: usize> Burrow<N> {
     //             .chain(self.rooms[3].state.iter()))
     // }
 
+    /// Packs every cell an amphipod can stop in into a nibble each, which is
+    /// zero for an empty cell or one more than the kind in it. The entrances
+    /// are always empty and the rooms are always filled from the bottom, so no
+    /// two burrows share a key.
     pub fn key(&self) -> u128 {
-        self.hall
-            .state
-            .iter()
-            .chain(self.rooms[0].state.iter())
-            .chain(self.rooms[1].state.iter())
-            .chain(self.rooms[2].state.iter())
-            .chain(self.rooms[3].state.iter())
-            .fold(0, |acc, ch| {
-                acc * 10 + (ch.to_digit(16).unwrap_or_default() as u128)
-            })
+        Hall::<H>::waiting_positions()
+            .map(|pos| self.hall.state[pos])
+            .chain(self.rooms.iter().flat_map(|r| r.state.iter().copied()))
+            .fold(0, |acc, ch| (acc << 4) | nibble(ch))
+    }
+
+    /// The burrow packed into `key`, see [`Burrow::key`].
+    pub fn from_key(key: u128) -> Self {
+        let mut burrow = Self::default();
+        let mut shift = 4 * Self::CELLS;
+        let mut next = || {
+            shift -= 4;
+            unnibble(key >> shift)
+        };
+
+        for pos in Hall::<H>::waiting_positions() {
+            burrow.hall.state[pos] = next();
+        }
+
+        for room in burrow.rooms.iter_mut() {
+            for ch in room.state.iter_mut() {
+                *ch = next();
+            }
+            room.capacity = room.state.iter().filter(|ch| **ch == EMPTY).count();
+        }
+
+        burrow
     }
 
     pub fn complete(&self) -> bool {
@@ -283,246 +573,458 @@ impl<const N: usize> Burrow<N> {
     }
 
     pub fn minimize(&self) -> Option<usize> {
-        let mut lowest: FxHashMap<u128, usize> = FxHashMap::default();
-        lowest.insert(self.key(), 0);
-        let mut heap = BinaryHeap::new();
-        heap.push(Node::new(*self, 0, 0));
+        self.minimize_by(Strategy::default())
+    }
+
+    pub fn minimize_by(&self, strategy: Strategy) -> Option<usize> {
+        match strategy {
+            Strategy::Dijkstra => self.search(false).map(|(cost, _)| cost),
+            Strategy::IdaStar => self.ida_star().map(|(cost, _)| cost),
+        }
+    }
+
+    /// The lowest energy needed to organize the burrow, along with the moves
+    /// that get there.
+    pub fn solve(&self) -> Option<(usize, Vec<Move>)> {
+        self.solve_by(Strategy::default())
+    }
+
+    pub fn solve_by(&self, strategy: Strategy) -> Option<(usize, Vec<Move>)> {
+        match strategy {
+            Strategy::Dijkstra => self.search(true),
+            Strategy::IdaStar => self.ida_star(),
+        }
+    }
+
+    /// Makes `mv`, failing if the amphipod isn't where it says or can't go
+    /// where it says.
+    pub fn apply(&mut self, mv: &Move) -> Result<()> {
+        // work on a copy so a bad move leaves the burrow alone
+        let mut next = *self;
+        let ch = match mv.from {
+            Location::Hall(pos) => {
+                let ch = *next
+                    .hall
+                    .state
+                    .get(pos)
+                    .ok_or_else(|| anyhow!("no hall cell {}", pos))?;
+                next.hall.unset(pos);
+                ch
+            }
+            Location::Room { room, slot } => {
+                let r = next
+                    .rooms
+                    .get_mut(room)
+                    .ok_or_else(|| anyhow!("no room {}", room))?;
+                if r.empty() || r.push_distance() != slot {
+                    bail!("slot {} is not the top of room {}", slot, room);
+                }
+                r.pop()
+            }
+        };
+
+        if AmphipodType::try_from(ch).ok() != Some(mv.kind) {
+            bail!("expected {} at {:?}, found '{}'", mv.kind, mv.from, ch);
+        }
+
+        match mv.to {
+            Location::Hall(pos) => {
+                if next.hall.state.get(pos) != Some(&EMPTY) {
+                    bail!("hall cell {} is not free", pos);
+                }
+                next.hall.set(pos, ch);
+            }
+            Location::Room { room, slot } => {
+                let r = next
+                    .rooms
+                    .get_mut(room)
+                    .ok_or_else(|| anyhow!("no room {}", room))?;
+                if r.full() || r.push_distance() != slot + 1 {
+                    bail!("slot {} is not the next free one of room {}", slot, room);
+                }
+                r.push(ch);
+            }
+        }
+
+        *self = next;
+        Ok(())
+    }
+
+    /// A frame for the burrow as it is and one after each of `moves`, failing
+    /// if any of them can't be made.
+    pub fn render_solution(&self, moves: &[Move]) -> Result<Vec<String>> {
+        // check them all first, since a replay just stops at a bad move
+        let mut check = *self;
+        for mv in moves.iter() {
+            check.apply(mv)?;
+        }
+
+        let mut replay = Replay::new(*self, moves);
+        Ok(visualize::frames(&mut replay, moves.len()))
+    }
 
-        while let Some(cur) = heap.pop() {
-            if cur.state.complete() {
-                return Some(cur.cost);
+    fn search(&self, track: bool) -> Option<(usize, Vec<Move>)> {
+        let start = Node::new(*self, 0, 0);
+        let start_key = start.key;
+        let mut search = Search::new(start, track);
+
+        while let Some(cur) = search.heap.pop() {
+            let state = Self::from_key(cur.key);
+            if state.complete() {
+                return Some((cur.cost, search.moves(start_key, cur.key)));
             }
 
             // while this seems fine, the cache lookup performance is just way
             // too slow because it has to be hashed instead of direct index
-            // if cur.cost > *lowest.get(&cur.state.key()).unwrap_or(&usize::MAX) {
+            // if cur.cost > *lowest.get(&cur.key).unwrap_or(&usize::MAX) {
             //     continue;
             // }
 
-            // if we can move directly, this is the thing with the lowest cost
-            let mut any_direct = false;
-            for (room_idx, room) in cur.state.rooms.iter().enumerate() {
-                if !room.empty() && !room.accepting_desired() {
-                    let ch = room.peek();
-                    let kind = AmphipodType::try_from(ch).unwrap();
-                    let desired = cur.state.rooms[kind.desired_room()];
-
-                    if desired.accepting_desired() {
-                        let origin_kind = AmphipodType::try_from(room.desired).unwrap();
-                        let origin_entrance = origin_kind.desired_room_entrance();
-                        let desired_room_entrance = kind.desired_room_entrance();
-
-                        if cur
-                            .state
-                            .hall
-                            .can_move_between(origin_entrance, desired_room_entrance)
-                        {
-                            any_direct = true;
-                            let mut new_state = cur.state;
-                            new_state.rooms[room_idx].pop();
-                            new_state.rooms[kind.desired_room()].push(ch);
-                            let entrance_dist =
-                                (origin_entrance as i64 - desired_room_entrance as i64).abs() + 1;
-                            let dist = room.push_distance()
-                                + desired.push_distance()
-                                + entrance_dist as usize;
-                            let cost = cur.cost + dist * kind.energy_per_step();
-                            let new_node = Node::new(new_state, cost, cost);
-
-                            lowest
-                                .entry(new_node.state.key())
-                                .and_modify(|e| {
-                                    if new_node.cost < *e {
-                                        *e = new_node.cost;
-                                        heap.push(new_node.clone());
-                                    }
-                                })
-                                .or_insert_with(|| {
-                                    let cost = new_node.cost;
-                                    heap.push(new_node);
-                                    cost
-                                });
-                        }
-                    }
-                }
+            state.for_each_move(|new_state, mv| {
+                let cost = cur.cost + mv.energy;
+                // let h = (pos as i32 - kind.desired_room_entrance() as i32).abs() as usize
+                //     + new_state.rooms[kind.desired_room()].push_distance();
+                let new_node =
+                    // Node::new(new_state, cost, cost + (dist + h) * kind.energy_per_step());
+                    Node::new(new_state, cost, cost);
+
+                search.visit(cur.key, new_node, mv);
+            });
+        }
+
+        None
+    }
+
+    fn ida_star(&self) -> Option<(usize, Vec<Move>)> {
+        let mut bound = self.heuristic();
+        let mut path = Vec::new();
+        let mut best = None;
+
+        loop {
+            let over = self.deepen(0, bound, &mut path, &mut best);
+
+            // everything that could have beaten it has been searched
+            if best.is_some() {
+                return best;
             }
 
-            // these are optimal, so don't bother checking anything else (they
-            // would seem sub-optimal compared to the halway movements or some
-            // of the room -> hallway moves
-            if any_direct {
-                continue;
+            if over == usize::MAX {
+                return None;
             }
 
-            // find a list of all the new game states
-            // for all items in the hall, attempt to move them to accepting rooms
-            for (pos, ch, kind, dist) in cur.state.hall.moveable(&cur.state.rooms) {
-                // copies
-                let mut new_state = cur.state;
-                new_state.rooms[kind.desired_room()].push(*ch);
-                new_state.hall.unset(pos);
-                let cost = cur.cost + dist * kind.energy_per_step();
-                let new_node = Node::new(new_state, cost, cost);
-
-                lowest
-                    .entry(new_node.state.key())
-                    .and_modify(|e| {
-                        if new_node.cost < *e {
-                            *e = new_node.cost;
-                            heap.push(new_node.clone());
-                        }
-                    })
-                    .or_insert_with(|| {
-                        let cost = new_node.cost;
-                        heap.push(new_node);
-                        cost
-                    });
+            // the energies are too varied to only raise the bound to the next
+            // estimate, which would take thousands of passes
+            bound = over.max(bound + bound / Self::BOUND_GROWTH);
+        }
+    }
+
+    /// How much of itself the IDA* bound grows by on each pass, at least.
+    const BOUND_GROWTH: usize = 32;
+
+    /// One pass of IDA*, depth first from here with `path` holding the moves
+    /// so far, giving up on any burrow whose estimate goes over `bound` or
+    /// can't beat the `best` found so far. Returns the lowest estimate that
+    /// went over the bound.
+    fn deepen(
+        &self,
+        cost: usize,
+        bound: usize,
+        path: &mut Vec<Move>,
+        best: &mut Option<(usize, Vec<Move>)>,
+    ) -> usize {
+        let estimate = cost + self.heuristic();
+        if estimate > bound {
+            return estimate;
+        }
+
+        if let Some((best_cost, _)) = best {
+            if estimate >= *best_cost {
+                return usize::MAX;
             }
+        }
+
+        if self.complete() {
+            *best = Some((cost, path.clone()));
+            return usize::MAX;
+        }
+
+        let mut next = Vec::new();
+        self.for_each_move(|burrow, mv| next.push((burrow, mv)));
+
+        let mut lowest = usize::MAX;
+        for (burrow, mv) in next {
+            path.push(mv);
+            lowest = lowest.min(burrow.deepen(cost + mv.energy, bound, path, best));
+            path.pop();
+        }
+
+        lowest
+    }
+
+    /// A lower bound on the energy left to spend, as if every amphipod that
+    /// still has to move could go straight home, with each room filling up
+    /// one slot deeper than the last.
+    fn heuristic(&self) -> usize {
+        let mut energy = 0;
+        let mut entering = [0_usize; R];
+
+        for (pos, ch) in self.hall.occupants() {
+            let kind = AmphipodType::try_from(*ch).unwrap();
+            let across = (kind.desired_room_entrance() as i64 - pos as i64).unsigned_abs() as usize;
+            energy += (across + 1) * kind.energy_per_step();
+            entering[kind.desired_room()] += 1;
+        }
 
-            // for all items in rooms where they don't belong
-            for (room_idx, room) in cur.state.rooms.iter().enumerate() {
-                let room_kind = AmphipodType::try_from(room.desired).unwrap();
-                if room.complete() {
+        for (room_idx, room) in self.rooms.iter().enumerate() {
+            // whoever is home at the bottom of the room can stay put
+            let settled = room
+                .state
+                .iter()
+                .rev()
+                .take_while(|ch| **ch == room.desired)
+                .count();
+
+            for (slot, ch) in room.state.iter().enumerate().take(N - settled) {
+                if *ch == EMPTY {
                     continue;
                 }
 
-                for (ch, pos) in room.valid_hall_moves(&cur.state.hall) {
-                    let mut new_state = cur.state;
-                    let kind = AmphipodType::try_from(ch).unwrap();
-                    let dist = room.push_distance()
-                        + 1
-                        + (room_kind.desired_room_entrance() as i32 - pos as i32).abs() as usize;
-                    new_state.rooms[room_idx].pop();
-                    new_state.hall.set(pos, ch);
-                    let cost = cur.cost + dist * kind.energy_per_step();
-                    // let h = (pos as i32 - kind.desired_room_entrance() as i32).abs() as usize
-                    //     + new_state.rooms[kind.desired_room()].push_distance();
-                    let new_node =
-                        // Node::new(new_state, cost, cost + (dist + h) * kind.energy_per_step());
-                        Node::new(new_state, cost, cost);
-
-                    lowest
-                        .entry(new_node.state.key())
-                        .and_modify(|e| {
-                            if new_node.cost < *e {
-                                *e = new_node.cost;
-                                heap.push(new_node.clone());
-                            }
-                        })
-                        .or_insert_with(|| {
-                            let cost = new_node.cost;
-                            heap.push(new_node);
-                            cost
-                        });
+                let kind = AmphipodType::try_from(*ch).unwrap();
+                // leaving the right room means stepping aside and back
+                let across = if kind.desired_room() == room_idx {
+                    2
+                } else {
+                    2 * (kind.desired_room() as i64 - room_idx as i64).unsigned_abs() as usize
+                };
+                energy += (slot + 1 + across + 1) * kind.energy_per_step();
+                entering[kind.desired_room()] += 1;
+            }
+        }
+
+        // everyone above was counted as going one slot down
+        for (room_idx, count) in entering.iter().enumerate() {
+            energy +=
+                count * count.saturating_sub(1) / 2 * AmphipodType(room_idx).energy_per_step();
+        }
+
+        energy
+    }
+
+    /// Calls `f` with every burrow one move away, and the move.
+    fn for_each_move<F: FnMut(Self, Move)>(&self, mut f: F) {
+        // if we can move directly, this is the thing with the lowest cost
+        let mut any_direct = false;
+        for (room_idx, room) in self.rooms.iter().enumerate() {
+            if !room.empty() && !room.accepting_desired() {
+                let ch = room.peek();
+                let kind = AmphipodType::try_from(ch).unwrap();
+                let desired = self.rooms[kind.desired_room()];
+
+                if desired.accepting_desired() {
+                    let origin_kind = AmphipodType::try_from(room.desired).unwrap();
+                    let origin_entrance = origin_kind.desired_room_entrance();
+                    let desired_room_entrance = kind.desired_room_entrance();
+
+                    if self
+                        .hall
+                        .can_move_between(origin_entrance, desired_room_entrance)
+                    {
+                        any_direct = true;
+                        let mut new_state = *self;
+                        new_state.rooms[room_idx].pop();
+                        new_state.rooms[kind.desired_room()].push(ch);
+                        let entrance_dist =
+                            (origin_entrance as i64 - desired_room_entrance as i64).abs() + 1;
+                        let dist =
+                            room.push_distance() + desired.push_distance() + entrance_dist as usize;
+                        let mv = Move {
+                            kind,
+                            from: Location::Room {
+                                room: room_idx,
+                                slot: room.push_distance(),
+                            },
+                            to: Location::Room {
+                                room: kind.desired_room(),
+                                slot: desired.push_distance() - 1,
+                            },
+                            energy: dist * kind.energy_per_step(),
+                        };
+
+                        f(new_state, mv);
+                    }
                 }
             }
         }
 
-        None
+        // these are optimal, so don't bother checking anything else (they
+        // would seem sub-optimal compared to the halway movements or some
+        // of the room -> hallway moves
+        if any_direct {
+            return;
+        }
+
+        // find a list of all the new game states
+        // for all items in the hall, attempt to move them to accepting rooms
+        for (pos, ch, kind, dist) in self.hall.moveable(&self.rooms) {
+            // copies
+            let mut new_state = *self;
+            let slot = new_state.rooms[kind.desired_room()].push_distance() - 1;
+            new_state.rooms[kind.desired_room()].push(*ch);
+            new_state.hall.unset(pos);
+            let mv = Move {
+                kind,
+                from: Location::Hall(pos),
+                to: Location::Room {
+                    room: kind.desired_room(),
+                    slot,
+                },
+                energy: dist * kind.energy_per_step(),
+            };
+
+            f(new_state, mv);
+        }
+
+        // for all items in rooms where they don't belong
+        for (room_idx, room) in self.rooms.iter().enumerate() {
+            let room_kind = AmphipodType::try_from(room.desired).unwrap();
+            if room.complete() {
+                continue;
+            }
+
+            for (ch, pos) in room.valid_hall_moves(&self.hall) {
+                let mut new_state = *self;
+                let kind = AmphipodType::try_from(ch).unwrap();
+                let dist = room.push_distance()
+                    + 1
+                    + (room_kind.desired_room_entrance() as i32 - pos as i32).unsigned_abs()
+                        as usize;
+                new_state.rooms[room_idx].pop();
+                new_state.hall.set(pos, ch);
+                let mv = Move {
+                    kind,
+                    from: Location::Room {
+                        room: room_idx,
+                        slot: room.push_distance(),
+                    },
+                    to: Location::Hall(pos),
+                    energy: dist * kind.energy_per_step(),
+                };
+                f(new_state, mv);
+            }
+        }
     }
 }
 
-impl<const N: usize> Default for Burrow<N> {
+impl<const N: usize, const R: usize, const H: usize> Default for Burrow<N, R, H> {
     fn default() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::SHAPE;
+
+        let mut rooms = [Room::<N>::new(EMPTY); R];
+        for (idx, room) in rooms.iter_mut().enumerate() {
+            *room = Room::new(AmphipodType(idx).letter());
+        }
+
         Self {
             hall: Hall::default(),
-            rooms: [
-                Room::<N>::new('A'),
-                Room::<N>::new('B'),
-                Room::<N>::new('C'),
-                Room::<N>::new('D'),
-            ],
+            rooms,
         }
     }
 }
 
-pub type SmallBurrow = Burrow<2>;
+impl<const N: usize, const R: usize, const H: usize> fmt::Display for Burrow<N, R, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", "#".repeat(H + 2))?;
+        writeln!(f, "#{}#", self.hall.state.iter().collect::<String>())?;
+
+        for slot in 0..N {
+            // the top row of the rooms is as wide as the hall
+            let edge = if slot == 0 { "##" } else { "  " };
+            write!(f, "{}", edge)?;
+            for room in self.rooms.iter() {
+                write!(f, "#{}", room.state[slot])?;
+            }
+            writeln!(f, "#{}", edge.trim())?;
+        }
 
-impl TryFrom<&Vec<String>> for SmallBurrow {
-    type Error = anyhow::Error;
+        write!(f, "  {}", "#".repeat(2 * R + 1))
+    }
+}
 
-    fn try_from(value: &Vec<String>) -> Result<Self> {
-        // so the parsing is dumb
-        let mut burrow = SmallBurrow::default();
-        let chars = value
-            .iter()
-            .map(|s| s.chars().collect::<Vec<_>>())
-            .collect::<Vec<_>>();
-        let c_offset = 1;
-        let rows = [3_usize, 2];
+/// Plays back a list of moves one at a time, from [`Burrow::render_solution`]
+/// or for [`visualize::animate`].
+#[derive(Debug, Clone)]
+pub struct Replay<const N: usize, const R: usize, const H: usize> {
+    burrow: Burrow<N, R, H>,
+    moves: Vec<Move>,
+    next: usize,
+    energy: usize,
+}
 
-        for room in burrow.rooms.iter_mut() {
-            let room_kind = AmphipodType::try_from(room.desired).unwrap();
-            let c_idx = c_offset + room_kind.desired_room_entrance();
-            for row in rows.iter() {
-                room.push(
-                    *chars.get(*row).and_then(|r| r.get(c_idx)).ok_or_else(|| {
-                        anyhow!("invalid input, could not find {}, {}", row, c_idx)
-                    })?,
-                );
-            }
+impl<const N: usize, const R: usize, const H: usize> Replay<N, R, H> {
+    pub fn new(burrow: Burrow<N, R, H>, moves: &[Move]) -> Self {
+        Self {
+            burrow,
+            moves: moves.to_vec(),
+            next: 0,
+            energy: 0,
         }
+    }
 
-        Ok(burrow)
+    pub fn burrow(&self) -> &Burrow<N, R, H> {
+        &self.burrow
+    }
+
+    /// The energy spent on the moves made so far.
+    pub fn energy(&self) -> usize {
+        self.energy
     }
 }
 
-impl fmt::Display for SmallBurrow {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "#############
-#{}#
-###{}#{}#{}#{}###
-  #{}#{}#{}#{}#
-  #########",
-            self.hall.state.iter().collect::<String>(),
-            self.rooms[0].state[0],
-            self.rooms[1].state[0],
-            self.rooms[2].state[0],
-            self.rooms[3].state[0],
-            self.rooms[0].state[1],
-            self.rooms[1].state[1],
-            self.rooms[2].state[1],
-            self.rooms[3].state[1],
-        )
+impl<const N: usize, const R: usize, const H: usize> Visualize for Replay<N, R, H> {
+    fn render_frame(&self) -> String {
+        format!("{}\nenergy: {}", self.burrow, self.energy)
+    }
+
+    fn advance(&mut self) -> bool {
+        match self.moves.get(self.next) {
+            // a move that doesn't fit ends the replay early
+            Some(mv) if self.burrow.apply(mv).is_ok() => {
+                self.next += 1;
+                self.energy += mv.energy;
+                true
+            }
+            _ => false,
+        }
     }
 }
 
-pub type LargeBurrow = Burrow<4>;
+pub type SmallBurrow = Burrow<2, 4, 11>;
 
-impl TryFrom<&Vec<String>> for LargeBurrow {
+impl TryFrom<&Vec<String>> for SmallBurrow {
     type Error = anyhow::Error;
 
     fn try_from(value: &Vec<String>) -> Result<Self> {
-        // so the parsing is dumb
-        let mut burrow = LargeBurrow::default();
-        let chars = value
-            .iter()
-            .map(|s| s.chars().collect::<Vec<_>>())
-            .collect::<Vec<_>>();
-        let c_offset = 1;
-        let rows = [3_usize, 2];
-        let padding = [['D', 'D'], ['B', 'C'], ['A', 'B'], ['C', 'A']];
+        Self::parse(value)
+    }
+}
 
-        for (room_idx, room) in burrow.rooms.iter_mut().enumerate() {
-            let room_kind = AmphipodType::try_from(room.desired).unwrap();
-            let c_idx = c_offset + room_kind.desired_room_entrance();
-            for (idx, row) in rows.iter().enumerate() {
-                room.push(
-                    *chars.get(*row).and_then(|r| r.get(c_idx)).ok_or_else(|| {
-                        anyhow!("invalid input, could not find {}, {}", row, c_idx)
-                    })?,
-                );
-                if idx == 0 {
-                    for p in padding[room_idx].iter() {
-                        room.push(*p);
-                    }
-                }
-            }
-        }
+pub type LargeBurrow = Burrow<4, 4, 11>;
 
-        Ok(burrow)
+impl TryFrom<&Vec<String>> for LargeBurrow {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Vec<String>) -> Result<Self> {
+        // the folded up part of the diagram goes between the two rows we're
+        // given
+        let mut unfolded = value.clone();
+        let fold = unfolded.len().min(3);
+        unfolded.splice(
+            fold..fold,
+            ["###D#C#B#A#", "###D#B#A#C#"].iter().map(|s| s.to_string()),
+        );
+
+        Self::parse(&unfolded)
     }
 }
 
@@ -604,7 +1106,16 @@ mod tests {
 
     #[test]
     fn halls() {
-        let mut hall = Hall::default();
+        assert_eq!(
+            Hall::<11>::waiting_positions().collect::<Vec<_>>(),
+            vec![0, 1, 3, 5, 7, 9, 10]
+        );
+        assert_eq!(
+            Hall::<13>::waiting_positions().collect::<Vec<_>>(),
+            vec![0, 1, 3, 5, 7, 9, 11, 12]
+        );
+
+        let mut hall = Hall::<11>::default();
         assert!(hall.can_move_between(0, 1));
         assert!(hall.can_move_between(1, 0));
 
@@ -630,6 +1141,377 @@ mod tests {
         assert_eq!(cost, 12521)
     }
 
+    #[test]
+    fn solve() {
+        let input = test_input(
+            "
+            #############
+            #...........#
+            ###B#C#B#D###
+            ###A#D#C#A#
+            ###########
+            ",
+        );
+        let burrow = SmallBurrow::try_from(&input).expect("could not parse input");
+        let (cost, moves) = burrow.solve().expect("could not solve");
+        assert_eq!(cost, 12521);
+        assert_eq!(moves.iter().map(|m| m.energy).sum::<usize>(), cost);
+
+        // replaying the moves should organize the burrow
+        let mut replay = burrow;
+        for mv in moves.iter() {
+            replay.apply(mv).expect("invalid move");
+        }
+        assert!(replay.complete());
+
+        // and a move that doesn't fit the burrow is rejected
+        let mut bad = burrow;
+        let mv = Move {
+            kind: AmphipodType::AMBER,
+            from: Location::Room { room: 0, slot: 0 },
+            to: Location::Hall(3),
+            energy: 2,
+        };
+        assert!(bad.apply(&mv).is_err());
+        assert_eq!(bad, burrow);
+    }
+
+    #[test]
+    fn ida_star() {
+        let input = test_input(
+            "
+            #############
+            #...........#
+            ###B#C#B#D###
+            ###A#D#C#A#
+            ###########
+            ",
+        );
+        let burrow = SmallBurrow::try_from(&input).expect("could not parse input");
+        assert_eq!(burrow.minimize_by(Strategy::IdaStar), Some(12521));
+
+        let (cost, moves) = burrow.solve_by(Strategy::IdaStar).expect("could not solve");
+        assert_eq!(cost, 12521);
+
+        // the heuristic never overestimates along the way
+        let mut replay = burrow;
+        let mut left = cost;
+        assert!(replay.heuristic() <= left);
+        for mv in moves.iter() {
+            replay.apply(mv).expect("invalid move");
+            left -= mv.energy;
+            assert!(replay.heuristic() <= left);
+        }
+        assert!(replay.complete());
+        assert_eq!(replay.heuristic(), 0);
+
+        let input = test_input(
+            "
+            ###############
+            #.............#
+            ###B#A#C#E#D###
+            #############
+            ",
+        );
+        let burrow = Burrow::<1, 5, 13>::parse(&input).expect("could not parse input");
+        assert_eq!(burrow.minimize_by(Strategy::IdaStar), Some(46046));
+    }
+
+    #[test]
+    #[ignore]
+    fn large_ida_star() {
+        let input = test_input(
+            "
+            #############
+            #...........#
+            ###B#C#B#D###
+            ###A#D#C#A#
+            ###########
+            ",
+        );
+        let burrow = LargeBurrow::try_from(&input).expect("could not parse input");
+        assert_eq!(burrow.minimize_by(Strategy::IdaStar), Some(44169));
+    }
+
+    #[test]
+    fn keys() {
+        let input = test_input(
+            "
+            #############
+            #...........#
+            ###B#C#B#D###
+            ###A#D#C#A#
+            ###########
+            ",
+        );
+        let burrow = LargeBurrow::try_from(&input).expect("could not parse input");
+        let (_, moves) = burrow.solve().expect("could not solve");
+
+        // every burrow along the way unpacks to itself, with its own key
+        let mut replay = burrow;
+        let mut seen = vec![burrow.key()];
+        assert_eq!(LargeBurrow::from_key(burrow.key()), burrow);
+        for mv in moves.iter() {
+            replay.apply(mv).expect("invalid move");
+            assert_eq!(LargeBurrow::from_key(replay.key()), replay);
+            assert!(!seen.contains(&replay.key()));
+            seen.push(replay.key());
+        }
+
+        // a hall of 7 waiting cells and four rooms of four slots
+        assert_eq!(replay.key() >> (4 * 16), 0);
+        assert_eq!(replay.key() & 0xffff, 0x4444);
+    }
+
+    #[test]
+    fn display() {
+        let input = test_input(
+            "
+            #############
+            #...........#
+            ###B#C#B#D###
+            ###A#D#C#A#
+            ###########
+            ",
+        );
+        let small = SmallBurrow::try_from(&input).expect("could not parse input");
+        assert_eq!(
+            small.to_string(),
+            "#############\n#           #\n###B#C#B#D###\n  #A#D#C#A#\n  #########"
+        );
+
+        let large = LargeBurrow::try_from(&input).expect("could not parse input");
+        assert_eq!(
+            large.to_string(),
+            "#############
+#           #
+###B#C#B#D###
+  #D#C#B#A#
+  #D#B#A#C#
+  #A#D#C#A#
+  #########"
+        );
+    }
+
+    #[test]
+    fn render_solution() {
+        let input = test_input(
+            "
+            #############
+            #...........#
+            ###B#C#B#D###
+            ###A#D#C#A#
+            ###########
+            ",
+        );
+        let burrow = SmallBurrow::try_from(&input).expect("could not parse input");
+        let (cost, moves) = burrow.solve().expect("could not solve");
+        let frames = burrow
+            .render_solution(&moves)
+            .expect("could not render solution");
+
+        assert_eq!(frames.len(), moves.len() + 1);
+        assert_eq!(frames[0], format!("{}\nenergy: 0", burrow));
+        assert!(frames[moves.len()].ends_with(&format!(
+            "###A#B#C#D###\n  #A#B#C#D#\n  #########\nenergy: {}",
+            cost
+        )));
+
+        // a move from an empty cell can't be rendered
+        let mut bad = moves.clone();
+        bad.insert(
+            0,
+            Move {
+                kind: AmphipodType::AMBER,
+                from: Location::Hall(0),
+                to: Location::Hall(1),
+                energy: 1,
+            },
+        );
+        assert!(burrow.render_solution(&bad).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        let parse = |diagram: &str| {
+            SmallBurrow::try_from(&test_input(diagram))
+                .expect_err("should not parse")
+                .downcast::<BurrowError>()
+                .expect("should be a burrow error")
+        };
+
+        // a letter that isn't an amphipod
+        assert_eq!(
+            parse(
+                "
+                #############
+                #...........#
+                ###B#C#X#D###
+                ###A#D#C#A#
+                ###########
+                "
+            ),
+            BurrowError::Unexpected {
+                row: 2,
+                col: 7,
+                expected: "an amphipod",
+                found: Some('X'),
+            }
+        );
+
+        // an amphipod with no room of its own
+        assert_eq!(
+            parse(
+                "
+                #############
+                #...........#
+                ###B#C#B#D###
+                ###A#E#C#A#
+                ###########
+                "
+            ),
+            BurrowError::Unexpected {
+                row: 3,
+                col: 5,
+                expected: "an amphipod",
+                found: Some('E'),
+            }
+        );
+
+        // an empty slot in a room
+        assert_eq!(
+            parse(
+                "
+                #############
+                #...........#
+                ###B#C#.#D###
+                ###A#D#C#A#
+                ###########
+                "
+            ),
+            BurrowError::Unexpected {
+                row: 2,
+                col: 7,
+                expected: "an amphipod",
+                found: Some('.'),
+            }
+        );
+
+        // someone already in the hall
+        assert_eq!(
+            parse(
+                "
+                #############
+                #..A........#
+                ###B#C#B#D###
+                ###A#D#C#A#
+                ###########
+                "
+            ),
+            BurrowError::Unexpected {
+                row: 1,
+                col: 3,
+                expected: "an empty hall",
+                found: Some('A'),
+            }
+        );
+
+        // a hole in the wall between two rooms
+        assert_eq!(
+            parse(
+                "
+                #############
+                #...........#
+                ###B#C.B#D###
+                ###A#D#C#A#
+                ###########
+                "
+            ),
+            BurrowError::Unexpected {
+                row: 2,
+                col: 6,
+                expected: "a wall",
+                found: Some('.'),
+            }
+        );
+
+        // the bottom of the rooms is missing
+        assert_eq!(
+            parse(
+                "
+                #############
+                #...........#
+                ###B#C#B#D###
+                "
+            ),
+            BurrowError::Unexpected {
+                row: 3,
+                col: 2,
+                expected: "a wall",
+                found: None,
+            }
+        );
+
+        // too many Bs and not enough As
+        assert_eq!(
+            parse(
+                "
+                #############
+                #...........#
+                ###B#C#B#D###
+                ###B#D#C#A#
+                ###########
+                "
+            ),
+            BurrowError::Count {
+                kind: AmphipodType::AMBER,
+                expected: 2,
+                found: 1,
+            }
+        );
+
+        assert_eq!(
+            BurrowError::Count {
+                kind: AmphipodType::AMBER,
+                expected: 2,
+                found: 1,
+            }
+            .to_string(),
+            "expected 2 amphipods of kind A, found 1"
+        );
+        assert_eq!(
+            BurrowError::Unexpected {
+                row: 3,
+                col: 2,
+                expected: "a wall",
+                found: None,
+            }
+            .to_string(),
+            "expected a wall at row 3, column 2, found the end of the diagram"
+        );
+    }
+
+    #[test]
+    fn custom_burrow() {
+        // the A steps out of the way for the B to go straight across, then goes
+        // home, for 2 + 40 + 4, and the same again for the D and E
+        let input = test_input(
+            "
+            ###############
+            #.............#
+            ###B#A#C#E#D###
+            #############
+            ",
+        );
+        let burrow = Burrow::<1, 5, 13>::parse(&input).expect("could not parse input");
+        let (cost, moves) = burrow.solve().expect("could not solve");
+        assert_eq!(cost, 46046);
+        assert_eq!(moves.len(), 6);
+        assert!(moves
+            .iter()
+            .any(|m| m.kind == AmphipodType::try_from('E').unwrap()));
+    }
+
     #[test]
     #[ignore]
     fn large_example() {
@@ -1,6 +1,6 @@
 use anyhow::{anyhow, bail, Result};
 use rustc_hash::FxHashSet;
-use std::{convert::TryFrom, fmt, str::FromStr};
+use std::{convert::TryFrom, fmt, io::Write, iter::FromIterator, str::FromStr};
 
 use aoc_helpers::{generic::Location, Solver};
 
@@ -35,60 +35,535 @@ impl FromStr for Instruction {
     }
 }
 
+/// The width of a letter in the folded code, not including the column of
+/// space between letters
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+
+/// The letters the folded code is known to be made of. Y is the only one
+/// wider than `GLYPH_WIDTH`, and reaches into the space after it.
+const GLYPHS: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// Pack a glyph into a bitmask, with bit `row * (GLYPH_WIDTH + 1) + col` set
+/// for each dot, so the space after the letter is included
+fn glyph_mask(rows: &[&str; GLYPH_HEIGHT]) -> u32 {
+    rows.iter()
+        .enumerate()
+        .flat_map(|(row, r)| {
+            r.chars()
+                .enumerate()
+                .filter(|(_, ch)| *ch == '#')
+                .map(move |(col, _)| row * (GLYPH_WIDTH + 1) + col)
+        })
+        .fold(0, |acc, idx| acc | 1 << idx)
+}
+
+impl Instruction {
+    /// The position of the fold line along its axis
+    fn line(&self) -> usize {
+        match self {
+            Instruction::X(m) | Instruction::Y(m) => *m,
+        }
+    }
+
+    /// The coordinate of `loc` along the axis this instruction folds
+    fn coord(&self, loc: &Location) -> usize {
+        // see Reflect for why x is row and y is col
+        match self {
+            Instruction::X(_) => loc.row,
+            Instruction::Y(_) => loc.col,
+        }
+    }
+
+    /// How far the furthest of `coords` would fold past the edge of the page
+    fn overhang<I: IntoIterator<Item = usize>>(&self, coords: I) -> usize {
+        let line = self.line();
+        coords
+            .into_iter()
+            .map(|coord| coord.saturating_sub(2 * line))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Where `coord` along this instruction's axis ends up after the fold, if
+    /// every dot is moved `shift` further along to fit
+    fn fold_coord(&self, coord: usize, shift: usize) -> usize {
+        let line = self.line();
+        if coord > line {
+            2 * line + shift - coord
+        } else {
+            coord + shift
+        }
+    }
+}
+
+/// How to handle folds that are not along the middle of the dots
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FoldPolicy {
+    /// Drop dots on the fold line and any that would fold past the edge of the
+    /// page
+    Clip,
+    /// Fail if any dot is on the fold line or would fold past the edge of the
+    /// page
+    Strict,
+    /// Keep dots on the fold line, and shift the page so that dots that fold
+    /// past the edge still fit
+    Overhang,
+}
+
+impl Default for FoldPolicy {
+    fn default() -> Self {
+        Self::Overhang
+    }
+}
+
+/// A fold that broke the rules of `FoldPolicy::Strict`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FoldError {
+    DotOnFoldLine {
+        dot: Location,
+        instruction: Instruction,
+    },
+    Overhang {
+        dot: Location,
+        instruction: Instruction,
+    },
+}
+
+impl fmt::Display for FoldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DotOnFoldLine { dot, instruction } => write!(
+                f,
+                "dot at {},{} is on the fold line of {:?}",
+                dot.row, dot.col, instruction
+            ),
+            Self::Overhang { dot, instruction } => write!(
+                f,
+                "dot at {},{} would fold past the edge of the page with {:?}",
+                dot.row, dot.col, instruction
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FoldError {}
+
 pub trait Reflect {
-    fn reflect(&self, instruction: &Instruction) -> Self;
+    /// Reflect across the fold line of `instruction`, then move `shift` along
+    /// its axis, which is how `Page::fold` makes room for dots that fold past
+    /// the edge of the page
+    fn reflect(&self, instruction: &Instruction, shift: usize) -> Self;
 }
 
 impl Reflect for Location {
-    fn reflect(&self, instruction: &Instruction) -> Self {
+    fn reflect(&self, instruction: &Instruction, shift: usize) -> Self {
         // So, Location, as implemented, is row, col. But, this problem is
         // specifying x, y. Rather than flip them, just treat row as x and
         // col as y, which is confusing, but I'm lazy.
+        let folded = instruction.fold_coord(instruction.coord(self), shift);
         match instruction {
-            Instruction::X(m) if self.row > *m => Location::new(2 * m - self.row, self.col),
-            Instruction::Y(m) if self.col > *m => Location::new(self.row, 2 * m - self.col),
-            _ => *self,
+            Instruction::X(_) => Location::new(folded, self.col),
+            Instruction::Y(_) => Location::new(self.row, folded),
         }
     }
 }
 
-#[derive(Debug, Clone, Default)]
+/// The formats a `Page` can be exported to as an image
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ImageFormat {
+    /// Binary portable bitmap
+    Pbm,
+    /// Black and white PNG
+    #[cfg(feature = "image")]
+    Png,
+}
+
+/// Read the 64 bits of a multi-word bitset starting at bit `start`, where
+/// bits outside of the set are 0
+fn word_at(words: &[u64], start: isize) -> u64 {
+    let get = |idx: isize| {
+        if idx < 0 {
+            0
+        } else {
+            words.get(idx as usize).copied().unwrap_or(0)
+        }
+    };
+
+    let idx = start.div_euclid(64);
+    let offset = start.rem_euclid(64) as u32;
+
+    if offset == 0 {
+        get(idx)
+    } else {
+        (get(idx) >> offset) | (get(idx + 1) << (64 - offset))
+    }
+}
+
+/// The dots on a sheet of transparent paper.
+///
+/// Each row (y) is a bitset, where a dot at x sets bit `x % 64` of word
+/// `x / 64`. Rows never have trailing empty words, and there are never any
+/// trailing empty rows, so equal pages always have equal representations.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct Page {
-    dots: FxHashSet<Location>,
+    rows: Vec<Vec<u64>>,
 }
 
 impl Page {
+    /// Fold the page, allowing folds that are not along the middle of the
+    /// dots as described by `FoldPolicy::Overhang`
     pub fn fold(&self, instruction: &Instruction) -> Self {
-        self.dots
-            .iter()
-            .map(|d| d.reflect(instruction))
-            .collect::<FxHashSet<Location>>()
-            .into()
+        let line = instruction.line();
+        let (max_x, max_y) = self.extent();
+
+        // how far the furthest dot would fold past the edge of the page
+        let shift = match instruction {
+            Instruction::X(_) => max_x.saturating_sub(2 * line),
+            Instruction::Y(_) => max_y.saturating_sub(2 * line),
+        };
+
+        if shift > 0 {
+            return self.fold_overhang(instruction, shift);
+        }
+
+        let mut folded = match instruction {
+            Instruction::X(m) => Self {
+                rows: self
+                    .rows
+                    .iter()
+                    .map(|row| Self::fold_row(row, *m))
+                    .collect(),
+            },
+            Instruction::Y(m) => {
+                let mut rows = self.rows.clone();
+                for y in (m + 1)..rows.len() {
+                    let target = 2 * m - y;
+                    let row = std::mem::take(&mut rows[y]);
+                    if rows[target].len() < row.len() {
+                        rows[target].resize(row.len(), 0);
+                    }
+
+                    for (dest, word) in rows[target].iter_mut().zip(row) {
+                        *dest |= word;
+                    }
+                }
+                rows.truncate(m + 1);
+
+                Self { rows }
+            }
+        };
+
+        folded.trim();
+        folded
+    }
+
+    /// Reflect the bits of `row` beyond `line` back across it
+    fn fold_row(row: &[u64], line: usize) -> Vec<u64> {
+        if row.is_empty() {
+            return Vec::new();
+        }
+
+        // reversing the row moves bit x to 64 * len - 1 - x, so reading it
+        // from `delta` moves bit x to 2 * line - x
+        let reversed: Vec<u64> = row.iter().rev().map(|w| w.reverse_bits()).collect();
+        let delta = (64 * row.len() - 1) as isize - (2 * line) as isize;
+
+        let len = line / 64 + 1;
+        let mut folded: Vec<u64> = (0..len)
+            .map(|idx| {
+                row.get(idx).copied().unwrap_or(0) | word_at(&reversed, 64 * idx as isize + delta)
+            })
+            .collect();
+
+        // anything past the line is the reflection of what was before it
+        let keep = line % 64;
+        if keep < 63 {
+            folded[len - 1] &= (1 << (keep + 1)) - 1;
+        }
+
+        folded
+    }
+
+    /// Fold the page where some dots fold `shift` past the edge, shifting
+    /// every dot so they all fit
+    fn fold_overhang(&self, instruction: &Instruction, shift: usize) -> Self {
+        self.dots().map(|d| d.reflect(instruction, shift)).collect()
+    }
+
+    /// Fold the page, handling dots on the fold line or dots that would fold
+    /// past the edge of the page according to `policy`
+    pub fn fold_with(
+        &self,
+        instruction: &Instruction,
+        policy: FoldPolicy,
+    ) -> std::result::Result<Self, FoldError> {
+        let line = instruction.line();
+
+        match policy {
+            FoldPolicy::Clip => Ok(self
+                .dots()
+                .filter(|d| {
+                    let coord = instruction.coord(d);
+                    coord != line && coord <= 2 * line
+                })
+                .collect::<Page>()
+                .fold(instruction)),
+            FoldPolicy::Strict => {
+                // check the dots in order so the error is always the same
+                let mut dots: Vec<Location> = self.dots().collect();
+                dots.sort_unstable_by_key(|d| (d.row, d.col));
+
+                for d in dots {
+                    let coord = instruction.coord(&d);
+                    if coord == line {
+                        return Err(FoldError::DotOnFoldLine {
+                            dot: d,
+                            instruction: *instruction,
+                        });
+                    }
+
+                    if coord > 2 * line {
+                        return Err(FoldError::Overhang {
+                            dot: d,
+                            instruction: *instruction,
+                        });
+                    }
+                }
+
+                Ok(self.fold(instruction))
+            }
+            FoldPolicy::Overhang => Ok(self.fold(instruction)),
+        }
+    }
+
+    /// Add a dot to the page, returning `false` if it was already there
+    pub fn add_dot(&mut self, dot: Location) -> bool {
+        // remember, row is x and col is y
+        if self.rows.len() <= dot.col {
+            self.rows.resize(dot.col + 1, Vec::new());
+        }
+
+        let row = &mut self.rows[dot.col];
+        if row.len() <= dot.row / 64 {
+            row.resize(dot.row / 64 + 1, 0);
+        }
+
+        let bit = 1 << (dot.row % 64);
+        let added = row[dot.row / 64] & bit == 0;
+        row[dot.row / 64] |= bit;
+
+        added
+    }
+
+    /// Remove a dot from the page, returning `false` if it was not there
+    pub fn remove_dot(&mut self, dot: &Location) -> bool {
+        if !self.contains(dot) {
+            return false;
+        }
+
+        self.rows[dot.col][dot.row / 64] &= !(1 << (dot.row % 64));
+        self.trim();
+
+        true
+    }
+
+    pub fn contains(&self, dot: &Location) -> bool {
+        self.rows
+            .get(dot.col)
+            .and_then(|row| row.get(dot.row / 64))
+            .map(|word| word & (1 << (dot.row % 64)) != 0)
+            .unwrap_or(false)
+    }
+
+    /// The dots on the page, in order of y then x
+    pub fn dots(&self) -> impl Iterator<Item = Location> + '_ {
+        self.rows.iter().enumerate().flat_map(|(y, row)| {
+            row.iter().enumerate().flat_map(move |(idx, word)| {
+                // clear the lowest set bit until there are none left
+                std::iter::successors(Some(*word), |w| Some(w & w.wrapping_sub(1)))
+                    .take_while(|w| *w != 0)
+                    .map(move |w| Location::new(idx * 64 + w.trailing_zeros() as usize, y))
+            })
+        })
+    }
+
+    /// Restore the invariant that there are no trailing empty words or rows
+    fn trim(&mut self) {
+        for row in self.rows.iter_mut() {
+            while row.last() == Some(&0) {
+                row.pop();
+            }
+        }
+
+        while self.rows.last().map(|row| row.is_empty()).unwrap_or(false) {
+            self.rows.pop();
+        }
+    }
+
+    /// Undo a fold by mirroring every dot back across the fold line. This is
+    /// only one of the pages that could have been folded to make this one,
+    /// where every dot ends up on the far side of the line.
+    ///
+    /// Fails if any dot lies beyond the fold line, as a fold would never have
+    /// left one there.
+    pub fn unfold(&self, instruction: &Instruction) -> Result<Self> {
+        self.dots()
+            .map(|d| match instruction {
+                Instruction::X(m) if d.row <= *m => Ok(Location::new(2 * m - d.row, d.col)),
+                Instruction::Y(m) if d.col <= *m => Ok(Location::new(d.row, 2 * m - d.col)),
+                _ => Err(anyhow!(
+                    "dot at {},{} is beyond the fold line of {:?}",
+                    d.row,
+                    d.col,
+                    instruction
+                )),
+            })
+            .collect::<Result<Page>>()
     }
 
     pub fn count_visible(&self) -> usize {
-        self.dots.len()
+        self.rows
+            .iter()
+            .flatten()
+            .map(|word| word.count_ones() as usize)
+            .sum()
     }
-}
 
-impl fmt::Display for Page {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut max_x = 0;
-        let mut max_y = 0;
+    /// Write the page to `writer` as an image in the given `format`, with each
+    /// dot drawn as a `scale` by `scale` block of black pixels on white.
+    pub fn to_image<W: Write>(
+        &self,
+        format: ImageFormat,
+        scale: usize,
+        writer: &mut W,
+    ) -> Result<()> {
+        if scale == 0 {
+            bail!("image scale must be at least 1");
+        }
 
-        for d in &self.dots {
-            if d.row > max_x {
-                max_x = d.row;
+        match format {
+            ImageFormat::Pbm => {
+                // in a bitmap, 1 is black
+                let (width, height, data) = self.packed_rows(scale, true);
+                write!(writer, "P4\n{} {}\n", width, height)?;
+                writer.write_all(&data)?;
+            }
+            #[cfg(feature = "image")]
+            ImageFormat::Png => {
+                // in grayscale, 0 is black
+                let (width, height, data) = self.packed_rows(scale, false);
+                let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+                encoder.set_color(png::ColorType::Grayscale);
+                encoder.set_depth(png::BitDepth::One);
+                encoder.write_header()?.write_image_data(&data)?;
             }
+        }
+
+        Ok(())
+    }
 
-            if d.col > max_y {
-                max_y = d.col;
+    /// Pack the page into rows of bits, most significant bit first, where each
+    /// row starts on a new byte. Dots are set to `dot` and everything else to
+    /// its opposite. Returns the width, height and packed data.
+    fn packed_rows(&self, scale: usize, dot: bool) -> (usize, usize, Vec<u8>) {
+        let (max_x, max_y) = self.extent();
+        let width = (max_x + 1) * scale;
+        let height = (max_y + 1) * scale;
+        // the page always has at least one column, so this rounds up
+        let row_bytes = (width - 1) / 8 + 1;
+
+        let background = if dot { 0 } else { 0xff };
+        let mut data = vec![background; row_bytes * height];
+
+        for d in self.dots() {
+            // remember, row is x and col is y
+            for y in (d.col * scale)..((d.col + 1) * scale) {
+                for x in (d.row * scale)..((d.row + 1) * scale) {
+                    data[y * row_bytes + x / 8] ^= 0x80 >> (x % 8);
+                }
             }
         }
 
+        (width, height, data)
+    }
+
+    /// The largest x and y coordinates of any dot
+    fn extent(&self) -> (usize, usize) {
+        let max_x = self
+            .rows
+            .iter()
+            .filter_map(|row| {
+                row.last()
+                    .map(|word| (row.len() - 1) * 64 + 63 - word.leading_zeros() as usize)
+            })
+            .max()
+            .unwrap_or(0);
+
+        (max_x, self.rows.len().saturating_sub(1))
+    }
+
+    /// Read the dots as a row of letters, each `GLYPH_WIDTH` wide and
+    /// separated by a column of space, except for Y, which fills that space.
+    pub fn text(&self) -> Result<String> {
+        let mut masks: Vec<u32> = Vec::new();
+
+        for d in self.dots() {
+            // remember, row is x and col is y
+            let letter = d.row / (GLYPH_WIDTH + 1);
+            let offset = d.row % (GLYPH_WIDTH + 1);
+
+            if d.col >= GLYPH_HEIGHT {
+                bail!("dot at {},{} is below the letters", d.row, d.col);
+            }
+
+            if masks.len() <= letter {
+                masks.resize(letter + 1, 0);
+            }
+
+            masks[letter] |= 1 << (d.col * (GLYPH_WIDTH + 1) + offset);
+        }
+
+        masks
+            .iter()
+            .enumerate()
+            .map(|(idx, mask)| {
+                GLYPHS
+                    .iter()
+                    .find(|(_, rows)| glyph_mask(rows) == *mask)
+                    .map(|(ch, _)| *ch)
+                    .ok_or_else(|| anyhow!("unrecognized letter at position {}", idx))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Page {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (max_x, max_y) = self.extent();
+
         let mut grid = vec![vec![' '; max_x + 1]; max_y + 1];
 
-        for d in &self.dots {
+        for d in self.dots() {
             grid[d.col][d.row] = '0';
         }
 
@@ -102,9 +577,20 @@ impl fmt::Display for Page {
     }
 }
 
+impl FromIterator<Location> for Page {
+    fn from_iter<T: IntoIterator<Item = Location>>(iter: T) -> Self {
+        let mut page = Self::default();
+        for d in iter {
+            page.add_dot(d);
+        }
+
+        page
+    }
+}
+
 impl From<FxHashSet<Location>> for Page {
     fn from(value: FxHashSet<Location>) -> Self {
-        Self { dots: value }
+        value.into_iter().collect()
     }
 }
 
@@ -131,6 +617,14 @@ impl Manual {
         Self { page, instructions }
     }
 
+    pub fn page(&self) -> &Page {
+        &self.page
+    }
+
+    pub fn page_mut(&mut self) -> &mut Page {
+        &mut self.page
+    }
+
     pub fn first_instruction(&self) -> Page {
         self.instructions
             .get(0)
@@ -143,6 +637,101 @@ impl Manual {
             .iter()
             .fold(self.page.clone(), |acc, inst| acc.fold(inst))
     }
+
+    /// Fold the page completely, like `folded`, but without building the page
+    /// after each fold.
+    ///
+    /// A fold along x never changes a y coordinate and vice versa, so this
+    /// works out where each x and y coordinate ends up after all of the folds
+    /// along its axis, then moves every dot straight to its final position.
+    pub fn folded_fast(&self) -> Page {
+        let (max_x, max_y) = self.page.extent();
+        let mut xs = vec![false; max_x + 1];
+        let mut ys = vec![false; max_y + 1];
+        for d in self.page.dots() {
+            // remember, row is x and col is y
+            xs[d.row] = true;
+            ys[d.col] = true;
+        }
+
+        let x_lines = self.instructions.iter().filter_map(|inst| match inst {
+            Instruction::X(m) => Some(*m),
+            _ => None,
+        });
+        let y_lines = self.instructions.iter().filter_map(|inst| match inst {
+            Instruction::Y(m) => Some(*m),
+            _ => None,
+        });
+
+        let x_map = Self::axis_map(&xs, x_lines);
+        let y_map = Self::axis_map(&ys, y_lines);
+
+        self.page
+            .dots()
+            .map(|d| Location::new(x_map[d.row], y_map[d.col]))
+            .collect()
+    }
+
+    /// Work out where each coordinate along an axis ends up after folding
+    /// along each of `lines` in turn, given which coordinates have dots.
+    fn axis_map<I: Iterator<Item = usize>>(present: &[bool], lines: I) -> Vec<usize> {
+        let mut map: Vec<usize> = (0..present.len()).collect();
+
+        for line in lines {
+            // this needs to shift the same way Page::fold does if any dot
+            // would fold past the edge
+            let shift = map
+                .iter()
+                .zip(present)
+                .filter(|(_, present)| **present)
+                .map(|(coord, _)| coord.saturating_sub(2 * line))
+                .max()
+                .unwrap_or(0);
+
+            // coordinates without dots are never looked up, and may not fit
+            // after the shift
+            for (coord, _) in map.iter_mut().zip(present).filter(|(_, present)| **present) {
+                *coord = if *coord > line {
+                    2 * line + shift - *coord
+                } else {
+                    *coord + shift
+                };
+            }
+        }
+
+        map
+    }
+
+    /// Fold the page completely, handling folds that are not along the middle
+    /// of the dots according to `policy`
+    pub fn folded_with(&self, policy: FoldPolicy) -> std::result::Result<Page, FoldError> {
+        self.instructions
+            .iter()
+            .try_fold(self.page.clone(), |acc, inst| acc.fold_with(inst, policy))
+    }
+
+    /// Count the visible dots after the first `n` instructions, or all of them
+    /// if there are fewer than `n`. Each dot is reflected through the folds in
+    /// turn, so none of the intermediate pages are built.
+    pub fn count_visible_after(&self, n: usize) -> usize {
+        let mut dots: Vec<Location> = self.page.dots().collect();
+
+        for inst in self.instructions.iter().take(n) {
+            // this needs to shift the same way Page::fold does if any dot
+            // would fold past the edge
+            let shift = inst.overhang(dots.iter().map(|d| inst.coord(d)));
+            for d in dots.iter_mut() {
+                *d = d.reflect(inst, shift);
+            }
+        }
+
+        dots.into_iter().collect::<FxHashSet<Location>>().len()
+    }
+
+    /// Fold the page completely and read the resulting code
+    pub fn folded_text(&self) -> Result<String> {
+        self.folded().text()
+    }
 }
 
 impl TryFrom<Vec<String>> for Manual {
@@ -180,7 +769,7 @@ impl Solver for Manual {
     }
 
     fn part_two(&mut self) -> Self::P2 {
-        self.folded().to_string()
+        self.folded_text().expect("could not read folded code")
     }
 }
 
@@ -190,6 +779,8 @@ mod tests {
     mod manual {
         use aoc_helpers::util::test_input;
 
+        use crate::rng::xorshift;
+
         use super::super::*;
 
         #[test]
@@ -224,6 +815,52 @@ mod tests {
             assert_eq!(p.count_visible(), 17);
         }
 
+        #[test]
+        fn count_visible_after() {
+            let input = test_input(
+                "
+                6,10
+                0,14
+                9,10
+                0,3
+                10,4
+                4,11
+                6,0
+                6,12
+                4,1
+                0,13
+                10,12
+                3,4
+                3,0
+                8,4
+                1,10
+                2,14
+                8,10
+                9,0
+
+                fold along y=7
+                fold along x=5
+                ",
+            );
+            let manual = Manual::try_from(input).expect("could not parse input");
+            assert_eq!(manual.count_visible_after(0), 18);
+            assert_eq!(manual.count_visible_after(1), 17);
+            assert_eq!(manual.count_visible_after(2), 16);
+            assert_eq!(manual.count_visible_after(10), 16);
+            assert_eq!(
+                manual.count_visible_after(2),
+                manual.folded().count_visible()
+            );
+
+            // a fold that overhangs the edge of the page
+            let manual = Manual::new(
+                Page::from_iter(vec![Location::new(0, 0), Location::new(10, 0)]),
+                vec![Instruction::X(3)],
+            );
+            assert_eq!(manual.folded().count_visible(), 2);
+            assert_eq!(manual.count_visible_after(1), 2);
+        }
+
         #[test]
         fn folded() {
             let input = test_input(
@@ -264,6 +901,405 @@ mod tests {
 
             println!("{}", p.to_string());
             assert_eq!(p.to_string(), expected);
+
+            // the example isn't made of letters
+            assert!(manual.folded_text().is_err());
+        }
+
+        #[test]
+        fn fold_policies() {
+            let dots = vec![
+                Location::new(0, 0),
+                Location::new(2, 1),
+                Location::new(3, 2),
+                Location::new(7, 3),
+            ]
+            .into_iter()
+            .collect::<FxHashSet<Location>>();
+            let page = Page::from(dots);
+            let inst = Instruction::X(2);
+
+            let clipped = page
+                .fold_with(&inst, FoldPolicy::Clip)
+                .expect("clipping should not fail");
+            let expected = vec![Location::new(0, 0), Location::new(1, 2)]
+                .into_iter()
+                .collect::<FxHashSet<Location>>();
+            assert_eq!(clipped, Page::from(expected));
+
+            // the furthest dot would end up at -3, so everything shifts by 3
+            let overhung = page
+                .fold_with(&inst, FoldPolicy::Overhang)
+                .expect("overhanging should not fail");
+            let expected = vec![
+                Location::new(3, 0),
+                Location::new(5, 1),
+                Location::new(4, 2),
+                Location::new(0, 3),
+            ]
+            .into_iter()
+            .collect::<FxHashSet<Location>>();
+            assert_eq!(overhung, Page::from(expected));
+            assert_eq!(page.fold(&inst), overhung);
+
+            assert_eq!(
+                page.fold_with(&inst, FoldPolicy::Strict),
+                Err(FoldError::DotOnFoldLine {
+                    dot: Location::new(2, 1),
+                    instruction: inst
+                })
+            );
+
+            let page = Page::from(
+                vec![Location::new(0, 0), Location::new(7, 0)]
+                    .into_iter()
+                    .collect::<FxHashSet<Location>>(),
+            );
+            assert_eq!(
+                page.fold_with(&inst, FoldPolicy::Strict),
+                Err(FoldError::Overhang {
+                    dot: Location::new(7, 0),
+                    instruction: inst
+                })
+            );
+
+            let input = test_input(
+                "
+                6,10
+                0,14
+                9,10
+                0,3
+                10,4
+                4,11
+                6,0
+                6,12
+                4,1
+                0,13
+                10,12
+                3,4
+                3,0
+                8,4
+                1,10
+                2,14
+                8,10
+                9,0
+
+                fold along y=7
+                fold along x=5
+                ",
+            );
+            let manual = Manual::try_from(input).expect("could not parse input");
+            for policy in [FoldPolicy::Clip, FoldPolicy::Strict, FoldPolicy::Overhang] {
+                let folded = manual.folded_with(policy).expect("could not fold");
+                assert_eq!(folded, manual.folded());
+            }
+        }
+
+        #[test]
+        fn editing_dots() {
+            let mut page = Page::default();
+            assert!(page.add_dot(Location::new(70, 2)));
+            assert!(page.add_dot(Location::new(1, 0)));
+            assert!(!page.add_dot(Location::new(1, 0)));
+            assert_eq!(page.count_visible(), 2);
+
+            assert!(page.contains(&Location::new(70, 2)));
+            assert!(!page.contains(&Location::new(6, 2)));
+            assert!(!page.contains(&Location::new(1, 100)));
+            assert_eq!(
+                page.dots().collect::<Vec<_>>(),
+                vec![Location::new(1, 0), Location::new(70, 2)]
+            );
+
+            // removing the furthest dot shrinks the page
+            assert!(page.remove_dot(&Location::new(70, 2)));
+            assert!(!page.remove_dot(&Location::new(70, 2)));
+            assert_eq!(page, Page::from_iter(vec![Location::new(1, 0)]));
+            assert_eq!(page.to_string(), "\n 0");
+
+            let mut manual = Manual::new(page, vec![Instruction::X(1)]);
+            manual.page_mut().add_dot(Location::new(2, 0));
+            assert_eq!(manual.page().count_visible(), 2);
+            assert_eq!(
+                manual.folded().dots().collect::<Vec<_>>(),
+                vec![Location::new(0, 0), Location::new(1, 0)]
+            );
+        }
+
+        #[test]
+        fn folded_fast() {
+            let input = test_input(
+                "
+                6,10
+                0,14
+                9,10
+                0,3
+                10,4
+                4,11
+                6,0
+                6,12
+                4,1
+                0,13
+                10,12
+                3,4
+                3,0
+                8,4
+                1,10
+                2,14
+                8,10
+                9,0
+
+                fold along y=7
+                fold along x=5
+                ",
+            );
+            let manual = Manual::try_from(input).expect("could not parse input");
+            assert_eq!(manual.folded_fast(), manual.folded());
+
+            let mut next = xorshift(0x6a09_e667_f3bc_c908);
+
+            // includes dots on fold lines and folds that overhang
+            for _ in 0..100 {
+                let page = (0..next(100))
+                    .map(|_| Location::new(next(100), next(100)))
+                    .collect::<Page>();
+                let instructions = (0..next(6))
+                    .map(|_| {
+                        if next(2) == 0 {
+                            Instruction::X(next(50))
+                        } else {
+                            Instruction::Y(next(50))
+                        }
+                    })
+                    .collect();
+
+                let manual = Manual::new(page, instructions);
+                assert_eq!(manual.folded_fast(), manual.folded());
+            }
+        }
+
+        #[test]
+        fn unfold() {
+            let input = test_input(
+                "
+                6,10
+                0,14
+                9,10
+                0,3
+                10,4
+                4,11
+                6,0
+                6,12
+                4,1
+                0,13
+                10,12
+                3,4
+                3,0
+                8,4
+                1,10
+                2,14
+                8,10
+                9,0
+
+                fold along y=7
+                fold along x=5
+                ",
+            );
+            let manual = Manual::try_from(input).expect("could not parse input");
+            let inst = Instruction::Y(7);
+            let folded = manual.first_instruction();
+            let unfolded = folded.unfold(&inst).expect("could not unfold");
+            assert_eq!(unfolded.count_visible(), 17);
+            assert!(unfolded.dots().all(|d| d.col >= 7));
+            assert_eq!(unfolded.fold(&inst), folded);
+
+            // the original page is beyond the fold line
+            assert!(manual.page.unfold(&inst).is_err());
+        }
+
+        #[test]
+        fn fold_undoes_unfold() {
+            let mut next = xorshift(0x2545_f491_4f6c_dd1d);
+
+            for _ in 0..200 {
+                let m = next(20) + 1;
+                let other = next(40) + 1;
+                let inst = if next(2) == 0 {
+                    Instruction::X(m)
+                } else {
+                    Instruction::Y(m)
+                };
+
+                // any page that fits within twice the fold line can be folded
+                let num_dots = next(50);
+                let dots = (0..num_dots)
+                    .map(|_| match inst {
+                        Instruction::X(_) => Location::new(next(2 * m + 1), next(other)),
+                        Instruction::Y(_) => Location::new(next(other), next(2 * m + 1)),
+                    })
+                    .collect::<FxHashSet<Location>>();
+
+                let folded = Page::from(dots).fold(&inst);
+                let unfolded = folded.unfold(&inst).expect("could not unfold");
+                assert_eq!(unfolded.count_visible(), folded.count_visible());
+                assert_eq!(unfolded.fold(&inst), folded);
+            }
+        }
+
+        #[test]
+        fn bitset_folds_match_reflection() {
+            let mut next = xorshift(0x9e37_79b9_7f4a_7c15);
+
+            // wide enough that rows span several words
+            for _ in 0..50 {
+                let width = next(300) + 1;
+                let height = next(300) + 1;
+                let dots = (0..next(500))
+                    .map(|_| Location::new(next(width), next(height)))
+                    .collect::<FxHashSet<Location>>();
+                let page = Page::from(dots.clone());
+
+                let inst = if next(2) == 0 {
+                    Instruction::X(width / 2)
+                } else {
+                    Instruction::Y(height / 2)
+                };
+
+                let expected = dots
+                    .iter()
+                    .map(|d| d.reflect(&inst, 0))
+                    .collect::<FxHashSet<Location>>();
+                let folded = page.fold(&inst);
+                assert_eq!(folded.dots().collect::<FxHashSet<Location>>(), expected);
+                assert_eq!(folded.count_visible(), expected.len());
+            }
+        }
+
+        #[test]
+        fn to_image() {
+            let dots = vec![Location::new(0, 0), Location::new(9, 1)]
+                .into_iter()
+                .collect::<FxHashSet<Location>>();
+            let page = Page::from(dots);
+
+            let mut out = Vec::new();
+            page.to_image(ImageFormat::Pbm, 1, &mut out)
+                .expect("could not write image");
+            let mut expected = b"P4\n10 2\n".to_vec();
+            expected.extend_from_slice(&[0b1000_0000, 0, 0, 0b0100_0000]);
+            assert_eq!(out, expected);
+
+            let mut out = Vec::new();
+            page.to_image(ImageFormat::Pbm, 2, &mut out)
+                .expect("could not write image");
+            let mut expected = b"P4\n20 4\n".to_vec();
+            let rows: [[u8; 3]; 4] = [
+                [0b1100_0000, 0, 0],
+                [0b1100_0000, 0, 0],
+                [0, 0, 0b0011_0000],
+                [0, 0, 0b0011_0000],
+            ];
+            expected.extend(rows.iter().flatten());
+            assert_eq!(out, expected);
+
+            assert!(page.to_image(ImageFormat::Pbm, 0, &mut out).is_err());
+        }
+
+        #[cfg(feature = "image")]
+        #[test]
+        fn to_png() {
+            let input = test_input(
+                "
+                6,10
+                0,14
+                9,10
+                0,3
+                10,4
+                4,11
+                6,0
+                6,12
+                4,1
+                0,13
+                10,12
+                3,4
+                3,0
+                8,4
+                1,10
+                2,14
+                8,10
+                9,0
+
+                fold along y=7
+                fold along x=5
+                ",
+            );
+            let manual = Manual::try_from(input).expect("could not parse input");
+
+            let mut out = Vec::new();
+            manual
+                .folded()
+                .to_image(ImageFormat::Png, 4, &mut out)
+                .expect("could not write image");
+
+            let decoder = png::Decoder::new(out.as_slice());
+            let (info, _) = decoder.read_info().expect("could not read image");
+            assert_eq!((info.width, info.height), (20, 20));
+        }
+
+        #[test]
+        fn text() {
+            let input = test_input(
+                "
+                #..#.####..##..#..#
+                #.#.....#.#..#.#..#
+                ##.....#..#..#.#..#
+                #.#...#...####.#..#
+                #.#..#....#..#.#..#
+                #..#.####.#..#..##.
+                ",
+            );
+
+            let dots = input
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .filter(|(_, ch)| *ch == '#')
+                        .map(move |(x, _)| Location::new(x, y))
+                })
+                .collect::<FxHashSet<Location>>();
+            let page = Page::from(dots);
+            assert_eq!(page.text().expect("could not read text"), "KZAU");
+
+            let page = page
+                .dots()
+                .chain(std::iter::once(Location::new(4, 0)))
+                .collect::<Page>();
+            assert!(page.text().is_err());
+
+            // Y runs into the space before the next letter
+            let input = test_input(
+                "
+                #..#.#...#.##.
+                #..#.#...##..#
+                ####..#.#.#..#
+                #..#...#..####
+                #..#...#..#..#
+                #..#...#..#..#
+                ",
+            );
+            let page = input
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .filter(|(_, ch)| *ch == '#')
+                        .map(move |(x, _)| Location::new(x, y))
+                })
+                .collect::<Page>();
+            assert_eq!(page.text().expect("could not read text"), "HYA");
         }
     }
 }
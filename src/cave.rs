@@ -1,7 +1,13 @@
-use std::convert::TryFrom;
+use std::{convert::TryFrom, fmt, io::Write};
 
-use anyhow::{anyhow, Result};
-use aoc_helpers::Solver;
+use anyhow::{anyhow, bail, Result};
+use aoc_helpers::{
+    generic::{
+        pathing::{dijkstra_cost, DEdge, DefaultLocationCache},
+        Location,
+    },
+    Solver,
+};
 use rayon::prelude::*;
 use rustc_hash::{FxHashMap, FxHashSet};
 
@@ -31,6 +37,106 @@ impl From<&str> for CaveType {
     }
 }
 
+/// Problems with the shape of a cave system
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CaveError {
+    /// Two big caves are linked, so paths could bounce between them forever
+    AdjacentBigCaves(String, String),
+}
+
+impl fmt::Display for CaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AdjacentBigCaves(a, b) => write!(
+                f,
+                "big caves {} and {} are linked, so there are infinitely many paths",
+                a, b
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CaveError {}
+
+/// Limits on how many times caves may be visited by a single path
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct VisitPolicy {
+    /// The number of times a small cave may be visited, if it is one of the
+    /// caves allowed to be revisited
+    pub max_small_visits: usize,
+    /// The number of different small caves that may be visited more than once
+    pub revisitable_small: usize,
+    /// The number of times any big cave may be visited, if limited at all
+    pub max_big_visits: Option<usize>,
+}
+
+impl VisitPolicy {
+    /// Small caves may only be visited once (part one)
+    pub fn single_visit() -> Self {
+        Self {
+            max_small_visits: 1,
+            revisitable_small: 0,
+            max_big_visits: None,
+        }
+    }
+
+    /// A single small cave may be visited twice (part two)
+    pub fn single_double_visit() -> Self {
+        Self {
+            max_small_visits: 2,
+            revisitable_small: 1,
+            max_big_visits: None,
+        }
+    }
+}
+
+impl VisitPolicy {
+    /// Check whether a cave of `kind` that has already been visited `seen`
+    /// times may be visited again, given that `revisits` small caves have
+    /// been visited more than once. If so, returns the new number of revisits.
+    fn admit(&self, kind: &CaveType, seen: usize, revisits: usize) -> Option<usize> {
+        match kind {
+            CaveType::Start => None,
+            CaveType::End => Some(revisits),
+            CaveType::Big => match self.max_big_visits {
+                Some(max) if seen >= max => None,
+                _ => Some(revisits),
+            },
+            CaveType::Small => {
+                if seen >= self.max_small_visits {
+                    return None;
+                }
+
+                // the first revisit of a small cave uses up one of the
+                // allowed revisits, any further visits do not
+                let revisits = if seen == 1 { revisits + 1 } else { revisits };
+                if revisits > self.revisitable_small {
+                    None
+                } else {
+                    Some(revisits)
+                }
+            }
+        }
+    }
+}
+
+impl Default for VisitPolicy {
+    fn default() -> Self {
+        Self::single_visit()
+    }
+}
+
+impl From<bool> for VisitPolicy {
+    /// Convert from the `allow_multi_visit` flag to the equivalent preset
+    fn from(allow_multi_visit: bool) -> Self {
+        if allow_multi_visit {
+            Self::single_double_visit()
+        } else {
+            Self::single_visit()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Cave {
     kind: CaveType,
@@ -57,6 +163,9 @@ impl From<String> for Cave {
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
 pub struct CaveSystem {
     caves: Vec<Cave>,
+    /// explicit link weights keyed by (lower index, higher index), links
+    /// without an entry have a weight of 1
+    weights: FxHashMap<(usize, usize), usize>,
 }
 
 impl CaveSystem {
@@ -74,10 +183,45 @@ impl CaveSystem {
         Ok(())
     }
 
+    pub fn link_weighted(&mut self, a: usize, b: usize, weight: usize) -> Result<()> {
+        self.link(a, b)?;
+        self.weights.insert((a.min(b), a.max(b)), weight);
+
+        Ok(())
+    }
+
+    /// Check that the number of paths through the system is finite, which is
+    /// not the case if any two big caves are linked.
+    pub fn validate(&self) -> std::result::Result<(), CaveError> {
+        for (idx, cave) in self.caves.iter().enumerate() {
+            if cave.kind != CaveType::Big {
+                continue;
+            }
+
+            let mut links: Vec<usize> = cave.links.iter().copied().filter(|l| *l >= idx).collect();
+            links.sort_unstable();
+
+            if let Some(other) = links
+                .into_iter()
+                .find(|l| self.caves[*l].kind == CaveType::Big)
+            {
+                return Err(CaveError::AdjacentBigCaves(
+                    cave.id.clone(),
+                    self.caves[other].id.clone(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     /// So the problem, as written, doesn't actually need you to know what the
     /// paths are. We only really need to know *how many* there are to answer
     /// the question.
-    pub fn paths_fast(&self, allow_multi_visit: bool) -> Result<usize> {
+    ///
+    /// `policy` can be anything convertible to a `VisitPolicy`, including the
+    /// `allow_multi_visit` flag for the puzzle's two modes.
+    pub fn paths_fast<P: Into<VisitPolicy>>(&self, policy: P) -> Result<usize> {
         // find the index of the start cave
         let start = self
             .caves
@@ -107,10 +251,12 @@ impl CaveSystem {
             .ok_or_else(|| anyhow!("cave system does not have an end"))?;
 
         let mut seen = vec![0; self.caves.len()];
-        self.recur_fast(start, end, !allow_multi_visit, &mut seen)
+        self.recur_fast(start, end, &policy.into(), 0, &mut seen)
     }
 
-    pub fn paths_semi_par(&self, allow_multi_visit: bool) -> Result<usize> {
+    pub fn paths_semi_par<P: Into<VisitPolicy>>(&self, policy: P) -> Result<usize> {
+        let policy = policy.into();
+
         // find the index of the start cave
         let start = self
             .caves
@@ -144,7 +290,7 @@ impl CaveSystem {
             .map(|ns| {
                 let mut seen = vec![0; self.caves.len()];
                 seen[*ns] = 1;
-                self.recur_fast(*ns, end, !allow_multi_visit, &mut seen)
+                self.recur_fast(*ns, end, &policy, 0, &mut seen)
             })
             .collect::<Result<Vec<usize>>>()?
             .iter()
@@ -152,11 +298,15 @@ impl CaveSystem {
         Ok(count)
     }
 
+    /// Count the paths from `start` to `end`, where `seen` holds the number of
+    /// times each cave has been visited so far and `revisits` is the number of
+    /// small caves that have already been visited more than once.
     pub fn recur_fast(
         &self,
         start: usize,
         end: usize,
-        allowance_used: bool,
+        policy: &VisitPolicy,
+        revisits: usize,
         seen: &mut Vec<usize>,
     ) -> Result<usize> {
         if start == end {
@@ -169,25 +319,208 @@ impl CaveSystem {
 
         for i in cave.links.iter() {
             let i = *i;
-            // otherwise
             let next = self.lookup(i)?;
-            if next.kind == CaveType::Big || next.kind == CaveType::End {
-                count += self.recur_fast(i, end, allowance_used, seen)?;
-            } else if next.kind == CaveType::Small {
-                if seen[i] > 0 {
-                    // simulate allowing this or not
-                    if !allowance_used {
-                        count += self.recur_fast(i, end, true, seen)?;
+            if let Some(revisits) = policy.admit(&next.kind, seen[i], revisits) {
+                seen[i] += 1;
+                count += self.recur_fast(i, end, policy, revisits, seen)?;
+                seen[i] -= 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Count the paths from start to end, spawning a parallel task for every
+    /// branch of the search until `split_depth` caves from the start, after
+    /// which each task continues serially.
+    ///
+    /// Unlike `paths_semi_par`, this keeps splitting past the start cave's
+    /// neighbors, so wide systems can make use of more threads.
+    pub fn paths_par<P: Into<VisitPolicy>>(&self, policy: P, split_depth: usize) -> Result<usize> {
+        let start = self.find_index(CaveType::Start)?;
+        let end = self.find_index(CaveType::End)?;
+
+        self.recur_par(
+            start,
+            end,
+            &policy.into(),
+            0,
+            vec![0; self.caves.len()],
+            split_depth,
+        )
+    }
+
+    fn recur_par(
+        &self,
+        cur: usize,
+        end: usize,
+        policy: &VisitPolicy,
+        revisits: usize,
+        mut seen: Vec<usize>,
+        depth: usize,
+    ) -> Result<usize> {
+        if depth == 0 {
+            return self.recur_fast(cur, end, policy, revisits, &mut seen);
+        }
+
+        if cur == end {
+            return Ok(1);
+        }
+
+        self.lookup(cur)?
+            .links
+            .par_iter()
+            .map(|i| {
+                let i = *i;
+                let next = self.lookup(i)?;
+                match policy.admit(&next.kind, seen[i], revisits) {
+                    Some(revisits) => {
+                        let mut seen = seen.clone();
+                        seen[i] += 1;
+                        self.recur_par(i, end, policy, revisits, seen, depth - 1)
                     }
-                } else {
-                    seen[i] += 1;
-                    count += self.recur_fast(i, end, allowance_used, seen)?;
-                    seen[i] -= 1;
+                    None => Ok(0),
                 }
+            })
+            .sum()
+    }
+
+    /// Count the paths from start to end, memoizing on the current cave and
+    /// the number of times each small cave has been visited.
+    ///
+    /// The visit counts are packed into a `u128`, so the number of small caves
+    /// multiplied by the number of bits needed for `max_small_visits` must fit.
+    /// Limits on big caves are not supported.
+    pub fn paths_memo<P: Into<VisitPolicy>>(&self, policy: P) -> Result<usize> {
+        let start = self.find_index(CaveType::Start)?;
+        MemoCounter::new(self, policy.into())?.count(start, 0, 0)
+    }
+
+    /// Find how many paths from start to end there are of each length, where
+    /// the length is the number of links followed, so the value at index `n`
+    /// is the number of paths of length `n`.
+    ///
+    /// This has the same limitations as `paths_memo`.
+    pub fn path_length_histogram<P: Into<VisitPolicy>>(&self, policy: P) -> Result<Vec<usize>> {
+        let start = self.find_index(CaveType::Start)?;
+        MemoCounter::new(self, policy.into())?.histogram(start, 0, 0)
+    }
+
+    /// Count the paths from start to end using whichever method is best for
+    /// `policy`, which is the memoized version unless that is not supported
+    pub fn paths<P: Into<VisitPolicy>>(&self, policy: P) -> Result<usize> {
+        let policy = policy.into();
+        self.paths_memo(policy)
+            .or_else(|_| self.paths_semi_par(policy))
+    }
+
+    /// Find the total weight of the lightest path from start to end, or `None`
+    /// if the end cannot be reached.
+    pub fn shortest_path(&self) -> Result<Option<usize>> {
+        let start = self.find_index(CaveType::Start)?;
+        let end = self.find_index(CaveType::End)?;
+
+        // the pathfinding works in terms of grid locations, so lay the caves
+        // out along a single row
+        let mut cache: DefaultLocationCache<usize> =
+            DefaultLocationCache::new(self.caves.len(), self.caves.len());
+
+        Ok(dijkstra_cost(
+            Location::new(0, start),
+            Location::new(0, end),
+            &mut cache,
+            |loc| {
+                self.caves[loc.col]
+                    .links
+                    .iter()
+                    .map(|link| DEdge::new(Location::new(0, *link), self.weight(loc.col, *link)))
+                    .collect()
+            },
+        ))
+    }
+
+    /// Find the total weight of the heaviest path from start to end that
+    /// visits no cave more than once, or `None` if the end cannot be reached.
+    ///
+    /// This is an exhaustive search, so it is only practical for small systems.
+    pub fn longest_simple_path(&self) -> Result<Option<usize>> {
+        let start = self.find_index(CaveType::Start)?;
+        let end = self.find_index(CaveType::End)?;
+
+        let mut seen = vec![false; self.caves.len()];
+        seen[start] = true;
+
+        Ok(self.recur_longest(start, end, &mut seen))
+    }
+
+    fn recur_longest(&self, cur: usize, end: usize, seen: &mut Vec<bool>) -> Option<usize> {
+        if cur == end {
+            return Some(0);
+        }
+
+        let mut best = None;
+
+        for i in self.caves[cur].links.iter() {
+            let i = *i;
+            if seen[i] {
+                continue;
+            }
+
+            seen[i] = true;
+            if let Some(len) = self.recur_longest(i, end, seen) {
+                best = best.max(Some(len + self.weight(cur, i)));
             }
+            seen[i] = false;
         }
 
-        Ok(count)
+        best
+    }
+
+    fn weight(&self, a: usize, b: usize) -> usize {
+        self.weights
+            .get(&(a.min(b), a.max(b)))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Write the cave system to `writer` in the graphviz DOT format
+    pub fn to_dot<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writeln!(writer, "graph caves {{")?;
+
+        for cave in self.caves.iter() {
+            let style = match cave.kind {
+                CaveType::Start => "shape=doublecircle, style=filled, fillcolor=palegreen",
+                CaveType::End => "shape=doublecircle, style=filled, fillcolor=salmon",
+                CaveType::Big => "shape=box, style=filled, fillcolor=lightblue",
+                CaveType::Small => "shape=ellipse",
+            };
+            writeln!(writer, "    \"{}\" [{}];", cave.id, style)?;
+        }
+
+        for (idx, cave) in self.caves.iter().enumerate() {
+            let mut links: Vec<usize> = cave.links.iter().copied().filter(|l| *l > idx).collect();
+            links.sort_unstable();
+
+            for link in links {
+                writeln!(
+                    writer,
+                    "    \"{}\" -- \"{}\";",
+                    cave.id,
+                    self.lookup(link)?.id
+                )?;
+            }
+        }
+
+        writeln!(writer, "}}")?;
+
+        Ok(())
+    }
+
+    fn find_index(&self, kind: CaveType) -> Result<usize> {
+        self.caves
+            .iter()
+            .position(|cave| cave.kind == kind)
+            .ok_or_else(|| anyhow!("cave system does not have a {:?} cave", kind))
     }
 
     fn lookup(&self, idx: usize) -> Result<&Cave> {
@@ -197,6 +530,133 @@ impl CaveSystem {
     }
 }
 
+/// The state for memoized path counting
+struct MemoCounter<'a> {
+    system: &'a CaveSystem,
+    end: usize,
+    policy: VisitPolicy,
+    /// the bit offset of the visit count for each small cave
+    slots: Vec<usize>,
+    /// the mask for a single visit count
+    mask: u128,
+    memo: FxHashMap<(usize, u128), usize>,
+    histograms: FxHashMap<(usize, u128), Vec<usize>>,
+}
+
+impl<'a> MemoCounter<'a> {
+    fn new(system: &'a CaveSystem, policy: VisitPolicy) -> Result<Self> {
+        if policy.max_big_visits.is_some() {
+            bail!("memoized path counting does not support limiting big cave visits");
+        }
+
+        let end = system.find_index(CaveType::End)?;
+
+        // each small cave gets a slot in the packed visit counts
+        let bits = (usize::BITS - policy.max_small_visits.leading_zeros()) as usize;
+        let mut slots = vec![0; system.caves.len()];
+        let mut num_small = 0;
+        for (idx, cave) in system.caves.iter().enumerate() {
+            if cave.kind == CaveType::Small {
+                slots[idx] = num_small * bits;
+                num_small += 1;
+            }
+        }
+
+        if num_small * bits > 128 {
+            bail!(
+                "too many small caves ({}) to memoize with at most {} visits each",
+                num_small,
+                policy.max_small_visits
+            );
+        }
+
+        Ok(Self {
+            system,
+            end,
+            policy,
+            slots,
+            mask: (1 << bits) - 1,
+            memo: FxHashMap::default(),
+            histograms: FxHashMap::default(),
+        })
+    }
+
+    /// Check whether cave `i` may be visited next, returning the updated
+    /// visit counts and number of revisits if so
+    fn visit(&self, i: usize, visits: u128, revisits: usize) -> Result<Option<(u128, usize)>> {
+        let kind = &self.system.lookup(i)?.kind;
+        if *kind != CaveType::Small {
+            return Ok(self.policy.admit(kind, 0, revisits).map(|r| (visits, r)));
+        }
+
+        let seen = ((visits >> self.slots[i]) & self.mask) as usize;
+        Ok(self
+            .policy
+            .admit(kind, seen, revisits)
+            .map(|r| (visits + (1 << self.slots[i]), r)))
+    }
+
+    /// Count the paths from `cur` to the end, where `visits` holds the packed
+    /// visit counts of the small caves and `revisits` is the number of small
+    /// caves that have been visited more than once
+    fn count(&mut self, cur: usize, visits: u128, revisits: usize) -> Result<usize> {
+        if cur == self.end {
+            return Ok(1);
+        }
+
+        // revisits is determined by visits, so it doesn't need to be part of
+        // the key
+        if let Some(count) = self.memo.get(&(cur, visits)) {
+            return Ok(*count);
+        }
+
+        let system = self.system;
+        let mut count = 0;
+
+        for i in system.lookup(cur)?.links.iter() {
+            if let Some((visits, revisits)) = self.visit(*i, visits, revisits)? {
+                count += self.count(*i, visits, revisits)?;
+            }
+        }
+
+        self.memo.insert((cur, visits), count);
+
+        Ok(count)
+    }
+
+    /// Like `count`, but counts the paths of each length separately
+    fn histogram(&mut self, cur: usize, visits: u128, revisits: usize) -> Result<Vec<usize>> {
+        if cur == self.end {
+            return Ok(vec![1]);
+        }
+
+        if let Some(histogram) = self.histograms.get(&(cur, visits)) {
+            return Ok(histogram.clone());
+        }
+
+        let system = self.system;
+        let mut histogram = Vec::new();
+
+        for i in system.lookup(cur)?.links.iter() {
+            if let Some((visits, revisits)) = self.visit(*i, visits, revisits)? {
+                let sub = self.histogram(*i, visits, revisits)?;
+                if histogram.len() < sub.len() + 1 {
+                    histogram.resize(sub.len() + 1, 0);
+                }
+
+                // every path from the next cave is one link longer from here
+                for (len, count) in sub.iter().enumerate() {
+                    histogram[len + 1] += count;
+                }
+            }
+        }
+
+        self.histograms.insert((cur, visits), histogram.clone());
+
+        Ok(histogram)
+    }
+}
+
 impl TryFrom<Vec<String>> for CaveSystem {
     type Error = anyhow::Error;
 
@@ -205,7 +665,21 @@ impl TryFrom<Vec<String>> for CaveSystem {
         let mut cs = CaveSystem::default();
 
         for s in value {
-            let mut parts = s.split('-');
+            // links may have an optional weight, like `A-b=7`
+            let (link, weight) = match s.split_once('=') {
+                Some((link, weight)) => (
+                    link,
+                    Some(
+                        weight
+                            .trim()
+                            .parse::<usize>()
+                            .map_err(|_| anyhow!("Invalid input, bad weight: {}", s))?,
+                    ),
+                ),
+                None => (s.as_str(), None),
+            };
+
+            let mut parts = link.split('-');
             let a = Cave::from(
                 parts
                     .next()
@@ -229,9 +703,83 @@ impl TryFrom<Vec<String>> for CaveSystem {
                 cs.caves.len() - 1
             });
 
-            cs.link(a_idx, b_idx)?;
+            match weight {
+                Some(weight) => cs.link_weighted(a_idx, b_idx, weight)?,
+                None => cs.link(a_idx, b_idx)?,
+            }
+        }
+
+        cs.validate()?;
+
+        Ok(cs)
+    }
+}
+
+#[cfg(feature = "graph")]
+impl From<&CaveSystem> for petgraph::Graph<CaveType, ()> {
+    /// Node indices match cave indices. The graph is directed, so every link
+    /// becomes a pair of edges, one in each direction.
+    fn from(value: &CaveSystem) -> Self {
+        let num_edges = value.caves.iter().map(|cave| cave.links.len()).sum();
+        let mut graph = Self::with_capacity(value.caves.len(), num_edges);
+        let nodes: Vec<_> = value
+            .caves
+            .iter()
+            .map(|cave| graph.add_node(cave.kind.clone()))
+            .collect();
+
+        for (idx, cave) in value.caves.iter().enumerate() {
+            for link in cave.links.iter() {
+                graph.add_edge(nodes[idx], nodes[*link], ());
+            }
         }
 
+        graph
+    }
+}
+
+#[cfg(feature = "graph")]
+impl<Ty: petgraph::EdgeType> TryFrom<&petgraph::Graph<CaveType, (), Ty>> for CaveSystem {
+    type Error = anyhow::Error;
+
+    /// Edges are treated as undirected links regardless of the type of graph.
+    /// Since the graph only knows the type of each cave, the caves are named
+    /// after their node indices.
+    fn try_from(value: &petgraph::Graph<CaveType, (), Ty>) -> Result<Self> {
+        use petgraph::visit::EdgeRef;
+
+        let caves = value
+            .node_indices()
+            .map(|node| {
+                let kind = value[node].clone();
+                let id = match kind {
+                    CaveType::Start => "start".to_string(),
+                    CaveType::End => "end".to_string(),
+                    CaveType::Big => format!("BIG{}", node.index()),
+                    CaveType::Small => format!("small{}", node.index()),
+                };
+
+                Cave {
+                    kind,
+                    id,
+                    links: FxHashSet::default(),
+                }
+            })
+            .collect();
+
+        let mut cs = CaveSystem {
+            caves,
+            ..CaveSystem::default()
+        };
+        for edge in value.edge_references() {
+            let a = edge.source().index();
+            let b = edge.target().index();
+            cs.caves[a].add_link(b);
+            cs.caves[b].add_link(a);
+        }
+
+        cs.validate()?;
+
         Ok(cs)
     }
 }
@@ -244,11 +792,11 @@ impl Solver for CaveSystem {
     type P2 = usize;
 
     fn part_one(&mut self) -> Self::P1 {
-        self.paths_fast(false).expect("could not find paths")
+        self.paths(false).expect("could not find paths")
     }
 
     fn part_two(&mut self) -> Self::P2 {
-        self.paths_semi_par(true).expect("could not find paths")
+        self.paths(true).expect("could not find paths")
     }
 }
 
@@ -344,5 +892,345 @@ mod tests {
             let paths = cs.paths_semi_par(true).expect("could not find paths");
             assert_eq!(paths, 103);
         }
+
+        #[test]
+        fn visit_policies() {
+            let input = test_input(
+                "
+                start-A
+                start-b
+                A-c
+                A-b
+                b-d
+                A-end
+                b-end
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+
+            let paths = cs
+                .paths_fast(VisitPolicy::single_visit())
+                .expect("could not find paths");
+            assert_eq!(paths, 10);
+
+            let paths = cs
+                .paths_fast(VisitPolicy::single_double_visit())
+                .expect("could not find paths");
+            assert_eq!(paths, 36);
+
+            let policy = VisitPolicy {
+                max_small_visits: 3,
+                revisitable_small: 1,
+                max_big_visits: None,
+            };
+            assert_eq!(cs.paths_fast(policy).expect("could not find paths"), 83);
+            assert_eq!(cs.paths_semi_par(policy).expect("could not find paths"), 83);
+
+            let policy = VisitPolicy {
+                max_small_visits: 2,
+                revisitable_small: 2,
+                max_big_visits: None,
+            };
+            assert_eq!(cs.paths_fast(policy).expect("could not find paths"), 54);
+
+            let policy = VisitPolicy {
+                max_big_visits: Some(1),
+                ..VisitPolicy::single_visit()
+            };
+            assert_eq!(cs.paths_fast(policy).expect("could not find paths"), 4);
+
+            let policy = VisitPolicy {
+                max_big_visits: Some(2),
+                ..VisitPolicy::single_double_visit()
+            };
+            assert_eq!(cs.paths_fast(policy).expect("could not find paths"), 18);
+            assert_eq!(cs.paths_semi_par(policy).expect("could not find paths"), 18);
+            assert!(cs.paths_memo(policy).is_err());
+            assert_eq!(cs.paths(policy).expect("could not find paths"), 18);
+        }
+
+        #[test]
+        fn to_dot() {
+            let input = test_input(
+                "
+                start-A
+                start-b
+                A-b
+                A-end
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+            let mut out = Vec::new();
+            cs.to_dot(&mut out).expect("could not write dot");
+
+            let expected = r#"graph caves {
+    "start" [shape=doublecircle, style=filled, fillcolor=palegreen];
+    "A" [shape=box, style=filled, fillcolor=lightblue];
+    "b" [shape=ellipse];
+    "end" [shape=doublecircle, style=filled, fillcolor=salmon];
+    "start" -- "A";
+    "start" -- "b";
+    "A" -- "b";
+    "A" -- "end";
+}
+"#;
+            assert_eq!(String::from_utf8(out).expect("invalid utf8"), expected);
+        }
+
+        #[cfg(feature = "graph")]
+        #[test]
+        fn petgraph_round_trip() {
+            use petgraph::{algo::has_path_connecting, graph::UnGraph, Graph};
+
+            let input = test_input(
+                "
+                dc-end
+                HN-start
+                start-kj
+                dc-start
+                dc-HN
+                LN-dc
+                HN-end
+                kj-sa
+                kj-HN
+                kj-dc
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+            let graph = Graph::<CaveType, ()>::from(&cs);
+            assert_eq!(graph.node_count(), 7);
+            assert_eq!(graph.edge_count(), 20);
+
+            // links can be followed either way
+            let start = graph
+                .node_indices()
+                .find(|n| graph[*n] == CaveType::Start)
+                .expect("no start");
+            let end = graph
+                .node_indices()
+                .find(|n| graph[*n] == CaveType::End)
+                .expect("no end");
+            assert!(has_path_connecting(&graph, start, end, None));
+            assert!(has_path_connecting(&graph, end, start, None));
+
+            let converted = CaveSystem::try_from(&graph).expect("could not convert graph");
+            assert_eq!(converted.paths(false).expect("could not find paths"), 19);
+            assert_eq!(converted.paths(true).expect("could not find paths"), 103);
+
+            // undirected graphs can be converted too
+            let mut undirected = UnGraph::<CaveType, ()>::default();
+            let nodes = [CaveType::Start, CaveType::Big, CaveType::End]
+                .iter()
+                .map(|kind| undirected.add_node(kind.clone()))
+                .collect::<Vec<_>>();
+            undirected.add_edge(nodes[1], nodes[0], ());
+            undirected.add_edge(nodes[1], nodes[2], ());
+            let converted = CaveSystem::try_from(&undirected).expect("could not convert graph");
+            assert_eq!(converted.paths(false).expect("could not find paths"), 1);
+        }
+
+        #[test]
+        fn weighted_paths() {
+            let input = test_input(
+                "
+                start-A=5
+                start-b=1
+                A-b=1
+                A-end=3
+                b-end=10
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+            assert_eq!(cs.shortest_path().expect("could not find path"), Some(5));
+            assert_eq!(
+                cs.longest_simple_path().expect("could not find path"),
+                Some(16)
+            );
+
+            // weights don't affect counting
+            assert_eq!(cs.paths(false).expect("could not find paths"), 5);
+
+            // unweighted links count as 1
+            let input = test_input(
+                "
+                start-A
+                start-b
+                A-c
+                A-b
+                b-d
+                A-end
+                b-end
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+            assert_eq!(cs.shortest_path().expect("could not find path"), Some(2));
+            assert_eq!(
+                cs.longest_simple_path().expect("could not find path"),
+                Some(3)
+            );
+
+            let input = test_input(
+                "
+                start-A
+                b-end
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+            assert_eq!(cs.shortest_path().expect("could not find path"), None);
+            assert_eq!(cs.longest_simple_path().expect("could not find path"), None);
+
+            assert!(CaveSystem::try_from(test_input("start-A=x")).is_err());
+        }
+
+        #[test]
+        fn parallel_matches_recursion() {
+            let input = test_input(
+                "
+                fs-end
+                he-DX
+                fs-he
+                start-DX
+                pj-DX
+                end-zg
+                zg-sl
+                zg-pj
+                pj-he
+                RW-he
+                fs-DX
+                pj-RW
+                zg-RW
+                start-pj
+                he-WI
+                zg-he
+                pj-fs
+                start-RW
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+
+            for depth in 0..6 {
+                assert_eq!(
+                    cs.paths_par(false, depth).expect("could not find paths"),
+                    226
+                );
+                assert_eq!(
+                    cs.paths_par(true, depth).expect("could not find paths"),
+                    3509
+                );
+            }
+
+            let policy = VisitPolicy {
+                max_small_visits: 3,
+                revisitable_small: 2,
+                max_big_visits: Some(2),
+            };
+            assert_eq!(
+                cs.paths_par(policy, 3).expect("could not find paths"),
+                cs.paths_fast(policy).expect("could not find paths")
+            );
+        }
+
+        #[test]
+        fn path_length_histogram() {
+            let input = test_input(
+                "
+                start-A
+                start-b
+                A-c
+                A-b
+                b-d
+                A-end
+                b-end
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+            let histogram = cs
+                .path_length_histogram(false)
+                .expect("could not find paths");
+            assert_eq!(histogram, vec![0, 0, 2, 2, 2, 2, 2]);
+
+            let histogram = cs
+                .path_length_histogram(true)
+                .expect("could not find paths");
+            assert_eq!(histogram.iter().sum::<usize>(), 36);
+        }
+
+        #[test]
+        fn adjacent_big_caves() {
+            let input = test_input(
+                "
+                start-A
+                A-b
+                b-CD
+                CD-A
+                b-end
+                ",
+            );
+            let err = CaveSystem::try_from(input).expect_err("should not parse");
+            assert_eq!(
+                err.downcast_ref::<CaveError>(),
+                Some(&CaveError::AdjacentBigCaves("A".into(), "CD".into()))
+            );
+
+            let input = test_input(
+                "
+                start-A
+                A-b
+                b-end
+                ",
+            );
+            let mut cs = CaveSystem::try_from(input).expect("could not parse input");
+            assert!(cs.validate().is_ok());
+
+            // links added after parsing are only caught by validating again
+            cs.caves.push(Cave::from("XY".to_string()));
+            cs.link(1, 4).expect("could not link caves");
+            assert_eq!(
+                cs.validate(),
+                Err(CaveError::AdjacentBigCaves("A".into(), "XY".into()))
+            );
+        }
+
+        #[test]
+        fn memoized_matches_recursion() {
+            let input = test_input(
+                "
+                fs-end
+                he-DX
+                fs-he
+                start-DX
+                pj-DX
+                end-zg
+                zg-sl
+                zg-pj
+                pj-he
+                RW-he
+                fs-DX
+                pj-RW
+                zg-RW
+                start-pj
+                he-WI
+                zg-he
+                pj-fs
+                start-RW
+                ",
+            );
+            let cs = CaveSystem::try_from(input).expect("could not parse input");
+
+            assert_eq!(cs.paths_memo(false).expect("could not find paths"), 226);
+            assert_eq!(cs.paths_memo(true).expect("could not find paths"), 3509);
+
+            for (max_small_visits, revisitable_small) in [(0, 0), (2, 2), (3, 1), (3, 2)] {
+                let policy = VisitPolicy {
+                    max_small_visits,
+                    revisitable_small,
+                    max_big_visits: None,
+                };
+                assert_eq!(
+                    cs.paths_memo(policy).expect("could not find paths"),
+                    cs.paths_fast(policy).expect("could not find paths")
+                );
+            }
+        }
     }
 }
@@ -1,9 +1,11 @@
 use std::{
+    collections::BinaryHeap,
     convert::{TryFrom, TryInto},
     ops::Deref,
 };
 
 use anyhow::{anyhow, Result};
+use rayon::prelude::*;
 
 use aoc_helpers::{
     generic::{
@@ -52,7 +54,114 @@ impl PartialOrd for Node {
     }
 }
 
-pub struct ChitonGrid(Grid<Chiton>);
+/// How the risk of a cell changes as the grid is repeated into tiles
+pub trait WrapRule: Sync {
+    /// The risk of a cell with the given original `risk`, in the tile
+    /// `tile_dx` tiles to the right and `tile_dy` tiles down
+    fn wrap(&self, risk: usize, tile_dx: usize, tile_dy: usize) -> usize;
+}
+
+impl<F> WrapRule for F
+where
+    F: Fn(usize, usize, usize) -> usize + Sync,
+{
+    fn wrap(&self, risk: usize, tile_dx: usize, tile_dy: usize) -> usize {
+        self(risk, tile_dx, tile_dy)
+    }
+}
+
+/// The puzzle's rule, where risk goes up by one for every tile right or down,
+/// wrapping from 9 back around to 1
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Increment;
+
+impl WrapRule for Increment {
+    fn wrap(&self, risk: usize, tile_dx: usize, tile_dy: usize) -> usize {
+        // a risk of 0 is never wrapped, so it's left alone in the first tile
+        match risk + tile_dx + tile_dy {
+            v if v > 9 => (v - 1) % 9 + 1,
+            v => v,
+        }
+    }
+}
+
+/// The grid repeated `rows` times down and `cols` times across, with the risk
+/// in each copy given by `wrap`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Tiling<W> {
+    rows: usize,
+    cols: usize,
+    wrap: W,
+}
+
+impl Tiling<Increment> {
+    /// The puzzle's tiling, `scale` times in each direction
+    pub fn square(scale: usize) -> Self {
+        Self::new(scale, scale, Increment)
+    }
+}
+
+impl<W: WrapRule> Tiling<W> {
+    pub fn new(rows: usize, cols: usize, wrap: W) -> Self {
+        Self { rows, cols, wrap }
+    }
+}
+
+/// The ways `shortest_by` can search for the cheapest path
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Strategy {
+    /// Dijkstra's algorithm, from the start
+    Dijkstra,
+    /// Dijkstra's algorithm from both ends at once, stopping when the two
+    /// searches can no longer improve on where they have met
+    Bidirectional,
+    /// Delta-stepping, which settles every cell within `delta` of the
+    /// cheapest unsettled cost at once, relaxing their edges in parallel
+    DeltaStepping { delta: usize },
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Self::Dijkstra
+    }
+}
+
+/// Which neighboring cells a path can step to
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Movement {
+    /// Only up, down, left and right
+    Orthogonal,
+    /// Diagonally as well, where a diagonal step costs `multiplier` times the
+    /// cost of entering the cell it lands on
+    Diagonal { multiplier: usize },
+}
+
+impl Movement {
+    /// The cells reachable in one step from `loc`, with the multiplier for the
+    /// cost of entering each
+    fn moves(self, loc: &Location) -> impl Iterator<Item = (Location, usize)> {
+        let here = *loc;
+        let neighbors = match self {
+            Self::Orthogonal => here.orthogonal_neighbors(),
+            Self::Diagonal { .. } => here.neighbors(),
+        };
+
+        neighbors.map(move |n| match self {
+            Self::Diagonal { multiplier } if n.row != here.row && n.col != here.col => {
+                (n, multiplier)
+            }
+            _ => (n, 1),
+        })
+    }
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Self::Orthogonal
+    }
+}
+
+pub struct ChitonGrid(Grid<Chiton>, Movement);
 
 impl Deref for ChitonGrid {
     type Target = Grid<Chiton>;
@@ -63,29 +172,391 @@ impl Deref for ChitonGrid {
 }
 
 impl ChitonGrid {
+    /// Use `movement` to decide which cells paths can step to in all future
+    /// searches
+    pub fn with_movement(mut self, movement: Movement) -> Self {
+        self.1 = movement;
+        self
+    }
+
+    pub fn movement(&self) -> Movement {
+        self.1
+    }
+
     pub fn shortest(&self, scale: usize, start: &Location, end: &Location) -> Option<usize> {
-        let mut cache: DefaultLocationCache<usize> =
-            DefaultLocationCache::new(self.size() * scale * scale, self.rows() * scale);
+        self.shortest_tiled(&Tiling::square(scale), start, end)
+    }
+
+    /// Like `shortest`, but over an arbitrary `tiling` of the grid
+    pub fn shortest_tiled<W: WrapRule>(
+        &self,
+        tiling: &Tiling<W>,
+        start: &Location,
+        end: &Location,
+    ) -> Option<usize> {
+        self.shortest_by(Strategy::Dijkstra, tiling, start, end)
+    }
+
+    /// Like `shortest_tiled`, but searching with the given `strategy`
+    pub fn shortest_by<W: WrapRule>(
+        &self,
+        strategy: Strategy,
+        tiling: &Tiling<W>,
+        start: &Location,
+        end: &Location,
+    ) -> Option<usize> {
+        self.search(
+            strategy,
+            tiling,
+            start,
+            end,
+            &|_: &Location, risk: usize| Some(risk),
+        )
+    }
+
+    /// Like `shortest_tiled`, but searching forwards from `start` and
+    /// backwards from `end` at the same time
+    pub fn shortest_bidirectional<W: WrapRule>(
+        &self,
+        tiling: &Tiling<W>,
+        start: &Location,
+        end: &Location,
+    ) -> Option<usize> {
+        self.shortest_by(Strategy::Bidirectional, tiling, start, end)
+    }
+
+    /// Like `shortest_by`, but with the cost of entering each cell given by
+    /// `cost`, from its location and risk. Cells where `cost` is `None` cannot
+    /// be entered, though the path may still start on one. Only a custom
+    /// `WrapRule` can make a risk too large for a `u8`, and those are passed
+    /// as `u8::MAX`.
+    pub fn shortest_weighted<W, F>(
+        &self,
+        strategy: Strategy,
+        tiling: &Tiling<W>,
+        start: &Location,
+        end: &Location,
+        cost: F,
+    ) -> Option<usize>
+    where
+        W: WrapRule,
+        F: Fn(&Location, u8) -> Option<usize> + Sync,
+    {
+        self.search(
+            strategy,
+            tiling,
+            start,
+            end,
+            &|loc: &Location, risk: usize| cost(loc, u8::try_from(risk).unwrap_or(u8::MAX)),
+        )
+    }
+
+    fn search<W, F>(
+        &self,
+        strategy: Strategy,
+        tiling: &Tiling<W>,
+        start: &Location,
+        end: &Location,
+        cost: &F,
+    ) -> Option<usize>
+    where
+        W: WrapRule,
+        F: Fn(&Location, usize) -> Option<usize> + Sync,
+    {
+        match strategy {
+            Strategy::Dijkstra => self.dijkstra(tiling, start, end, cost),
+            Strategy::Bidirectional => self.bidirectional(tiling, start, end, cost),
+            Strategy::DeltaStepping { delta } => {
+                self.delta_stepping(tiling, start, end, cost, delta)
+            }
+        }
+    }
+
+    fn dijkstra<W, F>(
+        &self,
+        tiling: &Tiling<W>,
+        start: &Location,
+        end: &Location,
+        cost: &F,
+    ) -> Option<usize>
+    where
+        W: WrapRule,
+        F: Fn(&Location, usize) -> Option<usize> + Sync,
+    {
+        self.risk(start, tiling)?;
+        self.risk(end, tiling)?;
+
+        let mut cache: DefaultLocationCache<usize> = DefaultLocationCache::new(
+            self.size() * tiling.rows * tiling.cols,
+            self.cols() * tiling.cols,
+        );
 
         dijkstra_cost(*start, *end, &mut cache, |loc| {
             // so this is a little weird, but we actually have much better
             // performance pre-allocating then extending. I would rather return
             // an iterator from the closure, but existential types, not really
             // a thing in that regard yet.
-            let mut edges = Vec::with_capacity(4);
-            edges.extend(loc.orthogonal_neighbors().filter_map(|n| {
-                self.get_scaled(&n, scale, |chiton, r_fac, c_fac| {
-                    let mut v = chiton.0 + r_fac + c_fac;
-                    if v > 9 {
-                        v = v % 10 + 1;
-                    }
-                    Chiton(v)
-                })
-                .map(|cost| DEdge::new(n, cost.0))
+            let mut edges = Vec::with_capacity(8);
+            edges.extend(self.1.moves(loc).filter_map(|(n, multiplier)| {
+                self.risk(&n, tiling)
+                    .and_then(|risk| cost(&n, risk))
+                    .map(|cost| DEdge::new(n, cost * multiplier))
             }));
             edges
         })
     }
+
+    /// Entering a cell costs its risk, so the backward search charges a
+    /// cell's cost when stepping away from it instead.
+    fn bidirectional<W, F>(
+        &self,
+        tiling: &Tiling<W>,
+        start: &Location,
+        end: &Location,
+        cost: &F,
+    ) -> Option<usize>
+    where
+        W: WrapRule,
+        F: Fn(&Location, usize) -> Option<usize> + Sync,
+    {
+        self.risk(start, tiling)?;
+        self.risk(end, tiling)?;
+        if start == end {
+            return Some(0);
+        }
+
+        let width = self.cols() * tiling.cols;
+        let size = self.size() * tiling.rows * tiling.cols;
+        let idx = |loc: &Location| loc.row * width + loc.col;
+
+        // the forward costs include the cost of the cell itself, the backward
+        // costs include everything after it, up to and including the end
+        let mut dist = [vec![usize::MAX; size], vec![usize::MAX; size]];
+        let mut heaps = [BinaryHeap::new(), BinaryHeap::new()];
+        dist[0][idx(start)] = 0;
+        dist[1][idx(end)] = 0;
+        heaps[0].push(Node::new(idx(start), 0, 0));
+        heaps[1].push(Node::new(idx(end), 0, 0));
+
+        let mut best = usize::MAX;
+        loop {
+            let forward = heaps[0].peek().map(|n| n.cost);
+            let backward = heaps[1].peek().map(|n| n.cost);
+
+            // expand whichever side is cheaper, stopping once no new meeting
+            // point could beat the best so far
+            let side = match (forward, backward) {
+                (Some(f), Some(b)) if f + b >= best => break,
+                (Some(f), Some(b)) => usize::from(b < f),
+                (Some(f), None) if f < best => 0,
+                (None, Some(b)) if b < best => 1,
+                _ => break,
+            };
+
+            let node = heaps[side].pop()?;
+            if node.cost > dist[side][node.idx] {
+                continue;
+            }
+
+            let here = Location::new(node.idx / width, node.idx % width);
+            let leaving = if side == 1 {
+                match self.risk(&here, tiling).and_then(|risk| cost(&here, risk)) {
+                    Some(leaving) => leaving,
+                    None => continue,
+                }
+            } else {
+                0
+            };
+
+            for (n, multiplier) in self.1.moves(&here) {
+                let step = match self.risk(&n, tiling) {
+                    Some(_) if side == 1 => leaving,
+                    Some(risk) => match cost(&n, risk) {
+                        Some(step) => step,
+                        None => continue,
+                    },
+                    None => continue,
+                };
+
+                let total = node.cost + step * multiplier;
+                let i = idx(&n);
+                if total < dist[side][i] {
+                    dist[side][i] = total;
+                    heaps[side].push(Node::new(i, total, total));
+
+                    let other = dist[1 - side][i];
+                    if other != usize::MAX {
+                        best = best.min(total + other);
+                    }
+                }
+            }
+        }
+
+        if best == usize::MAX {
+            None
+        } else {
+            Some(best)
+        }
+    }
+
+    fn delta_stepping<W, F>(
+        &self,
+        tiling: &Tiling<W>,
+        start: &Location,
+        end: &Location,
+        cost: &F,
+        delta: usize,
+    ) -> Option<usize>
+    where
+        W: WrapRule,
+        F: Fn(&Location, usize) -> Option<usize> + Sync,
+    {
+        self.risk(start, tiling)?;
+        self.risk(end, tiling)?;
+
+        let delta = delta.max(1);
+        let width = self.cols() * tiling.cols;
+        let size = self.size() * tiling.rows * tiling.cols;
+        let idx = |loc: &Location| loc.row * width + loc.col;
+        let target = idx(end);
+
+        // every step out of the cell `i`, given the cost to reach it
+        let edges = |i: usize, reached: usize| {
+            let here = Location::new(i / width, i % width);
+            self.1.moves(&here).filter_map(move |(n, multiplier)| {
+                self.risk(&n, tiling)
+                    .and_then(|risk| cost(&n, risk))
+                    .map(|step| (idx(&n), step * multiplier, reached))
+            })
+        };
+
+        let mut dist = vec![usize::MAX; size];
+        let mut buckets: Vec<Vec<usize>> = vec![vec![idx(start)]];
+        dist[idx(start)] = 0;
+
+        // apply the requested costs, which is cheap enough to do serially
+        let relax = |requests: Vec<(usize, usize)>,
+                     dist: &mut Vec<usize>,
+                     buckets: &mut Vec<Vec<usize>>| {
+            for (i, total) in requests {
+                if total < dist[i] {
+                    dist[i] = total;
+                    let bucket = total / delta;
+                    if bucket >= buckets.len() {
+                        buckets.resize_with(bucket + 1, Vec::new);
+                    }
+                    buckets[bucket].push(i);
+                }
+            }
+        };
+
+        let mut current = 0;
+        while current < buckets.len() {
+            // light steps can land back in this bucket, so keep going until
+            // it stays empty
+            let mut settled = Vec::new();
+            while !buckets[current].is_empty() {
+                let mut frontier = std::mem::take(&mut buckets[current]);
+                frontier.retain(|i| dist[*i] / delta == current);
+                frontier.sort_unstable();
+                frontier.dedup();
+
+                let requests = frontier
+                    .par_iter()
+                    .flat_map_iter(|i| edges(*i, dist[*i]))
+                    .filter(|(_, step, _)| *step <= delta)
+                    .map(|(n, step, reached)| (n, reached + step))
+                    .collect();
+                relax(requests, &mut dist, &mut buckets);
+                settled.extend(frontier);
+            }
+
+            // everything cheaper than the next bucket is final
+            if dist[target] / delta == current {
+                return Some(dist[target]);
+            }
+
+            settled.sort_unstable();
+            settled.dedup();
+            let requests = settled
+                .par_iter()
+                .flat_map_iter(|i| edges(*i, dist[*i]))
+                .filter(|(_, step, _)| *step > delta)
+                .map(|(n, step, reached)| (n, reached + step))
+                .collect();
+            relax(requests, &mut dist, &mut buckets);
+
+            current += 1;
+        }
+
+        None
+    }
+
+    /// The lowest total risk of reaching every cell of the grid, scaled as in
+    /// `shortest`, from `start`
+    pub fn distance_field(&self, scale: usize, start: &Location) -> Grid<usize> {
+        self.distance_field_tiled(&Tiling::square(scale), start)
+    }
+
+    /// Like `distance_field`, but over an arbitrary `tiling` of the grid.
+    /// Cells that cannot be reached, which is all of them if `start` is out of
+    /// bounds, are `usize::MAX`.
+    pub fn distance_field_tiled<W: WrapRule>(
+        &self,
+        tiling: &Tiling<W>,
+        start: &Location,
+    ) -> Grid<usize> {
+        let width = self.cols() * tiling.cols;
+        let size = self.size() * tiling.rows * tiling.cols;
+        let idx = |loc: &Location| loc.row * width + loc.col;
+
+        let mut dist = vec![usize::MAX; size];
+        let mut heap = BinaryHeap::new();
+        if self.risk(start, tiling).is_some() {
+            dist[idx(start)] = 0;
+            heap.push(Node::new(idx(start), 0, 0));
+        }
+
+        while let Some(node) = heap.pop() {
+            if node.cost > dist[node.idx] {
+                continue;
+            }
+
+            let here = Location::new(node.idx / width, node.idx % width);
+            for (n, multiplier) in self.1.moves(&here) {
+                if let Some(risk) = self.risk(&n, tiling) {
+                    let total = node.cost + risk * multiplier;
+                    let i = idx(&n);
+                    if total < dist[i] {
+                        dist[i] = total;
+                        heap.push(Node::new(i, total, total));
+                    }
+                }
+            }
+        }
+
+        Grid::new(if width == 0 {
+            Vec::new()
+        } else {
+            dist.chunks(width).map(|row| row.to_vec()).collect()
+        })
+    }
+
+    /// The risk at `loc` in the tiled grid, if it is in bounds
+    pub fn risk<W: WrapRule>(&self, loc: &Location, tiling: &Tiling<W>) -> Option<usize> {
+        let (rows, cols) = (self.rows(), self.cols());
+        if rows == 0 || loc.row >= rows * tiling.rows || loc.col >= cols * tiling.cols {
+            return None;
+        }
+
+        self.get(&Location::new(loc.row % rows, loc.col % cols))
+            .map(|chiton| tiling.wrap.wrap(chiton.0, loc.col / cols, loc.row / rows))
+    }
+
+    /// The bottom right corner of the tiled grid
+    pub fn tiled_bottom_right<W>(&self, tiling: &Tiling<W>) -> Location {
+        Location::new(self.rows() * tiling.rows - 1, self.cols() * tiling.cols - 1)
+    }
 }
 
 impl TryFrom<Vec<String>> for ChitonGrid {
@@ -105,7 +576,7 @@ impl TryFrom<Vec<String>> for ChitonGrid {
             })
             .collect::<Result<Vec<Vec<Chiton>>>>()?;
 
-        Ok(Self(locations.try_into()?))
+        Ok(Self(locations.try_into()?, Movement::default()))
     }
 }
 
@@ -132,6 +603,8 @@ impl Solver for ChitonGrid {
 mod tests {
     use aoc_helpers::util::test_input;
 
+    use crate::rng::xorshift;
+
     use super::*;
 
     #[test]
@@ -190,4 +663,485 @@ mod tests {
             Some(315)
         );
     }
+
+    #[test]
+    fn custom_tiling() {
+        let input = test_input(
+            "
+            1163751742
+            1381373672
+            2136511328
+            3694931569
+            7463417111
+            1319128137
+            1359912421
+            3125421639
+            1293138521
+            2311944581
+            ",
+        );
+        let grid = ChitonGrid::try_from(input).expect("could not parse input");
+
+        // the puzzle's rule, written out as a closure
+        let tiling = Tiling::new(5, 5, |risk: usize, dx: usize, dy: usize| {
+            (risk + dx + dy - 1) % 9 + 1
+        });
+        assert_eq!(
+            grid.shortest_tiled(&tiling, &grid.top_left(), &grid.tiled_bottom_right(&tiling)),
+            Some(315)
+        );
+
+        assert_eq!(grid.risk(&Location::new(0, 0), &Tiling::square(5)), Some(1));
+        assert_eq!(
+            grid.risk(&Location::new(0, 10), &Tiling::square(5)),
+            Some(2)
+        );
+        assert_eq!(
+            grid.risk(&Location::new(8, 46), &Tiling::square(5)),
+            Some(3)
+        );
+        assert_eq!(grid.risk(&Location::new(50, 0), &Tiling::square(5)), None);
+
+        // wider than it is tall, and every copy the same
+        let tiling = Tiling::new(1, 3, |risk: usize, _: usize, _: usize| risk);
+        let corner = grid.tiled_bottom_right(&tiling);
+        assert_eq!(corner, Location::new(9, 29));
+        assert_eq!(grid.risk(&Location::new(3, 23), &tiling), Some(4));
+        assert_eq!(grid.risk(&Location::new(10, 0), &tiling), None);
+        assert!(grid
+            .shortest_tiled(&tiling, &grid.top_left(), &corner)
+            .is_some());
+
+        let tiling = Tiling::new(2, 3, |risk: usize, dx: usize, dy: usize| {
+            risk + 10 * dx + 100 * dy
+        });
+        assert_eq!(grid.risk(&Location::new(13, 23), &tiling), Some(124));
+    }
+
+    #[test]
+    fn zero_risk() {
+        let input = test_input(
+            "
+            10
+            00
+            ",
+        );
+        let grid = ChitonGrid::try_from(input).expect("could not parse input");
+        let tiling = Tiling::square(2);
+        assert_eq!(grid.risk(&Location::new(1, 1), &tiling), Some(0));
+        assert_eq!(grid.risk(&Location::new(1, 3), &tiling), Some(1));
+        assert_eq!(grid.risk(&Location::new(2, 2), &tiling), Some(3));
+
+        assert_eq!(
+            grid.shortest(1, &grid.top_left(), &grid.bottom_right()),
+            Some(0)
+        );
+        assert_eq!(
+            grid.shortest(2, &grid.top_left(), &grid.scaled_bottom_right(2)),
+            Some(6)
+        );
+        assert_eq!(Increment.wrap(9, 4, 4), 8);
+    }
+
+    #[test]
+    fn bidirectional() {
+        let input = test_input(
+            "
+            1163751742
+            1381373672
+            2136511328
+            3694931569
+            7463417111
+            1319128137
+            1359912421
+            3125421639
+            1293138521
+            2311944581
+            ",
+        );
+        let grid = ChitonGrid::try_from(input).expect("could not parse input");
+        let tiling = Tiling::square(5);
+        let corner = grid.tiled_bottom_right(&tiling);
+        assert_eq!(
+            grid.shortest_by(
+                Strategy::Bidirectional,
+                &Tiling::square(1),
+                &grid.top_left(),
+                &grid.bottom_right()
+            ),
+            Some(40)
+        );
+        assert_eq!(
+            grid.shortest_by(Strategy::Bidirectional, &tiling, &grid.top_left(), &corner),
+            Some(315)
+        );
+
+        // going the other way round costs the risk of the start, not the end
+        assert_eq!(
+            grid.shortest_bidirectional(&tiling, &corner, &grid.top_left()),
+            Some(315 - 9 + 1)
+        );
+        assert_eq!(
+            grid.shortest_bidirectional(&tiling, &corner, &corner),
+            Some(0)
+        );
+        assert_eq!(
+            grid.shortest_bidirectional(&tiling, &grid.top_left(), &Location::new(50, 0)),
+            None
+        );
+
+        let mut next = xorshift(0x3c6e_f372_fe94_f82b);
+
+        for _ in 0..100 {
+            let (rows, cols) = (next(8) + 1, next(8) + 1);
+            let input = (0..rows)
+                .map(|_| (0..cols).map(|_| (b'1' + next(9) as u8) as char).collect())
+                .collect::<Vec<String>>();
+            let grid = ChitonGrid::try_from(input).expect("could not parse input");
+
+            let tiling = Tiling::new(next(3) + 1, next(3) + 1, Increment);
+            let corner = grid.tiled_bottom_right(&tiling);
+            let start = Location::new(next(corner.row + 1), next(corner.col + 1));
+            let end = Location::new(next(corner.row + 1), next(corner.col + 1));
+            assert_eq!(
+                grid.shortest_by(Strategy::Bidirectional, &tiling, &start, &end),
+                grid.shortest_by(Strategy::Dijkstra, &tiling, &start, &end),
+            );
+        }
+    }
+
+    #[test]
+    fn weighted() {
+        let input = test_input(
+            "
+            1163751742
+            1381373672
+            2136511328
+            3694931569
+            7463417111
+            1319128137
+            1359912421
+            3125421639
+            1293138521
+            2311944581
+            ",
+        );
+        let grid = ChitonGrid::try_from(input).expect("could not parse input");
+        let tiling = Tiling::square(1);
+        let (start, end) = (grid.top_left(), grid.bottom_right());
+
+        for strategy in [Strategy::Dijkstra, Strategy::Bidirectional] {
+            // unchanged risks
+            assert_eq!(
+                grid.shortest_weighted(strategy, &tiling, &start, &end, |_, risk| Some(
+                    risk.into()
+                )),
+                Some(40)
+            );
+
+            // every step costs the same, so this is the manhattan distance
+            assert_eq!(
+                grid.shortest_weighted(strategy, &tiling, &start, &end, |_, _| Some(1)),
+                Some(18)
+            );
+
+            // wall off everything but the top row and the right column
+            assert_eq!(
+                grid.shortest_weighted(strategy, &tiling, &start, &end, |loc, risk| {
+                    if loc.row == 0 || loc.col == 9 {
+                        Some(risk.into())
+                    } else {
+                        None
+                    }
+                }),
+                Some(1 + 6 + 3 + 7 + 5 + 1 + 7 + 4 + 2 + 2 + 8 + 9 + 1 + 7 + 1 + 9 + 1 + 1)
+            );
+
+            // or wall off the end entirely
+            assert_eq!(
+                grid.shortest_weighted(strategy, &tiling, &start, &end, |loc, risk| {
+                    if *loc == end {
+                        None
+                    } else {
+                        Some(risk.into())
+                    }
+                }),
+                None
+            );
+
+            // but starting on a blocked cell is fine
+            assert_eq!(
+                grid.shortest_weighted(strategy, &tiling, &start, &end, |loc, risk| {
+                    if *loc == start {
+                        None
+                    } else {
+                        Some(risk.into())
+                    }
+                }),
+                Some(40)
+            );
+        }
+
+        // risks too large for a u8 are capped
+        let tiling = Tiling::new(2, 1, |risk: usize, _: usize, dy: usize| risk + 300 * dy);
+        let cost = |_: &Location, risk: u8| Some(usize::from(risk));
+        let above = grid
+            .shortest_weighted(
+                Strategy::Dijkstra,
+                &tiling,
+                &start,
+                &Location::new(9, 0),
+                cost,
+            )
+            .expect("could not find cheapest path");
+        assert_eq!(
+            grid.shortest_weighted(
+                Strategy::Dijkstra,
+                &tiling,
+                &start,
+                &Location::new(10, 0),
+                cost
+            ),
+            Some(above + 255)
+        );
+
+        let mut next = xorshift(0xa54f_f53a_5f1d_36f1);
+
+        // damaged regions, compared across strategies
+        let tiling = Tiling::square(3);
+        let corner = grid.tiled_bottom_right(&tiling);
+        for _ in 0..50 {
+            let blocked: Vec<Location> = (0..200)
+                .map(|_| Location::new(next(corner.row + 1), next(corner.col + 1)))
+                .collect();
+            let factor = next(4) + 1;
+            let cost = |loc: &Location, risk: u8| {
+                if blocked.contains(loc) {
+                    None
+                } else {
+                    Some(usize::from(risk) * factor)
+                }
+            };
+            let start = Location::new(next(corner.row + 1), next(corner.col + 1));
+            let end = Location::new(next(corner.row + 1), next(corner.col + 1));
+
+            assert_eq!(
+                grid.shortest_weighted(Strategy::Bidirectional, &tiling, &start, &end, cost),
+                grid.shortest_weighted(Strategy::Dijkstra, &tiling, &start, &end, cost),
+            );
+        }
+    }
+
+    #[test]
+    fn diagonal_movement() {
+        let input = test_input(
+            "
+            1163751742
+            1381373672
+            2136511328
+            3694931569
+            7463417111
+            1319128137
+            1359912421
+            3125421639
+            1293138521
+            2311944581
+            ",
+        );
+        let grid = ChitonGrid::try_from(input).expect("could not parse input");
+        assert_eq!(grid.movement(), Movement::Orthogonal);
+
+        let tiling = Tiling::square(1);
+        let (start, end) = (grid.top_left(), grid.bottom_right());
+        let grid = grid.with_movement(Movement::Diagonal { multiplier: 1 });
+
+        for strategy in [Strategy::Dijkstra, Strategy::Bidirectional] {
+            // every step costs the same, so this is the chebyshev distance
+            assert_eq!(
+                grid.shortest_weighted(strategy, &tiling, &start, &end, |_, _| Some(1)),
+                Some(9)
+            );
+
+            // one step straight down the diagonal, bar the start
+            assert_eq!(
+                grid.shortest_weighted(strategy, &tiling, &start, &end, |loc, risk| {
+                    if loc.row == loc.col {
+                        Some(risk.into())
+                    } else {
+                        None
+                    }
+                }),
+                Some(3 + 3 + 4 + 4 + 2 + 2 + 6 + 2 + 1)
+            );
+
+            let diagonal = grid
+                .shortest_by(strategy, &tiling, &start, &end)
+                .expect("could not find cheapest path");
+            assert!(diagonal < 40);
+        }
+
+        // expensive enough diagonal steps are never worth taking
+        let mut grid = grid.with_movement(Movement::Diagonal { multiplier: 100 });
+        assert_eq!(grid.shortest(1, &start, &end), Some(40));
+        assert_eq!(
+            grid.shortest(5, &start, &grid.scaled_bottom_right(5)),
+            Some(315)
+        );
+
+        let mut next = xorshift(0x510e_527f_ade6_82d1);
+
+        for _ in 0..50 {
+            grid = grid.with_movement(Movement::Diagonal {
+                multiplier: next(3) + 1,
+            });
+            let tiling = Tiling::square(next(3) + 1);
+            let corner = grid.tiled_bottom_right(&tiling);
+            let start = Location::new(next(corner.row + 1), next(corner.col + 1));
+            let end = Location::new(next(corner.row + 1), next(corner.col + 1));
+            assert_eq!(
+                grid.shortest_by(Strategy::Bidirectional, &tiling, &start, &end),
+                grid.shortest_by(Strategy::Dijkstra, &tiling, &start, &end),
+            );
+        }
+    }
+
+    #[test]
+    fn delta_stepping() {
+        let input = test_input(
+            "
+            1163751742
+            1381373672
+            2136511328
+            3694931569
+            7463417111
+            1319128137
+            1359912421
+            3125421639
+            1293138521
+            2311944581
+            ",
+        );
+        let grid = ChitonGrid::try_from(input).expect("could not parse input");
+        let tiling = Tiling::square(5);
+        let corner = grid.tiled_bottom_right(&tiling);
+
+        for delta in [0, 1, 3, 9, 100] {
+            let strategy = Strategy::DeltaStepping { delta };
+            assert_eq!(
+                grid.shortest_by(
+                    strategy,
+                    &Tiling::square(1),
+                    &grid.top_left(),
+                    &grid.bottom_right()
+                ),
+                Some(40)
+            );
+            assert_eq!(
+                grid.shortest_by(strategy, &tiling, &grid.top_left(), &corner),
+                Some(315)
+            );
+            assert_eq!(
+                grid.shortest_by(strategy, &tiling, &corner, &corner),
+                Some(0)
+            );
+            assert_eq!(
+                grid.shortest_by(strategy, &tiling, &corner, &Location::new(0, 50)),
+                None
+            );
+        }
+
+        let mut next = xorshift(0x9b05_688c_2b3e_6c1f);
+
+        for _ in 0..100 {
+            let (rows, cols) = (next(8) + 1, next(8) + 1);
+            let input = (0..rows)
+                .map(|_| (0..cols).map(|_| (b'1' + next(9) as u8) as char).collect())
+                .collect::<Vec<String>>();
+            let movement = if next(2) == 0 {
+                Movement::Orthogonal
+            } else {
+                Movement::Diagonal {
+                    multiplier: next(3) + 1,
+                }
+            };
+            let grid = ChitonGrid::try_from(input)
+                .expect("could not parse input")
+                .with_movement(movement);
+
+            let tiling = Tiling::new(next(3) + 1, next(3) + 1, Increment);
+            let corner = grid.tiled_bottom_right(&tiling);
+            let start = Location::new(next(corner.row + 1), next(corner.col + 1));
+            let end = Location::new(next(corner.row + 1), next(corner.col + 1));
+
+            // wall off some cells, and make the rest more expensive
+            let blocked: Vec<Location> = (0..next(10))
+                .map(|_| Location::new(next(corner.row + 1), next(corner.col + 1)))
+                .collect();
+            let factor = next(5) + 1;
+            let cost = |loc: &Location, risk: u8| {
+                if blocked.contains(loc) {
+                    None
+                } else {
+                    Some(usize::from(risk) * factor)
+                }
+            };
+
+            let strategy = Strategy::DeltaStepping { delta: next(20) };
+            assert_eq!(
+                grid.shortest_weighted(strategy, &tiling, &start, &end, cost),
+                grid.shortest_weighted(Strategy::Dijkstra, &tiling, &start, &end, cost),
+            );
+        }
+    }
+
+    #[test]
+    fn distance_field() {
+        let input = test_input(
+            "
+            1163751742
+            1381373672
+            2136511328
+            3694931569
+            7463417111
+            1319128137
+            1359912421
+            3125421639
+            1293138521
+            2311944581
+            ",
+        );
+        let grid = ChitonGrid::try_from(input).expect("could not parse input");
+
+        let field = grid.distance_field(1, &grid.top_left());
+        assert_eq!(field.rows(), 10);
+        assert_eq!(field.cols(), 10);
+        assert_eq!(field.get(&Location::new(0, 0)), Some(&0));
+        assert_eq!(field.get(&Location::new(0, 2)), Some(&7));
+        assert_eq!(field.get(&grid.bottom_right()), Some(&40));
+
+        let start = Location::new(23, 17);
+        let field = grid.distance_field(5, &start);
+        assert_eq!(field.rows(), 50);
+        assert_eq!(field.cols(), 50);
+        for row in (0..50).step_by(7) {
+            for col in (0..50).step_by(3) {
+                let loc = Location::new(row, col);
+                assert_eq!(field.get(&loc).copied(), grid.shortest(5, &start, &loc));
+            }
+        }
+
+        // wider than it is tall, moving diagonally
+        let grid = grid.with_movement(Movement::Diagonal { multiplier: 2 });
+        let tiling = Tiling::new(1, 3, Increment);
+        let field = grid.distance_field_tiled(&tiling, &grid.top_left());
+        assert_eq!(field.rows(), 10);
+        assert_eq!(field.cols(), 30);
+        assert_eq!(
+            field.get(&grid.tiled_bottom_right(&tiling)).copied(),
+            grid.shortest_tiled(&tiling, &grid.top_left(), &grid.tiled_bottom_right(&tiling))
+        );
+
+        let field = grid.distance_field(1, &Location::new(10, 0));
+        assert!(field.locations.iter().flatten().all(|d| *d == usize::MAX));
+    }
 }
@@ -1,17 +1,28 @@
-use std::{convert::TryFrom, fmt, iter::FromIterator, num::ParseIntError, str::FromStr};
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{BufReader, Read},
+    iter::FromIterator,
+    str::FromStr,
+};
 
 use anyhow::{anyhow, bail, Result};
 use aoc_helpers::Solver;
 use itertools::Itertools;
 use nom::{
+    bits::complete::{tag, take},
     branch::alt,
-    bytes::complete::{tag, take},
-    combinator::{all_consuming, map_res},
+    combinator::{all_consuming, map, map_res},
+    error::{Error, ErrorKind},
     multi::{fold_many0, fold_many1, many1, many_m_n},
     sequence::{preceded, tuple},
     IResult,
 };
 
+/// The input to the parsers: the bytes of the transmission, and the offset of
+/// the next unread bit in the first byte
+type Bits<'a> = (&'a [u8], usize);
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum OpCode {
     Sum,
@@ -40,6 +51,22 @@ impl fmt::Display for OpCode {
     }
 }
 
+impl OpCode {
+    /// The type ID for this opcode
+    pub fn id(&self) -> usize {
+        match self {
+            Self::Sum => 0,
+            Self::Product => 1,
+            Self::Minimum => 2,
+            Self::Maximum => 3,
+            Self::Literal => 4,
+            Self::Greater => 5,
+            Self::Less => 6,
+            Self::Equal => 7,
+        }
+    }
+}
+
 impl TryFrom<usize> for OpCode {
     type Error = anyhow::Error;
 
@@ -58,6 +85,37 @@ impl TryFrom<usize> for OpCode {
     }
 }
 
+/// A packet whose value can't be computed
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EvalError {
+    /// The value does not fit in a `u128`
+    Overflow { code: OpCode },
+    /// A minimum or maximum with no operands, or a comparison with fewer than
+    /// two
+    MissingOperands { code: OpCode, found: usize },
+    /// A comparison with more than two operands
+    ExtraOperands { code: OpCode, found: usize },
+    /// An operator using the literal opcode
+    LiteralOperator,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow { code } => write!(f, "result of {} does not fit in a u128", code),
+            Self::MissingOperands { code, found } => {
+                write!(f, "{} is missing operands, found {}", code, found)
+            }
+            Self::ExtraOperands { code, found } => {
+                write!(f, "{} takes two operands, found {}", code, found)
+            }
+            Self::LiteralOperator => write!(f, "operator uses the literal opcode"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Length {
     Bits(usize),
@@ -65,15 +123,44 @@ pub enum Length {
 }
 
 impl Length {
+    /// Parse the sub-packets of an operator from a string of `'0'` and `'1'`
+    /// characters, as used by [`Transmission::parse_expanded`]
     pub fn sub_packets<'a>(&self, input: &'a str) -> IResult<&'a str, Vec<Packet>> {
         match self {
             Length::Bits(bits) => {
-                let (input, sub_bits) = take(*bits)(input)?;
-                let (_, packets) =
-                    all_consuming(fold_many1(packet, Vec::new, |mut acc: Vec<_>, item| {
+                let (input, sub_bits) = nom::bytes::complete::take(*bits)(input)?;
+                let (_, packets) = all_consuming(fold_many1(
+                    expanded::packet,
+                    Vec::new,
+                    |mut acc: Vec<_>, item| {
                         acc.push(item);
                         acc
-                    }))(sub_bits)?;
+                    },
+                ))(sub_bits)?;
+                Ok((input, packets))
+            }
+            Length::Packets(num) => many_m_n(*num, *num, expanded::packet)(input),
+        }
+    }
+
+    fn sub_packets_bits<'a>(&self, input: Bits<'a>) -> IResult<Bits<'a>, Vec<Packet>> {
+        match self {
+            Length::Bits(bits) => {
+                // the sub-packets have to fill the given number of bits
+                // exactly, and there has to be at least one of them
+                let start = remaining(input);
+                let mut input = input;
+                let mut packets = Vec::new();
+                while start - remaining(input) < *bits {
+                    let (rest, p) = packet(input)?;
+                    input = rest;
+                    packets.push(p);
+                }
+
+                if packets.is_empty() || start - remaining(input) != *bits {
+                    return Err(nom::Err::Error(Error::new(input, ErrorKind::Eof)));
+                }
+
                 Ok((input, packets))
             }
             Length::Packets(num) => many_m_n(*num, *num, packet)(input),
@@ -92,41 +179,164 @@ pub enum PacketType {
 }
 
 impl PacketType {
+    /// The value of this packet. This assumes the packet is well formed and
+    /// that the result fits in a `usize`; see `checked_value` otherwise.
     pub fn value(&self) -> usize {
         match self {
             PacketType::Literal(v) => *v,
             PacketType::Operator { code, packets, .. } => match code {
+                OpCode::Greater | OpCode::Less | OpCode::Equal => {
+                    debug_assert_eq!(packets.len(), 2, "{} takes two operands", code);
+                    Self::compare(*code, packets)
+                }
                 OpCode::Sum => packets.iter().fold(0, |acc, p| acc + p.value()),
                 OpCode::Product => packets.iter().fold(1, |acc, p| acc * p.value()),
                 OpCode::Minimum => packets.iter().map(|p| p.value()).min().unwrap_or(0),
                 OpCode::Maximum => packets.iter().map(|p| p.value()).max().unwrap_or(0),
-                OpCode::Greater => {
-                    if packets[0].value() > packets[1].value() {
-                        1
-                    } else {
-                        0
-                    }
-                }
-                OpCode::Less => {
-                    if packets[0].value() < packets[1].value() {
-                        1
-                    } else {
-                        0
-                    }
-                }
-                OpCode::Equal => {
-                    if packets[0].value() == packets[1].value() {
-                        1
-                    } else {
-                        0
-                    }
-                }
                 _ => {
                     unreachable!("this should not be possible unless this is manually constructed")
                 }
             },
         }
     }
+
+    fn compare(code: OpCode, packets: &[Packet]) -> usize {
+        let (a, b) = (packets[0].value(), packets[1].value());
+        let result = match code {
+            OpCode::Greater => a > b,
+            OpCode::Less => a < b,
+            _ => a == b,
+        };
+        usize::from(result)
+    }
+
+    /// Like `value`, but widened to a `u128`, with every operation checked
+    pub fn checked_value(&self) -> std::result::Result<u128, EvalError> {
+        let (code, packets) = match self {
+            PacketType::Literal(v) => return Ok(*v as u128),
+            PacketType::Operator { code, packets, .. } => (*code, packets),
+        };
+
+        let values = packets
+            .iter()
+            .map(|p| p.checked_value())
+            .collect::<std::result::Result<Vec<u128>, EvalError>>()?;
+
+        builtin(code, &values)
+    }
+}
+
+/// Apply the BITS semantics for `code` to the values of its operands
+fn builtin(code: OpCode, values: &[u128]) -> std::result::Result<u128, EvalError> {
+    let overflow = EvalError::Overflow { code };
+    let missing = EvalError::MissingOperands {
+        code,
+        found: values.len(),
+    };
+
+    match code {
+        OpCode::Sum => values
+            .iter()
+            .try_fold(0_u128, |acc, v| acc.checked_add(*v))
+            .ok_or(overflow),
+        OpCode::Product => values
+            .iter()
+            .try_fold(1_u128, |acc, v| acc.checked_mul(*v))
+            .ok_or(overflow),
+        OpCode::Minimum => values.iter().copied().min().ok_or(missing),
+        OpCode::Maximum => values.iter().copied().max().ok_or(missing),
+        OpCode::Greater | OpCode::Less | OpCode::Equal => match *values {
+            [a, b] => Ok(match code {
+                OpCode::Greater => u128::from(a > b),
+                OpCode::Less => u128::from(a < b),
+                _ => u128::from(a == b),
+            }),
+            _ if values.len() < 2 => Err(missing),
+            _ => Err(EvalError::ExtraOperands {
+                code,
+                found: values.len(),
+            }),
+        },
+        OpCode::Literal => Err(EvalError::LiteralOperator),
+    }
+}
+
+/// Evaluates an operator from the values of its operands
+type Operation = Box<dyn Fn(&[u128]) -> std::result::Result<u128, EvalError> + Send + Sync>;
+
+/// Evaluates packets, looking up what each operator does by its type ID.
+///
+/// Every type ID starts out with its BITS semantics, and any of them other
+/// than the literal ID can be replaced with `with_opcode`.
+pub struct Decoder {
+    /// indexed by type ID
+    operations: Vec<Operation>,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        let operations = (0..8)
+            .map(|id| {
+                let code = OpCode::try_from(id).expect("every 3 bit type ID is an opcode");
+                Box::new(move |values: &[u128]| builtin(code, values)) as Operation
+            })
+            .collect();
+
+        Self { operations }
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate operators with the type ID `id` using `f`, which is given the
+    /// values of the operands
+    pub fn with_opcode<F>(mut self, id: usize, f: F) -> Result<Self>
+    where
+        F: Fn(&[u128]) -> std::result::Result<u128, EvalError> + Send + Sync + 'static,
+    {
+        if id == OpCode::Literal.id() {
+            bail!("type ID {} is reserved for literals", id);
+        }
+
+        let slot = self
+            .operations
+            .get_mut(id)
+            .ok_or_else(|| anyhow!("type ID {} does not fit in 3 bits", id))?;
+        *slot = Box::new(f);
+
+        Ok(self)
+    }
+
+    /// The value of `packet`, with every operator looked up in the table
+    pub fn evaluate(&self, packet: &Packet) -> std::result::Result<u128, EvalError> {
+        match packet.type_id {
+            PacketType::Literal(v) => Ok(v as u128),
+            PacketType::Operator {
+                code, ref packets, ..
+            } => {
+                let values = packets
+                    .iter()
+                    .map(|p| self.evaluate(p))
+                    .collect::<std::result::Result<Vec<u128>, EvalError>>()?;
+
+                (self.operations[code.id()])(&values)
+            }
+        }
+    }
+
+    /// The sum of the values of the top level packets in `transmission`
+    pub fn evaluate_all(
+        &self,
+        transmission: &Transmission,
+    ) -> std::result::Result<u128, EvalError> {
+        transmission.packets.iter().try_fold(0_u128, |acc, p| {
+            acc.checked_add(self.evaluate(p)?)
+                .ok_or(EvalError::Overflow { code: OpCode::Sum })
+        })
+    }
 }
 
 impl fmt::Display for PacketType {
@@ -162,10 +372,104 @@ impl Packet {
         Self { version, type_id }
     }
 
+    /// Encode this packet on its own as hex, padded out to a whole byte
+    pub fn encode(&self) -> Result<String> {
+        let mut out = BitWriter::default();
+        self.write(&mut out)?;
+        Ok(out.to_hex())
+    }
+
+    /// Write the bits of this packet to `out`. Operators keep the length type
+    /// they were parsed with, with the length recomputed from their
+    /// sub-packets, unless the length does not fit, in which case they switch
+    /// to the other type.
+    fn write(&self, out: &mut BitWriter) -> Result<()> {
+        if self.version > 7 {
+            bail!("version {} does not fit in 3 bits", self.version);
+        }
+        out.push(self.version, 3);
+
+        match self.type_id {
+            PacketType::Literal(v) => {
+                out.push(4, 3);
+                let groups = (0..)
+                    .take_while(|n| *n == 0 || (*n < 16 && v >> (4 * n) > 0))
+                    .count();
+                for n in (0..groups).rev() {
+                    out.push(usize::from(n > 0), 1);
+                    out.push((v >> (4 * n)) & 0xF, 4);
+                }
+            }
+            PacketType::Operator {
+                code,
+                len,
+                ref packets,
+            } => {
+                if code == OpCode::Literal {
+                    bail!("operators cannot use the literal opcode");
+                }
+                out.push(code.id(), 3);
+
+                let mut sub = BitWriter::default();
+                for p in packets {
+                    p.write(&mut sub)?;
+                }
+
+                // a bit length of 0 can't be parsed, so empty operators have
+                // to count packets instead
+                let fits_bits = sub.len() < 1 << 15 && !packets.is_empty();
+                let fits_packets = packets.len() < 1 << 11;
+                let by_bits = match len {
+                    Length::Bits(_) => fits_bits || !fits_packets,
+                    Length::Packets(_) => !fits_packets,
+                };
+
+                if by_bits && fits_bits {
+                    out.push(0, 1);
+                    out.push(sub.len(), 15);
+                } else if fits_packets {
+                    out.push(1, 1);
+                    out.push(packets.len(), 11);
+                } else {
+                    bail!("too many sub-packets to encode: {}", packets.len());
+                }
+                out.append(&sub);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    pub fn type_id(&self) -> &PacketType {
+        &self.type_id
+    }
+
+    /// The opcode of this packet, `OpCode::Literal` for literals
+    pub fn code(&self) -> OpCode {
+        match self.type_id {
+            PacketType::Literal(_) => OpCode::Literal,
+            PacketType::Operator { code, .. } => code,
+        }
+    }
+
+    /// This packet and every packet nested inside it, parents before their
+    /// children
+    pub fn walk(&self) -> Walk<'_> {
+        Walk { stack: vec![self] }
+    }
+
     pub fn value(&self) -> usize {
         self.type_id.value()
     }
 
+    pub fn checked_value(&self) -> std::result::Result<u128, EvalError> {
+        self.type_id.checked_value()
+    }
+
     pub fn version_sum(&self) -> usize {
         let mut sum = self.version;
         if let PacketType::Operator { ref packets, .. } = self.type_id {
@@ -227,11 +531,153 @@ impl fmt::Display for Packet {
     }
 }
 
+/// A depth first walk over a packet and its sub-packets, in the order they
+/// appear in the transmission
+#[derive(Debug, Clone)]
+pub struct Walk<'a> {
+    stack: Vec<&'a Packet>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = &'a Packet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let p = self.stack.pop()?;
+        if let PacketType::Operator { ref packets, .. } = p.type_id {
+            self.stack.extend(packets.iter().rev());
+        }
+        Some(p)
+    }
+}
+
+/// Builds packets in code, filling in the lengths of operators so that the
+/// result is exactly what parsing its encoding would give.
+///
+/// Packets default to version 0, and operators to counting their
+/// sub-packets.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PacketBuilder {
+    version: usize,
+    kind: BuilderKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum BuilderKind {
+    Literal(usize),
+    Operator {
+        code: OpCode,
+        by_bits: bool,
+        children: Vec<PacketBuilder>,
+    },
+}
+
+impl PacketBuilder {
+    pub fn literal(value: usize) -> Self {
+        Self {
+            version: 0,
+            kind: BuilderKind::Literal(value),
+        }
+    }
+
+    pub fn operator<I>(code: OpCode, children: I) -> Self
+    where
+        I: IntoIterator<Item = PacketBuilder>,
+    {
+        Self {
+            version: 0,
+            kind: BuilderKind::Operator {
+                code,
+                by_bits: false,
+                children: children.into_iter().collect(),
+            },
+        }
+    }
+
+    pub fn version(mut self, version: usize) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Give the length of this operator in bits, rather than sub-packets. This
+    /// has no effect on literals.
+    pub fn by_bits(mut self) -> Self {
+        if let BuilderKind::Operator {
+            ref mut by_bits, ..
+        } = self.kind
+        {
+            *by_bits = true;
+        }
+        self
+    }
+
+    /// Build the packet, failing if it could not be encoded, or if a
+    /// comparison does not have exactly two sub-packets
+    pub fn build(&self) -> Result<Packet> {
+        if self.version > 7 {
+            bail!("version {} does not fit in 3 bits", self.version);
+        }
+
+        let (code, by_bits, children) = match self.kind {
+            BuilderKind::Literal(v) => {
+                return Ok(Packet::new(self.version, PacketType::Literal(v)))
+            }
+            BuilderKind::Operator {
+                code,
+                by_bits,
+                ref children,
+            } => (code, by_bits, children),
+        };
+
+        match code {
+            OpCode::Literal => bail!("operators cannot use the literal opcode"),
+            OpCode::Greater | OpCode::Less | OpCode::Equal if children.len() != 2 => {
+                bail!("{} takes two sub-packets, found {}", code, children.len())
+            }
+            _ => {}
+        }
+
+        let packets = children
+            .iter()
+            .map(|c| c.build())
+            .collect::<Result<Vec<Packet>>>()?;
+
+        let len = if by_bits {
+            let mut sub = BitWriter::default();
+            for p in &packets {
+                p.write(&mut sub)?;
+            }
+
+            if packets.is_empty() || sub.len() >= 1 << 15 {
+                bail!("a length of {} bits can't be encoded", sub.len());
+            }
+            Length::Bits(sub.len())
+        } else {
+            if packets.len() >= 1 << 11 {
+                bail!("a length of {} sub-packets can't be encoded", packets.len());
+            }
+            Length::Packets(packets.len())
+        };
+
+        Ok(Packet::new(
+            self.version,
+            PacketType::Operator { code, len, packets },
+        ))
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Transmission {
     packets: Vec<Packet>,
 }
 
+impl FromIterator<Packet> for Transmission {
+    fn from_iter<I: IntoIterator<Item = Packet>>(iter: I) -> Self {
+        Self {
+            packets: iter.into_iter().collect(),
+        }
+    }
+}
+
 impl Transmission {
     pub fn version_sum(&self) -> usize {
         self.packets.iter().fold(0, |acc, p| acc + p.version_sum())
@@ -241,18 +687,47 @@ impl Transmission {
         self.packets.iter().fold(0, |acc, p| acc + p.value())
     }
 
+    /// Like `value`, but widened to a `u128`, with every operation checked
+    pub fn checked_value(&self) -> std::result::Result<u128, EvalError> {
+        self.packets.iter().try_fold(0_u128, |acc, p| {
+            acc.checked_add(p.checked_value()?)
+                .ok_or(EvalError::Overflow { code: OpCode::Sum })
+        })
+    }
+
     pub fn packets(&self) -> &Vec<Packet> {
         &self.packets
     }
-}
 
-impl FromStr for Transmission {
-    type Err = anyhow::Error;
+    /// Every packet in the transmission, at any depth, for which `pred`
+    /// holds, in the order they appear
+    pub fn find_all<'a, P>(&'a self, pred: P) -> impl Iterator<Item = &'a Packet> + 'a
+    where
+        P: Fn(&Packet) -> bool + 'a,
+    {
+        self.packets
+            .iter()
+            .flat_map(Packet::walk)
+            .filter(move |p| pred(p))
+    }
 
-    fn from_str(input: &str) -> Result<Self> {
-        // convert all the hex digits to a string of bits.
-        // so, yeah. I realize that I should just operate on a byte array, but
-        // this just seemed easier given the time contstraint
+    pub fn by_version(&self, version: usize) -> impl Iterator<Item = &Packet> {
+        self.find_all(move |p| p.version == version)
+    }
+
+    pub fn by_opcode(&self, code: OpCode) -> impl Iterator<Item = &Packet> {
+        self.find_all(move |p| p.code() == code)
+    }
+
+    /// Literal packets whose value is strictly greater than `value`
+    pub fn literals_above(&self, value: usize) -> impl Iterator<Item = &Packet> {
+        self.find_all(move |p| matches!(p.type_id, PacketType::Literal(v) if v > value))
+    }
+
+    /// Parse `input` the way `from_str` used to, by expanding every hex digit
+    /// into four `'0'` or `'1'` characters first. This is several times
+    /// slower, and only kept to compare against.
+    pub fn parse_expanded(input: &str) -> Result<Self> {
         let s = input
             .chars()
             .map(|ch| {
@@ -263,8 +738,43 @@ impl FromStr for Transmission {
             .collect::<Result<Vec<String>>>()?
             .join("");
 
+        let (_, packets) =
+            many1(expanded::packet)(&s).map_err(|_| anyhow!("Failed to parse input"))?;
+        Ok(Self { packets })
+    }
+
+    /// Encode every packet, back to back, as hex padded out to a whole byte
+    pub fn to_hex(&self) -> Result<String> {
+        let mut out = BitWriter::default();
+        for p in &self.packets {
+            p.write(&mut out)?;
+        }
+        Ok(out.to_hex())
+    }
+}
+
+impl FromStr for Transmission {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        // pack the hex digits into bytes, with an odd trailing digit taking
+        // the high half of the last byte
+        let digits = input
+            .chars()
+            .map(|ch| {
+                ch.to_digit(16)
+                    .map(|d| d as u8)
+                    .ok_or_else(|| anyhow!("Invalid characters in input"))
+            })
+            .collect::<Result<Vec<u8>>>()?;
+        let bytes: Vec<u8> = digits
+            .chunks(2)
+            .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+            .collect();
+
         // we have to do this because of the lifetime on the value from the parser
-        let (_, packets) = (many1(packet)(&s)).map_err(|_| anyhow!("Failed to parse input"))?;
+        let (_, packets) =
+            (many1(packet)((&bytes, 0))).map_err(|_| anyhow!("Failed to parse input"))?;
         Ok(Self { packets })
     }
 }
@@ -277,6 +787,138 @@ impl TryFrom<&Vec<String>> for Transmission {
     }
 }
 
+/// The fewest hex digits a `StreamingDecoder` reads at a time
+const MIN_READ: usize = 64;
+
+/// Decodes top level packets one at a time, reading hex digits from a source
+/// only as they are needed, so the whole transmission never has to be held at
+/// once. Whitespace in the source is skipped.
+///
+/// There is no way to tell a packet that is still arriving from a malformed
+/// one, so a malformed packet is only reported once the source runs out.
+/// Trailing zero bits after the last packet are treated as padding.
+pub struct StreamingDecoder<'a> {
+    source: Box<dyn Iterator<Item = Result<char>> + 'a>,
+    /// digits that have been read but not yet parsed, packed into bytes
+    bytes: Vec<u8>,
+    /// the next unread bit in the first byte
+    offset: usize,
+    /// the first digit of a byte that is still waiting on its second
+    pending: Option<u8>,
+    exhausted: bool,
+    failed: bool,
+}
+
+impl<'a> StreamingDecoder<'a> {
+    /// Decode the hex digits produced by `chars`
+    pub fn from_chars<I>(chars: I) -> Self
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: 'a,
+    {
+        Self::new(Box::new(chars.into_iter().map(Ok)))
+    }
+
+    /// Decode the hex digits read from `reader`
+    pub fn from_reader<R: Read + 'a>(reader: R) -> Self {
+        Self::new(Box::new(
+            BufReader::new(reader)
+                .bytes()
+                .map(|b| b.map(char::from).map_err(anyhow::Error::from)),
+        ))
+    }
+
+    fn new(source: Box<dyn Iterator<Item = Result<char>> + 'a>) -> Self {
+        Self {
+            source,
+            bytes: Vec::new(),
+            offset: 0,
+            pending: None,
+            exhausted: false,
+            failed: false,
+        }
+    }
+
+    /// Read at least as many digits as are already buffered, so that
+    /// re-parsing a packet that spans many reads stays linear overall
+    fn fill(&mut self) -> Result<()> {
+        let want = (self.bytes.len() * 2).max(MIN_READ);
+        let mut read = 0;
+        while read < want {
+            let ch = match self.source.next() {
+                Some(ch) => ch?,
+                None => {
+                    self.exhausted = true;
+                    if let Some(high) = self.pending.take() {
+                        self.bytes.push(high << 4);
+                    }
+                    break;
+                }
+            };
+
+            if ch.is_whitespace() {
+                continue;
+            }
+
+            let digit =
+                ch.to_digit(16)
+                    .ok_or_else(|| anyhow!("Invalid characters in input"))? as u8;
+            match self.pending.take() {
+                Some(high) => self.bytes.push((high << 4) | digit),
+                None => self.pending = Some(digit),
+            }
+            read += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Whether every buffered bit that has not been parsed is zero
+    fn only_padding(&self) -> bool {
+        match self.bytes.split_first() {
+            Some((first, rest)) => first << self.offset == 0 && rest.iter().all(|b| *b == 0),
+            None => true,
+        }
+    }
+
+    fn fail(&mut self, e: anyhow::Error) -> Option<Result<Packet>> {
+        self.failed = true;
+        self.bytes.clear();
+        Some(Err(e))
+    }
+}
+
+impl<'a> Iterator for StreamingDecoder<'a> {
+    type Item = Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if let Ok(((rest, offset), p)) = packet((&self.bytes, self.offset)) {
+                let used = self.bytes.len() - rest.len();
+                self.bytes.drain(..used);
+                self.offset = offset;
+                return Some(Ok(p));
+            }
+
+            if self.exhausted {
+                if self.only_padding() {
+                    self.failed = true;
+                    return None;
+                }
+                return self.fail(anyhow!("Failed to parse input"));
+            }
+
+            if let Err(e) = self.fill() {
+                return self.fail(e);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct TransmissionWrapper {
     input: Vec<String>,
@@ -320,40 +962,36 @@ impl Solver for TransmissionWrapper {
 }
 
 // So let's take this opportunity to play around with nom a bit
-// Parsers below
+// Parsers below, all operating on individual bits
 
-// Used for converting string of binary characters to usize
-fn from_bin(input: &str) -> Result<usize, ParseIntError> {
-    usize::from_str_radix(input, 2)
+// The number of bits left in the input
+fn remaining(input: Bits) -> usize {
+    input.0.len() * 8 - input.1
 }
 
 // extract a version u8 from the input
-fn version(input: &str) -> IResult<&str, usize> {
-    map_res(take(3_usize), from_bin)(input)
+fn version(input: Bits) -> IResult<Bits, usize> {
+    take(3_usize)(input)
 }
 
 // Length type 0 has 15 bits specifying a number
-fn length_bits(input: &str) -> IResult<&str, Length> {
-    let (input, v) = map_res(preceded(tag("0"), take(15_usize)), from_bin)(input)?;
-
-    Ok((input, Length::Bits(v)))
+fn length_bits(input: Bits) -> IResult<Bits, Length> {
+    map(preceded(tag(0, 1_usize), take(15_usize)), Length::Bits)(input)
 }
 
 // Length type 1 has 11 bits specifying a number
-fn length_packets(input: &str) -> IResult<&str, Length> {
-    let (input, v) = map_res(preceded(tag("1"), take(11_usize)), from_bin)(input)?;
-
-    Ok((input, Length::Packets(v)))
+fn length_packets(input: Bits) -> IResult<Bits, Length> {
+    map(preceded(tag(1, 1_usize), take(11_usize)), Length::Packets)(input)
 }
 
 // extract the Length value for an operator
-fn operator_length(input: &str) -> IResult<&str, Length> {
+fn operator_length(input: Bits) -> IResult<Bits, Length> {
     alt((length_bits, length_packets))(input)
 }
 
 // extract a PacketType from the input
-fn packet_type(input: &str) -> IResult<&str, PacketType> {
-    let (input, code) = map_res(map_res(take(3_usize), from_bin), OpCode::try_from)(input)?;
+fn packet_type(input: Bits) -> IResult<Bits, PacketType> {
+    let (input, code) = map_res(take(3_usize), |v: usize| OpCode::try_from(v))(input)?;
 
     match code {
         OpCode::Literal => {
@@ -363,7 +1001,7 @@ fn packet_type(input: &str) -> IResult<&str, PacketType> {
         x => {
             // if we're not 4, we need to parse out the proper operator
             let (input, len) = operator_length(input)?;
-            let (input, packets) = len.sub_packets(input)?;
+            let (input, packets) = len.sub_packets_bits(input)?;
             Ok((
                 input,
                 PacketType::Operator {
@@ -377,20 +1015,20 @@ fn packet_type(input: &str) -> IResult<&str, PacketType> {
 }
 
 // extract a Packet the input
-fn packet(input: &str) -> IResult<&str, Packet> {
+fn packet(input: Bits) -> IResult<Bits, Packet> {
     let (input, (version, packet_type)) = tuple((version, packet_type))(input)?;
     Ok((input, Packet::new(version, packet_type)))
 }
 
-fn literal_group(input: &str) -> IResult<&str, usize> {
-    map_res(preceded(tag("1"), take(4_usize)), from_bin)(input)
+fn literal_group(input: Bits) -> IResult<Bits, usize> {
+    preceded(tag(1, 1_usize), take(4_usize))(input)
 }
 
-fn literal_end_group(input: &str) -> IResult<&str, usize> {
-    map_res(preceded(tag("0"), take(4_usize)), from_bin)(input)
+fn literal_end_group(input: Bits) -> IResult<Bits, usize> {
+    preceded(tag(0, 1_usize), take(4_usize))(input)
 }
 
-fn literal_value(input: &str) -> IResult<&str, usize> {
+fn literal_value(input: Bits) -> IResult<Bits, usize> {
     let (input, (groups, end)) = tuple((
         fold_many0(literal_group, || 0_usize, |acc, item| (acc << 4) + item),
         literal_end_group,
@@ -399,9 +1037,142 @@ fn literal_value(input: &str) -> IResult<&str, usize> {
     Ok((input, (groups << 4) + end))
 }
 
+// The original parsers, over a string with a '0' or '1' for every bit. These
+// are much slower, but kept for comparison and for `Length::sub_packets`
+mod expanded {
+    use std::{convert::TryFrom, num::ParseIntError};
+
+    use nom::{
+        branch::alt,
+        bytes::complete::{tag, take},
+        combinator::map_res,
+        multi::fold_many0,
+        sequence::{preceded, tuple},
+        IResult,
+    };
+
+    use super::{Length, OpCode, Packet, PacketType};
+
+    // Used for converting string of binary characters to usize
+    fn from_bin(input: &str) -> Result<usize, ParseIntError> {
+        usize::from_str_radix(input, 2)
+    }
+
+    // extract a version u8 from the input
+    fn version(input: &str) -> IResult<&str, usize> {
+        map_res(take(3_usize), from_bin)(input)
+    }
+
+    // Length type 0 has 15 bits specifying a number
+    fn length_bits(input: &str) -> IResult<&str, Length> {
+        let (input, v) = map_res(preceded(tag("0"), take(15_usize)), from_bin)(input)?;
+
+        Ok((input, Length::Bits(v)))
+    }
+
+    // Length type 1 has 11 bits specifying a number
+    fn length_packets(input: &str) -> IResult<&str, Length> {
+        let (input, v) = map_res(preceded(tag("1"), take(11_usize)), from_bin)(input)?;
+
+        Ok((input, Length::Packets(v)))
+    }
+
+    // extract the Length value for an operator
+    fn operator_length(input: &str) -> IResult<&str, Length> {
+        alt((length_bits, length_packets))(input)
+    }
+
+    // extract a PacketType from the input
+    fn packet_type(input: &str) -> IResult<&str, PacketType> {
+        let (input, code) = map_res(map_res(take(3_usize), from_bin), OpCode::try_from)(input)?;
+
+        match code {
+            OpCode::Literal => {
+                let (input, val) = literal_value(input)?;
+                Ok((input, PacketType::Literal(val)))
+            }
+            x => {
+                // if we're not 4, we need to parse out the proper operator
+                let (input, len) = operator_length(input)?;
+                let (input, packets) = len.sub_packets(input)?;
+                Ok((
+                    input,
+                    PacketType::Operator {
+                        code: x,
+                        len,
+                        packets,
+                    },
+                ))
+            }
+        }
+    }
+
+    // extract a Packet the input
+    pub(super) fn packet(input: &str) -> IResult<&str, Packet> {
+        let (input, (version, packet_type)) = tuple((version, packet_type))(input)?;
+        Ok((input, Packet::new(version, packet_type)))
+    }
+
+    fn literal_group(input: &str) -> IResult<&str, usize> {
+        map_res(preceded(tag("1"), take(4_usize)), from_bin)(input)
+    }
+
+    fn literal_end_group(input: &str) -> IResult<&str, usize> {
+        map_res(preceded(tag("0"), take(4_usize)), from_bin)(input)
+    }
+
+    fn literal_value(input: &str) -> IResult<&str, usize> {
+        let (input, (groups, end)) = tuple((
+            fold_many0(literal_group, || 0_usize, |acc, item| (acc << 4) + item),
+            literal_end_group,
+        ))(input)?;
+
+        Ok((input, (groups << 4) + end))
+    }
+}
+
+// Encoding below
+
+/// Collects bits, most significant first, for encoding packets
+#[derive(Debug, Clone, Default)]
+struct BitWriter {
+    bits: Vec<bool>,
+}
+
+impl BitWriter {
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Push the lowest `width` bits of `value`
+    fn push(&mut self, value: usize, width: usize) {
+        self.bits
+            .extend((0..width).rev().map(|shift| (value >> shift) & 1 == 1));
+    }
+
+    fn append(&mut self, other: &BitWriter) {
+        self.bits.extend_from_slice(&other.bits);
+    }
+
+    fn to_hex(&self) -> String {
+        self.bits
+            .chunks(8)
+            .map(|chunk| {
+                let byte = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0_u8, |acc, (i, b)| acc | (u8::from(*b) << (7 - i)));
+                format!("{:02X}", byte)
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     mod transmission {
+        use crate::rng::xorshift;
+
         use super::super::*;
 
         #[test]
@@ -457,24 +1228,499 @@ mod tests {
             let t = Transmission::from_str(input).expect("Could not make transmission");
             assert_eq!(t.value(), 1);
         }
+
+        #[test]
+        fn encode() {
+            // these are all packed as tightly as they can be
+            for input in [
+                "D2FE28",
+                "38006F45291200",
+                "EE00D40C823060",
+                "8A004A801A8002F478",
+                "620080001611562C8802118E34",
+                "C0015000016115A2E0802F182340",
+                "A0016C880162017C3686B18A3D4780",
+                "C200B40A82",
+                "04005AC33890",
+                "9C0141080250320F1802104A08",
+            ] {
+                let t = Transmission::from_str(input).expect("Could not make transmission");
+                assert_eq!(t.to_hex().expect("could not encode"), input);
+                assert_eq!(t.packets()[0].encode().expect("could not encode"), input);
+            }
+
+            let literal = |v| Packet::new(1, PacketType::Literal(v));
+            assert_eq!(literal(0).encode().unwrap(), "3000");
+            assert_eq!(literal(usize::MAX).encode().unwrap().len(), 22);
+            assert!(Packet::new(8, PacketType::Literal(1)).encode().is_err());
+
+            // lengths that don't fit switch type
+            let many = Packet::new(
+                0,
+                PacketType::Operator {
+                    code: OpCode::Sum,
+                    len: Length::Packets(0),
+                    packets: vec![literal(1); 2048],
+                },
+            );
+            let t = Transmission::from_str(&many.encode().unwrap()).unwrap();
+            assert!(matches!(
+                t.packets()[0].type_id,
+                PacketType::Operator {
+                    len: Length::Bits(22528),
+                    ..
+                }
+            ));
+
+            let empty = Packet::new(
+                0,
+                PacketType::Operator {
+                    code: OpCode::Sum,
+                    len: Length::Bits(0),
+                    packets: Vec::new(),
+                },
+            );
+            let t = Transmission::from_str(&empty.encode().unwrap()).unwrap();
+            assert!(matches!(
+                t.packets()[0].type_id,
+                PacketType::Operator {
+                    len: Length::Packets(0),
+                    ..
+                }
+            ));
+        }
+
+        #[test]
+        fn round_trip() {
+            let mut next = xorshift(0x1f83_d9ab_fb41_bd6b);
+
+            fn random(next: &mut dyn FnMut(usize) -> usize, depth: usize) -> Packet {
+                let version = next(8);
+                if depth == 0 || next(3) == 0 {
+                    let v = next(1 << 20) << (4 * next(10));
+                    return Packet::new(version, PacketType::Literal(v));
+                }
+
+                let code = OpCode::try_from([0, 1, 2, 3, 5, 6, 7][next(7)]).unwrap();
+                let count = match code {
+                    OpCode::Greater | OpCode::Less | OpCode::Equal => 2,
+                    _ => next(4) + 1,
+                };
+                let len = if next(2) == 0 {
+                    Length::Bits(0)
+                } else {
+                    Length::Packets(0)
+                };
+                let packets = (0..count).map(|_| random(next, depth - 1)).collect();
+
+                Packet::new(version, PacketType::Operator { code, len, packets })
+            }
+
+            for _ in 0..200 {
+                let count = next(3) + 1;
+                let packets = (0..count).map(|_| random(&mut next, 4)).collect();
+                let original = Transmission { packets };
+
+                // the lengths get filled in by the first round trip, after
+                // which encoding and parsing should agree exactly
+                let hex = original.to_hex().expect("could not encode");
+                let parsed = Transmission::from_str(&hex).expect("could not parse");
+                assert_eq!(parsed.to_hex().expect("could not encode"), hex);
+                assert_eq!(
+                    Transmission::from_str(&hex).expect("could not parse"),
+                    parsed
+                );
+
+                assert_eq!(parsed.version_sum(), original.version_sum());
+                for (a, b) in parsed.packets().iter().zip(original.packets()) {
+                    assert_eq!(a.to_string(), b.to_string());
+                }
+            }
+        }
+
+        #[test]
+        fn checked_value() {
+            for input in [
+                "C200B40A82",
+                "04005AC33890",
+                "880086C3E88112",
+                "CE00C43D881120",
+                "D8005AC2A8F0",
+                "F600BC2D8F",
+                "9C005AC2F8F0",
+                "9C0141080250320F1802104A08",
+            ] {
+                let t = Transmission::from_str(input).expect("Could not make transmission");
+                assert_eq!(t.checked_value(), Ok(t.value() as u128));
+            }
+
+            let literal = |v| Packet::new(0, PacketType::Literal(v));
+            let operator = |code, packets| {
+                Packet::new(
+                    0,
+                    PacketType::Operator {
+                        code,
+                        len: Length::Packets(0),
+                        packets,
+                    },
+                )
+            };
+
+            // too big for a usize, but not a u128
+            let big = operator(OpCode::Product, vec![literal(usize::MAX), literal(4)]);
+            assert_eq!(big.checked_value(), Ok(usize::MAX as u128 * 4));
+
+            let huge = operator(OpCode::Product, vec![big.clone(), big.clone(), big]);
+            assert_eq!(
+                huge.checked_value(),
+                Err(EvalError::Overflow {
+                    code: OpCode::Product
+                })
+            );
+
+            // errors come out of nested packets
+            let nested = operator(OpCode::Sum, vec![literal(1), huge]);
+            assert!(nested.checked_value().is_err());
+
+            assert_eq!(
+                operator(OpCode::Minimum, vec![]).checked_value(),
+                Err(EvalError::MissingOperands {
+                    code: OpCode::Minimum,
+                    found: 0
+                })
+            );
+            assert_eq!(
+                operator(OpCode::Less, vec![literal(1)]).checked_value(),
+                Err(EvalError::MissingOperands {
+                    code: OpCode::Less,
+                    found: 1
+                })
+            );
+            assert_eq!(
+                operator(OpCode::Equal, vec![literal(1); 3]).checked_value(),
+                Err(EvalError::ExtraOperands {
+                    code: OpCode::Equal,
+                    found: 3
+                })
+            );
+            assert_eq!(
+                operator(OpCode::Literal, vec![literal(1)]).checked_value(),
+                Err(EvalError::LiteralOperator)
+            );
+            assert_eq!(operator(OpCode::Sum, vec![]).checked_value(), Ok(0));
+            assert_eq!(operator(OpCode::Product, vec![]).checked_value(), Ok(1));
+        }
+
+        #[test]
+        fn decoder() {
+            let inputs = [
+                "C200B40A82",
+                "04005AC33890",
+                "880086C3E88112",
+                "CE00C43D881120",
+                "D8005AC2A8F0",
+                "F600BC2D8F",
+                "9C005AC2F8F0",
+                "9C0141080250320F1802104A08",
+            ];
+
+            // the defaults are the puzzle's rules
+            let decoder = Decoder::new();
+            for input in inputs {
+                let t = Transmission::from_str(input).expect("Could not make transmission");
+                assert_eq!(decoder.evaluate_all(&t), t.checked_value());
+            }
+
+            // sum of squares instead of a sum, and a comparison that can't
+            // be satisfied
+            let decoder = Decoder::new()
+                .with_opcode(0, |values| Ok(values.iter().map(|v| v * v).sum()))
+                .unwrap()
+                .with_opcode(OpCode::Less.id(), |values| {
+                    if values.len() == 2 {
+                        Ok(0)
+                    } else {
+                        Err(EvalError::MissingOperands {
+                            code: OpCode::Less,
+                            found: values.len(),
+                        })
+                    }
+                })
+                .unwrap();
+
+            let t = Transmission::from_str("C200B40A82").unwrap();
+            assert_eq!(decoder.evaluate_all(&t), Ok(1 + 4));
+
+            // 1 + 3 == 2 * 2, but now 1 + 9 != 4
+            let t = Transmission::from_str("9C0141080250320F1802104A08").unwrap();
+            assert_eq!(t.value(), 1);
+            assert_eq!(decoder.evaluate_all(&t), Ok(0));
+
+            let t = Transmission::from_str("D8005AC2A8F0").unwrap();
+            assert_eq!(t.value(), 1);
+            assert_eq!(decoder.evaluate_all(&t), Ok(0));
+
+            // the others keep their usual meaning
+            let t = Transmission::from_str("04005AC33890").unwrap();
+            assert_eq!(decoder.evaluate_all(&t), Ok(54));
+
+            assert!(Decoder::new().with_opcode(4, |_| Ok(0)).is_err());
+            assert!(Decoder::new().with_opcode(8, |_| Ok(0)).is_err());
+        }
+
+        #[test]
+        fn builder() {
+            // the example from the puzzle description
+            let packet = PacketBuilder::operator(
+                OpCode::Less,
+                vec![
+                    PacketBuilder::literal(10).version(6),
+                    PacketBuilder::literal(20).version(2),
+                ],
+            )
+            .version(1)
+            .by_bits()
+            .build()
+            .expect("could not build packet");
+            assert_eq!(packet.encode().unwrap(), "38006F45291200");
+
+            let packet = PacketBuilder::operator(
+                OpCode::Maximum,
+                (1..=3).map(|v| PacketBuilder::literal(v).version([2, 4, 1][v - 1])),
+            )
+            .version(7)
+            .build()
+            .expect("could not build packet");
+            assert_eq!(packet.encode().unwrap(), "EE00D40C823060");
+
+            // built packets are exactly what parsing their encoding gives
+            let t: Transmission = [
+                PacketBuilder::operator(
+                    OpCode::Product,
+                    vec![
+                        PacketBuilder::operator(
+                            OpCode::Sum,
+                            vec![PacketBuilder::literal(2), PacketBuilder::literal(3)],
+                        )
+                        .by_bits(),
+                        PacketBuilder::literal(7).version(3),
+                    ],
+                ),
+                PacketBuilder::literal(1 << 40).version(5),
+            ]
+            .iter()
+            .map(|b| b.build().expect("could not build packet"))
+            .collect();
+            assert_eq!(t.value(), 35 + (1 << 40));
+            assert_eq!(t.version_sum(), 8);
+            assert_eq!(Transmission::from_str(&t.to_hex().unwrap()).unwrap(), t);
+
+            assert!(PacketBuilder::literal(1).version(8).build().is_err());
+            assert!(PacketBuilder::operator(OpCode::Literal, vec![])
+                .build()
+                .is_err());
+            assert!(
+                PacketBuilder::operator(OpCode::Equal, vec![PacketBuilder::literal(1)])
+                    .build()
+                    .is_err()
+            );
+            assert!(PacketBuilder::operator(OpCode::Sum, vec![])
+                .by_bits()
+                .build()
+                .is_err());
+            assert!(PacketBuilder::operator(OpCode::Sum, vec![]).build().is_ok());
+            assert!(
+                PacketBuilder::operator(OpCode::Sum, (0..2048).map(PacketBuilder::literal))
+                    .build()
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn find_all() {
+            // the example from the puzzle description: 1 + 3 == 2 * 2
+            let t = Transmission::from_str("9C0141080250320F1802104A08").unwrap();
+            let codes: Vec<OpCode> = t.find_all(|_| true).map(Packet::code).collect();
+            assert_eq!(
+                codes,
+                vec![
+                    OpCode::Equal,
+                    OpCode::Sum,
+                    OpCode::Literal,
+                    OpCode::Literal,
+                    OpCode::Product,
+                    OpCode::Literal,
+                    OpCode::Literal,
+                ]
+            );
+
+            let values: Vec<usize> = t.by_opcode(OpCode::Literal).map(Packet::value).collect();
+            assert_eq!(values, vec![1, 3, 2, 2]);
+            assert_eq!(t.literals_above(2).count(), 1);
+            assert_eq!(t.literals_above(3).count(), 0);
+            assert_eq!(t.by_opcode(OpCode::Maximum).count(), 0);
+
+            let total: usize = (0..8).map(|v| v * t.by_version(v).count()).sum();
+            assert_eq!(total, t.version_sum());
+
+            // top level packets are searched as well as nested ones
+            let t = Transmission::from_str("D2FE28").unwrap();
+            assert_eq!(t.find_all(|p| p.version() == 6).count(), 1);
+            assert_eq!(t.literals_above(2020).count(), 1);
+        }
+
+        #[test]
+        fn streaming() {
+            for input in [
+                "D2FE28",
+                "38006F45291200",
+                "8A004A801A8002F478",
+                "C0015000016115A2E0802F182340",
+                "9C0141080250320F1802104A08",
+            ] {
+                let t = Transmission::from_str(input).expect("Could not make transmission");
+                let packets = StreamingDecoder::from_chars(input.chars())
+                    .collect::<Result<Vec<Packet>>>()
+                    .expect("could not decode");
+                assert_eq!(&packets, t.packets());
+            }
+
+            // lots of top level packets, back to back, from a reader
+            let packets: Vec<Packet> =
+                ["D2FE28", "38006F45291200", "A0016C880162017C3686B18A3D4780"]
+                    .iter()
+                    .cycle()
+                    .take(300)
+                    .map(|input| {
+                        Transmission::from_str(input)
+                            .expect("Could not make transmission")
+                            .packets[0]
+                            .clone()
+                    })
+                    .collect();
+            let hex = Transmission {
+                packets: packets.clone(),
+            }
+            .to_hex()
+            .expect("could not encode");
+
+            let reader = std::io::Cursor::new(format!("{}\n", hex).into_bytes());
+            let mut decoder = StreamingDecoder::from_reader(reader);
+            for expected in packets.iter() {
+                assert_eq!(
+                    &decoder.next().unwrap().expect("could not decode"),
+                    expected
+                );
+            }
+            assert!(decoder.next().is_none());
+
+            // packets are available before the source runs out
+            let mut decoder =
+                StreamingDecoder::from_chars(hex.chars().chain(std::iter::repeat('G')));
+            assert_eq!(
+                &decoder.next().unwrap().expect("could not decode"),
+                &packets[0]
+            );
+
+            // errors end the stream
+            let mut decoder = StreamingDecoder::from_chars("D2FE28G".chars());
+            assert!(decoder.next().unwrap().is_err());
+            assert!(decoder.next().is_none());
+
+            let mut decoder = StreamingDecoder::from_chars("D2FE28D2FE".chars());
+            assert!(decoder.next().unwrap().is_ok());
+            assert!(decoder.next().unwrap().is_err());
+            assert!(decoder.next().is_none());
+
+            assert!(StreamingDecoder::from_chars("".chars()).next().is_none());
+        }
+
+        #[test]
+        fn invalid() {
+            assert!(Transmission::from_str("C200B40G82").is_err());
+            assert!(Transmission::from_str("").is_err());
+
+            // truncated in the middle of a sub-packet
+            assert!(Transmission::from_str("C200B40A").is_err());
+
+            // an odd number of digits is fine, so long as the packets fit
+            let t = Transmission::from_str("D2FE28").expect("Could not make transmission");
+            let odd = Transmission::from_str("D2FE280").expect("Could not make transmission");
+            assert_eq!(t, odd);
+            assert_eq!(odd.value(), 2021);
+        }
+
+        #[test]
+        fn expanded() {
+            for input in [
+                "8A004A801A8002F478",
+                "620080001611562C8802118E34",
+                "C0015000016115A2E0802F182340",
+                "A0016C880162017C3686B18A3D4780",
+                "9C0141080250320F1802104A08",
+                "D2FE28",
+            ] {
+                assert_eq!(
+                    Transmission::parse_expanded(input).expect("Could not make transmission"),
+                    Transmission::from_str(input).expect("Could not make transmission")
+                );
+            }
+            assert!(Transmission::parse_expanded("C200B40G82").is_err());
+
+            // two literals, 10 and 20, in 27 bits
+            let input = "1101000101001010010001001001";
+            let (rest, packets) = Length::Bits(27)
+                .sub_packets(input)
+                .expect("Could not parse sub-packets");
+            assert_eq!(rest, "1");
+            assert_eq!(
+                packets,
+                vec![
+                    Packet::new(6, PacketType::Literal(10)),
+                    Packet::new(2, PacketType::Literal(20)),
+                ]
+            );
+            assert!(Length::Packets(3).sub_packets(input).is_err());
+        }
     }
 
     mod parsers {
         use super::super::*;
 
+        // run `parser` over a string of '0' and '1', returning the unread part
+        // of the string. Reading past the end of the string counts as failing,
+        // even though the bytes are padded out with zeros.
+        fn parse<T>(parser: impl Fn(Bits) -> IResult<Bits, T>, s: &str) -> Option<(&str, T)> {
+            let bytes: Vec<u8> = s
+                .as_bytes()
+                .chunks(8)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0, |acc, (i, b)| acc | ((b - b'0') << (7 - i)))
+                })
+                .collect();
+
+            let (rest, v) = parser((&bytes, 0)).ok()?;
+            let consumed = bytes.len() * 8 - remaining(rest);
+            if consumed > s.len() {
+                None
+            } else {
+                Some((&s[consumed..], v))
+            }
+        }
+
         #[test]
         fn parse_version() {
             let input = "100";
-            assert_eq!(version(input).unwrap(), ("", 4));
+            assert_eq!(parse(version, input).unwrap(), ("", 4));
 
             let input = "1011";
-            assert_eq!(version(input).unwrap(), ("1", 5));
+            assert_eq!(parse(version, input).unwrap(), ("1", 5));
 
             let input = "10";
-            assert!(version(input).is_err());
-
-            let input = "1A0";
-            assert!(version(input).is_err());
+            assert!(parse(version, input).is_none());
         }
 
         #[test]
@@ -488,7 +1734,7 @@ mod tests {
                     Packet::new(2, PacketType::Literal(20)),
                 ],
             };
-            assert_eq!(packet_type(input).unwrap(), ("10011", expected));
+            assert_eq!(parse(packet_type, input).unwrap(), ("10011", expected));
 
             let input = "01110000000001101010000001100100000100011000001110011";
             let expected = PacketType::Operator {
@@ -500,19 +1746,20 @@ mod tests {
                     Packet::new(1, PacketType::Literal(3)),
                 ],
             };
-            assert_eq!(packet_type(input).unwrap(), ("10011", expected));
+            assert_eq!(parse(packet_type, input).unwrap(), ("10011", expected));
 
             let input = "100101111111000101000";
             assert_eq!(
-                packet_type(input).unwrap(),
+                parse(packet_type, input).unwrap(),
                 ("000", PacketType::Literal(2021))
             );
 
             let input = "10";
-            assert!(packet_type(input).is_err());
+            assert!(parse(packet_type, input).is_none());
 
-            let input = "1A0";
-            assert!(packet_type(input).is_err());
+            // sub-packets overrunning their length
+            let input = "110000000000001101011010001010010100100010010010011";
+            assert!(parse(packet_type, input).is_none());
         }
 
         #[test]
@@ -530,27 +1777,27 @@ mod tests {
                     ],
                 },
             );
-            assert_eq!(packet(input).unwrap(), ("10011", expected));
+            assert_eq!(parse(packet, input).unwrap(), ("10011", expected));
 
             let input = "110100101111111000101000";
             let expected = Packet::new(6, PacketType::Literal(2021));
-            assert_eq!(packet(input).unwrap(), ("000", expected));
+            assert_eq!(parse(packet, input).unwrap(), ("000", expected));
 
             let input = "11111";
-            assert!(packet(input).is_err());
+            assert!(parse(packet, input).is_none());
         }
 
         #[test]
         fn parse_literal_value() {
             let input = "10111111100010111000";
-            assert_eq!(literal_value(input).unwrap(), ("11000", 2021));
+            assert_eq!(parse(literal_value, input).unwrap(), ("11000", 2021));
 
             let input = "0011111000";
-            assert_eq!(literal_value(input).unwrap(), ("11000", 7));
+            assert_eq!(parse(literal_value, input).unwrap(), ("11000", 7));
 
             // missing end group
             let input = "1011111000";
-            assert!(literal_value(input).is_err());
+            assert!(parse(literal_value, input).is_none());
         }
     }
 }
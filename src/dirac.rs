@@ -7,6 +7,108 @@ pub const BOARD_MAX: usize = 10;
 // [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
 // [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
 
+/// The rules of a game: a die with `die_sides` sides is rolled `rolls_per_turn`
+/// times a turn, players move around a track of `track_length` spaces, and the
+/// first to reach `target` points wins.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct GameConfig {
+    die_sides: usize,
+    rolls_per_turn: usize,
+    target: usize,
+    track_length: usize,
+}
+
+impl GameConfig {
+    pub fn new(
+        die_sides: usize,
+        rolls_per_turn: usize,
+        target: usize,
+        track_length: usize,
+    ) -> Result<Self> {
+        if die_sides == 0 {
+            bail!("A die needs at least one side");
+        }
+
+        if rolls_per_turn == 0 {
+            bail!("Players must roll at least once per turn");
+        }
+
+        if target == 0 {
+            bail!("The target score must be positive");
+        }
+
+        if track_length == 0 {
+            bail!("The track needs at least one space");
+        }
+
+        Ok(Self {
+            die_sides,
+            rolls_per_turn,
+            target,
+            track_length,
+        })
+    }
+
+    /// The puzzle's deterministic game: three rolls of a d100 a turn, to 1000
+    /// points.
+    pub fn deterministic() -> Self {
+        Self {
+            die_sides: 100,
+            rolls_per_turn: 3,
+            target: 1000,
+            track_length: BOARD_MAX,
+        }
+    }
+
+    /// The puzzle's quantum game: three rolls of a d3 a turn, to 21 points.
+    pub fn quantum() -> Self {
+        Self {
+            die_sides: 3,
+            rolls_per_turn: 3,
+            target: 21,
+            track_length: BOARD_MAX,
+        }
+    }
+
+    pub fn die_sides(&self) -> usize {
+        self.die_sides
+    }
+
+    pub fn rolls_per_turn(&self) -> usize {
+        self.rolls_per_turn
+    }
+
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    pub fn track_length(&self) -> usize {
+        self.track_length
+    }
+
+    /// Every total a turn's rolls of a fair die can add up to, as (frequency
+    /// of value, value), in order of value.
+    pub fn roll_frequencies(&self) -> Vec<(u128, usize)> {
+        QuantumDie::fair(self.die_sides)
+            .rolled(self.rolls_per_turn)
+            .faces()
+            .to_vec()
+    }
+
+    /// Make sure every player starts on the track.
+    fn check_players(&self, players: &[Player]) -> Result<()> {
+        if let Some(p) = players.iter().find(|p| p.pos >= self.track_length) {
+            bail!(
+                "Starting position {} is not on a track of length {}",
+                p.pos + 1,
+                self.track_length
+            );
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
 pub struct Player {
     score: usize,
@@ -14,15 +116,15 @@ pub struct Player {
 }
 
 impl Player {
-    pub fn turn(&mut self, move_dist: usize) -> usize {
-        self.pos = (self.pos + move_dist) % BOARD_MAX;
+    pub fn turn(&mut self, move_dist: usize, track_length: usize) -> usize {
+        self.pos = (self.pos + move_dist) % track_length;
         self.score += self.pos + 1;
         self.score
     }
 
-    pub fn pretend(&self, move_dist: usize) -> Self {
+    pub fn pretend(&self, move_dist: usize, track_length: usize) -> Self {
         let mut new = *self;
-        new.turn(move_dist);
+        new.turn(move_dist, track_length);
         new
     }
 }
@@ -43,10 +145,28 @@ impl FromStr for Player {
     }
 }
 
-pub trait Die: Iterator<Item = usize> + Default {
+/// One turn of a [`Game`], as recorded by [`Game::play_traced`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Turn {
+    /// The index of the player who moved.
+    pub player: usize,
+    /// The values the die rolled, in order.
+    pub rolls: Vec<usize>,
+    /// The space the player landed on, counting from 1.
+    pub position: usize,
+    /// The player's score after moving.
+    pub score: usize,
+}
+
+pub trait Die: Iterator<Item = usize> {
+    /// A fresh die with `sides` sides.
+    fn with_sides(sides: usize) -> Self;
+
+    /// The number of times the die has been rolled.
     fn rolls(&self) -> usize;
 }
 
+/// A die that rolls 1, 2, 3 and so on, going back to 1 after its last side.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct DeterministicDie {
     cur: usize,
@@ -56,11 +176,7 @@ pub struct DeterministicDie {
 
 impl Default for DeterministicDie {
     fn default() -> Self {
-        Self {
-            cur: 1,
-            max: 100,
-            rolls: 0,
-        }
+        Self::with_sides(100)
     }
 }
 
@@ -69,36 +185,32 @@ impl Iterator for DeterministicDie {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.rolls += 1;
-
-        if self.cur + 2 > self.max {
-            let c = self.cur - 1;
-            let d = self.cur + ((c + 1) % self.max) + ((c + 2) % self.max) + 2;
-            self.cur = (self.cur + 3) % self.max;
-            return Some(d);
-        }
-
-        let d = self.cur * 3 + 3;
-        self.cur += 3;
-
-        if self.cur > self.max {
-            self.cur = 1;
-        }
-
+        let d = self.cur;
+        self.cur = self.cur % self.max + 1;
         Some(d)
     }
 }
 
 impl Die for DeterministicDie {
+    fn with_sides(sides: usize) -> Self {
+        Self {
+            cur: 1,
+            max: sides,
+            rolls: 0,
+        }
+    }
+
     fn rolls(&self) -> usize {
         self.rolls
     }
 }
 
-#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Game<T>
 where
     T: Die,
 {
+    config: GameConfig,
     die: T,
     players: Vec<Player>,
 }
@@ -107,18 +219,72 @@ impl<T> Game<T>
 where
     T: Die,
 {
+    /// Play by `config` instead of the puzzle's rules, with a fresh die.
+    pub fn with_config(mut self, config: GameConfig) -> Result<Self> {
+        config.check_players(&self.players)?;
+        self.config = config;
+        self.die = T::with_sides(config.die_sides);
+        Ok(self)
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    /// The score of every player, in turn order.
+    pub fn scores(&self) -> Vec<usize> {
+        self.players.iter().map(|p| p.score).collect()
+    }
+
+    /// Play until someone wins, returning the lowest score of everyone else
+    /// times the number of rolls. With more than two players, the ones after
+    /// the winner have had one turn fewer than the ones before.
     pub fn play(&mut self) -> Result<usize> {
+        let winner = self.play_with(|_| {})?;
+        let losing = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != winner)
+            .map(|(_, p)| p.score)
+            .min()
+            .unwrap_or_default();
+        Ok(losing * self.die.rolls())
+    }
+
+    /// Play until someone wins, returning every turn taken, in order.
+    pub fn play_traced(&mut self) -> Result<Vec<Turn>> {
+        let mut trace = Vec::new();
+        self.play_with(|turn| trace.push(turn.clone()))?;
+        Ok(trace)
+    }
+
+    /// Play until someone wins, calling `on_turn` after every turn, and return
+    /// the index of the winner.
+    fn play_with<F>(&mut self, mut on_turn: F) -> Result<usize>
+    where
+        F: FnMut(&Turn),
+    {
+        let mut turn = Turn::default();
         for player in (0..self.players.len()).cycle() {
-            let roll = self
-                .die
-                .next()
-                .ok_or_else(|| anyhow!("Die did not produce a value!"))?;
-            let score = self.players[player].turn(roll);
-
-            if score >= 1000 {
-                return Ok(self.players[(player + 1) % self.players.len()].score
-                    * self.die.rolls()
-                    * 3);
+            turn.rolls.clear();
+            for _ in 0..self.config.rolls_per_turn {
+                turn.rolls.push(
+                    self.die
+                        .next()
+                        .ok_or_else(|| anyhow!("Die did not produce a value!"))?,
+                );
+            }
+            let roll = turn.rolls.iter().sum();
+            let score = self.players[player].turn(roll, self.config.track_length);
+
+            turn.player = player;
+            turn.position = self.players[player].pos + 1;
+            turn.score = score;
+            on_turn(&turn);
+
+            if score >= self.config.target {
+                return Ok(player);
             }
         }
 
@@ -137,19 +303,153 @@ where
             .iter()
             .map(|s| Player::from_str(s))
             .collect::<Result<Vec<Player>>>()?;
+        if players.is_empty() {
+            bail!("A game needs at least one player");
+        }
+
+        let config = GameConfig::deterministic();
+        config.check_players(&players)?;
         Ok(Game {
+            config,
+            die: T::with_sides(config.die_sides),
             players,
-            ..Game::default()
         })
     }
 }
 
+/// A die for a [`QuantumGame`], as its faces: (multiplicity, value) pairs in
+/// order of value. Every roll splits the universe once per face, and
+/// `multiplicity` of those universes see `value`, so a loaded die is one with
+/// some multiplicities higher than others.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct QuantumDie {
+    faces: Vec<(u128, usize)>,
+}
+
+impl QuantumDie {
+    /// A die with the given (multiplicity, value) faces. Faces that share a
+    /// value are merged, and faces with no multiplicity are dropped.
+    pub fn new(faces: &[(u128, usize)]) -> Result<Self> {
+        let mut merged: Vec<(u128, usize)> = Vec::with_capacity(faces.len());
+        for (multiplicity, value) in faces.iter().filter(|(m, _)| *m > 0) {
+            match merged.iter().position(|(_, v)| v == value) {
+                Some(idx) => merged[idx].0 += multiplicity,
+                None => merged.push((*multiplicity, *value)),
+            }
+        }
+
+        if merged.is_empty() {
+            bail!("A die needs at least one face");
+        }
+
+        merged.sort_unstable_by_key(|(_, value)| *value);
+        Ok(Self { faces: merged })
+    }
+
+    /// A die showing each of 1 to `sides` once.
+    pub fn fair(sides: usize) -> Self {
+        Self {
+            faces: (1..=sides).map(|value| (1, value)).collect(),
+        }
+    }
+
+    pub fn faces(&self) -> &[(u128, usize)] {
+        &self.faces
+    }
+
+    /// The die whose faces are every total of rolling this one `times` times.
+    pub fn rolled(&self, times: usize) -> Self {
+        let max = self.faces.last().map(|(_, v)| *v).unwrap_or_default();
+        // ways[i] is the number of ways to roll a total of i
+        let mut ways = vec![1_u128];
+        for _ in 0..times {
+            let mut next = vec![0; ways.len() + max];
+            for (total, count) in ways.iter().enumerate().filter(|(_, c)| **c > 0) {
+                for (multiplicity, value) in self.faces.iter() {
+                    next[total + value] += count * multiplicity;
+                }
+            }
+            ways = next;
+        }
+
+        Self {
+            faces: ways
+                .into_iter()
+                .enumerate()
+                .filter(|(_, count)| *count > 0)
+                .map(|(value, count)| (count, value))
+                .collect(),
+        }
+    }
+}
+
+/// How many universes each player of a [`QuantumGame`] wins in. The counts grow
+/// by a factor of the die's faces every roll, so they're kept as `u128`: the
+/// puzzle's game fits a `u64` up to a target of 27, but not 28.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Outcomes {
+    /// The universes won by each player, in turn order.
+    pub wins: Vec<u128>,
+    /// Every universe ends with someone winning, so this is the sum of the
+    /// wins.
+    pub total_universes: u128,
+}
+
+impl Outcomes {
+    pub fn new(wins: Vec<u128>) -> Self {
+        Self {
+            total_universes: wins.iter().sum(),
+            wins,
+        }
+    }
+
+    pub fn p1_wins(&self) -> u128 {
+        self.wins[0]
+    }
+
+    pub fn p2_wins(&self) -> u128 {
+        self.wins[1]
+    }
+
+    /// The most universes any player wins in.
+    pub fn most_wins(&self) -> u128 {
+        self.wins.iter().copied().max().unwrap_or_default()
+    }
+
+    /// The chance that a universe picked at random is one that `player`
+    /// wins, counting from 0.
+    ///
+    /// This isn't the chance of the player winning with a fair die. A game
+    /// that ends sooner splits into fewer universes, so each of its universes
+    /// is more likely than one from a longer game.
+    pub fn probability(&self, player: usize) -> f64 {
+        self.wins[player] as f64 / self.total_universes as f64
+    }
+
+    /// The chance that a universe picked at random is one that player 1 wins,
+    /// with the same caveat as [`Outcomes::probability`].
+    pub fn p1_probability(&self) -> f64 {
+        self.probability(0)
+    }
+
+    /// The chance that a universe picked at random is one that player 2 wins,
+    /// with the same caveat as [`Outcomes::probability`].
+    pub fn p2_probability(&self) -> f64 {
+        self.probability(1)
+    }
+}
+
 /// So I'm really bummed my part 1 gamble didn't pay off here and I have to
 /// implement this struct
-#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct QuantumGame {
+    config: GameConfig,
+    /// The die rolled `rolls_per_turn` times a turn, in place of the config's
+    /// fair one.
+    die: QuantumDie,
+    /// The index of the player about to move.
     turn: usize,
-    players: [Player; 2],
+    players: Vec<Player>,
 }
 
 // 1                  2
@@ -162,42 +462,346 @@ pub struct QuantumGame {
 // 2,6,
 // 1,7
 impl QuantumGame {
-    pub const TARGET: usize = 21;
-    // (frequncy of value, value)
-    pub const ROLL_VALUES: [(usize, usize); 7] =
-        [(1, 3), (3, 4), (6, 5), (7, 6), (6, 7), (3, 8), (1, 9)];
+    /// Play by `config` instead of the puzzle's rules, with a fair die.
+    pub fn with_config(mut self, config: GameConfig) -> Result<Self> {
+        config.check_players(&self.players)?;
+        self.config = config;
+        self.die = QuantumDie::fair(config.die_sides);
+        Ok(self)
+    }
 
-    pub fn play(&self) -> usize {
-        let mut cache = FxHashMap::default();
-        let wins = self.take_turn(&mut cache);
-        wins[0].max(wins[1])
+    /// Roll `die` instead of a fair one. The config's `die_sides` no longer
+    /// matters, but its `rolls_per_turn` still does.
+    pub fn with_die(mut self, die: QuantumDie) -> Self {
+        self.die = die;
+        self
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn die(&self) -> &QuantumDie {
+        &self.die
+    }
+
+    pub fn play(&self) -> u128 {
+        self.outcomes().most_wins()
+    }
+
+    pub fn outcomes(&self) -> Outcomes {
+        let turn_die = self.die.rolled(self.config.rolls_per_turn);
+        let rolls = turn_die.faces();
+        let order = self.turn_order();
+        let wins = if let [mover, other] = order[..] {
+            let pair = self.take_turn(rolls, [mover, other], &mut FxHashMap::default());
+            pair.to_vec()
+        } else {
+            self.take_turn_party(rolls, &order, &mut FxHashMap::default())
+        };
+
+        self.in_player_order(wins)
+    }
+
+    /// The same as [`QuantumGame::outcomes`], but read from a [`WinTable`]
+    /// instead of recursing. A table for more than two players would need
+    /// `track^n * target^n` entries, so those games fall back to recursing.
+    pub fn outcomes_dense(&self) -> Outcomes {
+        match self.turn_order()[..] {
+            [mover, other] => {
+                let wins = WinTable::with_die(&self.config, &self.die)
+                    .wins(&mover, &other)
+                    .expect("players are always on the table before the game ends");
+                self.in_player_order(wins.to_vec())
+            }
+            _ => self.outcomes(),
+        }
     }
 
-    pub fn take_turn(&self, cache: &mut FxHashMap<Self, [usize; 2]>) -> [usize; 2] {
-        if let Some(wins) = cache.get(self) {
+    /// The expected number of turns, counting every player's, until someone
+    /// wins. Unlike the universe counts, this weighs every path by how likely
+    /// its rolls are, so longer games don't count for more.
+    pub fn expected_turns(&self) -> f64 {
+        let turn_die = self.die.rolled(self.config.rolls_per_turn);
+        let total = turn_die.faces().iter().map(|(freq, _)| freq).sum::<u128>() as f64;
+        let rolls = turn_die
+            .faces()
+            .iter()
+            .map(|(freq, value)| (*freq as f64 / total, *value))
+            .collect::<Vec<_>>();
+        self.turns_from(&rolls, &self.turn_order(), &mut FxHashMap::default())
+    }
+
+    /// How many universes end with the winner on each score, as (universes,
+    /// score) in order of score. Scores nobody finishes on are left out.
+    pub fn winning_scores(&self) -> Vec<(u128, usize)> {
+        let turn_die = self.die.rolled(self.config.rolls_per_turn);
+        self.scores_from(
+            turn_die.faces(),
+            &self.turn_order(),
+            &mut FxHashMap::default(),
+        )
+        .into_iter()
+        .enumerate()
+        .filter(|(_, universes)| *universes > 0)
+        .map(|(over, universes)| (universes, self.config.target + over))
+        .collect()
+    }
+
+    /// The wins of two players, `mover` who is about to move and the other.
+    /// `rolls` are the (frequency of value, value) of every total a turn can
+    /// roll.
+    pub fn take_turn(
+        &self,
+        rolls: &[(u128, usize)],
+        players: [Player; 2],
+        cache: &mut FxHashMap<[Player; 2], [u128; 2]>,
+    ) -> [u128; 2] {
+        if let Some(wins) = cache.get(&players) {
             return *wins;
         }
 
-        let idx = self.turn % 2;
+        let [mover, other] = players;
+        let mut wins = [0_u128, 0_u128];
+        for (freq, value) in rolls.iter() {
+            let moved = mover.pretend(*value, self.config.track_length);
+            if moved.score >= self.config.target {
+                wins[0] += freq;
+            } else {
+                // the other player moves next
+                let res = self.take_turn(rolls, [other, moved], cache);
+                wins[0] += res[1] * freq;
+                wins[1] += res[0] * freq;
+            }
+        }
+
+        cache.insert(players, wins);
 
-        let mut wins = [0_usize, 0_usize];
-        for (freq, value) in QuantumGame::ROLL_VALUES.iter() {
-            let mut new_game = *self;
-            let score = new_game.players[idx].turn(*value);
-            if score >= QuantumGame::TARGET {
-                wins[idx] += freq;
+        wins
+    }
+
+    /// Like [`QuantumGame::take_turn`] for any number of players, in turn
+    /// order starting with the one about to move.
+    pub fn take_turn_party(
+        &self,
+        rolls: &[(u128, usize)],
+        players: &[Player],
+        cache: &mut FxHashMap<Vec<Player>, Vec<u128>>,
+    ) -> Vec<u128> {
+        if let Some(wins) = cache.get(players) {
+            return wins.clone();
+        }
+
+        let mut wins = vec![0; players.len()];
+        let mut next = players.to_vec();
+        next.rotate_left(1);
+        for (freq, value) in rolls.iter() {
+            let moved = players[0].pretend(*value, self.config.track_length);
+            if moved.score >= self.config.target {
+                wins[0] += freq;
             } else {
-                new_game.turn = (new_game.turn + 1) % 2;
-                let res = new_game.take_turn(cache);
-                wins[0] += res[0] * freq;
-                wins[1] += res[1] * freq;
+                // everyone else moves up a place, and the mover goes last
+                *next.last_mut().expect("there is always a mover") = moved;
+                let res = self.take_turn_party(rolls, &next, cache);
+                wins[0] += res[res.len() - 1] * freq;
+                for (w, r) in wins[1..].iter_mut().zip(res.iter()) {
+                    *w += r * freq;
+                }
             }
         }
 
-        cache.insert(*self, wins);
+        cache.insert(players.to_vec(), wins.clone());
 
         wins
     }
+
+    /// The expected turns from `players`, in turn order, where `rolls` are the
+    /// (chance of value, value) of every total a turn can roll.
+    fn turns_from(
+        &self,
+        rolls: &[(f64, usize)],
+        players: &[Player],
+        cache: &mut FxHashMap<Vec<Player>, f64>,
+    ) -> f64 {
+        if let Some(turns) = cache.get(players) {
+            return *turns;
+        }
+
+        let mut turns = 1.0;
+        let mut next = players.to_vec();
+        next.rotate_left(1);
+        for (chance, value) in rolls.iter() {
+            let moved = players[0].pretend(*value, self.config.track_length);
+            if moved.score < self.config.target {
+                *next.last_mut().expect("there is always a mover") = moved;
+                turns += chance * self.turns_from(rolls, &next, cache);
+            }
+        }
+
+        cache.insert(players.to_vec(), turns);
+
+        turns
+    }
+
+    /// The universes from `players`, in turn order, that end with the winner
+    /// on each score, counting up from the target. A winner can overshoot the
+    /// target by less than the length of the track.
+    fn scores_from(
+        &self,
+        rolls: &[(u128, usize)],
+        players: &[Player],
+        cache: &mut FxHashMap<Vec<Player>, Vec<u128>>,
+    ) -> Vec<u128> {
+        if let Some(scores) = cache.get(players) {
+            return scores.clone();
+        }
+
+        let mut scores = vec![0; self.config.track_length];
+        let mut next = players.to_vec();
+        next.rotate_left(1);
+        for (freq, value) in rolls.iter() {
+            let moved = players[0].pretend(*value, self.config.track_length);
+            if moved.score >= self.config.target {
+                scores[moved.score - self.config.target] += freq;
+            } else {
+                *next.last_mut().expect("there is always a mover") = moved;
+                let res = self.scores_from(rolls, &next, cache);
+                for (s, r) in scores.iter_mut().zip(res) {
+                    *s += r * freq;
+                }
+            }
+        }
+
+        cache.insert(players.to_vec(), scores.clone());
+
+        scores
+    }
+
+    /// The players in turn order, starting with the one about to move.
+    fn turn_order(&self) -> Vec<Player> {
+        let mut order = self.players.clone();
+        let len = order.len();
+        order.rotate_left(self.turn % len);
+        order
+    }
+
+    /// The outcomes from wins listed in [`QuantumGame::turn_order`].
+    fn in_player_order(&self, mut wins: Vec<u128>) -> Outcomes {
+        let len = wins.len();
+        wins.rotate_right(self.turn % len);
+        Outcomes::new(wins)
+    }
+}
+
+/// The wins of both players from every state of a quantum game that nobody has
+/// won yet, filled in bottom up over one flat table rather than by memoized
+/// recursion.
+///
+/// A state is seen from the point of view of the player about to move, so the
+/// turn doesn't need to be part of it: after a move, the other player becomes
+/// the one about to move.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WinTable {
+    config: GameConfig,
+    /// The wins of the player about to move and of the other player, as laid
+    /// out by [`WinTable::index`].
+    wins: Vec<[u128; 2]>,
+}
+
+impl WinTable {
+    pub fn new(config: &GameConfig) -> Self {
+        Self::with_die(config, &QuantumDie::fair(config.die_sides))
+    }
+
+    /// The table for rolling `die` `rolls_per_turn` times a turn, instead of
+    /// the config's fair die.
+    pub fn with_die(config: &GameConfig, die: &QuantumDie) -> Self {
+        let track = config.track_length;
+        let target = config.target;
+        let turn_die = die.rolled(config.rolls_per_turn);
+        let rolls = turn_die.faces();
+        let mut table = Self {
+            config: *config,
+            wins: vec![[0, 0]; track * track * target * target],
+        };
+
+        // A move always raises the mover's score, so every state a state leads
+        // to has a higher combined score. Working down from the highest
+        // combined score means those are always filled in first.
+        for total in (0..=2 * (target - 1)).rev() {
+            for mover_score in total.saturating_sub(target - 1)..=total.min(target - 1) {
+                let other_score = total - mover_score;
+                for mover_pos in 0..track {
+                    for other_pos in 0..track {
+                        let mut wins = [0, 0];
+                        for (freq, value) in rolls.iter() {
+                            let pos = (mover_pos + value) % track;
+                            let score = mover_score + pos + 1;
+                            if score >= target {
+                                wins[0] += freq;
+                            } else {
+                                // the other player moves next
+                                let next =
+                                    table.wins[table.index(other_pos, pos, other_score, score)];
+                                wins[0] += next[1] * freq;
+                                wins[1] += next[0] * freq;
+                            }
+                        }
+
+                        let idx = table.index(mover_pos, other_pos, mover_score, other_score);
+                        table.wins[idx] = wins;
+                    }
+                }
+            }
+        }
+
+        table
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    /// The wins of `mover`, who is about to move, and of `other`, or `None`
+    /// if either of them is off the track or has already won.
+    pub fn wins(&self, mover: &Player, other: &Player) -> Option<[u128; 2]> {
+        let on_table =
+            |p: &Player| p.pos < self.config.track_length && p.score < self.config.target;
+        if on_table(mover) && on_table(other) {
+            Some(self.wins[self.index(mover.pos, other.pos, mover.score, other.score)])
+        } else {
+            None
+        }
+    }
+
+    /// The share of universes the player about to move wins in from the start
+    /// of a game, for every pair of starting positions: `rates[a][b]` is for
+    /// the mover starting on space `a + 1` and the other player on `b + 1`.
+    pub fn win_rates(&self) -> Vec<Vec<f64>> {
+        let track = self.config.track_length;
+        (0..track)
+            .map(|mover_pos| {
+                (0..track)
+                    .map(|other_pos| {
+                        let wins = self.wins[self.index(mover_pos, other_pos, 0, 0)];
+                        wins[0] as f64 / (wins[0] + wins[1]) as f64
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn index(
+        &self,
+        mover_pos: usize,
+        other_pos: usize,
+        mover_score: usize,
+        other_score: usize,
+    ) -> usize {
+        let track = self.config.track_length;
+        let target = self.config.target;
+        ((mover_score * target + other_score) * track + mover_pos) * track + other_pos
+    }
 }
 
 impl TryFrom<&[String]> for QuantumGame {
@@ -208,13 +812,17 @@ impl TryFrom<&[String]> for QuantumGame {
             .iter()
             .map(|s| Player::from_str(s))
             .collect::<Result<Vec<Player>>>()?;
-        if players.len() != 2 {
+        if players.len() < 2 {
             bail!("Wrong number of players: {}", players.len());
         }
 
+        let config = GameConfig::quantum();
+        config.check_players(&players)?;
         Ok(Self {
-            players: [players[0], players[1]],
-            ..QuantumGame::default()
+            config,
+            die: QuantumDie::fair(config.die_sides),
+            turn: 0,
+            players,
         })
     }
 }
@@ -241,7 +849,7 @@ impl Solver for Games {
     const DAY: usize = 21;
 
     type P1 = usize;
-    type P2 = usize;
+    type P2 = u128;
 
     fn part_one(&mut self) -> Self::P1 {
         let mut g = self.deterministic.clone();
@@ -249,7 +857,7 @@ impl Solver for Games {
     }
 
     fn part_two(&mut self) -> Self::P2 {
-        self.quantum.play()
+        self.quantum.outcomes_dense().most_wins()
     }
 }
 
@@ -282,5 +890,335 @@ mod tests {
         );
         let game = QuantumGame::try_from(input.as_ref()).expect("could not parse game");
         assert_eq!(game.play(), 444356092776315);
+
+        let outcomes = game.outcomes();
+        assert_eq!(game.outcomes_dense(), outcomes);
+        assert_eq!(outcomes.p1_wins(), 444356092776315);
+        assert_eq!(outcomes.p2_wins(), 341960390180808);
+        assert_eq!(outcomes.total_universes, 786316482957123);
+        assert!((outcomes.p1_probability() - 0.5651).abs() < 1e-4);
+        assert!((outcomes.p1_probability() + outcomes.p2_probability() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn config() {
+        assert!(GameConfig::new(0, 3, 21, 10).is_err());
+        assert!(GameConfig::new(3, 0, 21, 10).is_err());
+        assert!(GameConfig::new(3, 3, 0, 10).is_err());
+        assert!(GameConfig::new(3, 3, 21, 0).is_err());
+        assert_eq!(
+            GameConfig::new(3, 3, 21, 10).expect("invalid config"),
+            GameConfig::quantum()
+        );
+
+        assert_eq!(
+            GameConfig::quantum().roll_frequencies(),
+            vec![(1, 3), (3, 4), (6, 5), (7, 6), (6, 7), (3, 8), (1, 9)]
+        );
+        assert_eq!(
+            GameConfig::new(6, 1, 21, 10)
+                .expect("invalid config")
+                .roll_frequencies(),
+            (1..=6).map(|v| (1, v)).collect::<Vec<_>>()
+        );
+
+        // players have to start on the track
+        let input = test_input(
+            "
+            Player 1 starting position: 4
+            Player 2 starting position: 8
+            ",
+        );
+        let short = GameConfig::new(3, 3, 21, 7).expect("invalid config");
+        let game: Game<DeterministicDie> =
+            Game::try_from(input.as_ref()).expect("could not parse game");
+        assert!(game.with_config(short).is_err());
+        let game = QuantumGame::try_from(input.as_ref()).expect("could not parse game");
+        assert!(game.with_config(short).is_err());
+    }
+
+    #[test]
+    fn win_table() {
+        let table = WinTable::new(&GameConfig::quantum());
+        let player = |pos: usize, score: usize| Player {
+            pos: pos - 1,
+            score,
+        };
+
+        assert_eq!(
+            table.wins(&player(4, 0), &player(8, 0)),
+            Some([444356092776315, 341960390180808])
+        );
+        // one point short, and every roll scores at least one
+        assert_eq!(table.wins(&player(4, 20), &player(8, 0)), Some([27, 0]));
+        assert_eq!(table.wins(&player(4, 21), &player(8, 0)), None);
+        assert_eq!(table.wins(&player(11, 0), &player(8, 0)), None);
+
+        // the table agrees with the recursion for every starting position,
+        // whoever moves first
+        let mut cache = FxHashMap::default();
+        let rolls = GameConfig::quantum().roll_frequencies();
+        for (a, b) in (1..=10).zip((1..=10).rev()) {
+            let game = QuantumGame {
+                config: GameConfig::quantum(),
+                die: QuantumDie::fair(3),
+                turn: 1,
+                players: vec![player(a, 3), player(b, 5)],
+            };
+            let wins = game.take_turn(&rolls, [game.players[1], game.players[0]], &mut cache);
+            assert_eq!(table.wins(&game.players[1], &game.players[0]), Some(wins));
+            assert_eq!(game.outcomes_dense(), Outcomes::new(vec![wins[1], wins[0]]));
+        }
+    }
+
+    #[test]
+    fn custom_deterministic() {
+        let input = test_input(
+            "
+            Player 1 starting position: 1
+            Player 2 starting position: 1
+            ",
+        );
+        // one roll of a d6 a turn: player 1 scores 2 and then 7, while player 2
+        // scores 3 and then 10 to win on the fourth roll
+        let config = GameConfig::new(6, 1, 10, 10).expect("invalid config");
+        let mut game: Game<DeterministicDie> = Game::try_from(input.as_ref())
+            .expect("could not parse game")
+            .with_config(config)
+            .expect("invalid config");
+        assert_eq!(game.play().expect("unexpected failure"), 7 * 4);
+    }
+
+    #[test]
+    fn more_players() {
+        let input = test_input(
+            "
+            Player 1 starting position: 4
+            Player 2 starting position: 8
+            Player 3 starting position: 1
+            ",
+        );
+        // player 1 wins with 1001, ahead of 987 and 855
+        let mut game: Game<DeterministicDie> =
+            Game::try_from(input.as_ref()).expect("could not parse game");
+        assert_eq!(game.play().expect("unexpected failure"), 855 * 1380);
+        assert_eq!(game.scores(), vec![1001, 987, 855]);
+
+        assert!(Game::<DeterministicDie>::try_from(&[] as &[String]).is_err());
+    }
+
+    #[test]
+    fn traced() {
+        let input = test_input(
+            "
+            Player 1 starting position: 4
+            Player 2 starting position: 8
+            ",
+        );
+        let mut game: Game<DeterministicDie> =
+            Game::try_from(input.as_ref()).expect("could not parse game");
+        let mut replay = game.clone();
+        let trace = game.play_traced().expect("unexpected failure");
+
+        assert_eq!(trace.len(), 993 / 3);
+        assert_eq!(
+            trace[..2],
+            [
+                Turn {
+                    player: 0,
+                    rolls: vec![1, 2, 3],
+                    position: 10,
+                    score: 10,
+                },
+                Turn {
+                    player: 1,
+                    rolls: vec![4, 5, 6],
+                    position: 3,
+                    score: 3,
+                },
+            ]
+        );
+        let last = trace.last().expect("no turns were taken");
+        assert_eq!((last.player, last.position, last.score), (0, 10, 1000));
+        assert_eq!(game.scores(), vec![1000, 745]);
+
+        // the trace doesn't change the outcome
+        assert_eq!(replay.play().expect("unexpected failure"), 739785);
+        assert!(QuantumGame::try_from(&input[..1]).is_err());
+    }
+
+    #[test]
+    fn custom_quantum() {
+        // every sequence of rolls, one at a time, with no memoization
+        fn brute_force(
+            config: &GameConfig,
+            sides: &[usize],
+            players: &[Player],
+            turn: usize,
+        ) -> Vec<u128> {
+            let mut wins = vec![0; players.len()];
+            for value in sides.iter() {
+                let mut players = players.to_vec();
+                if players[turn].turn(*value, config.track_length()) >= config.target() {
+                    wins[turn] += 1;
+                } else {
+                    let res = brute_force(config, sides, &players, (turn + 1) % players.len());
+                    for (w, r) in wins.iter_mut().zip(res) {
+                        *w += r;
+                    }
+                }
+            }
+            wins
+        }
+
+        let input = test_input(
+            "
+            Player 1 starting position: 1
+            Player 2 starting position: 3
+            Player 3 starting position: 2
+            ",
+        );
+        for count in [2, 3] {
+            let base = QuantumGame::try_from(&input[..count]).expect("could not parse game");
+            for (sides, target, track) in [(2, 8, 4), (3, 9, 5), (4, 7, 10)] {
+                let config = GameConfig::new(sides, 1, target, track).expect("invalid config");
+                let mut game = base.clone().with_config(config).expect("invalid config");
+                let sides = (1..=sides).collect::<Vec<_>>();
+                for turn in 0..count {
+                    game.turn = turn;
+                    let wins = brute_force(&config, &sides, &game.players, turn);
+                    assert_eq!(game.outcomes(), Outcomes::new(wins));
+                    assert_eq!(game.outcomes_dense(), game.outcomes());
+                }
+            }
+        }
+
+        // the general search agrees with the two player one
+        let game = QuantumGame::try_from(&input[..2]).expect("could not parse game");
+        let rolls = game.config().roll_frequencies();
+        let pair = [game.players[0], game.players[1]];
+        assert_eq!(
+            game.take_turn_party(&rolls, &pair, &mut FxHashMap::default()),
+            game.take_turn(&rolls, pair, &mut FxHashMap::default())
+                .to_vec()
+        );
+
+        // a loaded d3 that rolls a 1 twice as often as anything else, which is
+        // the same as a d4 with two sides showing 1
+        let loaded = QuantumDie::new(&[(1, 2), (1, 1), (1, 3), (1, 1)]).expect("invalid die");
+        assert_eq!(loaded.faces(), &[(2, 1), (1, 2), (1, 3)]);
+        let config = GameConfig::new(4, 1, 9, 5).expect("invalid config");
+        let mut game = QuantumGame::try_from(&input[..2])
+            .expect("could not parse game")
+            .with_config(config)
+            .expect("invalid config")
+            .with_die(loaded);
+        for turn in 0..2 {
+            game.turn = turn;
+            let wins = brute_force(&config, &[1, 1, 2, 3], &game.players, turn);
+            assert_eq!(game.outcomes(), Outcomes::new(wins));
+            assert_eq!(game.outcomes_dense(), game.outcomes());
+        }
+    }
+
+    #[test]
+    fn large_counts() {
+        let input = test_input(
+            "
+            Player 1 starting position: 4
+            Player 2 starting position: 8
+            ",
+        );
+        let game = QuantumGame::try_from(input.as_ref()).expect("could not parse game");
+
+        // the last target where every count still fits in a u64
+        let config = GameConfig::new(3, 3, 27, 10).expect("invalid config");
+        let outcomes = game
+            .clone()
+            .with_config(config)
+            .expect("invalid config")
+            .outcomes();
+        assert_eq!(outcomes.total_universes, 8135111322865339463);
+        assert!(outcomes.total_universes <= u64::MAX as u128);
+
+        // and the first where they don't
+        let config = GameConfig::new(3, 3, 28, 10).expect("invalid config");
+        let game = game.with_config(config).expect("invalid config");
+        let outcomes = game.outcomes();
+        assert_eq!(game.outcomes_dense(), outcomes);
+        assert_eq!(outcomes.p1_wins(), 20867804065144576999);
+        assert_eq!(outcomes.p2_wins(), 17124971870502525468);
+        assert_eq!(outcomes.total_universes, 37992775935647102467);
+        assert!(outcomes.p1_wins() > u64::MAX as u128);
+        assert_eq!(
+            game.winning_scores()
+                .iter()
+                .map(|(universes, _)| universes)
+                .sum::<u128>(),
+            outcomes.total_universes
+        );
+    }
+
+    #[test]
+    fn statistics() {
+        let input = test_input(
+            "
+            Player 1 starting position: 4
+            Player 2 starting position: 8
+            ",
+        );
+        let game = QuantumGame::try_from(input.as_ref()).expect("could not parse game");
+        assert!((game.expected_turns() - 6.9187).abs() < 1e-4);
+
+        let scores = game.winning_scores();
+        assert_eq!(scores.len(), 10);
+        assert_eq!(scores[0], (39260457743870, 21));
+        assert_eq!(scores[9], (37200246187524, 30));
+        assert_eq!(
+            scores.iter().map(|(universes, _)| universes).sum::<u128>(),
+            game.outcomes().total_universes
+        );
+
+        let rates = WinTable::new(&GameConfig::quantum()).win_rates();
+        assert!((rates[3][7] - game.outcomes().p1_probability()).abs() < 1e-12);
+        assert!((rates[0][0] - 0.5950).abs() < 1e-4);
+
+        // a one space track and a one sided die score one point a turn, so
+        // player 1 reaches 3 on the fifth turn
+        let input = test_input(
+            "
+            Player 1 starting position: 1
+            Player 2 starting position: 1
+            ",
+        );
+        let config = GameConfig::new(1, 1, 3, 1).expect("invalid config");
+        let game = QuantumGame::try_from(input.as_ref())
+            .expect("could not parse game")
+            .with_config(config)
+            .expect("invalid config");
+        assert!((game.expected_turns() - 5.0).abs() < 1e-12);
+        assert_eq!(game.winning_scores(), vec![(1, 3)]);
+        assert_eq!(WinTable::new(&config).win_rates(), vec![vec![1.0]]);
+    }
+
+    #[test]
+    fn quantum_die() {
+        assert!(QuantumDie::new(&[]).is_err());
+        assert!(QuantumDie::new(&[(0, 1), (0, 2)]).is_err());
+        assert_eq!(
+            QuantumDie::new(&[(1, 3), (0, 7), (1, 1), (1, 2)]).expect("invalid die"),
+            QuantumDie::fair(3)
+        );
+
+        // the puzzle's three rolls of a d3 are one instance of rolling any die
+        assert_eq!(
+            QuantumDie::fair(3).rolled(3).faces(),
+            GameConfig::quantum().roll_frequencies()
+        );
+        assert_eq!(QuantumDie::fair(6).rolled(1), QuantumDie::fair(6));
+
+        // two rolls of a coin that lands on 1 three times as often as on 2
+        let coin = QuantumDie::new(&[(3, 1), (1, 2)]).expect("invalid die");
+        assert_eq!(coin.rolled(2).faces(), &[(9, 2), (6, 3), (1, 4)]);
     }
 }
@@ -1,5 +1,5 @@
 pub use self::lanternfish::Sim;
-pub use self::snailfish::Homework;
+pub use self::snailfish::{Element, FlatNumber, Homework, Pair, ParseError};
 
 mod lanternfish;
 mod snailfish;
@@ -5,7 +5,7 @@ use std::{
     str::FromStr,
 };
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Result};
 use aoc_helpers::Solver;
 use itertools::Itertools;
 use nom::{
@@ -13,6 +13,7 @@ use nom::{
     bytes::complete::tag,
     character::complete::digit1,
     combinator::map_res,
+    error::{context, VerboseError, VerboseErrorKind},
     sequence::{delimited, separated_pair},
     IResult,
 };
@@ -48,6 +49,21 @@ impl Element {
             Self::Pair(p) => p.magnitude(),
         }
     }
+
+    pub fn checked_magnitude(&self) -> Result<i64> {
+        match self {
+            Self::Num(v) => Ok(*v),
+            Self::Pair(p) => p.checked_magnitude(),
+        }
+    }
+
+    #[cfg(feature = "bigint")]
+    pub fn magnitude_big(&self) -> num_bigint::BigInt {
+        match self {
+            Self::Num(v) => (*v).into(),
+            Self::Pair(p) => p.magnitude_big(),
+        }
+    }
 }
 
 impl AddAssign<i64> for Element {
@@ -91,10 +107,56 @@ impl Pair {
         Self { left, right }
     }
 
+    /// A random snailfish number, nested at most `depth_limit` pairs deep,
+    /// with regular numbers from 0 to 9. `rng(n)` should return a value in
+    /// `0..n`. Each element above the depth limit is a pair half of the time.
+    pub fn random(depth_limit: usize, rng: &mut dyn FnMut(usize) -> usize) -> Self {
+        Self::random_sized(depth_limit, 50, rng)
+    }
+
+    /// Like `random`, but each element above the depth limit is a pair
+    /// `pair_percent` percent of the time, so numbers can be made bushier or
+    /// sparser for the same limit
+    pub fn random_sized(
+        depth_limit: usize,
+        pair_percent: usize,
+        rng: &mut dyn FnMut(usize) -> usize,
+    ) -> Self {
+        let elem = |rng: &mut dyn FnMut(usize) -> usize| -> Element {
+            if depth_limit > 1 && rng(100) < pair_percent {
+                Self::random_sized(depth_limit - 1, pair_percent, rng).into()
+            } else {
+                Element::Num(rng(10) as i64)
+            }
+        };
+
+        let left = elem(rng);
+        let right = elem(rng);
+        Self::new(left, right)
+    }
+
     pub fn magnitude(&self) -> i64 {
         self.left.magnitude() * 3 + self.right.magnitude() * 2
     }
 
+    /// Like `magnitude`, but an error rather than a wrapped value if the
+    /// magnitude doesn't fit in an `i64`, which can happen for deeply nested
+    /// numbers that haven't been reduced
+    pub fn checked_magnitude(&self) -> Result<i64> {
+        let left = self.left.checked_magnitude()?;
+        let right = self.right.checked_magnitude()?;
+        left.checked_mul(3)
+            .zip(right.checked_mul(2))
+            .and_then(|(l, r)| l.checked_add(r))
+            .ok_or_else(|| anyhow!("magnitude of {} overflows an i64", self))
+    }
+
+    /// The magnitude, however large it gets
+    #[cfg(feature = "bigint")]
+    pub fn magnitude_big(&self) -> num_bigint::BigInt {
+        self.left.magnitude_big() * 3 + self.right.magnitude_big() * 2
+    }
+
     pub fn reduce(&mut self) {
         let mut action_taken = false;
         loop {
@@ -263,39 +325,267 @@ impl Add<Pair> for Pair {
 }
 
 impl FromStr for Pair {
-    type Err = anyhow::Error;
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_pair(s) {
+            Ok(("", p)) => Ok(p),
+            Ok((rest, _)) => Err(ParseError::new(s, rest, "end of input")),
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                Err(ParseError::from_verbose(s, e))
+            }
+            Err(nom::Err::Incomplete(_)) => Err(ParseError::new(s, "", "more input")),
+        }
+    }
+}
+
+/// Where parsing a snailfish number failed, and what was expected there
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    /// the byte offset of the failure in the input
+    pub offset: usize,
+    pub expected: &'static str,
+    /// the character at the offset, if the input hadn't run out
+    pub found: Option<char>,
+}
 
-    fn from_str(s: &str) -> anyhow::Result<Self> {
-        let (_, p) = parse_pair(s).map_err(|_| anyhow!("Unable to parse pair from: {}", s))?;
-        Ok(p)
+impl ParseError {
+    fn new(input: &str, rest: &str, expected: &'static str) -> Self {
+        Self {
+            offset: input.len() - rest.len(),
+            expected,
+            found: rest.chars().next(),
+        }
+    }
+
+    fn from_verbose(input: &str, e: VerboseError<&str>) -> Self {
+        // the failure furthest into the input is the real one, and the last
+        // context recorded there is the most general description of what
+        // was expected
+        let rest = e
+            .errors
+            .iter()
+            .map(|(rest, _)| *rest)
+            .min_by_key(|rest| rest.len())
+            .unwrap_or(input);
+
+        let expected = e
+            .errors
+            .iter()
+            .rev()
+            .filter(|(r, _)| r.len() == rest.len())
+            .find_map(|(_, kind)| match kind {
+                VerboseErrorKind::Context(c) => Some(*c),
+                _ => None,
+            })
+            .unwrap_or("a pair");
+
+        Self::new(input, rest, expected)
     }
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.found {
+            Some(ch) => write!(
+                f,
+                "Unable to parse pair: expected {} at offset {}, found '{}'",
+                self.expected, self.offset, ch
+            ),
+            None => write!(
+                f,
+                "Unable to parse pair: expected {} at offset {}, found the end of input",
+                self.expected, self.offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type ParseResult<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
+
 // nom parsers
-fn parse_num(input: &str) -> IResult<&str, Element> {
+fn parse_num(input: &str) -> ParseResult<'_, Element> {
     let (input, v) = map_res(digit1, i64::from_str)(input)?;
     Ok((input, Element::Num(v)))
 }
 
-fn parse_elem_pair(input: &str) -> IResult<&str, Element> {
+fn parse_elem_pair(input: &str) -> ParseResult<'_, Element> {
     let (input, v) = parse_pair(input)?;
     Ok((input, Element::Pair(v.into())))
 }
 
-fn parse_elem(input: &str) -> IResult<&str, Element> {
-    alt((parse_num, parse_elem_pair))(input)
+fn parse_elem(input: &str) -> ParseResult<'_, Element> {
+    context("a number or '['", alt((parse_num, parse_elem_pair)))(input)
 }
 
-fn parse_pair(input: &str) -> IResult<&str, Pair> {
+fn parse_pair(input: &str) -> ParseResult<'_, Pair> {
     let (input, (left, right)) = delimited(
-        tag("["),
-        separated_pair(parse_elem, tag(","), parse_elem),
-        tag("]"),
+        context("'['", tag("[")),
+        separated_pair(parse_elem, context("','", tag(",")), parse_elem),
+        context("']'", tag("]")),
     )(input)?;
 
     Ok((input, Pair::new(left, right)))
 }
 
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FlatNumber {
+    tokens: Vec<(usize, i64)>,
+}
+
+impl FlatNumber {
+    pub fn magnitude(&self) -> i64 {
+        if self.tokens.is_empty() {
+            return 0;
+        }
+
+        let mut idx = 0;
+        Self::recur_magnitude(&self.tokens, &mut idx, 0)
+    }
+
+    fn recur_magnitude(tokens: &[(usize, i64)], idx: &mut usize, depth: usize) -> i64 {
+        let (d, v) = tokens[*idx];
+        if d == depth {
+            *idx += 1;
+            v
+        } else {
+            let left = Self::recur_magnitude(tokens, idx, depth + 1);
+            let right = Self::recur_magnitude(tokens, idx, depth + 1);
+            left * 3 + right * 2
+        }
+    }
+
+    /// Add `self` and `rhs`, leaving the reduced sum in `out`. Reusing `out`
+    /// across additions means its buffer only has to grow once.
+    pub fn add_into(&self, rhs: &FlatNumber, out: &mut FlatNumber) {
+        out.tokens.clear();
+        out.tokens.extend(
+            self.tokens
+                .iter()
+                .chain(rhs.tokens.iter())
+                .map(|(depth, v)| (depth + 1, *v)),
+        );
+        out.reduce();
+    }
+
+    pub fn reduce(&mut self) {
+        // exploding never makes anything deeper, so every pair that starts out
+        // too deep can be exploded in a single pass, left to right. The first
+        // two numbers in a row at the same depth are always a pair, and when
+        // something is nested more than one level too deep, exploding a pair
+        // can leave its parent as the next one
+        let mut idx = 0;
+        while idx + 1 < self.tokens.len() {
+            let depth = self.tokens[idx].0;
+            if depth > 4 && self.tokens[idx + 1].0 == depth {
+                self.explode(idx);
+                idx = idx.saturating_sub(1);
+            } else {
+                idx += 1;
+            }
+        }
+
+        // after that, only a split can make a pair too deep, and that pair
+        // explodes straight away. everything before idx is already reduced
+        let mut idx = 0;
+        while idx < self.tokens.len() {
+            if self.tokens[idx].1 > 9 {
+                self.split(idx);
+                if self.tokens[idx].0 > 4 {
+                    self.explode(idx);
+                    // the number before may now need splitting
+                    idx = idx.saturating_sub(1);
+                }
+            } else {
+                idx += 1;
+            }
+        }
+    }
+
+    /// Explode the pair whose left half is at `idx`
+    fn explode(&mut self, idx: usize) {
+        let (depth, left) = self.tokens[idx];
+        let (_, right) = self.tokens[idx + 1];
+
+        if idx > 0 {
+            self.tokens[idx - 1].1 += left;
+        }
+
+        if let Some(next) = self.tokens.get_mut(idx + 2) {
+            next.1 += right;
+        }
+
+        self.tokens[idx] = (depth - 1, 0);
+        self.tokens.remove(idx + 1);
+    }
+
+    fn split(&mut self, idx: usize) {
+        let (depth, v) = self.tokens[idx];
+        self.tokens[idx] = (depth + 1, v / 2);
+        self.tokens.insert(idx + 1, (depth + 1, v - v / 2));
+    }
+
+    fn flatten(elem: &Element, depth: usize, tokens: &mut Vec<(usize, i64)>) {
+        match elem {
+            Element::Num(v) => tokens.push((depth, *v)),
+            Element::Pair(p) => {
+                Self::flatten(&p.left, depth + 1, tokens);
+                Self::flatten(&p.right, depth + 1, tokens);
+            }
+        }
+    }
+
+    fn unflatten(tokens: &[(usize, i64)], idx: &mut usize, depth: usize) -> Element {
+        let (d, v) = tokens[*idx];
+        if d == depth {
+            *idx += 1;
+            Element::Num(v)
+        } else {
+            let left = Self::unflatten(tokens, idx, depth + 1);
+            let right = Self::unflatten(tokens, idx, depth + 1);
+            Pair::new(left, right).into()
+        }
+    }
+}
+
+impl From<&Pair> for FlatNumber {
+    fn from(p: &Pair) -> Self {
+        let mut tokens = Vec::new();
+        FlatNumber::flatten(&p.left, 1, &mut tokens);
+        FlatNumber::flatten(&p.right, 1, &mut tokens);
+        Self { tokens }
+    }
+}
+
+impl From<&FlatNumber> for Pair {
+    fn from(n: &FlatNumber) -> Self {
+        let mut idx = 0;
+        let left = FlatNumber::unflatten(&n.tokens, &mut idx, 1);
+        let right = FlatNumber::unflatten(&n.tokens, &mut idx, 1);
+        Pair::new(left, right)
+    }
+}
+
+impl fmt::Display for FlatNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Pair::from(self))
+    }
+}
+
+impl Add<&FlatNumber> for &FlatNumber {
+    type Output = FlatNumber;
+
+    fn add(self, rhs: &FlatNumber) -> Self::Output {
+        let mut sum = FlatNumber {
+            tokens: Vec::with_capacity(self.tokens.len() + rhs.tokens.len()),
+        };
+        self.add_into(rhs, &mut sum);
+        sum
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Homework {
     pairs: Vec<Pair>,
@@ -313,6 +603,28 @@ impl Homework {
             return None;
         }
 
+        // every ordered pair of distinct numbers, by index, so nothing has to
+        // be cloned or collected per pair, and each thread builds its sums in
+        // the same scratch number
+        let flat: Vec<FlatNumber> = self.pairs.iter().map(FlatNumber::from).collect();
+        let n = flat.len();
+        (0..n * n)
+            .into_par_iter()
+            .filter(|k| k / n != k % n)
+            .map_init(FlatNumber::default, |scratch, k| {
+                flat[k / n].add_into(&flat[k % n], scratch);
+                scratch.magnitude()
+            })
+            .max()
+    }
+
+    /// The same as `largest_magnitude_of_pairs`, but adding the boxed pairs
+    /// directly
+    pub fn largest_magnitude_of_pairs_boxed(&self) -> Option<i64> {
+        if self.pairs.is_empty() {
+            return None;
+        }
+
         self.pairs
             .iter()
             .permutations(2)
@@ -329,7 +641,7 @@ impl TryFrom<Vec<String>> for Homework {
         let pairs = value
             .iter()
             .map(|s| Pair::from_str(s))
-            .collect::<anyhow::Result<Vec<Pair>>>()?;
+            .collect::<Result<Vec<Pair>, ParseError>>()?;
         Ok(Self { pairs })
     }
 }
@@ -369,6 +681,8 @@ mod tests {
     }
 
     mod pair {
+        use crate::rng::xorshift;
+
         use super::super::*;
 
         #[test]
@@ -378,6 +692,45 @@ mod tests {
             assert_eq!(p.to_string(), input);
         }
 
+        #[test]
+        fn parse_errors() {
+            for (input, offset, expected, found) in [
+                ("", 0, "'['", None),
+                ("1", 0, "'['", Some('1')),
+                // missing brackets
+                ("[1,2", 4, "']'", None),
+                ("[[1,2],3", 8, "']'", None),
+                ("[[1,2,3]", 5, "']'", Some(',')),
+                ("1,2]", 0, "'['", Some('1')),
+                // missing or misplaced elements
+                ("[,2]", 1, "a number or '['", Some(',')),
+                ("[1,]", 3, "a number or '['", Some(']')),
+                ("[1 2]", 2, "','", Some(' ')),
+                ("[[1,2],[3,x]]", 10, "a number or '['", Some('x')),
+                // trailing garbage
+                ("[1,2]]", 5, "end of input", Some(']')),
+                ("[1,2] ", 5, "end of input", Some(' ')),
+            ] {
+                let e = Pair::from_str(input).expect_err(input);
+                assert_eq!(
+                    e,
+                    ParseError {
+                        offset,
+                        expected,
+                        found
+                    },
+                    "{}",
+                    input
+                );
+            }
+
+            let e = Pair::from_str("[1,2").unwrap_err();
+            assert_eq!(
+                e.to_string(),
+                "Unable to parse pair: expected ']' at offset 4, found the end of input"
+            );
+        }
+
         #[test]
         fn addition() {
             let p1 = Pair::new(1.into(), 2.into());
@@ -405,6 +758,79 @@ mod tests {
             assert_eq!(p.magnitude(), 3488);
         }
 
+        #[test]
+        fn random() {
+            let mut next = xorshift(0x2545_f491_4f6c_dd1d);
+
+            let depth = |s: &str| {
+                s.chars()
+                    .scan(0_i64, |d, ch| {
+                        match ch {
+                            '[' => *d += 1,
+                            ']' => *d -= 1,
+                            _ => {}
+                        }
+                        Some(*d)
+                    })
+                    .max()
+                    .unwrap_or_default()
+            };
+
+            // every element a pair fills the number out to the limit
+            let full = Pair::random_sized(4, 100, &mut next).to_string();
+            assert_eq!(full.matches(',').count(), 15);
+            assert_eq!(Pair::random_sized(4, 0, &mut next).to_string().len(), 5);
+
+            for _ in 0..200 {
+                let p = Pair::random(4, &mut next);
+                let q = Pair::random(4, &mut next);
+                assert!(depth(&p.to_string()) <= 4);
+
+                // the parser and the flat form agree with the boxed form
+                assert_eq!(Pair::from_str(&p.to_string()).unwrap(), p);
+                let (fp, fq) = (FlatNumber::from(&p), FlatNumber::from(&q));
+                assert_eq!(fp.magnitude(), p.magnitude());
+
+                let sum = &p + &q;
+                assert_eq!(Pair::from(&(&fp + &fq)), sum);
+                assert_eq!((&fp + &fq).magnitude(), sum.magnitude());
+
+                // reduction is idempotent
+                let mut again = sum.clone();
+                again.reduce();
+                assert_eq!(again, sum);
+            }
+        }
+
+        #[test]
+        fn checked_magnitude() {
+            let input = "[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]";
+            let p = Pair::from_str(input).expect("could not parse pair");
+            assert_eq!(p.checked_magnitude().unwrap(), 3488);
+
+            // each level of nesting on the left triples the magnitude, so
+            // this overflows long before it is 64 levels deep
+            let mut p = Pair::new(9.into(), 9.into());
+            for _ in 0..63 {
+                p = Pair::new(p.into(), 9.into());
+            }
+            assert!(p.checked_magnitude().is_err());
+
+            #[cfg(feature = "bigint")]
+            {
+                use num_bigint::BigInt;
+
+                let mut expected = BigInt::from(45);
+                for _ in 0..63 {
+                    expected = expected * 3 + 18;
+                }
+                assert_eq!(p.magnitude_big(), expected);
+
+                let p = Pair::from_str(input).expect("could not parse pair");
+                assert_eq!(p.magnitude_big(), BigInt::from(3488));
+            }
+        }
+
         #[test]
         fn reduce() {
             let input = "[[[[[9,8],1],2],3],4]";
@@ -445,6 +871,108 @@ mod tests {
         }
     }
 
+    mod flat_number {
+        use super::super::*;
+
+        #[test]
+        fn round_trip() {
+            let input = "[[[[[9,8],1],2],3],[4,[5,[6,7]]]]";
+            let p = Pair::from_str(input).expect("could not parse pair");
+            let flat = FlatNumber::from(&p);
+            assert_eq!(flat.to_string(), input);
+            assert_eq!(Pair::from(&flat), p);
+        }
+
+        #[test]
+        fn reduce() {
+            for (input, expected) in [
+                ("[[[[[9,8],1],2],3],4]", "[[[[0,9],2],3],4]"),
+                ("[7,[6,[5,[4,[3,2]]]]]", "[7,[6,[5,[7,0]]]]"),
+                ("[[6,[5,[4,[3,2]]]],1]", "[[6,[5,[7,0]]],3]"),
+                (
+                    "[[3,[2,[1,[7,3]]]],[6,[5,[4,[3,2]]]]]",
+                    "[[3,[2,[8,0]]],[9,[5,[7,0]]]]",
+                ),
+                (
+                    "[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]",
+                    "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]",
+                ),
+            ] {
+                let p = Pair::from_str(input).expect("could not parse pair");
+                let mut flat = FlatNumber::from(&p);
+                flat.reduce();
+                assert_eq!(flat.to_string(), expected);
+            }
+        }
+
+        #[test]
+        fn deep_nesting() {
+            for (input, expected) in [
+                ("[1,[2,[3,[4,[5,[6,7]]]]]]", "[1,[2,[[5,5],[0,8]]]]"),
+                ("[[[[[1,[2,3]],4],5],6],7]", "[[[[0,7],5],6],7]"),
+            ] {
+                let p = Pair::from_str(input).expect("could not parse pair");
+                let mut flat = FlatNumber::from(&p);
+                flat.reduce();
+                assert_eq!(flat.to_string(), expected);
+
+                let reduced = Pair::from_str(expected).expect("could not parse pair");
+                assert_eq!(flat.magnitude(), reduced.magnitude());
+            }
+        }
+
+        #[test]
+        fn addition_and_magnitude() {
+            let p1 = Pair::from_str("[[[[4,3],4],4],[7,[[8,4],9]]]").expect("could not parse pair");
+            let p2 = Pair::from_str("[1,1]").expect("could not parse pair");
+            let sum = &FlatNumber::from(&p1) + &FlatNumber::from(&p2);
+            assert_eq!(sum.to_string(), "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+
+            // a scratch number gives the same sum, whatever it held before
+            let mut scratch = FlatNumber::from(&p1);
+            FlatNumber::from(&p1).add_into(&FlatNumber::from(&p2), &mut scratch);
+            assert_eq!(scratch, sum);
+            assert_eq!(Pair::from(&sum), p1 + p2);
+
+            let numbers: Vec<FlatNumber> = [
+                "[[[0,[4,5]],[0,0]],[[[4,5],[2,6]],[9,5]]]",
+                "[7,[[[3,7],[4,3]],[[6,3],[8,8]]]]",
+                "[[2,[[0,8],[3,4]]],[[[6,7],1],[7,[1,6]]]]",
+                "[[[[2,4],7],[6,[0,5]]],[[[6,8],[2,8]],[[2,1],[4,5]]]]",
+                "[7,[5,[[3,8],[1,4]]]]",
+                "[[2,[2,2]],[8,[8,1]]]",
+                "[2,9]",
+                "[1,[[[9,3],9],[[9,0],[0,7]]]]",
+                "[[[5,[7,4]],7],1]",
+                "[[[[4,2],2],6],[8,7]]",
+            ]
+            .iter()
+            .map(|s| FlatNumber::from(&Pair::from_str(s).expect("could not parse pair")))
+            .collect();
+            let mut sum = numbers[0].clone();
+            let mut scratch = FlatNumber::default();
+            for n in &numbers[1..] {
+                sum.add_into(n, &mut scratch);
+                std::mem::swap(&mut sum, &mut scratch);
+            }
+            assert_eq!(
+                sum.to_string(),
+                "[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]"
+            );
+
+            for (input, expected) in [
+                ("[[1,2],[[3,4],5]]", 143),
+                (
+                    "[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]",
+                    3488,
+                ),
+            ] {
+                let p = Pair::from_str(input).expect("could not parse pair");
+                assert_eq!(FlatNumber::from(&p).magnitude(), expected);
+            }
+        }
+    }
+
     mod homework {
         use aoc_helpers::util::test_input;
 
@@ -496,6 +1024,7 @@ mod tests {
                 .expect("No magnitude calculated");
 
             assert_eq!(m, 3993);
+            assert_eq!(homework.largest_magnitude_of_pairs_boxed(), Some(3993));
         }
     }
 }
@@ -16,6 +16,8 @@ pub mod octopus;
 pub mod polymer;
 pub mod probe;
 pub mod reactor;
+#[cfg(test)]
+mod rng;
 pub mod scanner;
 pub mod solutions;
 pub mod sonar;
@@ -23,3 +25,4 @@ pub mod ssd;
 pub mod submarine;
 pub mod trench;
 pub mod vents;
+pub mod visualize;
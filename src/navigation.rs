@@ -1,6 +1,6 @@
-use std::{convert::TryFrom, str::FromStr};
+use std::{convert::TryFrom, fmt, str::FromStr};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use aoc_helpers::{parse_input, Solver};
 use itertools::Itertools;
 
@@ -38,10 +38,108 @@ impl Delimiter for char {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DelimiterKind {
+    Paren,
+    Bracket,
+    Brace,
+    Angle,
+}
+
+impl DelimiterKind {
+    pub const ALL: [DelimiterKind; 4] = [
+        DelimiterKind::Paren,
+        DelimiterKind::Bracket,
+        DelimiterKind::Brace,
+        DelimiterKind::Angle,
+    ];
+
+    /// Get the kind of delimiter for `ch`, which may be either the opening or
+    /// closing character
+    pub fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            '(' | ')' => Some(Self::Paren),
+            '[' | ']' => Some(Self::Bracket),
+            '{' | '}' => Some(Self::Brace),
+            '<' | '>' => Some(Self::Angle),
+            _ => None,
+        }
+    }
+
+    pub fn opening(&self) -> char {
+        match self {
+            Self::Paren => '(',
+            Self::Bracket => '[',
+            Self::Brace => '{',
+            Self::Angle => '<',
+        }
+    }
+
+    pub fn closing(&self) -> char {
+        match self {
+            Self::Paren => ')',
+            Self::Bracket => ']',
+            Self::Brace => '}',
+            Self::Angle => '>',
+        }
+    }
+}
+
+/// Nesting information gathered while checking a line (or lines).
+///
+/// For corrupted lines, this only covers the portion of the line before the
+/// corrupted character.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct DepthMetrics {
+    pub max_depth: usize,
+    /// `depth_histogram[d]` is the number of chunks that were opened while `d`
+    /// other chunks were already open
+    pub depth_histogram: Vec<usize>,
+    delimiter_counts: [usize; 4],
+}
+
+impl DepthMetrics {
+    /// The number of chunks opened with the given kind of delimiter
+    pub fn count(&self, kind: DelimiterKind) -> usize {
+        self.delimiter_counts[kind as usize]
+    }
+
+    /// Record a chunk of `kind` being opened at `depth`
+    fn record(&mut self, kind: DelimiterKind, depth: usize) {
+        if self.depth_histogram.len() <= depth {
+            self.depth_histogram.resize(depth + 1, 0);
+        }
+        self.depth_histogram[depth] += 1;
+        self.max_depth = self.max_depth.max(depth + 1);
+        self.delimiter_counts[kind as usize] += 1;
+    }
+
+    pub fn merge(&mut self, other: &DepthMetrics) {
+        if self.depth_histogram.len() < other.depth_histogram.len() {
+            self.depth_histogram.resize(other.depth_histogram.len(), 0);
+        }
+
+        for (depth, count) in other.depth_histogram.iter().enumerate() {
+            self.depth_histogram[depth] += count;
+        }
+
+        for (a, b) in self
+            .delimiter_counts
+            .iter_mut()
+            .zip(other.delimiter_counts.iter())
+        {
+            *a += b;
+        }
+
+        self.max_depth = self.max_depth.max(other.max_depth);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CheckResult {
     pub corrupted_char: Option<char>,
     pub remaining_openings: Vec<char>,
+    pub metrics: DepthMetrics,
 }
 
 impl CheckResult {
@@ -61,15 +159,114 @@ impl CheckResult {
     }
 }
 
-impl From<(Option<char>, Vec<char>)> for CheckResult {
-    fn from(value: (Option<char>, Vec<char>)) -> Self {
+impl From<(Option<char>, Vec<char>, DepthMetrics)> for CheckResult {
+    fn from(value: (Option<char>, Vec<char>, DepthMetrics)) -> Self {
         Self {
             corrupted_char: value.0,
             remaining_openings: value.1,
+            metrics: value.2,
         }
     }
 }
 
+/// The chunk structure of a non-corrupted line
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Node {
+    /// The top level of a line, holding every outermost chunk
+    Root(Vec<Node>),
+    /// A chunk and any chunks nested inside it. Chunks from incomplete lines
+    /// may not be closed.
+    Chunk {
+        kind: DelimiterKind,
+        closed: bool,
+        children: Vec<Node>,
+    },
+}
+
+impl Node {
+    pub fn children(&self) -> &[Node] {
+        match self {
+            Self::Root(children) => children,
+            Self::Chunk { children, .. } => children,
+        }
+    }
+
+    /// The maximum number of nested chunks in this tree, including this node
+    /// (if it is a chunk)
+    pub fn depth(&self) -> usize {
+        let inner = self.children().iter().map(|c| c.depth()).max().unwrap_or(0);
+        match self {
+            Self::Root(_) => inner,
+            Self::Chunk { .. } => inner + 1,
+        }
+    }
+
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            Self::Root(children) => {
+                for child in children {
+                    child.fmt_pretty(f, indent)?;
+                }
+            }
+            Self::Chunk {
+                kind,
+                closed,
+                children,
+            } => {
+                writeln!(f, "{:indent$}{}", "", kind.opening(), indent = indent)?;
+                for child in children {
+                    child.fmt_pretty(f, indent + 2)?;
+                }
+                if *closed {
+                    writeln!(f, "{:indent$}{}", "", kind.closing(), indent = indent)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Node {
+    /// The default format reproduces the source line, while the alternate
+    /// format (`{:#}`) prints one delimiter per line, indented by depth
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.fmt_pretty(f, 0);
+        }
+
+        match self {
+            Self::Root(children) => {
+                for child in children {
+                    write!(f, "{}", child)?;
+                }
+            }
+            Self::Chunk {
+                kind,
+                closed,
+                children,
+            } => {
+                write!(f, "{}", kind.opening())?;
+                for child in children {
+                    write!(f, "{}", child)?;
+                }
+                if *closed {
+                    write!(f, "{}", kind.closing())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A single character edit that removes the corruption from a line
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Repair {
+    Substitute { index: usize, from: char, to: char },
+    Delete { index: usize, ch: char },
+}
+
 #[derive(Debug, Clone)]
 pub struct Line {
     chars: Vec<char>,
@@ -78,25 +275,160 @@ pub struct Line {
 impl Line {
     pub fn check_corrupt(&self) -> CheckResult {
         let mut remainder = Vec::with_capacity(self.chars.len());
+        let mut metrics = DepthMetrics::default();
         for ch in self.chars.iter() {
             match ch {
                 '(' | '[' | '<' | '{' => {
+                    if let Some(kind) = DelimiterKind::from_char(*ch) {
+                        metrics.record(kind, remainder.len());
+                    }
                     remainder.push(*ch);
                 }
                 ')' | ']' | '>' | '}' => {
                     if let Some(last) = remainder.pop() {
                         if !ch.closes(&last) {
-                            return (Some(*ch), remainder).into();
+                            return (Some(*ch), remainder, metrics).into();
                         }
                     } else {
-                        return (Some(*ch), remainder).into();
+                        return (Some(*ch), remainder, metrics).into();
                     }
                 }
                 _ => unreachable!("todo: fix this"),
             };
         }
 
-        (None, remainder).into()
+        (None, remainder, metrics).into()
+    }
+
+    /// Build the tree of chunks for this line. Unclosed chunks from incomplete
+    /// lines are kept, but corrupted lines are an error.
+    pub fn parse_tree(&self) -> Result<Node> {
+        // each open chunk and the children found inside it so far
+        let mut stack: Vec<(DelimiterKind, Vec<Node>)> = Vec::new();
+        let mut root = Vec::new();
+
+        for (idx, ch) in self.chars.iter().enumerate() {
+            let kind = DelimiterKind::from_char(*ch)
+                .ok_or_else(|| anyhow!("invalid character '{}' at {}", ch, idx))?;
+
+            if *ch == kind.opening() {
+                stack.push((kind, Vec::new()));
+                continue;
+            }
+
+            match stack.pop() {
+                Some((open, children)) if open == kind => {
+                    let node = Node::Chunk {
+                        kind,
+                        closed: true,
+                        children,
+                    };
+                    Self::attach(&mut stack, &mut root, node);
+                }
+                _ => bail!("line is corrupted: unexpected '{}' at {}", ch, idx),
+            }
+        }
+
+        while let Some((kind, children)) = stack.pop() {
+            let node = Node::Chunk {
+                kind,
+                closed: false,
+                children,
+            };
+            Self::attach(&mut stack, &mut root, node);
+        }
+
+        Ok(Node::Root(root))
+    }
+
+    /// Suggest a single substitution or deletion that would make this
+    /// corrupted line valid (though possibly still incomplete).
+    ///
+    /// Edits closest to the detected corruption are preferred, and
+    /// substitutions are preferred over deletions at the same position.
+    /// Returns `None` if the line is not corrupted or if no single edit is
+    /// sufficient.
+    pub fn suggest_repair(&self) -> Option<Repair> {
+        let corrupted_at = Self::corruption_index(&self.chars)?;
+        let mut candidate = self.chars.clone();
+
+        // an edit after the point of corruption cannot fix it, so we work
+        // backwards from there, re-checking the entire line for each edit,
+        // since fixing one closing delimiter can shift the mismatch elsewhere
+        for index in (0..=corrupted_at).rev() {
+            let original = self.chars[index];
+
+            // closing delimiters first, as those fix the line without
+            // leaving it any less complete
+            let replacements = DelimiterKind::ALL
+                .iter()
+                .map(|k| k.closing())
+                .chain(DelimiterKind::ALL.iter().map(|k| k.opening()));
+
+            for to in replacements {
+                if to == original {
+                    continue;
+                }
+
+                candidate[index] = to;
+                if Self::corruption_index(&candidate).is_none() {
+                    return Some(Repair::Substitute {
+                        index,
+                        from: original,
+                        to,
+                    });
+                }
+            }
+            candidate[index] = original;
+
+            candidate.remove(index);
+            if Self::corruption_index(&candidate).is_none() {
+                return Some(Repair::Delete {
+                    index,
+                    ch: original,
+                });
+            }
+            candidate.insert(index, original);
+        }
+
+        None
+    }
+
+    /// Make a copy of this line with the given `repair` applied
+    pub fn repaired(&self, repair: &Repair) -> Self {
+        let mut chars = self.chars.clone();
+        match repair {
+            Repair::Substitute { index, to, .. } => chars[*index] = *to,
+            Repair::Delete { index, .. } => {
+                chars.remove(*index);
+            }
+        }
+
+        Self { chars }
+    }
+
+    /// The index of the first character that makes `chars` corrupt
+    fn corruption_index(chars: &[char]) -> Option<usize> {
+        let mut stack = Vec::with_capacity(chars.len());
+        for (idx, ch) in chars.iter().enumerate() {
+            match ch {
+                '(' | '[' | '<' | '{' => stack.push(*ch),
+                ')' | ']' | '>' | '}' => match stack.pop() {
+                    Some(last) if ch.closes(&last) => {}
+                    _ => return Some(idx),
+                },
+                _ => return Some(idx),
+            }
+        }
+
+        None
+    }
+
+    fn attach(stack: &mut [(DelimiterKind, Vec<Node>)], root: &mut Vec<Node>, node: Node) {
+        match stack.last_mut() {
+            Some((_, siblings)) => siblings.push(node),
+            None => root.push(node),
+        }
     }
 }
 
@@ -140,6 +472,16 @@ impl ProgramCheckResult {
         let middle = scores.len() / 2;
         scores.get(middle).copied().unwrap_or(0)
     }
+
+    /// Nesting metrics aggregated over every checked line
+    pub fn metrics(&self) -> DepthMetrics {
+        self.results
+            .iter()
+            .fold(DepthMetrics::default(), |mut acc, r| {
+                acc.merge(&r.metrics);
+                acc
+            })
+    }
 }
 
 impl From<Vec<CheckResult>> for ProgramCheckResult {
@@ -244,6 +586,115 @@ mod tests {
         }
     }
 
+    mod line {
+        use super::super::*;
+
+        #[test]
+        fn metrics() {
+            let line = Line::from_str("([]{<>})").expect("could not parse line");
+            let res = line.check_corrupt();
+            assert!(!res.is_corrupted());
+            assert_eq!(res.metrics.max_depth, 3);
+            assert_eq!(res.metrics.depth_histogram, vec![1, 2, 1]);
+            for kind in DelimiterKind::ALL {
+                assert_eq!(res.metrics.count(kind), 1);
+            }
+
+            // metrics for corrupted lines stop at the corruption
+            let line = Line::from_str("(<[>{{{{").expect("could not parse line");
+            let res = line.check_corrupt();
+            assert!(res.is_corrupted());
+            assert_eq!(res.metrics.max_depth, 3);
+            assert_eq!(res.metrics.depth_histogram, vec![1, 1, 1]);
+            assert_eq!(res.metrics.count(DelimiterKind::Brace), 0);
+        }
+
+        #[test]
+        fn parse_tree() {
+            let line = Line::from_str("([]{<>})[").expect("could not parse line");
+            let tree = line.parse_tree().expect("could not build tree");
+
+            let expected = Node::Root(vec![
+                Node::Chunk {
+                    kind: DelimiterKind::Paren,
+                    closed: true,
+                    children: vec![
+                        Node::Chunk {
+                            kind: DelimiterKind::Bracket,
+                            closed: true,
+                            children: vec![],
+                        },
+                        Node::Chunk {
+                            kind: DelimiterKind::Brace,
+                            closed: true,
+                            children: vec![Node::Chunk {
+                                kind: DelimiterKind::Angle,
+                                closed: true,
+                                children: vec![],
+                            }],
+                        },
+                    ],
+                },
+                Node::Chunk {
+                    kind: DelimiterKind::Bracket,
+                    closed: false,
+                    children: vec![],
+                },
+            ]);
+
+            assert_eq!(tree, expected);
+            assert_eq!(tree.depth(), 3);
+            assert_eq!(tree.to_string(), "([]{<>})[");
+            assert_eq!(
+                format!("{:#}", tree),
+                "(\n  [\n  ]\n  {\n    <\n    >\n  }\n)\n[\n"
+            );
+        }
+
+        #[test]
+        fn parse_tree_corrupted() {
+            let line = Line::from_str("{([(<{}[<>[]}>{[]{[(<()>").expect("could not parse line");
+            assert!(line.parse_tree().is_err());
+
+            let line = Line::from_str("(]").expect("could not parse line");
+            assert!(line.parse_tree().is_err());
+        }
+
+        #[test]
+        fn suggest_repair() {
+            let line = Line::from_str("(]").expect("could not parse line");
+            let repair = line.suggest_repair().expect("could not find repair");
+            assert_eq!(
+                repair,
+                Repair::Substitute {
+                    index: 1,
+                    from: ']',
+                    to: ')'
+                }
+            );
+            assert!(!line.repaired(&repair).check_corrupt().is_corrupted());
+
+            let line = Line::from_str("(<>)>[]").expect("could not parse line");
+            let repair = line.suggest_repair().expect("could not find repair");
+            assert_eq!(
+                repair,
+                Repair::Substitute {
+                    index: 4,
+                    from: '>',
+                    to: '('
+                }
+            );
+
+            // nothing to repair
+            let line = Line::from_str("([]").expect("could not parse line");
+            assert_eq!(line.suggest_repair(), None);
+
+            // no single edit is sufficient
+            let line = Line::from_str("]]]").expect("could not parse line");
+            assert_eq!(line.suggest_repair(), None);
+        }
+    }
+
     mod program {
         use aoc_helpers::util::{parse_input, test_input};
 
@@ -271,6 +722,36 @@ mod tests {
             assert_eq!(program.check().score_corruptions(), 26397);
         }
 
+        #[test]
+        fn repairs() {
+            let input = test_input(
+                "
+                [({(<(())[]>[[{[]{<()<>>
+                [(()[<>])]({[<{<<[]>>(
+                {([(<{}[<>[]}>{[]{[(<()>
+                (((({<>}<{<{<>}{[]{[]{}
+                [[<[([]))<([[{}[[()]]]
+                [{[{({}]{}}([{[{{{}}([]
+                {<[[]]>}<{[{[{[]{()[[[]
+                [<(<(<(<{}))><([]([]()
+                <{([([[(<>()){}]>(<<{{
+                <{([{{}}[<[[[<>{}]]]>[]]
+                ",
+            );
+
+            let lines: Vec<Line> = parse_input(&input).expect("could not parse input");
+            for line in lines {
+                let corrupted = line.check_corrupt().is_corrupted();
+                match line.suggest_repair() {
+                    Some(repair) => {
+                        assert!(corrupted);
+                        assert!(!line.repaired(&repair).check_corrupt().is_corrupted());
+                    }
+                    None => assert!(!corrupted),
+                }
+            }
+        }
+
         #[test]
         fn score_completions() {
             let input = test_input(
@@ -293,5 +774,26 @@ mod tests {
 
             assert_eq!(program.check().score_completions(), 288957);
         }
+
+        #[test]
+        fn metrics() {
+            let input = test_input(
+                "
+                ([]{<>})
+                [[<>]
+                ",
+            );
+
+            let lines: Vec<Line> = parse_input(&input).expect("could not parse input");
+            let program = Program::from(lines);
+            let metrics = program.check().metrics();
+
+            assert_eq!(metrics.max_depth, 3);
+            assert_eq!(metrics.depth_histogram, vec![2, 3, 2]);
+            assert_eq!(metrics.count(DelimiterKind::Paren), 1);
+            assert_eq!(metrics.count(DelimiterKind::Bracket), 3);
+            assert_eq!(metrics.count(DelimiterKind::Brace), 1);
+            assert_eq!(metrics.count(DelimiterKind::Angle), 2);
+        }
     }
 }
@@ -1,13 +1,95 @@
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
 
-use anyhow::{anyhow, Result};
-use rustc_hash::FxHashSet;
+use anyhow::{anyhow, bail, Result};
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use aoc_helpers::{
-    generic::{prelude::*, Grid, Location},
+    generic::{Grid, Location},
     Solver,
 };
 
+use crate::visualize::Visualize;
+
+/// A repeating sequence of grid states
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+struct Cycle {
+    /// the number of steps before the cycle begins
+    start: usize,
+    period: usize,
+    /// `totals[n]` is the number of flashes in the first `n` steps, up to the
+    /// first repeated state
+    totals: Vec<u128>,
+}
+
+/// How the edges of the grid are treated when finding neighbors
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Topology {
+    /// Octopuses on the edges have fewer neighbors
+    Bounded,
+    /// The grid wraps around, so octopuses on the edges see the octopuses on
+    /// the opposite edge as neighbors
+    Toroidal,
+}
+
+impl Topology {
+    /// The positions adjacent to (and including) `pos` along an axis of `len`
+    /// positions. Only the first `n` entries of the returned array are valid.
+    fn adjacent(&self, pos: usize, len: usize) -> ([usize; 3], usize) {
+        match self {
+            Self::Bounded => {
+                let start = pos.saturating_sub(1);
+                let end = (pos + 1).min(len - 1);
+                let mut out = [0; 3];
+                for (i, p) in (start..=end).enumerate() {
+                    out[i] = p;
+                }
+                (out, end - start + 1)
+            }
+            // with fewer than 3 positions, wrapping around would visit the
+            // same position more than once
+            Self::Toroidal if len < 3 => ([0, 1, 2], len),
+            Self::Toroidal => ([(pos + len - 1) % len, pos, (pos + 1) % len], 3),
+        }
+    }
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Self::Bounded
+    }
+}
+
+/// The rules governing how octopuses gain energy and flash.
+///
+/// The default rules are the ones from the puzzle. Energy levels saturate at
+/// `u8::MAX`, and `reset` is expected to be no greater than `threshold`.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct Rules {
+    /// An octopus flashes once its energy level exceeds this value
+    pub threshold: u8,
+    /// The energy level an octopus returns to after flashing
+    pub reset: u8,
+    /// The energy gained every step and every time a neighbor flashes
+    pub increment: u8,
+}
+
+impl Rules {
+    pub fn new(threshold: u8, reset: u8, increment: u8) -> Self {
+        Self {
+            threshold,
+            reset,
+            increment,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::new(9, 0, 1)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default, Hash, Eq, PartialEq)]
 pub struct Octopus(pub i64);
 
@@ -17,14 +99,24 @@ impl Octopus {
     }
 
     pub fn reset(&mut self) {
-        self.0 = 0;
+        self.reset_with(&Rules::default());
     }
 
     /// Increase the current energy level by one and return `true` if the
     /// octopus would now flash
     pub fn charge(&mut self) -> bool {
-        self.0 += 1;
-        self.0 > 9
+        self.charge_with(&Rules::default())
+    }
+
+    pub fn reset_with(&mut self, rules: &Rules) {
+        self.0 = rules.reset.into();
+    }
+
+    /// Increase the current energy level according to `rules` and return
+    /// `true` if the octopus would now flash
+    pub fn charge_with(&mut self, rules: &Rules) -> bool {
+        self.0 += i64::from(rules.increment);
+        self.0 > rules.threshold.into()
     }
 }
 
@@ -34,26 +126,154 @@ impl From<i64> for Octopus {
     }
 }
 
+/// The parts of an `OctopusGrid` needed to propagate flashes through one band
+/// of the grid independently of the others
+#[derive(Debug, Clone, Copy)]
+struct Engine {
+    rows: usize,
+    cols: usize,
+    rules: Rules,
+    topology: Topology,
+}
+
+impl Engine {
+    fn charge(&self, energy: &mut u8) -> bool {
+        *energy = energy.saturating_add(self.rules.increment);
+        *energy > self.rules.threshold
+    }
+
+    /// Propagate flashes through the band of octopi `energy`, which starts at
+    /// (global) index `offset`. If `charge_all` is set, every octopus in the
+    /// band is charged first, then each octopus in `charges` is charged.
+    ///
+    /// Returns the global indices of the octopi outside this band that need to
+    /// be charged due to flashes within it.
+    fn cascade(
+        &self,
+        energy: &mut [u8],
+        offset: usize,
+        charges: &[usize],
+        charge_all: bool,
+    ) -> Vec<usize> {
+        let end = offset + energy.len();
+        let mut pending = Vec::new();
+        let mut outgoing = Vec::new();
+
+        if charge_all {
+            for (local, e) in energy.iter_mut().enumerate() {
+                if self.charge(e) {
+                    pending.push(offset + local);
+                }
+            }
+        }
+
+        for idx in charges {
+            let e = &mut energy[idx - offset];
+            if *e <= self.rules.threshold && self.charge(e) {
+                pending.push(*idx);
+            }
+        }
+
+        while let Some(idx) = pending.pop() {
+            let (rows, num_rows) = self.topology.adjacent(idx / self.cols, self.rows);
+            let (cols, num_cols) = self.topology.adjacent(idx % self.cols, self.cols);
+
+            for r in &rows[..num_rows] {
+                for c in &cols[..num_cols] {
+                    let neighbor = r * self.cols + c;
+                    if neighbor == idx {
+                        continue;
+                    }
+
+                    if neighbor < offset || neighbor >= end {
+                        outgoing.push(neighbor);
+                        continue;
+                    }
+
+                    let e = &mut energy[neighbor - offset];
+                    if *e <= self.rules.threshold && self.charge(e) {
+                        pending.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        outgoing
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct OctopusGrid {
-    octopuses: Grid<Octopus>,
+    /// energy levels in row-major order, packed into a byte each since
+    /// `Rules` never take them past `u8::MAX`
+    energy: Vec<u8>,
+    rows: usize,
+    cols: usize,
+    rules: Rules,
+    topology: Topology,
+    /// scratch space for the flash cascade, kept to avoid reallocating
+    pending: Vec<usize>,
+    /// which octopuses flashed during the most recent step
+    flashed: Vec<bool>,
+    /// how many times each octopus has flashed
+    flash_counts: Vec<usize>,
+    /// the generation in which each octopus first flashed
+    first_flashes: Vec<Option<usize>>,
+    total_flashes: usize,
     syncd_genrations: Vec<usize>,
     generations: usize,
 }
 
 impl OctopusGrid {
+    /// Use `rules` instead of the default puzzle rules for all future steps
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Use `topology` to determine the neighbors of the octopuses on the edges
+    /// of the grid
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn size(&self) -> usize {
+        self.energy.len()
+    }
+
+    /// The current energy level of the octopus specified by `loc`
+    pub fn get(&self, loc: &Location) -> Option<Octopus> {
+        self.index(loc).map(|idx| Octopus(self.energy[idx].into()))
+    }
+
     /// Charge the octopus specified by `loc` and return `true` if it flashes
     pub fn charge(&mut self, loc: &Location) -> bool {
-        self.octopuses
-            .get_mut(loc)
-            .map(|oct| oct.charge())
+        self.index(loc)
+            .map(|idx| self.charge_index(idx))
             .unwrap_or(false)
     }
 
     /// Reset the octopus specified by `loc`
     pub fn reset(&mut self, loc: &Location) {
-        if let Some(oct) = self.octopuses.get_mut(loc) {
-            oct.reset();
+        if let Some(idx) = self.index(loc) {
+            self.energy[idx] = self.rules.reset;
         }
     }
 
@@ -74,72 +294,366 @@ impl OctopusGrid {
             return *gen;
         }
 
+        self.simulate_until(|grid, flashes| flashes == grid.size())
+    }
+
+    /// Simulate the grid of octopi until `pred` returns `true`, returning the
+    /// generation in which that happened.
+    ///
+    /// After every step, `pred` is given the grid and the number of octopi that
+    /// flashed during that step.
+    pub fn simulate_until<F>(&mut self, mut pred: F) -> usize
+    where
+        F: FnMut(&OctopusGrid, usize) -> bool,
+    {
         loop {
-            if self.octopuses.size() == self.step() {
+            let flashes = self.step();
+            if pred(self, flashes) {
                 break self.generations;
             }
         }
     }
 
+    /// The number of steps in the cycle of states this grid eventually
+    /// enters, starting from the current state. Once the octopi synchronize,
+    /// this is the number of steps between each synchronized flash.
+    ///
+    /// This does not modify the grid.
+    pub fn period(&self) -> usize {
+        self.find_cycle().period
+    }
+
+    /// The number of flashes there would be in the next `n` steps, starting
+    /// from the current state. Because the grid always ends up in a cycle,
+    /// this only has to simulate until the first repeated state, no matter how
+    /// large `n` is.
+    ///
+    /// This does not modify the grid.
+    pub fn flashes_after(&self, n: u64) -> u128 {
+        let cycle = self.find_cycle();
+
+        if n < cycle.totals.len() as u64 {
+            return cycle.totals[n as usize];
+        }
+
+        let before = cycle.totals[cycle.start];
+        let per_cycle = cycle.totals[cycle.start + cycle.period] - before;
+        let offset = n - cycle.start as u64;
+        let full_cycles = (offset / cycle.period as u64) as u128;
+        let remainder = (offset % cycle.period as u64) as usize;
+
+        before + full_cycles * per_cycle + (cycle.totals[cycle.start + remainder] - before)
+    }
+
+    /// Simulate a copy of this grid until it returns to a state it has been
+    /// in before
+    fn find_cycle(&self) -> Cycle {
+        let mut grid = self.clone();
+        let mut seen: FxHashMap<Vec<u8>, usize> = FxHashMap::default();
+        let mut totals = vec![0_u128];
+
+        loop {
+            let steps = totals.len() - 1;
+            if let Some(start) = seen.insert(grid.energy.clone(), steps) {
+                return Cycle {
+                    start,
+                    period: steps - start,
+                    totals,
+                };
+            }
+
+            let flashes = grid.step() as u128;
+            totals.push(totals[steps] + flashes);
+        }
+    }
+
     /// Perform one step of the simulation, returning the number of octopi that
     /// flashed during the step
     pub fn step(&mut self) -> usize {
         self.generations += 1;
-        // 1. increase every octopus by 1, storing the locations of flashes
+        let mut pending = std::mem::take(&mut self.pending);
+
+        // 1. increase every octopus, storing the locations of flashes
+        for idx in 0..self.energy.len() {
+            if self.charge_index(idx) {
+                pending.push(idx);
+            }
+        }
+
+        // 2. propagate flashes. An octopus that has flashed stays above the
+        // threshold for the rest of the step, which is enough to prevent it
+        // from being charged (and flashing) again
+        while let Some(idx) = pending.pop() {
+            let (rows, num_rows) = self.topology.adjacent(idx / self.cols, self.rows);
+            let (cols, num_cols) = self.topology.adjacent(idx % self.cols, self.cols);
+
+            for r in &rows[..num_rows] {
+                for c in &cols[..num_cols] {
+                    let neighbor = r * self.cols + c;
+                    if neighbor != idx
+                        && self.energy[neighbor] <= self.rules.threshold
+                        && self.charge_index(neighbor)
+                    {
+                        pending.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        self.pending = pending;
+
+        // 3. reset everything that flashed
+        self.finish_step()
+    }
+
+    /// Simulate the grid for `generations` generations using `step_par`, and
+    /// return the total number of flashes in that time
+    pub fn simulate_par(&mut self, generations: usize, band_rows: usize) -> usize {
+        (0..generations).map(|_| self.step_par(band_rows)).sum()
+    }
+
+    /// Perform one step of the simulation in parallel, returning the number of
+    /// octopi that flashed during the step.
+    ///
+    /// The grid is split into bands of `band_rows` rows, and each band
+    /// propagates flashes within itself. Flashes that reach past the edge of a
+    /// band are handed to the neighboring band, which propagates them in the
+    /// next round. Rounds continue until no band has anything left to hand
+    /// off. This is only worthwhile for grids far larger than the puzzle input.
+    pub fn step_par(&mut self, band_rows: usize) -> usize {
+        self.generations += 1;
+
+        if !self.energy.is_empty() {
+            let band_len = band_rows.max(1) * self.cols;
+            let num_bands = self.energy.chunks(band_len).len();
+            let engine = Engine {
+                rows: self.rows,
+                cols: self.cols,
+                rules: self.rules,
+                topology: self.topology,
+            };
+
+            let mut incoming: Vec<Vec<usize>> = vec![Vec::new(); num_bands];
+            let mut charge_all = true;
+
+            loop {
+                let outgoing: Vec<Vec<usize>> = self
+                    .energy
+                    .par_chunks_mut(band_len)
+                    .zip(incoming.par_iter())
+                    .enumerate()
+                    .map(|(band, (energy, charges))| {
+                        engine.cascade(energy, band * band_len, charges, charge_all)
+                    })
+                    .collect();
+
+                charge_all = false;
+                incoming.iter_mut().for_each(|charges| charges.clear());
+
+                let mut done = true;
+                for idx in outgoing.into_iter().flatten() {
+                    incoming[idx / band_len].push(idx);
+                    done = false;
+                }
+
+                if done {
+                    break;
+                }
+            }
+        }
+
+        self.finish_step()
+    }
+
+    /// Simulate the grid for `generations` generations using `step_hashed`,
+    /// and return the total number of flashes in that time
+    pub fn simulate_hashed(&mut self, generations: usize) -> usize {
+        (0..generations).map(|_| self.step_hashed()).sum()
+    }
+
+    /// Perform one step of the simulation the way it was originally done,
+    /// returning the number of octopi that flashed during the step.
+    ///
+    /// Flashes spread one wave at a time, with a set of the locations that
+    /// have already flashed to keep them from being charged again. This is
+    /// slower than `step`, and is only kept as a baseline for benchmarks.
+    pub fn step_hashed(&mut self) -> usize {
+        self.generations += 1;
+
+        // 1. increase every octopus, storing the locations of flashes
         let mut flashes: FxHashSet<Location> = FxHashSet::default();
-        for row in 0..self.octopuses.rows() {
-            for col in 0..self.octopuses.cols() {
-                let loc = (row, col).into();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let loc = Location::new(row, col);
                 if self.charge(&loc) {
-                    self.reset(&loc);
                     flashes.insert(loc);
                 }
             }
         }
-        // 2. rerusively propagate flash
-        self.recur(&flashes.clone(), &mut flashes);
 
-        // 3. since we reset during the charge check, and, since the cache
-        // prevents us from ever modifying an octopus that's already flashed
-        // this step, there's no need to zero the octopuses that flashed at
-        // this point
+        // 2. propagate flashes, one wave at a time
+        let mut already_flashed = flashes.clone();
+        while !flashes.is_empty() {
+            let mut next: FxHashSet<Location> = FxHashSet::default();
+            for loc in flashes.iter() {
+                let (rows, num_rows) = self.topology.adjacent(loc.row, self.rows);
+                let (cols, num_cols) = self.topology.adjacent(loc.col, self.cols);
+
+                for r in &rows[..num_rows] {
+                    for c in &cols[..num_cols] {
+                        let neighbor = Location::new(*r, *c);
+                        if already_flashed.contains(&neighbor) {
+                            continue;
+                        }
+
+                        if self.charge(&neighbor) {
+                            next.insert(neighbor);
+                            already_flashed.insert(neighbor);
+                        }
+                    }
+                }
+            }
+            flashes = next;
+        }
+
+        // 3. reset everything that flashed
+        self.finish_step()
+    }
 
-        let count = flashes.len();
-        if count == self.octopuses.size() {
+    /// Reset every octopus that flashed during the current step and update
+    /// the statistics, returning the number of flashes
+    fn finish_step(&mut self) -> usize {
+        let mut count = 0;
+        self.flashed.resize(self.energy.len(), false);
+        self.flash_counts.resize(self.energy.len(), 0);
+        self.first_flashes.resize(self.energy.len(), None);
+        for idx in 0..self.energy.len() {
+            self.flashed[idx] = self.energy[idx] > self.rules.threshold;
+            if self.flashed[idx] {
+                self.energy[idx] = self.rules.reset;
+                self.flash_counts[idx] += 1;
+                self.first_flashes[idx].get_or_insert(self.generations);
+                count += 1;
+            }
+        }
+        self.total_flashes += count;
+
+        if count == self.size() {
             self.syncd_genrations.push(self.generations);
         }
 
         count
     }
 
-    fn recur(
-        &mut self,
-        flash_locations: &FxHashSet<Location>,
-        already_flashed: &mut FxHashSet<Location>,
-    ) {
-        if flash_locations.is_empty() {
-            return;
-        }
-        // for every location that flashed, modify all neighboring locations by
-        // one, storing any "new" flashes
-        let mut flashes: FxHashSet<Location> = FxHashSet::default();
-        for loc in flash_locations.iter() {
-            for neighbor in loc.neighbors() {
-                if already_flashed.contains(&neighbor) {
-                    continue;
-                }
+    /// The number of steps simulated so far
+    pub fn generations(&self) -> usize {
+        self.generations
+    }
 
-                // charge the neighbor and, if it flashes, add it to the new
-                // list of flashes and the already_flashed cache
-                if self.charge(&neighbor) {
-                    self.reset(&neighbor);
-                    flashes.insert(neighbor);
-                    already_flashed.insert(neighbor);
-                }
+    /// The total number of flashes over every step simulated so far
+    pub fn total_flashes(&self) -> usize {
+        self.total_flashes
+    }
+
+    /// The number of times each octopus has flashed so far
+    pub fn flash_counts(&self) -> Grid<usize> {
+        self.to_grid(&self.flash_counts, 0)
+    }
+
+    /// The generation in which each octopus first flashed, if it has
+    pub fn first_flashes(&self) -> Grid<Option<usize>> {
+        self.to_grid(&self.first_flashes, None)
+    }
+
+    /// Render the grid as text, one row per line. Octopuses that flashed
+    /// during the most recent step are shown as `*`, and energy levels above 9
+    /// are shown as `+`.
+    pub fn render_frame(&self) -> String {
+        let mut out = String::with_capacity(self.rows * (self.cols + 1));
+
+        for (idx, energy) in self.energy.iter().enumerate() {
+            if idx > 0 && idx % self.cols == 0 {
+                out.push('\n');
             }
+
+            let ch = if self.flashed.get(idx).copied().unwrap_or(false) {
+                '*'
+            } else {
+                std::char::from_digit(*energy as u32, 10).unwrap_or('+')
+            };
+            out.push(ch);
         }
 
-        self.recur(&flashes, already_flashed);
+        out
+    }
+
+    /// Write the current state and up to `steps` subsequent steps to `writer`
+    /// as a looping animated GIF, stepping the grid as it goes. Each octopus is
+    /// drawn as a `scale` by `scale` block, in a shade of blue that brightens
+    /// with its energy level, or white if it flashed during that step.
+    ///
+    /// Returns the number of frames written.
+    #[cfg(feature = "image")]
+    pub fn write_gif<W: std::io::Write>(
+        &mut self,
+        steps: usize,
+        scale: usize,
+        writer: W,
+    ) -> Result<usize> {
+        let mut palette: Vec<(char, [u8; 3])> = (0..=9)
+            .map(|level| {
+                let shade = 16 + level as u8 * 16;
+                (
+                    std::char::from_digit(level, 10).unwrap_or('0'),
+                    [0, shade / 2, shade],
+                )
+            })
+            .collect();
+        palette.push(('+', [0, 96, 192]));
+        palette.push(('*', [255, 255, 255]));
+
+        crate::visualize::write_gif(
+            self,
+            steps,
+            std::time::Duration::from_millis(100),
+            scale,
+            &palette,
+            writer,
+        )
+    }
+
+    /// Lay out per-octopus `values` in the shape of the grid, using `default`
+    /// for any octopus without a value (i.e. before the first step)
+    fn to_grid<T: Clone>(&self, values: &[T], default: T) -> Grid<T> {
+        let rows = (0..self.rows)
+            .map(|row| {
+                (0..self.cols)
+                    .map(|col| {
+                        values
+                            .get(row * self.cols + col)
+                            .cloned()
+                            .unwrap_or_else(|| default.clone())
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Grid::new(rows)
+    }
+
+    fn index(&self, loc: &Location) -> Option<usize> {
+        if loc.row < self.rows && loc.col < self.cols {
+            Some(loc.row * self.cols + loc.col)
+        } else {
+            None
+        }
+    }
+
+    /// Charge the octopus at `idx`, returning `true` if it flashes
+    fn charge_index(&mut self, idx: usize) -> bool {
+        let energy = &mut self.energy[idx];
+        *energy = energy.saturating_add(self.rules.increment);
+        *energy > self.rules.threshold
     }
 }
 
@@ -147,27 +661,51 @@ impl TryFrom<Vec<String>> for OctopusGrid {
     type Error = anyhow::Error;
 
     fn try_from(value: Vec<String>) -> Result<Self> {
-        let octopuses = value
-            .iter()
-            .map(|s| {
-                s.chars()
-                    .map(|ch| {
-                        ch.to_digit(10)
-                            .map(|d| Octopus::new(d as i64))
-                            .ok_or_else(|| anyhow!("Invalid characters"))
-                    })
-                    .collect::<Result<Vec<Octopus>>>()
-            })
-            .collect::<Result<Vec<Vec<Octopus>>>>()?;
+        let rows = value.len();
+        let cols = value.first().map(|s| s.len()).unwrap_or(0);
+        let mut energy = Vec::with_capacity(rows * cols);
+
+        for s in value.iter() {
+            if s.len() != cols {
+                bail!("Rows must all be the same length");
+            }
+
+            for ch in s.chars() {
+                let d = ch
+                    .to_digit(10)
+                    .ok_or_else(|| anyhow!("Invalid characters"))?;
+                energy.push(d as u8);
+            }
+        }
 
         Ok(Self {
-            octopuses: octopuses.try_into()?,
+            energy,
+            rows,
+            cols,
+            rules: Rules::default(),
+            topology: Topology::default(),
+            pending: Vec::new(),
+            flashed: Vec::new(),
+            flash_counts: Vec::new(),
+            first_flashes: Vec::new(),
+            total_flashes: 0,
             syncd_genrations: Vec::new(),
             generations: 0,
         })
     }
 }
 
+impl Visualize for OctopusGrid {
+    fn render_frame(&self) -> String {
+        OctopusGrid::render_frame(self)
+    }
+
+    fn advance(&mut self) -> bool {
+        self.step();
+        true
+    }
+}
+
 impl Solver for OctopusGrid {
     const ID: &'static str = "dumbo octopus";
     const DAY: usize = 11;
@@ -207,6 +745,16 @@ mod tests {
             o.reset();
             assert_eq!(o, Octopus::default())
         }
+
+        #[test]
+        fn charge_with() {
+            let rules = Rules::new(5, 2, 3);
+            let mut o = Octopus::new(0);
+            assert!(!o.charge_with(&rules));
+            assert!(o.charge_with(&rules));
+            o.reset_with(&rules);
+            assert_eq!(o, Octopus::new(2));
+        }
     }
 
     mod grid {
@@ -253,5 +801,313 @@ mod tests {
             let mut grid = OctopusGrid::try_from(input).expect("could not construt grid");
             assert_eq!(grid.simulate_until_sync(), 195);
         }
+
+        #[test]
+        fn custom_rules() {
+            let input = test_input(
+                "
+                11111
+                19991
+                19191
+                19991
+                11111
+                ",
+            );
+            let grid = OctopusGrid::try_from(input).expect("could not construt grid");
+
+            // the default rules match the puzzle
+            let mut default = grid.clone().with_rules(Rules::default());
+            assert_eq!(default.step(), 9);
+
+            // with a lower threshold, the flashing 9s are enough to make every
+            // other octopus flash as well
+            let mut lowered = grid.with_rules(Rules::new(2, 0, 1));
+            assert_eq!(lowered.step(), 25);
+        }
+
+        #[test]
+        fn simulate_until() {
+            let input = test_input(
+                "
+                5483143223
+                2745854711
+                5264556173
+                6141336146
+                6357385478
+                4167524645
+                2176841721
+                6882881134
+                4846848554
+                5283751526
+                ",
+            );
+            let grid = OctopusGrid::try_from(input).expect("could not construt grid");
+
+            let mut g = grid.clone();
+            assert_eq!(g.simulate_until(|g, _| g.total_flashes() >= 1656), 100);
+
+            // the top left octopus first flashes in step 3
+            let mut g = grid.clone();
+            let loc = Location::new(0, 0);
+            assert_eq!(
+                g.simulate_until(|g, _| g.get(&loc) == Some(Octopus::new(0))),
+                3
+            );
+
+            let mut g = grid;
+            assert_eq!(g.simulate_until(|g, flashes| flashes == g.size()), 195);
+        }
+
+        #[test]
+        fn step_par() {
+            let input = test_input(
+                "
+                5483143223
+                2745854711
+                5264556173
+                6141336146
+                6357385478
+                4167524645
+                2176841721
+                6882881134
+                4846848554
+                5283751526
+                ",
+            );
+            let grid = OctopusGrid::try_from(input).expect("could not construt grid");
+
+            for band_rows in [1, 3, 10, 20] {
+                let mut serial = grid.clone();
+                let mut par = grid.clone();
+                assert_eq!(par.simulate_par(100, band_rows), 1656);
+                serial.simulate(100);
+
+                for _ in 0..100 {
+                    assert_eq!(par.step_par(band_rows), serial.step());
+                }
+                assert_eq!(par.render_frame(), serial.render_frame());
+
+                let mut serial = grid.clone().with_topology(Topology::Toroidal);
+                let mut par = grid.clone().with_topology(Topology::Toroidal);
+                assert_eq!(par.simulate_par(150, band_rows), serial.simulate(150));
+                assert_eq!(par.render_frame(), serial.render_frame());
+            }
+        }
+
+        #[test]
+        fn step_hashed() {
+            let input = test_input(
+                "
+                5483143223
+                2745854711
+                5264556173
+                6141336146
+                6357385478
+                4167524645
+                2176841721
+                6882881134
+                4846848554
+                5283751526
+                ",
+            );
+            let grid = OctopusGrid::try_from(input).expect("could not construt grid");
+
+            for topology in [Topology::Bounded, Topology::Toroidal] {
+                let mut serial = grid.clone().with_topology(topology);
+                let mut hashed = grid.clone().with_topology(topology);
+                for _ in 0..200 {
+                    assert_eq!(hashed.step_hashed(), serial.step());
+                }
+                assert_eq!(hashed.render_frame(), serial.render_frame());
+            }
+
+            let mut hashed = grid;
+            assert_eq!(hashed.simulate_hashed(100), 1656);
+        }
+
+        #[test]
+        fn toroidal() {
+            let input = test_input(
+                "
+                9111
+                1111
+                1111
+                1111
+                ",
+            );
+            let grid = OctopusGrid::try_from(input).expect("could not construt grid");
+
+            let mut bounded = grid.clone();
+            bounded.step();
+            assert_eq!(bounded.render_frame(), "*322\n3322\n2222\n2222");
+
+            let mut wrapped = grid.with_topology(Topology::Toroidal);
+            wrapped.step();
+            assert_eq!(wrapped.render_frame(), "*323\n3323\n2222\n3323");
+        }
+
+        #[test]
+        fn toroidal_small() {
+            // every octopus is a neighbor of every other octopus exactly once
+            let input = test_input(
+                "
+                91
+                11
+                ",
+            );
+            let mut grid = OctopusGrid::try_from(input)
+                .expect("could not construt grid")
+                .with_topology(Topology::Toroidal);
+            grid.step();
+            assert_eq!(grid.render_frame(), "*3\n33");
+        }
+
+        #[test]
+        fn period() {
+            let input = test_input(
+                "
+                5483143223
+                2745854711
+                5264556173
+                6141336146
+                6357385478
+                4167524645
+                2176841721
+                6882881134
+                4846848554
+                5283751526
+                ",
+            );
+            let mut grid = OctopusGrid::try_from(input).expect("could not construt grid");
+            // after synchronizing, every octopus is back to 0 every 10 steps
+            assert_eq!(grid.period(), 10);
+
+            assert_eq!(grid.flashes_after(100), 1656);
+
+            let expected = grid.clone().simulate(1000) as u128;
+            assert_eq!(grid.flashes_after(1000), expected);
+
+            // every step after the sync is 1 synchronized flash every 10 steps
+            let at_sync = grid.flashes_after(195);
+            assert_eq!(
+                grid.flashes_after(u64::MAX),
+                at_sync + ((u64::MAX - 195) / 10) as u128 * 100
+            );
+
+            // starting from a later state
+            grid.simulate(200);
+            assert_eq!(grid.period(), 10);
+            assert_eq!(grid.flashes_after(25), 300);
+        }
+
+        #[test]
+        fn render_frame() {
+            let input = test_input(
+                "
+                11111
+                19991
+                19191
+                19991
+                11111
+                ",
+            );
+            let mut grid = OctopusGrid::try_from(input).expect("could not construt grid");
+            assert_eq!(grid.render_frame(), "11111\n19991\n19191\n19991\n11111");
+
+            grid.step();
+            assert_eq!(grid.render_frame(), "34543\n4***4\n5***5\n4***4\n34543");
+
+            grid.step();
+            assert_eq!(grid.render_frame(), "45654\n51115\n61116\n51115\n45654");
+        }
+
+        #[test]
+        fn flash_statistics() {
+            let input = test_input(
+                "
+                11111
+                19991
+                19191
+                19991
+                11111
+                ",
+            );
+            let mut grid = OctopusGrid::try_from(input).expect("could not construt grid");
+            assert_eq!(grid.flash_counts().locations, vec![vec![0; 5]; 5]);
+
+            assert_eq!(grid.simulate(2), 9);
+            assert_eq!(grid.total_flashes(), 9);
+            assert_eq!(grid.generations(), 2);
+
+            let counts = grid.flash_counts();
+            assert_eq!(counts.locations[0], vec![0; 5]);
+            assert_eq!(counts.locations[2], vec![0, 1, 1, 1, 0]);
+
+            let first = grid.first_flashes();
+            assert_eq!(first.locations[0][0], None);
+            assert_eq!(first.locations[2][2], Some(1));
+        }
+
+        #[test]
+        fn frames() {
+            let input = test_input(
+                "
+                11111
+                19991
+                19191
+                19991
+                11111
+                ",
+            );
+            let mut grid = OctopusGrid::try_from(input).expect("could not construt grid");
+            let frames = crate::visualize::frames(&mut grid, 2);
+            assert_eq!(frames.len(), 3);
+            assert_eq!(frames[1], "34543\n4***4\n5***5\n4***4\n34543");
+        }
+
+        #[cfg(feature = "image")]
+        #[test]
+        fn write_gif() {
+            let input = test_input(
+                "
+                11111
+                19991
+                19191
+                19991
+                11111
+                ",
+            );
+            let mut grid = OctopusGrid::try_from(input).expect("could not construt grid");
+
+            let mut out = Vec::new();
+            let written = grid.write_gif(2, 3, &mut out).expect("could not write gif");
+            assert_eq!(written, 3);
+            assert_eq!(grid.generations(), 2);
+
+            let mut options = gif::DecodeOptions::new();
+            options.set_color_output(gif::ColorOutput::Indexed);
+            let mut decoder = options
+                .read_info(out.as_slice())
+                .expect("could not read gif");
+            assert_eq!((decoder.width(), decoder.height()), (15, 15));
+
+            let mut frames = Vec::new();
+            while let Some(frame) = decoder.read_next_frame().expect("could not read frame") {
+                frames.push(frame.buffer.to_vec());
+            }
+            assert_eq!(frames.len(), 3);
+
+            // pixels are palette indices: energy levels, then `+`, then `*`
+            let block = |frame: &[u8], row: usize, col: usize| {
+                (0..3)
+                    .flat_map(|r| (0..3).map(move |c| (row * 3 + r) * 15 + col * 3 + c))
+                    .map(|idx| frame[idx])
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(block(&frames[0], 0, 0), vec![1; 9]);
+            assert_eq!(block(&frames[0], 1, 1), vec![9; 9]);
+            assert_eq!(block(&frames[1], 0, 0), vec![3; 9]);
+            assert_eq!(block(&frames[1], 1, 1), vec![11; 9]);
+        }
     }
 }
@@ -1,115 +1,474 @@
-use std::{convert::TryFrom, str::FromStr};
+use std::{collections::VecDeque, convert::TryFrom, fmt};
 
 use anyhow::{anyhow, bail, Result};
 use aoc_helpers::Solver;
 use itertools::{Itertools, MinMaxResult};
-use rustc_hash::FxHashMap;
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
 
-type Cache = FxHashMap<(usize, [char; 2]), [usize; 26]>;
+type Cache = FxHashMap<(usize, [usize; 2]), Vec<usize>>;
+
+/// The number of each element in a polymer, sorted by name, leaving out
+/// elements that do not appear
+pub type ElementCounts = Vec<(String, u128)>;
+
+/// Alphabets up to this size keep pair counts in a dense array indexed by
+/// pair, rather than a map
+const DENSE_ALPHABET: usize = 64;
+
+/// Sparse pair counts with at least this many pairs are updated in parallel
+const PARALLEL_PAIRS: usize = 4096;
+
+/// The number of pairs each thread handles at a time in a parallel update
+const PAIR_CHUNK: usize = 1024;
+
+/// The longest polymer, in elements, that `Polymerizer::expand` will build
+const MAX_EXPANSION: usize = 1 << 20;
+
+/// A type that element and pair counts can be kept in
+pub trait Count: Clone + Ord + Default + Send + Sync {
+    fn one() -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+}
+
+macro_rules! impl_count {
+    ($($t:ty),*) => {
+        $(
+            impl Count for $t {
+                fn one() -> Self {
+                    1
+                }
+
+                fn checked_add(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_add(*self, *other)
+                }
+
+                fn checked_sub(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_sub(*self, *other)
+                }
+
+                fn checked_mul(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_mul(*self, *other)
+                }
+            }
+        )*
+    };
+}
+
+impl_count!(usize, u64, u128);
+
+#[cfg(feature = "bigint")]
+impl Count for num_bigint::BigUint {
+    fn one() -> Self {
+        Self::from(1_u8)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if self >= other {
+            Some(self - other)
+        } else {
+            None
+        }
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+/// Add `value` to `slot`, failing if the result would overflow
+fn add_to<T: Count>(slot: &mut T, value: &T) -> Result<()> {
+    *slot = slot
+        .checked_add(value)
+        .ok_or_else(|| anyhow!("counts are too large for {}", std::any::type_name::<T>()))?;
+
+    Ok(())
+}
+
+/// Multiply `a` by `b`, failing if the result would overflow
+fn mul<T: Count>(a: &T, b: &T) -> Result<T> {
+    a.checked_mul(b)
+        .ok_or_else(|| anyhow!("counts are too large for {}", std::any::type_name::<T>()))
+}
+
+/// A square matrix of counts, indexed by row then column
+type Matrix<T> = Vec<Vec<T>>;
+
+fn identity<T: Count>(size: usize) -> Matrix<T> {
+    (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|col| if row == col { T::one() } else { T::default() })
+                .collect()
+        })
+        .collect()
+}
+
+fn multiply<T: Count>(a: &Matrix<T>, b: &Matrix<T>) -> Result<Matrix<T>> {
+    let zero = T::default();
+    let mut out = vec![vec![T::default(); b.len()]; a.len()];
+
+    for (row, a_row) in a.iter().enumerate() {
+        for (k, a_val) in a_row.iter().enumerate() {
+            // most of the matrix is empty, so skip what we can
+            if *a_val == zero {
+                continue;
+            }
+
+            for (col, b_val) in b[k].iter().enumerate() {
+                if *b_val != zero {
+                    add_to(&mut out[row][col], &mul(a_val, b_val)?)?;
+                }
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Raise `matrix` to the power `exp` by repeated squaring
+fn power<T: Count>(matrix: &Matrix<T>, mut exp: usize) -> Result<Matrix<T>> {
+    let mut result = identity(matrix.len());
+    let mut base = matrix.clone();
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = multiply(&result, &base)?;
+        }
+
+        exp >>= 1;
+        if exp > 0 {
+            base = multiply(&base, &base)?;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Split a formula, or part of a rule, into element names. Elements are
+/// single characters, unless `words` is set, in which case they are the
+/// whitespace separated words.
+fn split_elements(s: &str, words: bool) -> Vec<&str> {
+    if words {
+        s.split_whitespace().collect()
+    } else {
+        s.char_indices()
+            .map(|(idx, ch)| &s[idx..idx + ch.len_utf8()])
+            .collect()
+    }
+}
+
+/// Whether `s` uses whitespace separated element names
+fn uses_words(s: &str) -> bool {
+    s.trim().contains(char::is_whitespace)
+}
+
+/// The names of the elements used by a set of rules and its formula, interned
+/// so they can be referred to by index
+#[derive(Debug, Clone, Default)]
+pub struct Alphabet {
+    names: Vec<String>,
+    lookup: FxHashMap<String, usize>,
+}
+
+impl Alphabet {
+    /// Get the index of the element called `name`, adding it if necessary
+    pub fn intern(&mut self, name: &str) -> usize {
+        if let Some(idx) = self.lookup.get(name) {
+            return *idx;
+        }
+
+        self.names.push(name.to_string());
+        self.lookup.insert(name.to_string(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.lookup.get(name).copied()
+    }
+
+    pub fn name(&self, idx: usize) -> Option<&str> {
+        self.names.get(idx).map(|n| n.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Rule {
-    key: [char; 2],
-    insertion: char,
-    insertion_value: usize,
-    left: [char; 2],
-    right: [char; 2],
+    key: [usize; 2],
+    insertion: usize,
+    left: [usize; 2],
+    right: [usize; 2],
 }
 
 impl Rule {
-    pub fn iterations(&self, num: usize, rules: &Rules, cache: &mut Cache) -> [usize; 26] {
+    /// Parse a rule like `AB -> C`, adding its elements to `alphabet`
+    pub fn parse(s: &str, alphabet: &mut Alphabet) -> Result<Self> {
+        let mut parts = s.split("->");
+        let key = parts
+            .next()
+            .ok_or_else(|| anyhow!("Missing key: {}", s))?
+            .trim();
+
+        // the key decides whether element names are words or characters
+        let words = uses_words(key);
+        let key = split_elements(key, words);
+
+        if key.len() != 2 {
+            bail!("Key is invalid length: {}", s);
+        }
+
+        let insertion = split_elements(
+            parts
+                .next()
+                .ok_or_else(|| anyhow!("Missing insertion: {}", s))?
+                .trim(),
+            words,
+        );
+
+        if insertion.len() != 1 {
+            bail!("Rule must insert exactly one element: {}", s);
+        }
+
+        let key = [alphabet.intern(key[0]), alphabet.intern(key[1])];
+        let insertion = alphabet.intern(insertion[0]);
+
+        Ok(Rule {
+            key,
+            insertion,
+            left: [key[0], insertion],
+            right: [insertion, key[1]],
+        })
+    }
+
+    pub fn iterations(&self, num: usize, rules: &Rules, cache: &mut Cache) -> Vec<usize> {
         self.recur(num, rules, cache)
     }
 
-    pub fn recur(&self, depth: usize, rules: &Rules, cache: &mut Cache) -> [usize; 26] {
+    pub fn recur(&self, depth: usize, rules: &Rules, cache: &mut Cache) -> Vec<usize> {
         if let Some(cached) = cache.get(&(depth, self.key)) {
-            return *cached;
+            return cached.clone();
         }
 
-        let mut counts = [0; 26];
-        counts[self.insertion_value] += 1;
+        let mut counts = vec![0; rules.alphabet.len()];
+        counts[self.insertion] += 1;
 
         if depth < 2 {
             return counts;
         }
 
-        if let Some(left) = rules.get(&[self.key[0], self.insertion]) {
+        if let Some(left) = rules.get(&self.left) {
             for (i, v) in left.recur(depth - 1, rules, cache).iter().enumerate() {
                 counts[i] += v;
             }
         }
 
-        if let Some(right) = rules.get(&[self.insertion, self.key[1]]) {
+        if let Some(right) = rules.get(&self.right) {
             for (i, v) in right.recur(depth - 1, rules, cache).iter().enumerate() {
                 counts[i] += v;
             }
         }
 
-        cache.insert((depth, self.key), counts);
+        cache.insert((depth, self.key), counts.clone());
 
         counts
     }
 }
 
-impl FromStr for Rule {
-    type Err = anyhow::Error;
+/// Problems found by `Rules::validate`, with pairs given by element names
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RuleReport {
+    /// Pairs that can appear in the polymer but have no rule, so nothing is
+    /// ever inserted between them
+    pub missing: Vec<[String; 2]>,
+    /// Pairs with more than one rule, all inserting the same element
+    pub duplicates: Vec<[String; 2]>,
+    /// Pairs with rules inserting different elements, along with each of the
+    /// insertions in the order they were given. Only the last one is used.
+    pub conflicts: Vec<([String; 2], Vec<String>)>,
+}
+
+impl RuleReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.duplicates.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+impl fmt::Display for RuleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "rules are complete");
+        }
 
-    fn from_str(s: &str) -> Result<Self> {
-        let mut parts = s.split(" -> ");
-        let key: Vec<char> = parts
-            .next()
-            .ok_or_else(|| anyhow!("Missing key: {}", s))?
-            .chars()
-            .collect();
+        for [a, b] in &self.missing {
+            writeln!(f, "missing rule: {} {}", a, b)?;
+        }
 
-        if key.len() != 2 {
-            bail!("Key is invalid length: {}", s);
+        for [a, b] in &self.duplicates {
+            writeln!(f, "duplicate rule: {} {}", a, b)?;
         }
 
-        let insertion = parts
-            .next()
-            .and_then(|p| p.chars().next())
-            .ok_or_else(|| anyhow!("Missing insertion: {}", s))?;
-        let insertion_value = insertion as usize - 'A' as usize;
+        for ([a, b], insertions) in &self.conflicts {
+            writeln!(
+                f,
+                "conflicting rules: {} {} -> {}",
+                a,
+                b,
+                insertions.join(", ")
+            )?;
+        }
 
-        Ok(Rule {
-            key: [key[0], key[1]],
-            insertion,
-            insertion_value,
-            left: [key[0], insertion],
-            right: [insertion, key[1]],
-        })
+        Ok(())
     }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Rules {
-    rules: FxHashMap<[char; 2], Rule>,
+    rules: FxHashMap<[usize; 2], Rule>,
+    alphabet: Alphabet,
+    /// rules that were replaced by a later rule for the same pair
+    overridden: Vec<Rule>,
 }
 
 impl Rules {
-    pub fn get(&self, key: &[char; 2]) -> Option<&Rule> {
+    pub fn get(&self, key: &[usize; 2]) -> Option<&Rule> {
         self.rules.get(key)
     }
+
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// Check these rules against `formula`, finding the pairs that can appear
+    /// in the polymer but have no rule, and any pairs with more than one rule.
+    pub fn validate(&self, formula: &Formula) -> RuleReport {
+        let mut alphabet = self.alphabet.clone();
+        let template: Vec<usize> = formula
+            .elements()
+            .into_iter()
+            .map(|name| alphabet.intern(name))
+            .collect();
+
+        let names = |key: &[usize; 2]| -> [String; 2] {
+            [
+                alphabet.names[key[0]].clone(),
+                alphabet.names[key[1]].clone(),
+            ]
+        };
+
+        let mut missing: Vec<[String; 2]> = self
+            .reachable(&template)
+            .iter()
+            .filter(|key| !self.rules.contains_key(*key))
+            .map(&names)
+            .collect();
+
+        let mut duplicates = Vec::new();
+        let mut conflicts = Vec::new();
+        for key in self.overridden.iter().map(|rule| rule.key).unique() {
+            let mut insertions: Vec<usize> = self
+                .overridden
+                .iter()
+                .filter(|rule| rule.key == key)
+                .map(|rule| rule.insertion)
+                .collect();
+            insertions.push(self.rules[&key].insertion);
+
+            if insertions.iter().all_equal() {
+                duplicates.push(names(&key));
+            } else {
+                let insertions = insertions
+                    .iter()
+                    .map(|el| alphabet.names[*el].clone())
+                    .collect();
+                conflicts.push((names(&key), insertions));
+            }
+        }
+
+        missing.sort();
+        duplicates.sort();
+        conflicts.sort();
+
+        RuleReport {
+            missing,
+            duplicates,
+            conflicts,
+        }
+    }
+
+    /// Every pair that can appear in a polymer grown from `template`, in the
+    /// order they are first reached
+    fn reachable(&self, template: &[usize]) -> Vec<[usize; 2]> {
+        let mut seen: FxHashSet<[usize; 2]> = FxHashSet::default();
+        let mut queue: VecDeque<[usize; 2]> =
+            template.windows(2).map(|pair| [pair[0], pair[1]]).collect();
+
+        let mut pairs = Vec::new();
+        while let Some(key) = queue.pop_front() {
+            if !seen.insert(key) {
+                continue;
+            }
+
+            pairs.push(key);
+            if let Some(rule) = self.rules.get(&key) {
+                queue.push_back(rule.left);
+                queue.push_back(rule.right);
+            }
+        }
+
+        pairs
+    }
 }
 
 impl TryFrom<Vec<String>> for Rules {
     type Error = anyhow::Error;
 
     fn try_from(value: Vec<String>) -> Result<Self> {
+        let mut alphabet = Alphabet::default();
         let mut rules = FxHashMap::default();
+        let mut overridden = Vec::new();
         for s in &value {
-            let r = Rule::from_str(s)?;
-            rules.insert(r.key, r);
+            let r = Rule::parse(s, &mut alphabet)?;
+            if let Some(prev) = rules.insert(r.key, r) {
+                overridden.push(prev);
+            }
         }
 
-        Ok(Rules { rules })
+        Ok(Rules {
+            rules,
+            alphabet,
+            overridden,
+        })
     }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Formula(String);
 
+impl Formula {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The names of the elements in the formula, in order
+    pub fn elements(&self) -> Vec<&str> {
+        split_elements(&self.0, uses_words(&self.0))
+    }
+}
+
 impl From<String> for Formula {
     fn from(value: String) -> Self {
         Self(value)
@@ -120,15 +479,39 @@ impl From<String> for Formula {
 pub struct Polymerizer {
     formula: Formula,
     rules: Rules,
+    /// the formula's elements, as indices into the rules' alphabet
+    template: Vec<usize>,
 }
 
 impl Polymerizer {
+    pub fn new(formula: Formula, mut rules: Rules) -> Self {
+        let template = formula
+            .elements()
+            .into_iter()
+            .map(|name| rules.alphabet.intern(name))
+            .collect();
+
+        Self {
+            formula,
+            rules,
+            template,
+        }
+    }
+
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
     pub fn iterations(&self, num: usize) -> usize {
-        let mut final_rules: FxHashMap<[char; 2], [usize; 26]> = FxHashMap::default();
-        let mut counts = [0_usize; 26];
+        let mut final_rules: FxHashMap<[usize; 2], Vec<usize>> = FxHashMap::default();
+        let mut counts = vec![0_usize; self.rules.alphabet.len()];
 
-        for ch in self.formula.0.chars() {
-            counts[ch as usize - 'A' as usize] += 1;
+        for el in self.template.iter() {
+            counts[*el] += 1;
         }
 
         let mut cache: Cache = FxHashMap::default();
@@ -137,8 +520,8 @@ impl Polymerizer {
             final_rules.insert(*key, rule.iterations(num, &self.rules, &mut cache));
         }
 
-        for (begin, end) in self.formula.0.chars().tuple_windows() {
-            let search = [begin, end];
+        for (begin, end) in self.template.iter().tuple_windows() {
+            let search = [*begin, *end];
             if let Some(map) = final_rules.get(&search) {
                 for (i, v) in map.iter().enumerate() {
                     counts[i] += v;
@@ -152,43 +535,284 @@ impl Polymerizer {
         }
     }
 
-    pub fn iterations_fast(&self, num: usize) -> usize {
-        let mut rule_counts: FxHashMap<[char; 2], usize> = FxHashMap::default();
-        let mut counts = [0_usize; 26];
-        let a = 'A' as usize;
+    /// Build the polymer after `num` iterations, failing if it would be longer
+    /// than `MAX_EXPANSION` elements. Elements with multi-character names are
+    /// separated by spaces, like the formula.
+    pub fn expand(&self, num: usize) -> Result<String> {
+        let mut polymer = self.template.clone();
+
+        for step in 0..num {
+            let mut next = Vec::with_capacity(polymer.len() * 2);
+            for pair in polymer.windows(2) {
+                next.push(pair[0]);
+                if let Some(rule) = self.rules.get(&[pair[0], pair[1]]) {
+                    next.push(rule.insertion);
+                }
+            }
+            next.extend(polymer.last());
+
+            if next.len() > MAX_EXPANSION {
+                bail!(
+                    "polymer is longer than {} elements after {} iterations",
+                    MAX_EXPANSION,
+                    step + 1
+                );
+            }
+
+            polymer = next;
+        }
+
+        let separator = if uses_words(&self.formula.0) { " " } else { "" };
+        Ok(polymer
+            .iter()
+            .map(|el| self.rules.alphabet.names[*el].as_str())
+            .join(separator))
+    }
+
+    /// The difference between the most and least common elements after `num`
+    /// iterations, or an error if the counts would overflow a `usize`.
+    pub fn iterations_fast(&self, num: usize) -> Result<usize> {
+        self.iterations_wide(num)
+    }
+
+    /// Like `iterations_fast`, but with the counts kept in any `Count`, such
+    /// as `u128` (or `BigUint`, with the `bigint` feature) for large `num`.
+    pub fn iterations_wide<T: Count>(&self, num: usize) -> Result<T> {
+        Self::spread(&self.element_counts_wide::<T>(num)?)
+    }
+
+    /// Like `iterations_wide`, but treating one round of insertion as a linear
+    /// map on the pair counts and raising it to the power `num` by repeated
+    /// squaring. With `p` reachable pairs this is O(p³ log num) rather than
+    /// O(p num), so it only pays off for `num` far beyond what fits in a
+    /// `u128`; for the puzzle input stepping is much faster.
+    pub fn iterations_matrix<T: Count>(&self, num: usize) -> Result<T> {
+        Self::spread(&self.element_counts_matrix::<T>(num)?)
+    }
+
+    /// The difference between the largest and smallest non-zero counts
+    fn spread<T: Count>(counts: &[T]) -> Result<T> {
+        match counts.iter().filter(|v| **v > T::default()).minmax() {
+            MinMaxResult::MinMax(a, b) => b
+                .checked_sub(a)
+                .ok_or_else(|| anyhow!("most common element is less common than the least")),
+            _ => Ok(T::default()),
+        }
+    }
+
+    fn element_counts_matrix<T: Count>(&self, num: usize) -> Result<Vec<T>> {
+        let mut counts = vec![T::default(); self.rules.alphabet.len()];
+        let last = self
+            .template
+            .last()
+            .ok_or_else(|| anyhow!("formula is empty"))?;
+        counts[*last] = T::one();
+
+        let pairs = self.rules.reachable(&self.template);
+        let index: FxHashMap<[usize; 2], usize> = pairs
+            .iter()
+            .enumerate()
+            .map(|(idx, key)| (*key, idx))
+            .collect();
+
+        // column `from` holds the pairs that pair `from` turns into in one
+        // round; pairs without a rule are carried over unchanged
+        let mut step = vec![vec![T::default(); pairs.len()]; pairs.len()];
+        for (from, key) in pairs.iter().enumerate() {
+            if let Some(rule) = self.rules.get(key) {
+                add_to(&mut step[index[&rule.left]][from], &T::one())?;
+                add_to(&mut step[index[&rule.right]][from], &T::one())?;
+            } else {
+                step[from][from] = T::one();
+            }
+        }
+
+        let mut initial = vec![T::default(); pairs.len()];
+        for (begin, end) in self.template.iter().tuple_windows() {
+            add_to(&mut initial[index[&[*begin, *end]]], &T::one())?;
+        }
+
+        let transform = power(&step, num)?;
+        for (key, row) in pairs.iter().zip(transform.iter()) {
+            for (factor, count) in row.iter().zip(initial.iter()) {
+                add_to(&mut counts[key[0]], &mul(factor, count)?)?;
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// The number of each element in the polymer after `num` iterations
+    pub fn element_counts(&self, num: usize) -> Result<ElementCounts> {
+        Ok(self.named(self.element_counts_wide::<u128>(num)?))
+    }
+
+    /// The most and least common elements after `num` iterations, with their
+    /// counts. Ties go to the element whose name sorts first.
+    pub fn extremes(&self, num: usize) -> Result<((String, u128), (String, u128))> {
+        let counts = self.element_counts(num)?;
+
+        let most = counts
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .ok_or_else(|| anyhow!("formula is empty"))?;
+        let least = counts
+            .iter()
+            .min_by_key(|(_, count)| *count)
+            .ok_or_else(|| anyhow!("formula is empty"))?;
 
-        let chars = self.formula.0.chars().collect::<Vec<_>>();
-        let last = chars[chars.len() - 1] as usize - a;
-        counts[last] += 1;
+        Ok((most.clone(), least.clone()))
+    }
 
-        for (begin, end) in chars.into_iter().tuple_windows() {
-            let e = rule_counts.entry([begin, end]).or_default();
-            *e += 1;
+    /// The number of each element after every round of insertion, starting
+    /// with the first. This never ends on its own, unless the counts no
+    /// longer fit in a `u128`, so callers should `take` what they need or
+    /// stop once some condition is met.
+    pub fn steps(&self) -> impl Iterator<Item = ElementCounts> + '_ {
+        let mut rule_counts: FxHashMap<[usize; 2], u128> = FxHashMap::default();
+        for (begin, end) in self.template.iter().tuple_windows() {
+            *rule_counts.entry([*begin, *end]).or_default() += 1;
         }
 
-        for _ in 0..num {
-            let mut new: FxHashMap<[char; 2], usize> = FxHashMap::default();
+        let last = self.template.last().copied();
+        std::iter::from_fn(move || {
+            let last = last?;
+            rule_counts = self
+                .step_pairs(&rule_counts, rule_counts.len() >= PARALLEL_PAIRS)
+                .ok()?;
+
+            let mut counts = vec![0; self.rules.alphabet.len()];
+            counts[last] = 1;
             for (k, v) in rule_counts.iter() {
-                if let Some(rule) = self.rules.get(k) {
-                    let e = new.entry(rule.left).or_default();
-                    *e += v;
+                add_to(&mut counts[k[0]], v).ok()?;
+            }
+
+            Some(self.named(counts))
+        })
+    }
+
+    /// Pair up counts indexed by element with the names of the elements
+    fn named(&self, counts: Vec<u128>) -> ElementCounts {
+        let alphabet = &self.rules.alphabet;
+
+        counts
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .map(|(idx, count)| (alphabet.names[idx].clone(), count))
+            .sorted()
+            .collect()
+    }
+
+    /// Count each element after `num` iterations, indexed by element
+    fn element_counts_wide<T: Count>(&self, num: usize) -> Result<Vec<T>> {
+        let n = self.rules.alphabet.len();
+        let mut counts = vec![T::default(); n];
+
+        // every pair contributes its first element, which leaves out the last
+        // element of the polymer, and that never changes
+        let last = self
+            .template
+            .last()
+            .ok_or_else(|| anyhow!("formula is empty"))?;
+        counts[*last] = T::one();
 
-                    let e = new.entry(rule.right).or_default();
-                    *e += v;
+        if n <= DENSE_ALPHABET {
+            let pair = |key: &[usize; 2]| key[0] * n + key[1];
+            let transitions: Vec<Option<(usize, usize)>> = (0..n * n)
+                .map(|p| {
+                    self.rules
+                        .get(&[p / n, p % n])
+                        .map(|rule| (pair(&rule.left), pair(&rule.right)))
+                })
+                .collect();
+
+            let mut pair_counts = vec![T::default(); n * n];
+            for (begin, end) in self.template.iter().tuple_windows() {
+                add_to(&mut pair_counts[pair(&[*begin, *end])], &T::one())?;
+            }
+
+            for _ in 0..num {
+                let mut new = vec![T::default(); n * n];
+                for (p, v) in pair_counts.iter().enumerate() {
+                    match transitions[p] {
+                        Some((left, right)) => {
+                            add_to(&mut new[left], v)?;
+                            add_to(&mut new[right], v)?;
+                        }
+                        None => add_to(&mut new[p], v)?,
+                    }
                 }
+
+                pair_counts = new;
             }
 
-            rule_counts = new;
-        }
+            for (p, v) in pair_counts.iter().enumerate() {
+                add_to(&mut counts[p / n], v)?;
+            }
+        } else {
+            let mut rule_counts: FxHashMap<[usize; 2], T> = FxHashMap::default();
+            for (begin, end) in self.template.iter().tuple_windows() {
+                add_to(rule_counts.entry([*begin, *end]).or_default(), &T::one())?;
+            }
+
+            for _ in 0..num {
+                rule_counts = self.step_pairs(&rule_counts, rule_counts.len() >= PARALLEL_PAIRS)?;
+            }
 
-        for (k, v) in rule_counts.iter() {
-            counts[k[0] as usize - a] += v;
+            for (k, v) in rule_counts.iter() {
+                add_to(&mut counts[k[0]], v)?;
+            }
         }
 
-        match counts.iter().filter(|v| **v > 0).minmax() {
-            MinMaxResult::MinMax(a, b) => b - a,
-            _ => 0,
+        Ok(counts)
+    }
+
+    /// Apply one round of insertion to sparse pair counts. When `parallel` is
+    /// set, the pairs are split into chunks that are updated on separate
+    /// threads, and the partial counts merged afterwards.
+    fn step_pairs<T: Count>(
+        &self,
+        rule_counts: &FxHashMap<[usize; 2], T>,
+        parallel: bool,
+    ) -> Result<FxHashMap<[usize; 2], T>> {
+        let update = |pairs: &[(&[usize; 2], &T)]| -> Result<FxHashMap<[usize; 2], T>> {
+            let mut new: FxHashMap<[usize; 2], T> = FxHashMap::default();
+            for (k, v) in pairs {
+                match self.rules.get(k) {
+                    Some(rule) => {
+                        add_to(new.entry(rule.left).or_default(), v)?;
+                        add_to(new.entry(rule.right).or_default(), v)?;
+                    }
+                    // nothing is inserted, so the pair carries over
+                    None => add_to(new.entry(**k).or_default(), v)?,
+                }
+            }
+
+            Ok(new)
+        };
+
+        let pairs: Vec<(&[usize; 2], &T)> = rule_counts.iter().collect();
+        if !parallel {
+            return update(&pairs);
         }
+
+        pairs
+            .par_chunks(PAIR_CHUNK)
+            .map(update)
+            .try_reduce(FxHashMap::default, |mut a, mut b| {
+                // merge the smaller map into the larger one
+                if a.len() < b.len() {
+                    std::mem::swap(&mut a, &mut b);
+                }
+
+                for (k, v) in b {
+                    add_to(a.entry(k).or_default(), &v)?;
+                }
+
+                Ok(a)
+            })
     }
 }
 
@@ -207,7 +831,7 @@ impl TryFrom<Vec<String>> for Polymerizer {
 
         let rules = Rules::try_from(parts.collect::<Vec<String>>())?;
 
-        Ok(Self { formula, rules })
+        Ok(Self::new(formula, rules))
     }
 }
 
@@ -219,11 +843,11 @@ impl Solver for Polymerizer {
     type P2 = usize;
 
     fn part_one(&mut self) -> Self::P1 {
-        self.iterations_fast(10)
+        self.iterations_fast(10).expect("could not count elements")
     }
 
     fn part_two(&mut self) -> Self::P2 {
-        self.iterations_fast(40)
+        self.iterations_fast(40).expect("could not count elements")
     }
 }
 
@@ -261,14 +885,14 @@ mod tests {
 
             let p = Polymerizer::try_from(input).expect("could not parse input");
             assert_eq!(p.iterations(10), 1588);
-            assert_eq!(p.iterations_fast(10), 1588);
+            assert_eq!(p.iterations_fast(10).expect("could not count"), 1588);
         }
 
         #[test]
-        fn comparison() {
+        fn element_counts() {
             let input = test_input(
                 "
-                NNCBC
+                NNCB
 
                 CH -> B
                 HH -> N
@@ -290,7 +914,593 @@ mod tests {
             );
 
             let p = Polymerizer::try_from(input).expect("could not parse input");
-            assert_eq!(p.iterations(10), p.iterations_fast(10));
+            assert_eq!(
+                p.element_counts(0).expect("could not count"),
+                vec![("B".into(), 1), ("C".into(), 1), ("N".into(), 2)]
+            );
+            assert_eq!(
+                p.element_counts(10).expect("could not count"),
+                vec![
+                    ("B".into(), 1749),
+                    ("C".into(), 298),
+                    ("H".into(), 161),
+                    ("N".into(), 865)
+                ]
+            );
+        }
+
+        #[test]
+        fn extremes() {
+            let input = test_input(
+                "
+                NNCB
+
+                CH -> B
+                HH -> N
+                CB -> H
+                NH -> C
+                HB -> C
+                HC -> B
+                HN -> C
+                NN -> C
+                BH -> H
+                NC -> B
+                NB -> B
+                BN -> B
+                BB -> N
+                BC -> B
+                CC -> N
+                CN -> C
+                ",
+            );
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert_eq!(
+                p.extremes(10).expect("could not count"),
+                (("B".into(), 1749), ("H".into(), 161))
+            );
+
+            let ((_, most), (_, least)) = p.extremes(40).expect("could not count");
+            assert_eq!(most - least, 2188189693529);
+
+            // ties go to the first name
+            assert_eq!(
+                p.extremes(0).expect("could not count"),
+                (("N".into(), 2), ("B".into(), 1))
+            );
+            let p = Polymerizer::try_from(test_input("ABBA\n\nAB -> A"))
+                .expect("could not parse input");
+            assert_eq!(
+                p.extremes(0).expect("could not count"),
+                (("A".into(), 2), ("A".into(), 2))
+            );
+        }
+
+        #[test]
+        fn steps() {
+            let input = test_input(
+                "
+                NNCB
+
+                CH -> B
+                HH -> N
+                CB -> H
+                NH -> C
+                HB -> C
+                HC -> B
+                HN -> C
+                NN -> C
+                BH -> H
+                NC -> B
+                NB -> B
+                BN -> B
+                BB -> N
+                BC -> B
+                CC -> N
+                CN -> C
+                ",
+            );
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            for (num, counts) in p.steps().take(20).enumerate() {
+                assert_eq!(counts, p.element_counts(num + 1).expect("could not count"));
+            }
+
+            assert_eq!(
+                p.steps().nth(1).expect("missing step"),
+                vec![
+                    ("B".into(), 6),
+                    ("C".into(), 4),
+                    ("H".into(), 1),
+                    ("N".into(), 2)
+                ]
+            );
+
+            // stopping early
+            let first = p
+                .steps()
+                .position(|counts| counts.iter().any(|(_, count)| *count > 1000))
+                .expect("never exceeded");
+            assert_eq!(first + 1, 10);
+
+            // the counts stop once they get too large
+            assert!(p.steps().count() < 150);
+        }
+
+        #[test]
+        fn other_alphabets() {
+            // the example, with lowercase letters and digits
+            let input = test_input(
+                "
+                nn4b
+
+                4h -> b
+                hh -> n
+                4b -> h
+                nh -> 4
+                hb -> 4
+                h4 -> b
+                hn -> 4
+                nn -> 4
+                bh -> h
+                n4 -> b
+                nb -> b
+                bn -> b
+                bb -> n
+                b4 -> b
+                44 -> n
+                4n -> 4
+                ",
+            );
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert_eq!(p.iterations_fast(10).expect("could not count"), 1588);
+            assert_eq!(p.iterations(10), 1588);
+
+            // the example, with multi-character element names
+            let input = test_input(
+                "
+                Na Na Cl B
+
+                Cl He -> B
+                He He -> Na
+                Cl B -> He
+                Na He -> Cl
+                He B -> Cl
+                He Cl -> B
+                He Na -> Cl
+                Na Na -> Cl
+                B He -> He
+                Na Cl -> B
+                Na B -> B
+                B Na -> B
+                B B -> Na
+                B Cl -> B
+                Cl Cl -> Na
+                Cl Na -> Cl
+                ",
+            );
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert_eq!(p.iterations_fast(10).expect("could not count"), 1588);
+            assert_eq!(
+                p.element_counts(10).expect("could not count"),
+                vec![
+                    ("B".into(), 1749),
+                    ("Cl".into(), 298),
+                    ("He".into(), 161),
+                    ("Na".into(), 865)
+                ]
+            );
+
+            assert!(Polymerizer::try_from(test_input("AB\n\nA B C -> D")).is_err());
+            assert!(Polymerizer::try_from(test_input("AB\n\nA B -> C D")).is_err());
+            assert!(Polymerizer::try_from(test_input("AB\n\nAB -> CD")).is_err());
+        }
+
+        #[test]
+        fn large_alphabets() {
+            // more elements than fit in the dense representation
+            let names: Vec<char> = ('a'..='z')
+                .chain('A'..='Z')
+                .chain('0'..='9')
+                .chain("!@#$%^&*".chars())
+                .collect();
+            assert!(names.len() > DENSE_ALPHABET);
+
+            let mut input = vec![names.iter().step_by(7).collect::<String>(), String::new()];
+            for (i, a) in names.iter().enumerate() {
+                for (j, b) in names.iter().enumerate() {
+                    input.push(format!(
+                        "{}{} -> {}",
+                        a,
+                        b,
+                        names[(i * j + 3) % names.len()]
+                    ));
+                }
+            }
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert!(p.rules.alphabet().len() > DENSE_ALPHABET);
+            assert_eq!(
+                p.iterations_fast(8).expect("could not count"),
+                p.iterations(8)
+            );
+        }
+
+        #[test]
+        fn parallel_update() {
+            // enough elements that the pair counts get updated in parallel
+            let names: Vec<String> = (0..100).map(|i| format!("e{}", i)).collect();
+            let mut input = vec![names.join(" "), String::new()];
+            for (i, a) in names.iter().enumerate() {
+                for (j, b) in names.iter().enumerate() {
+                    input.push(format!(
+                        "{} {} -> {}",
+                        a,
+                        b,
+                        names[(i * 7 + j * 13 + 5) % names.len()]
+                    ));
+                }
+            }
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+
+            let mut rule_counts: FxHashMap<[usize; 2], u128> = FxHashMap::default();
+            for (begin, end) in p.template.iter().tuple_windows() {
+                *rule_counts.entry([*begin, *end]).or_default() += 1;
+            }
+
+            let mut reached_parallel = false;
+            for _ in 0..10 {
+                let serial = p.step_pairs(&rule_counts, false).expect("could not step");
+                let parallel = p.step_pairs(&rule_counts, true).expect("could not step");
+                assert_eq!(serial, parallel);
+
+                rule_counts = serial;
+                reached_parallel |= rule_counts.len() >= PARALLEL_PAIRS;
+            }
+            assert!(reached_parallel);
+
+            assert_eq!(
+                p.iterations_fast(10).expect("could not count"),
+                p.iterations(10)
+            );
+        }
+
+        #[test]
+        fn validate() {
+            let input = test_input(
+                "
+                NNCB
+
+                CH -> B
+                HH -> N
+                CB -> H
+                NH -> C
+                HB -> C
+                HC -> B
+                HN -> C
+                NN -> C
+                BH -> H
+                NC -> B
+                NB -> B
+                BN -> B
+                BB -> N
+                BC -> B
+                CC -> N
+                CN -> C
+                ",
+            );
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            let report = p.rules().validate(p.formula());
+            assert!(report.is_ok());
+            assert_eq!(report, RuleReport::default());
+
+            let input = test_input(
+                "
+                NNCB
+
+                NN -> C
+                CB -> H
+                NN -> C
+                CB -> N
+                ",
+            );
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            let report = p.rules().validate(p.formula());
+            assert!(!report.is_ok());
+
+            let pair = |a: &str, b: &str| [a.to_string(), b.to_string()];
+            assert_eq!(
+                report.missing,
+                vec![pair("C", "N"), pair("N", "B"), pair("N", "C")]
+            );
+            assert_eq!(report.duplicates, vec![pair("N", "N")]);
+            assert_eq!(
+                report.conflicts,
+                vec![(pair("C", "B"), vec!["H".to_string(), "N".to_string()])]
+            );
+
+            // elements that only appear in the formula still count
+            let report = p.rules().validate(&Formula::from("NNX".to_string()));
+            assert_eq!(
+                report.missing,
+                vec![pair("C", "N"), pair("N", "C"), pair("N", "X")]
+            );
+        }
+
+        #[test]
+        fn expand() {
+            let input = test_input(
+                "
+                NNCB
+
+                CH -> B
+                HH -> N
+                CB -> H
+                NH -> C
+                HB -> C
+                HC -> B
+                HN -> C
+                NN -> C
+                BH -> H
+                NC -> B
+                NB -> B
+                BN -> B
+                BB -> N
+                BC -> B
+                CC -> N
+                CN -> C
+                ",
+            );
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert_eq!(p.expand(0).expect("could not expand"), "NNCB");
+            assert_eq!(p.expand(1).expect("could not expand"), "NCNBCHB");
+            assert_eq!(p.expand(2).expect("could not expand"), "NBCCNBBBCBHCB");
+            assert_eq!(
+                p.expand(4).expect("could not expand"),
+                "NBBNBNBBCCNBCNCCNBBNBBNBBBNBBNBBCBHCBHHNHCBBCBHCB"
+            );
+
+            // check the counts against the real thing
+            let polymer = p.expand(10).expect("could not expand");
+            assert_eq!(polymer.len(), 3073);
+            let counts = polymer
+                .chars()
+                .counts()
+                .into_iter()
+                .map(|(ch, count)| (ch.to_string(), count as u128))
+                .sorted()
+                .collect::<Vec<_>>();
+            assert_eq!(counts, p.element_counts(10).expect("could not count"));
+
+            assert!(p.expand(40).is_err());
+
+            let input = test_input(
+                "
+                Na Cl
+
+                Na Cl -> He
+                ",
+            );
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert_eq!(p.expand(2).expect("could not expand"), "Na He Cl");
+        }
+
+        #[test]
+        fn wide_counts() {
+            let input = test_input(
+                "
+                NNCB
+
+                CH -> B
+                HH -> N
+                CB -> H
+                NH -> C
+                HB -> C
+                HC -> B
+                HN -> C
+                NN -> C
+                BH -> H
+                NC -> B
+                NB -> B
+                BN -> B
+                BB -> N
+                BC -> B
+                CC -> N
+                CN -> C
+                ",
+            );
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert_eq!(
+                p.iterations_wide::<u128>(40).expect("could not count"),
+                2188189693529
+            );
+
+            // the polymer roughly doubles in length every step
+            assert!(p.iterations_fast(100).is_err());
+            let wide = p.iterations_wide::<u128>(100).expect("could not count");
+            assert!(wide > u64::MAX as u128);
+            assert!(p.iterations_wide::<u128>(150).is_err());
+
+            #[cfg(feature = "bigint")]
+            {
+                use num_bigint::BigUint;
+
+                let big = p.iterations_wide::<BigUint>(100).expect("could not count");
+                assert_eq!(big, BigUint::from(wide));
+                assert!(p.iterations_wide::<BigUint>(200).is_ok());
+            }
+        }
+
+        #[test]
+        fn matrix_counts() {
+            let input = test_input(
+                "
+                NNCB
+
+                CH -> B
+                HH -> N
+                CB -> H
+                NH -> C
+                HB -> C
+                HC -> B
+                HN -> C
+                NN -> C
+                BH -> H
+                NC -> B
+                NB -> B
+                BN -> B
+                BB -> N
+                BC -> B
+                CC -> N
+                CN -> C
+                ",
+            );
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            for num in 0..=100 {
+                assert_eq!(
+                    p.iterations_matrix::<u128>(num).expect("could not count"),
+                    p.iterations_wide::<u128>(num).expect("could not count")
+                );
+            }
+            assert!(p.iterations_matrix::<u128>(150).is_err());
+
+            #[cfg(feature = "bigint")]
+            {
+                use num_bigint::BigUint;
+
+                assert_eq!(
+                    p.iterations_matrix::<BigUint>(500)
+                        .expect("could not count"),
+                    p.iterations_wide::<BigUint>(500).expect("could not count")
+                );
+            }
+        }
+
+        #[test]
+        fn sparse_rules() {
+            // most pairs have no rule, and carry over unchanged
+            let input = test_input(
+                "
+                NNCB
+
+                NN -> C
+                NC -> B
+                CB -> H
+                ",
+            );
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert_eq!(p.expand(2).expect("could not expand"), "NBCNBCHB");
+            assert_eq!(
+                p.element_counts(2).expect("could not count"),
+                vec![
+                    ("B".to_string(), 3),
+                    ("C".to_string(), 2),
+                    ("H".to_string(), 1),
+                    ("N".to_string(), 2),
+                ]
+            );
+
+            for num in 0..10 {
+                let polymer = p.expand(num).expect("could not expand");
+                let expected = polymer
+                    .chars()
+                    .counts()
+                    .into_iter()
+                    .map(|(ch, count)| (ch.to_string(), count as u128))
+                    .sorted()
+                    .collect::<Vec<_>>();
+                let spread = match expected.iter().map(|(_, count)| *count).minmax() {
+                    MinMaxResult::MinMax(a, b) => b - a,
+                    _ => 0,
+                };
+
+                assert_eq!(p.element_counts(num).expect("could not count"), expected);
+                assert_eq!(
+                    p.named(
+                        p.element_counts_matrix::<u128>(num)
+                            .expect("could not count")
+                    ),
+                    expected
+                );
+                assert_eq!(p.iterations(num), spread as usize);
+                assert_eq!(
+                    p.iterations_fast(num).expect("could not count"),
+                    spread as usize
+                );
+                assert_eq!(
+                    p.iterations_matrix::<u128>(num).expect("could not count"),
+                    spread
+                );
+
+                if num > 0 {
+                    assert_eq!(p.steps().nth(num - 1), Some(expected));
+                }
+            }
+
+            // the sparse backend, both serial and parallel
+            let mut rule_counts: FxHashMap<[usize; 2], u128> = FxHashMap::default();
+            for (begin, end) in p.template.iter().tuple_windows() {
+                *rule_counts.entry([*begin, *end]).or_default() += 1;
+            }
+            for _ in 0..5 {
+                let serial = p.step_pairs(&rule_counts, false).expect("could not step");
+                let parallel = p.step_pairs(&rule_counts, true).expect("could not step");
+                assert_eq!(serial, parallel);
+                rule_counts = serial;
+            }
+            let mut counts = vec![0_u128; p.rules.alphabet.len()];
+            counts[*p.template.last().unwrap()] = 1;
+            for (k, v) in rule_counts.iter() {
+                counts[k[0]] += v;
+            }
+            let polymer = p.expand(5).expect("could not expand");
+            assert_eq!(
+                p.named(counts),
+                polymer
+                    .chars()
+                    .counts()
+                    .into_iter()
+                    .map(|(ch, count)| (ch.to_string(), count as u128))
+                    .sorted()
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        #[test]
+        fn comparison() {
+            let input = test_input(
+                "
+                NNCBC
+
+                CH -> B
+                HH -> N
+                CB -> H
+                NH -> C
+                HB -> C
+                HC -> B
+                HN -> C
+                NN -> C
+                BH -> H
+                NC -> B
+                NB -> B
+                BN -> B
+                BB -> N
+                BC -> B
+                CC -> N
+                CN -> C
+                ",
+            );
+
+            let p = Polymerizer::try_from(input).expect("could not parse input");
+            assert_eq!(
+                p.iterations(10),
+                p.iterations_fast(10).expect("could not count")
+            );
         }
     }
 }
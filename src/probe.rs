@@ -27,7 +27,7 @@ impl Target {
     }
 }
 
-use std::{convert::TryFrom, num::ParseIntError, str::FromStr};
+use std::{collections::BTreeMap, convert::TryFrom, num::ParseIntError, str::FromStr};
 
 impl FromStr for Target {
     type Err = anyhow::Error;
@@ -60,141 +60,569 @@ impl FromStr for Target {
             bail!("Invalid input: {}", s);
         }
 
+        if y[0] <= 0 && y[1] >= 0 {
+            // a probe that stalls over the target falls back through the
+            // launch height from any height, so there would be no end to the
+            // velocities that hit it
+            bail!("Target spans the launch height: {}", s);
+        }
+
         Ok(Self::new(x[0], x[1], y[0], y[1]))
     }
 }
 
+/// The forces acting on the probe at each step: drag slows it toward zero in
+/// x, and gravity pulls it down in y. The puzzle loses one unit of speed to
+/// each per step.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Physics {
+    drag: i64,
+    gravity: i64,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            drag: 1,
+            gravity: 1,
+        }
+    }
+}
+
+impl Physics {
+    /// `drag` and `gravity` are the speed lost in x and y at each step. There
+    /// can be no drag at all, but without gravity a probe launched upward
+    /// would never come back down, so there has to be some.
+    pub fn new(drag: i64, gravity: i64) -> Result<Self> {
+        if drag < 0 {
+            bail!("Drag cannot be negative: {}", drag);
+        }
+
+        if gravity < 1 {
+            bail!("Gravity must be positive: {}", gravity);
+        }
+
+        Ok(Self { drag, gravity })
+    }
+
+    pub fn drag(&self) -> i64 {
+        self.drag
+    }
+
+    pub fn gravity(&self) -> i64 {
+        self.gravity
+    }
+
+    /// How many steps a probe moving at `speed` in x keeps moving for, or
+    /// `None` if there is no drag to stop it
+    fn moving_steps(&self, speed: i64) -> Option<i64> {
+        if speed == 0 {
+            Some(0)
+        } else if self.drag == 0 {
+            None
+        } else {
+            Some((speed + self.drag - 1) / self.drag)
+        }
+    }
+
+    /// How far a probe moving at `speed` in x gets before drag stops it, or
+    /// `None` if there is no drag to stop it
+    fn stall_distance(&self, speed: i64) -> Option<i64> {
+        self.moving_steps(speed)
+            .map(|n| speed * n - self.drag * n * (n - 1) / 2)
+    }
+
+    /// The lowest speed in x that covers at least `distance` before drag
+    /// stops the probe
+    fn min_speed(&self, distance: i64) -> i64 {
+        (1..)
+            .find(|speed| match self.stall_distance(*speed) {
+                Some(d) => d >= distance,
+                // without drag, any speed gets there eventually
+                None => true,
+            })
+            .unwrap_or_default()
+    }
+
+    /// The first step at which a probe moving at `speed` in x has covered at
+    /// least `distance`, if it ever does
+    fn first_step_covering(&self, speed: i64, distance: i64) -> Option<i64> {
+        if distance <= 0 {
+            return Some(0);
+        }
+
+        match self.stall_distance(speed) {
+            _ if speed == 0 => None,
+            None => Some((distance + speed - 1) / speed),
+            Some(d) if d < distance => None,
+            // until it stalls, the distance covered is a parabola
+            Some(_) => steps_at_least(speed, self.drag, distance).map(|(lo, _)| lo),
+        }
+    }
+
+    /// The range of speeds along an axis with drag, like x, outside of which
+    /// a probe can never be between `min` and `max`
+    fn drift_speeds(&self, min: i64, max: i64) -> (i64, i64) {
+        // any faster and the first step overshoots the target, and a probe
+        // that is too slow stalls before reaching it
+        let lo = if min > 0 { self.min_speed(min) } else { min };
+        let hi = if max < 0 { -self.min_speed(-max) } else { max };
+        (lo, hi)
+    }
+
+    /// The range of speeds along the axis with gravity, y, outside of which
+    /// a probe can never be between `min` and `max`
+    fn fall_speeds(&self, min: i64, max: i64) -> (i64, i64) {
+        // below the target, any faster downward overshoots it in one step.
+        // going up at vy, every point above the origin is at least vy - g/2
+        // high, and the first point below it is at least vy/g deep, so a
+        // probe going up much faster than the target is distant overshoots it
+        // both ways
+        let g = self.gravity;
+        (min.min(0), g * min.abs().max(max.abs()) + g)
+    }
+
+    /// The range of steps during which a probe launched at `v` along an axis
+    /// with drag is between `min` and `max`. A probe that stalls within them
+    /// stays there forever, so the end of the range may be `i64::MAX`.
+    fn drift_steps(&self, v: i64, min: i64, max: i64) -> Option<(i64, i64)> {
+        // mirror the range so that the probe is moving forward
+        let (speed, min, max) = if v >= 0 {
+            (v, min, max)
+        } else {
+            (-v, -max, -min)
+        };
+
+        let lo = self.first_step_covering(speed, min)?;
+        let hi = self
+            .first_step_covering(speed, max + 1)
+            .map_or(i64::MAX, |t| t - 1);
+
+        if lo <= hi {
+            Some((lo, hi))
+        } else {
+            None
+        }
+    }
+
+    /// The ranges of steps during which a probe launched at `vy` is between
+    /// `min` and `max` in y. A range above the origin can be passed through
+    /// twice, once going up and once coming down.
+    fn fall_steps(&self, vy: i64, min: i64, max: i64) -> Vec<(i64, i64)> {
+        let (lo, hi) = match steps_at_least(vy, self.gravity, min) {
+            Some(range) => range,
+            None => return Vec::new(),
+        };
+
+        match steps_at_least(vy, self.gravity, max + 1) {
+            None => vec![(lo, hi)],
+            // above the range in the middle of the steps
+            Some((above_lo, above_hi)) => [(lo, hi.min(above_lo - 1)), (lo.max(above_hi + 1), hi)]
+                .iter()
+                .copied()
+                .filter(|(lo, hi)| lo <= hi)
+                .collect(),
+        }
+    }
+}
+
+/// The steps t >= 0 at which `p t - q t (t - 1) / 2` is at least `a`, for a
+/// positive `q`. The curve rises and then falls, so these form a single range.
+fn steps_at_least(p: i64, q: i64, a: i64) -> Option<(i64, i64)> {
+    let f = |t: i64| p * t - q * t * (t - 1) / 2;
+    let b = (2 * p + q) as f64;
+    let discriminant = b * b - 8_f64 * q as f64 * a as f64;
+    if discriminant < 0_f64 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    let mut lo = ((b - root) / (2 * q) as f64).ceil().max(0_f64) as i64;
+    let mut hi = ((b + root) / (2 * q) as f64).floor() as i64;
+
+    // the roots are only approximate, so nudge the ends onto the exact steps
+    while lo > 0 && f(lo - 1) >= a {
+        lo -= 1;
+    }
+    while lo <= hi && f(lo) < a {
+        lo += 1;
+    }
+    while f(hi + 1) >= a {
+        hi += 1;
+    }
+    while hi >= lo && f(hi) < a {
+        hi -= 1;
+    }
+
+    if lo <= hi {
+        Some((lo, hi))
+    } else {
+        None
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Probe {
     vx: i64,
     vy: i64,
+    physics: Physics,
 }
 
 impl Probe {
     pub fn new(vx: i64, vy: i64) -> Self {
-        Self { vx, vy }
+        Self {
+            vx,
+            vy,
+            physics: Physics::default(),
+        }
+    }
+
+    pub fn with_physics(mut self, physics: Physics) -> Self {
+        self.physics = physics;
+        self
+    }
+
+    pub fn physics(&self) -> Physics {
+        self.physics
     }
 
     pub fn xt(&self, t: i64) -> i64 {
-        // after vx steps, there's no additional change in x, since vx would
-        // then be zero
-        let t_max = self.vx.abs().min(t);
-        self.vx * t_max - (t_max * (t_max - 1)) / 2
+        // once drag has brought vx to zero, there's no additional change in
+        // x. drag slows the probe the same way in either direction
+        let speed = self.vx.abs();
+        let t_max = self.physics.moving_steps(speed).map_or(t, |n| n.min(t));
+        self.vx.signum() * (speed * t_max - self.physics.drag * (t_max * (t_max - 1)) / 2)
     }
 
     pub fn yt(&self, t: i64) -> i64 {
-        self.vy * t - (t * (t - 1)) / 2
+        self.vy * t - self.physics.gravity * (t * (t - 1)) / 2
     }
 
+    /// A lower bound on the first step at which the probe has covered `x`,
+    /// or `None` if it never gets that far
     pub fn min_t_to_x(&self, x: i64) -> Option<i64> {
         if x > self.max_x() {
             None
+        } else if x <= 0 {
+            Some(0)
+        } else if self.physics.drag == 0 {
+            Some(x / self.vx)
         } else {
             let v = self.vx as f64;
-            let b = 2_f64 * v + 1_f64;
-            let t1 = (0.5 * ((b * b - 8_f64 * x as f64).sqrt() + b)).floor() as i64;
-            let t2 = (-0.5 * ((b * b - 8_f64 * x as f64).sqrt() + 0.5 * b)).floor() as i64;
-            Some(0.max(t1.min(t2)))
+            let d = self.physics.drag as f64;
+            let b = 2_f64 * v + d;
+            let t = ((b - (b * b - 8_f64 * d * x as f64).sqrt()) / (2_f64 * d)).floor() as i64;
+            Some(0.max(t))
         }
     }
 
+    /// A lower bound on the first step at which the probe reaches height `y`,
+    /// or `None` if it never climbs that high
     pub fn min_t_to_y(&self, y: i64) -> Option<i64> {
         let v = self.vy as f64;
-        let b = 2_f64 * v + 1_f64;
-        let t1 = (0.5 * ((b * b - 8_f64 * y as f64).sqrt() + b)).floor() as i64;
-        let t2 = (-0.5 * ((b * b - 8_f64 * y as f64).sqrt() + 0.5 * b)).floor() as i64;
-        Some(0.max(t1.min(t2)))
+        let g = self.physics.gravity as f64;
+        let b = 2_f64 * v + g;
+        let discriminant = b * b - 8_f64 * g * y as f64;
+        if discriminant < 0_f64 {
+            None
+        } else {
+            let t = ((b - discriminant.sqrt()) / (2_f64 * g)).floor() as i64;
+            Some(0.max(t))
+        }
     }
 
     pub fn point_at(&self, t: i64) -> (i64, i64) {
         (self.xt(t), self.yt(t))
     }
 
+    /// How far the probe gets in x before drag stops it. Without drag there
+    /// is no limit, so this saturates.
     pub fn max_x(&self) -> i64 {
-        self.xt(self.vx.abs())
+        match self.physics.moving_steps(self.vx.abs()) {
+            Some(n) => self.xt(n),
+            None => self.vx.signum() * i64::MAX,
+        }
     }
 
     pub fn max_height(&self) -> i64 {
         if self.vy <= 0 {
             0
         } else {
-            self.yt(self.vy.abs())
+            // the probe climbs until gravity takes away the last of vy
+            let g = self.physics.gravity;
+            self.yt((self.vy + g - 1) / g)
+        }
+    }
+
+    /// The positions of the probe at each step, simulated one step at a
+    /// time, starting from the origin and stopping once the probe is past
+    /// the target and can never reach it
+    pub fn trajectory<'a>(&self, target: &'a Target) -> Trajectory<'a> {
+        Trajectory {
+            pos: (0, 0),
+            vel: (self.vx, self.vy),
+            physics: self.physics,
+            target,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Trajectory<'a> {
+    pos: (i64, i64),
+    vel: (i64, i64),
+    physics: Physics,
+    target: &'a Target,
+}
+
+impl<'a> Trajectory<'a> {
+    fn past_target(&self) -> bool {
+        let ((x, y), (vx, vy)) = (self.pos, self.vel);
+        (y < self.target.y_min && vy <= 0)
+            || (x > self.target.x_max && vx >= 0)
+            || (x < self.target.x_min && vx <= 0)
+    }
+}
+
+impl<'a> Iterator for Trajectory<'a> {
+    type Item = (i64, i64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.past_target() {
+            return None;
         }
+
+        let cur = self.pos;
+        let (vx, vy) = self.vel;
+        self.pos = (self.pos.0 + vx, self.pos.1 + vy);
+        // drag never takes the probe past zero
+        let drag = self.physics.drag.min(vx.abs());
+        self.vel = (vx - vx.signum() * drag, vy - self.physics.gravity);
+        Some(cur)
     }
 }
 
+/// The launch that reaches the greatest height while still hitting the target
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Shot {
+    pub velocity: (i64, i64),
+    pub max_height: i64,
+    /// the first step at which the probe is inside the target
+    pub time_in_target: i64,
+}
+
+/// Statistics across every launch that hits the target
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LaunchReport {
+    pub count: usize,
+    pub max_height: i64,
+    /// the earliest and latest steps at which a probe first reaches the
+    /// target, if any do
+    pub min_time_to_target: Option<i64>,
+    pub max_time_to_target: Option<i64>,
+    /// how many launches reach each apex height
+    pub apex_heights: BTreeMap<i64, usize>,
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Launcher {
     target: Target,
+    physics: Physics,
 }
 
 impl Launcher {
+    pub fn new(target: Target) -> Self {
+        Self {
+            target,
+            physics: Physics::default(),
+        }
+    }
+
+    pub fn with_physics(mut self, physics: Physics) -> Self {
+        self.physics = physics;
+        self
+    }
+
+    pub fn physics(&self) -> Physics {
+        self.physics
+    }
+
     pub fn launch(&self, target: &Target) -> (i64, usize) {
-        let mut size = 0_usize;
-        let min_vx = (0.5 * ((target.x_min as f64 * 8_f64 + 1_f64).sqrt() - 1_f64)).ceil() as i64;
-        let max_vx = target.x_max;
+        let physics = self.physics;
+        self.valid_velocities(target)
+            .fold((0, 0), |(max, size), (vx, vy)| {
+                let probe = Probe::new(vx, vy).with_physics(physics);
+                (max.max(probe.max_height()), size + 1)
+            })
+    }
+
+    /// Every initial velocity that puts the probe inside the target at some
+    /// step, ordered by vx and then by vy. The target can be anywhere
+    /// relative to the origin, including above it, as long as it does not
+    /// span the launch height.
+    pub fn valid_velocities<'a>(
+        &self,
+        target: &'a Target,
+    ) -> impl Iterator<Item = (i64, i64)> + 'a {
+        let physics = self.physics;
+        let ((min_vx, max_vx), (min_vy, max_vy)) = self.bounds(target);
 
         // given min/max vx, figure all all times t which are valid in target area
-        let mut max = 0;
-        // similar for vx, our starting min is the y_min of the target
-        // (reaching in 1 step)
-        for vx in min_vx..=max_vx {
-            let mut probe = Probe::new(vx, 0);
-            if let Some(t_min) = probe.min_t_to_x(target.x_min) {
-                for vy in target.y_min..=target.y_min.abs() {
-                    probe.vy = vy;
-
-                    let mut t = t_min;
-                    // find first t where x is in the target
-                    // sim until x pos is in target or beyond it
-                    let contained = loop {
-                        let x = probe.xt(t);
-                        if target.contains((x, target.y_min)) {
-                            break true;
-                        }
-                        t += 1;
+        (min_vx..=max_vx)
+            .filter_map(move |vx| Self::first_t_in_x(vx, target, physics).map(|t| (vx, t)))
+            .flat_map(move |(vx, t_x)| {
+                (min_vy..=max_vy)
+                    .filter(move |vy| {
+                        Self::hits(Probe::new(vx, *vy).with_physics(physics), t_x, target)
+                    })
+                    .map(move |vy| (vx, vy))
+            })
+    }
 
-                        if x > target.x_max {
-                            break false;
-                        }
-                    };
+    /// The velocity that reaches the greatest height while still hitting the
+    /// target, or `None` if nothing hits it. Of the velocities that reach the
+    /// same height, the one with the lowest vx wins.
+    pub fn best_shot(&self, target: &Target) -> Option<Shot> {
+        let physics = self.physics;
+        let ((vx, vy), max_height) = self
+            .valid_velocities(target)
+            .map(|(vx, vy)| {
+                let height = Probe::new(vx, vy).with_physics(physics).max_height();
+                ((vx, vy), height)
+            })
+            .fold(None, |best, (v, height)| match best {
+                Some((_, best_height)) if best_height >= height => best,
+                _ => Some((v, height)),
+            })?;
 
-                    if !contained {
-                        // we couldn't actually get a valid x position for any t,
-                        // so no point in looking at additional y values
-                        break;
-                    }
+        Some(Shot {
+            velocity: (vx, vy),
+            max_height,
+            time_in_target: self.time_in_target(vx, vy, target)?,
+        })
+    }
 
-                    // adjust t to the time the probe would be crossing the zero
-                    // line again
-                    if vy > 0 && t < vy * 2 {
-                        t = vy * 2;
-                    }
+    pub fn launch_report(&self, target: &Target) -> LaunchReport {
+        let physics = self.physics;
+        self.valid_velocities(target)
+            .fold(LaunchReport::default(), |mut report, (vx, vy)| {
+                let height = Probe::new(vx, vy).with_physics(physics).max_height();
+                report.count += 1;
+                report.max_height = report.max_height.max(height);
+                *report.apex_heights.entry(height).or_default() += 1;
 
-                    // we now know the first t to start simulation of y from
-                    loop {
-                        let p = probe.point_at(t);
-                        if target.contains(p) {
-                            // this probe would be valid
-                            size += 1;
-                            let cur_max = probe.yt(probe.vy.min(t));
-                            if cur_max > max {
-                                max = cur_max;
-                            }
-                            break;
-                        }
+                if let Some(t) = self.time_in_target(vx, vy, target) {
+                    report.min_time_to_target =
+                        Some(report.min_time_to_target.map_or(t, |cur| cur.min(t)));
+                    report.max_time_to_target =
+                        Some(report.max_time_to_target.map_or(t, |cur| cur.max(t)));
+                }
 
-                        if p.1 < target.y_min {
-                            // this probe is not valid
-                            break;
-                        }
+                report
+            })
+    }
 
-                        t += 1;
-                    }
-                }
+    /// The first step at which a probe launched at `(vx, vy)` is inside the
+    /// target, if it ever is
+    fn time_in_target(&self, vx: i64, vy: i64, target: &Target) -> Option<i64> {
+        let (x_lo, x_hi) = self.physics.drift_steps(vx, target.x_min, target.x_max)?;
+        self.physics
+            .fall_steps(vy, target.y_min, target.y_max)
+            .into_iter()
+            .filter(|(lo, hi)| *lo <= x_hi && x_lo <= *hi)
+            .map(|(lo, _)| lo.max(x_lo))
+            .min()
+    }
+
+    /// Count the valid velocities without simulating any steps at all: the
+    /// steps at which the probe is within the x bounds and the steps at which
+    /// it is within the y bounds are both found by solving for where the
+    /// position crosses the edges of the target, and a velocity is valid if
+    /// the two overlap
+    pub fn count_analytic(&self, target: &Target) -> usize {
+        let physics = self.physics;
+        let ((min_vx, max_vx), (min_vy, max_vy)) = self.bounds(target);
+
+        let y_steps: Vec<_> = (min_vy..=max_vy)
+            .map(|vy| physics.fall_steps(vy, target.y_min, target.y_max))
+            .collect();
+
+        (min_vx..=max_vx)
+            .filter_map(|vx| physics.drift_steps(vx, target.x_min, target.x_max))
+            .map(|(x_lo, x_hi)| {
+                y_steps
+                    .iter()
+                    .filter(|ranges| ranges.iter().any(|(lo, hi)| *lo <= x_hi && x_lo <= *hi))
+                    .count()
+            })
+            .sum()
+    }
+
+    /// The ranges of vx and vy outside of which no probe can hit the target
+    fn bounds(&self, target: &Target) -> ((i64, i64), (i64, i64)) {
+        (
+            self.physics.drift_speeds(target.x_min, target.x_max),
+            self.physics.fall_speeds(target.y_min, target.y_max),
+        )
+    }
+
+    /// The first t at which a probe launched with `vx` is within the x bounds
+    /// of the target, if there is one
+    fn first_t_in_x(vx: i64, target: &Target, physics: Physics) -> Option<i64> {
+        if target.x_min <= 0 && target.x_max >= 0 {
+            // the probe starts within the x bounds
+            return Some(0);
+        }
+
+        // the target is entirely to one side, so mirror it to the right
+        let (vx, x_min, x_max) = if target.x_max < 0 {
+            (-vx, -target.x_max, -target.x_min)
+        } else {
+            (vx, target.x_min, target.x_max)
+        };
+
+        let probe = Probe::new(vx, 0).with_physics(physics);
+        let mut t = probe.min_t_to_x(x_min)?;
+        // find first t where x is in the target
+        // sim until x pos is in target or beyond it
+        loop {
+            let x = probe.xt(t);
+            if x >= x_min && x <= x_max {
+                return Some(t);
+            }
+
+            if x > x_max {
+                // we couldn't actually get a valid x position for any t
+                return None;
+            }
+            t += 1;
+        }
+    }
+
+    fn hits(probe: Probe, t_x: i64, target: &Target) -> bool {
+        // if the target is below the origin, adjust t to the last time the
+        // probe is still above the zero line, since it can't be in the
+        // target before then
+        let g = probe.physics.gravity;
+        let mut t = t_x;
+        if target.y_max < 0 && probe.vy > 0 && t < probe.vy * 2 / g {
+            t = probe.vy * 2 / g;
+        }
+
+        // we now know the first t to start simulation of y from
+        loop {
+            let p = probe.point_at(t);
+            if target.contains(p) {
+                return true;
             }
+
+            // below the target, and no longer rising
+            if p.1 < target.y_min && probe.vy - g * t <= 0 {
+                return false;
+            }
+
+            t += 1;
         }
-        (max, size)
     }
 }
 
@@ -204,7 +632,7 @@ impl TryFrom<Vec<String>> for Launcher {
     fn try_from(value: Vec<String>) -> Result<Self> {
         let target = Target::from_str(value.first().ok_or_else(|| anyhow!("input is empty!"))?)?;
 
-        Ok(Self { target })
+        Ok(Self::new(target))
     }
 }
 
@@ -236,16 +664,469 @@ impl Solver for Launcher {
     }
 }
 
+/// A target in three dimensions, where z behaves like x
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Target3 {
+    xy: Target,
+    z_min: i64,
+    z_max: i64,
+}
+
+impl Target3 {
+    pub fn new(x_min: i64, x_max: i64, y_min: i64, y_max: i64, z_min: i64, z_max: i64) -> Self {
+        Self {
+            xy: Target::new(x_min, x_max, y_min, y_max),
+            z_min,
+            z_max,
+        }
+    }
+
+    pub fn contains(&self, point: (i64, i64, i64)) -> bool {
+        self.xy.contains((point.0, point.1)) && point.2 >= self.z_min && point.2 <= self.z_max
+    }
+}
+
+/// A probe in three dimensions. Drag slows it toward zero in z just as it
+/// does in x.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Probe3 {
+    xy: Probe,
+    z: Probe,
+}
+
+impl Probe3 {
+    pub fn new(vx: i64, vy: i64, vz: i64) -> Self {
+        Self {
+            xy: Probe::new(vx, vy),
+            z: Probe::new(vz, 0),
+        }
+    }
+
+    pub fn with_physics(mut self, physics: Physics) -> Self {
+        self.xy = self.xy.with_physics(physics);
+        self.z = self.z.with_physics(physics);
+        self
+    }
+
+    pub fn point_at(&self, t: i64) -> (i64, i64, i64) {
+        let (x, y) = self.xy.point_at(t);
+        (x, y, self.z.xt(t))
+    }
+
+    pub fn max_height(&self) -> i64 {
+        self.xy.max_height()
+    }
+}
+
+/// Launches probes at a target in three dimensions, finding the hits the same
+/// way as `Launcher::count_analytic`, without simulating any steps
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Launcher3 {
+    physics: Physics,
+}
+
+impl Default for Launcher3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Launcher3 {
+    pub fn new() -> Self {
+        Self {
+            physics: Physics::default(),
+        }
+    }
+
+    pub fn with_physics(mut self, physics: Physics) -> Self {
+        self.physics = physics;
+        self
+    }
+
+    pub fn physics(&self) -> Physics {
+        self.physics
+    }
+
+    /// The highest point reached by any probe that hits the target, and the
+    /// number of velocities that hit it
+    pub fn launch(&self, target: &Target3) -> (i64, usize) {
+        let physics = self.physics;
+        self.valid_velocities(target)
+            .fold((0, 0), |(max, size), (vx, vy, vz)| {
+                let probe = Probe3::new(vx, vy, vz).with_physics(physics);
+                (max.max(probe.max_height()), size + 1)
+            })
+    }
+
+    /// Every initial velocity that puts the probe inside the target at some
+    /// step, ordered by vx, then vy, then vz. As in two dimensions, the
+    /// target must not span the launch height.
+    pub fn valid_velocities<'a>(
+        &self,
+        target: &'a Target3,
+    ) -> impl Iterator<Item = (i64, i64, i64)> + 'a {
+        let physics = self.physics;
+        let (min_vx, max_vx) = physics.drift_speeds(target.xy.x_min, target.xy.x_max);
+        let (min_vy, max_vy) = physics.fall_speeds(target.xy.y_min, target.xy.y_max);
+        let (min_vz, max_vz) = physics.drift_speeds(target.z_min, target.z_max);
+
+        (min_vx..=max_vx)
+            .filter_map(move |vx| {
+                physics
+                    .drift_steps(vx, target.xy.x_min, target.xy.x_max)
+                    .map(|range| (vx, range))
+            })
+            .flat_map(move |(vx, (x_lo, x_hi))| {
+                (min_vy..=max_vy)
+                    .map(move |vy| {
+                        // the steps during which the probe is within both the
+                        // x and y bounds
+                        let ranges: Vec<_> = physics
+                            .fall_steps(vy, target.xy.y_min, target.xy.y_max)
+                            .into_iter()
+                            .map(|(lo, hi)| (lo.max(x_lo), hi.min(x_hi)))
+                            .filter(|(lo, hi)| lo <= hi)
+                            .collect();
+                        (vy, ranges)
+                    })
+                    .filter(|(_, ranges)| !ranges.is_empty())
+                    .flat_map(move |(vy, ranges)| {
+                        (min_vz..=max_vz)
+                            .filter(move |vz| {
+                                match physics.drift_steps(*vz, target.z_min, target.z_max) {
+                                    Some((z_lo, z_hi)) => {
+                                        ranges.iter().any(|(lo, hi)| *lo <= z_hi && z_lo <= *hi)
+                                    }
+                                    None => false,
+                                }
+                            })
+                            .map(move |vz| (vx, vy, vz))
+                    })
+            })
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::rng::xorshift;
+
     use super::*;
 
+    fn brute_force(target: &Target, physics: Physics) -> Vec<(i64, i64)> {
+        let mut valid = Vec::new();
+        for vx in -40..=40 {
+            for vy in -40..=40 {
+                if Probe::new(vx, vy)
+                    .with_physics(physics)
+                    .trajectory(target)
+                    .any(|p| target.contains(p))
+                {
+                    valid.push((vx, vy));
+                }
+            }
+        }
+        valid
+    }
+
     #[test]
     fn example() {
         let target = Target::new(20, 30, -10, -5);
-        let l = Launcher { target };
+        let l = Launcher::new(target);
         let (highest, num) = l.launch(&target);
         assert_eq!(highest, 45);
         assert_eq!(num, 112);
     }
+
+    #[test]
+    fn quadrants() {
+        // the example target, mirrored into each quadrant, and moved so
+        // that it is directly below the origin
+        for target in [
+            Target::new(20, 30, -10, -5),
+            Target::new(-30, -20, -10, -5),
+            Target::new(20, 30, 5, 10),
+            Target::new(-30, -20, 5, 10),
+            Target::new(-5, 5, -10, -5),
+            Target::new(-5, 5, 5, 10),
+        ] {
+            let l = Launcher::new(target);
+            let valid: Vec<_> = l.valid_velocities(&target).collect();
+            assert_eq!(valid, brute_force(&target, l.physics()), "{:?}", target);
+
+            let highest = valid
+                .iter()
+                .map(|(vx, vy)| Probe::new(*vx, *vy).max_height())
+                .max()
+                .unwrap();
+            assert_eq!(l.launch(&target), (highest, valid.len()));
+            assert_eq!(l.count_analytic(&target), valid.len());
+        }
+
+        // mirroring in x mirrors the velocities
+        let target = Target::new(-30, -20, -10, -5);
+        let l = Launcher::new(target);
+        assert_eq!(l.launch(&target), (45, 112));
+        assert!(l.valid_velocities(&target).any(|v| v == (-6, 9)));
+
+        assert_eq!(
+            Target::from_str("target area: x=-30..-20, y=5..10").unwrap(),
+            Target::new(-30, -20, 5, 10)
+        );
+        assert!(Target::from_str("target area: x=20..30, y=-5..5").is_err());
+        assert!(Target::from_str("target area: x=20..30, y=0..5").is_err());
+    }
+
+    #[test]
+    fn count_analytic() {
+        let target = Target::new(20, 30, -10, -5);
+        assert_eq!(Launcher::new(target).count_analytic(&target), 112);
+
+        let mut next = xorshift(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..200 {
+            let x_min = next(120) as i64 - 60;
+            let x_max = x_min + next(30) as i64;
+            let mut y_min = next(50) as i64 + 1;
+            let mut y_max = y_min + next(20) as i64;
+            if next(2) == 0 {
+                std::mem::swap(&mut y_min, &mut y_max);
+                y_min = -y_min;
+                y_max = -y_max;
+            }
+            let target = Target::new(x_min, x_max, y_min, y_max);
+            let physics = Physics::new(next(4) as i64, next(3) as i64 + 1).unwrap();
+
+            let l = Launcher::new(target).with_physics(physics);
+            assert_eq!(
+                l.count_analytic(&target),
+                l.launch(&target).1,
+                "{:?} {:?}",
+                target,
+                physics
+            );
+        }
+    }
+
+    #[test]
+    fn three_dimensions() {
+        // with no room in z, only probes that stay at z = 0 hit, and those
+        // are the same as in two dimensions
+        let target = Target3::new(20, 30, -10, -5, 0, 0);
+        assert_eq!(Launcher3::new().launch(&target), (45, 112));
+
+        for physics in [Physics::default(), Physics::new(2, 3).unwrap()] {
+            for target in [
+                Target3::new(20, 30, -10, -5, -4, 6),
+                Target3::new(-8, -3, 5, 10, 10, 15),
+                Target3::new(-5, 5, -10, -5, -30, -20),
+            ] {
+                // simulate every velocity in a box around the search space,
+                // a step at a time
+                let mut expected = Vec::new();
+                for vx in -30..=30 {
+                    for vy in -10..=40 {
+                        for vz in -30..=30 {
+                            let (mut pos, mut vel) = ((0, 0, 0), (vx, vy, vz));
+                            while pos.1 >= -10 || vel.1 > 0 {
+                                if target.contains(pos) {
+                                    expected.push((vx, vy, vz));
+                                    break;
+                                }
+
+                                pos = (pos.0 + vel.0, pos.1 + vel.1, pos.2 + vel.2);
+                                let (dx, dz) = (
+                                    physics.drag().min(vel.0.abs()),
+                                    physics.drag().min(vel.2.abs()),
+                                );
+                                vel = (
+                                    vel.0 - vel.0.signum() * dx,
+                                    vel.1 - physics.gravity(),
+                                    vel.2 - vel.2.signum() * dz,
+                                );
+                            }
+                        }
+                    }
+                }
+
+                let l = Launcher3::new().with_physics(physics);
+                let valid: Vec<_> = l.valid_velocities(&target).collect();
+                assert!(!valid.is_empty());
+                assert_eq!(valid, expected, "{:?}", target);
+
+                let highest = valid
+                    .iter()
+                    .map(|(vx, vy, vz)| {
+                        Probe3::new(*vx, *vy, *vz)
+                            .with_physics(physics)
+                            .max_height()
+                    })
+                    .max()
+                    .unwrap_or_default();
+                assert_eq!(l.launch(&target), (highest, valid.len()));
+            }
+        }
+    }
+
+    #[test]
+    fn best_shot() {
+        let target = Target::new(20, 30, -10, -5);
+        let shot = Launcher::new(target).best_shot(&target).unwrap();
+        assert_eq!(
+            shot,
+            Shot {
+                velocity: (6, 9),
+                max_height: 45,
+                time_in_target: 20,
+            }
+        );
+
+        for target in [Target::new(-30, -20, 5, 10), Target::new(-5, 5, -10, -5)] {
+            for physics in [Physics::default(), Physics::new(2, 3).unwrap()] {
+                let l = Launcher::new(target).with_physics(physics);
+                let shot = l.best_shot(&target).unwrap();
+                assert_eq!(shot.max_height, l.launch(&target).0);
+
+                // the time matches the first point of the trajectory inside
+                // the target
+                let (vx, vy) = shot.velocity;
+                let arrival = Probe::new(vx, vy)
+                    .with_physics(physics)
+                    .trajectory(&target)
+                    .position(|p| target.contains(p));
+                assert_eq!(arrival, Some(shot.time_in_target as usize));
+            }
+        }
+    }
+
+    #[test]
+    fn launch_report() {
+        let target = Target::new(20, 30, -10, -5);
+        let l = Launcher::new(target);
+        let report = l.launch_report(&target);
+        assert_eq!(report.count, 112);
+        assert_eq!(report.max_height, 45);
+        // straight into the target in one step, and the highest shot
+        assert_eq!(report.min_time_to_target, Some(1));
+        assert_eq!(report.max_time_to_target, Some(20));
+        assert_eq!(report.apex_heights.values().sum::<usize>(), 112);
+        // probes that never climb have their apex at the launch point
+        let never_climb = l.valid_velocities(&target).filter(|v| v.1 <= 0).count();
+        assert_eq!(report.apex_heights.get(&0), Some(&never_climb));
+        assert_eq!(report.apex_heights.get(&45), Some(&2));
+
+        let empty = l.launch_report(&Target::new(5, 1, -10, -5));
+        assert_eq!(empty, LaunchReport::default());
+    }
+
+    #[test]
+    fn physics() {
+        assert_eq!(Physics::new(1, 1).unwrap(), Physics::default());
+        assert!(Physics::new(-1, 1).is_err());
+        assert!(Physics::new(1, 0).is_err());
+
+        let target = Target::new(20, 30, -10, -5);
+        let l = Launcher::new(target).with_physics(Physics::default());
+        assert_eq!(l.launch(&target), (45, 112));
+
+        for physics in [
+            Physics::new(2, 3).unwrap(),
+            Physics::new(0, 1).unwrap(),
+            Physics::new(3, 2).unwrap(),
+        ] {
+            for target in [
+                Target::new(20, 30, -10, -5),
+                Target::new(-30, -20, 5, 10),
+                Target::new(-5, 5, -10, -5),
+            ] {
+                let l = Launcher::new(target).with_physics(physics);
+                let valid: Vec<_> = l.valid_velocities(&target).collect();
+                assert_eq!(valid, brute_force(&target, physics), "{:?}", physics);
+
+                let highest = valid
+                    .iter()
+                    .map(|(vx, vy)| Probe::new(*vx, *vy).with_physics(physics).max_height())
+                    .max()
+                    .unwrap_or_default();
+                assert_eq!(l.launch(&target), (highest, valid.len()));
+                assert_eq!(l.count_analytic(&target), valid.len());
+            }
+
+            // the step simulation agrees with the closed form positions and
+            // apex
+            let target = Target::new(-1000, 1000, -200, -100);
+            for vx in -20..=20 {
+                for vy in -10..=20 {
+                    let probe = Probe::new(vx, vy).with_physics(physics);
+                    let points: Vec<_> = probe.trajectory(&target).collect();
+                    for (t, p) in points.iter().enumerate() {
+                        assert_eq!(*p, probe.point_at(t as i64), "{:?}", probe);
+                    }
+
+                    let apex = points.iter().map(|p| p.1).max().unwrap();
+                    assert_eq!(apex, probe.max_height(), "{:?}", probe);
+                    if physics.drag() > 0 {
+                        assert_eq!(probe.xt(1000), probe.max_x(), "{:?}", probe);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn trajectory() {
+        let target = Target::new(20, 30, -10, -5);
+        let points: Vec<_> = Probe::new(7, 2).trajectory(&target).collect();
+        assert_eq!(
+            points,
+            vec![
+                (0, 0),
+                (7, 2),
+                (13, 3),
+                (18, 3),
+                (22, 2),
+                (25, 0),
+                (27, -3),
+                (28, -7),
+            ]
+        );
+
+        // a probe that overshoots stops as soon as it is past the target
+        let points: Vec<_> = Probe::new(17, -4).trajectory(&target).collect();
+        assert_eq!(points, vec![(0, 0), (17, -4)]);
+
+        // the step simulation agrees with the closed form positions
+        let target = Target::new(-100, 100, -50, -40);
+        for vx in -30..=30 {
+            for vy in -10..=10 {
+                let probe = Probe::new(vx, vy);
+                for (t, p) in probe.trajectory(&target).enumerate() {
+                    assert_eq!(p, probe.point_at(t as i64), "{} {} {}", vx, vy, t);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn valid_velocities() {
+        let target = Target::new(20, 30, -10, -5);
+        let l = Launcher::new(target);
+        let valid: Vec<_> = l.valid_velocities(&target).collect();
+        assert_eq!(valid.len(), 112);
+        assert!(valid.contains(&(6, 9)));
+        assert!(valid.contains(&(30, -10)));
+        assert!(!valid.contains(&(17, -4)));
+
+        // every velocity found really does hit the target, and every other
+        // velocity in a box around the search space misses it
+        let hits = |vx: i64, vy: i64| {
+            Probe::new(vx, vy)
+                .trajectory(&target)
+                .any(|p| target.contains(p))
+        };
+        for vx in -5..=40 {
+            for vy in -20..=20 {
+                assert_eq!(hits(vx, vy), valid.contains(&(vx, vy)), "{} {}", vx, vy);
+            }
+        }
+    }
 }
@@ -0,0 +1,1555 @@
+use anyhow::{anyhow, bail, Result};
+use aoc_helpers::Solver;
+use itertools::Itertools;
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{self, space1},
+    combinator::map,
+    multi::separated_list1,
+    sequence::{preceded, separated_pair, terminated, tuple},
+    IResult,
+};
+use rayon::prelude::*;
+use rustc_hash::FxHashSet;
+use serde::Serialize;
+use std::{convert::TryFrom, io::Write, iter::FromIterator, str::FromStr};
+
+pub use self::octree::Octree;
+pub use self::shape::{Cylinder, Shape, Solid, Sphere};
+
+mod octree;
+mod shape;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, Serialize)]
+pub struct Point {
+    x: i64,
+    y: i64,
+    z: i64,
+}
+
+impl From<(i64, i64, i64)> for Point {
+    fn from(v: (i64, i64, i64)) -> Self {
+        Self {
+            x: v.0,
+            y: v.1,
+            z: v.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Line {
+    start: i64,
+    end: i64,
+}
+
+impl Line {
+    pub fn new(start: i64, end: i64) -> Self {
+        Self { start, end }
+    }
+
+    pub fn length(&self) -> i64 {
+        (self.end - self.start).abs() + 1
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.start >= other.start && self.start <= other.end
+            || other.start >= self.start && other.start <= self.end
+            || self.start >= other.start && self.end <= other.end
+            || other.start >= self.start && other.end <= self.end
+    }
+
+    pub fn merge(&mut self, other: &Self) -> bool {
+        if !self.intersects(other) {
+            return false;
+        }
+
+        self.start = self.start.min(other.start);
+        self.end = self.end.max(other.end);
+
+        true
+    }
+
+    pub fn overlap(&self, other: &Self) -> Self {
+        Self::new(self.start.max(other.start), self.end.min(other.end))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Rectangle {
+    min_x: i64,
+    max_x: i64,
+    min_y: i64,
+    max_y: i64,
+}
+
+impl Rectangle {
+    pub fn new(min_x: i64, max_x: i64, min_y: i64, max_y: i64) -> Self {
+        Self {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+        }
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.x_instersects(other) && self.y_instersects(other)
+    }
+
+    fn x_instersects(&self, other: &Self) -> bool {
+        other.min_x >= self.min_x && other.min_x <= self.max_x
+            || self.min_x >= other.min_x && self.min_x <= other.max_x
+            // they contain us
+            || self.min_x >= other.min_x && self.max_x <= other.max_x
+            // we contain them
+            || other.min_x >= self.min_x && other.max_x <= self.max_x
+    }
+
+    fn y_instersects(&self, other: &Self) -> bool {
+        other.min_y >= self.min_y && other.min_y <= self.max_y
+            || self.min_y >= other.min_y && self.min_y <= other.max_y
+            // they contain us
+            || self.min_y >= other.min_y && self.max_y <= other.max_y
+            // we contain them
+            || other.min_y >= self.min_y && other.max_y <= self.max_y
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::new(
+            self.min_x.max(other.min_x),
+            self.max_x.min(other.max_x),
+            self.min_y.max(other.min_y),
+            self.max_y.min(other.max_y),
+        )
+    }
+
+    pub fn width(&self) -> i64 {
+        (self.max_x - self.min_x).abs() + 1
+    }
+
+    pub fn height(&self) -> i64 {
+        (self.max_y - self.min_y).abs() + 1
+    }
+
+    pub fn area(&self) -> i64 {
+        self.width() * self.height()
+    }
+
+    fn intersects_x(&self, x: i64) -> bool {
+        x >= self.min_x && x <= self.max_x
+    }
+
+    fn y_line(&self) -> Line {
+        Line::new(self.min_y, self.max_y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, Serialize)]
+pub struct Cuboid {
+    begin: Point,
+    end: Point,
+}
+
+impl Cuboid {
+    pub fn new(begin: Point, end: Point) -> Self {
+        Self { begin, end }
+    }
+
+    pub fn intersects_plane(&self, z: i64) -> bool {
+        z >= self.begin.z && z <= self.end.z
+    }
+
+    pub fn rect_for_intersect(&self) -> Rectangle {
+        Rectangle::new(self.begin.x, self.end.x, self.begin.y, self.end.y)
+    }
+
+    pub fn width(&self) -> i64 {
+        (self.end.x - self.begin.x).abs() + 1
+    }
+
+    pub fn height(&self) -> i64 {
+        (self.begin.y - self.end.y).abs() + 1
+    }
+
+    pub fn depth(&self) -> i64 {
+        (self.begin.z - self.end.z).abs() + 1
+    }
+
+    pub fn volume(&self) -> i64 {
+        self.width() * self.height() * self.depth()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let int_b_x = self.begin.x.max(other.begin.x);
+        let int_e_x = self.end.x.min(other.end.x);
+        if int_b_x > int_e_x {
+            return None;
+        }
+
+        let int_b_y = self.begin.y.max(other.begin.y);
+        let int_e_y = self.end.y.min(other.end.y);
+        if int_b_y > int_e_y {
+            return None;
+        }
+
+        let int_b_z = self.begin.z.max(other.begin.z);
+        let int_e_z = self.end.z.min(other.end.z);
+
+        if int_b_z > int_e_z {
+            return None;
+        }
+
+        Some(Self::new(
+            (int_b_x, int_b_y, int_b_z).into(),
+            (int_e_x, int_e_y, int_e_z).into(),
+        ))
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        point.x >= self.begin.x
+            && point.x <= self.end.x
+            && point.y >= self.begin.y
+            && point.y <= self.end.y
+            && point.z >= self.begin.z
+            && point.z <= self.end.z
+    }
+
+    pub fn fully_contains(&self, other: &Self) -> bool {
+        other.begin.x >= self.begin.x
+            && other.end.x <= self.end.x
+            && other.begin.y >= self.begin.y
+            && other.end.y <= self.end.y
+            && other.begin.z >= self.begin.z
+            && other.end.z <= self.end.z
+    }
+
+    /// The disjoint cuboids covering everything in this one that isn't in
+    /// `other`: at most two slabs either side of the overlap in x, then two in
+    /// y and two in z within the overlap's x range.
+    pub fn subtract(&self, other: &Self) -> Vec<Self> {
+        let overlap = match self.intersection(other) {
+            Some(overlap) => overlap,
+            None => return vec![*self],
+        };
+
+        let mut pieces = Vec::with_capacity(6);
+        let mut rest = *self;
+
+        if rest.begin.x < overlap.begin.x {
+            let mut piece = rest;
+            piece.end.x = overlap.begin.x - 1;
+            pieces.push(piece);
+            rest.begin.x = overlap.begin.x;
+        }
+
+        if rest.end.x > overlap.end.x {
+            let mut piece = rest;
+            piece.begin.x = overlap.end.x + 1;
+            pieces.push(piece);
+            rest.end.x = overlap.end.x;
+        }
+
+        if rest.begin.y < overlap.begin.y {
+            let mut piece = rest;
+            piece.end.y = overlap.begin.y - 1;
+            pieces.push(piece);
+            rest.begin.y = overlap.begin.y;
+        }
+
+        if rest.end.y > overlap.end.y {
+            let mut piece = rest;
+            piece.begin.y = overlap.end.y + 1;
+            pieces.push(piece);
+            rest.end.y = overlap.end.y;
+        }
+
+        if rest.begin.z < overlap.begin.z {
+            let mut piece = rest;
+            piece.end.z = overlap.begin.z - 1;
+            pieces.push(piece);
+        }
+
+        if rest.end.z > overlap.end.z {
+            let mut piece = rest;
+            piece.begin.z = overlap.end.z + 1;
+            pieces.push(piece);
+        }
+
+        pieces
+    }
+}
+
+/// The cubes of a cuboid, or only those of them also in every one of a list
+/// of shapes. Regions parsed from instructions hold at most one shape, but
+/// their intersections can pile up more.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Region {
+    /// we can track when this region was created
+    index: usize,
+    cuboid: Cuboid,
+    on: bool,
+    shapes: Vec<Solid>,
+}
+
+impl Region {
+    pub fn new(index: usize, cuboid: Cuboid, on: bool) -> Self {
+        Self {
+            index,
+            cuboid,
+            on,
+            shapes: Vec::new(),
+        }
+    }
+
+    /// A region holding every cube of `shape`.
+    pub fn with_shape<S: Into<Solid>>(index: usize, shape: S, on: bool) -> Self {
+        let shape = shape.into();
+        Self {
+            index,
+            cuboid: shape.bounding_box(),
+            on,
+            shapes: vec![shape],
+        }
+    }
+
+    pub fn shapes(&self) -> &[Solid] {
+        &self.shapes
+    }
+
+    pub fn volume(&self) -> i64 {
+        let volume = self.intersection_volume(&self.cuboid);
+        if self.on {
+            volume
+        } else {
+            -volume
+        }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let cuboid = self.cuboid.intersection(&other.cuboid)?;
+        let shapes = [self.shapes.as_slice(), other.shapes.as_slice()].concat();
+        if !shapes.iter().all(|s| s.intersects(&cuboid)) {
+            return None;
+        }
+
+        Some(Self {
+            index: self.index,
+            cuboid,
+            on: !self.on,
+            shapes,
+        })
+    }
+
+    /// The region clipped to `limit`, unless none of it is left.
+    pub fn clip(&self, limit: &Cuboid) -> Option<Self> {
+        let cuboid = self.cuboid.intersection(limit)?;
+        if !self.shapes.iter().all(|s| s.intersects(&cuboid)) {
+            return None;
+        }
+
+        Some(Self {
+            cuboid,
+            ..self.clone()
+        })
+    }
+
+    /// The region as disjoint cuboids, for the backends that can only split
+    /// cuboids: each column through its shapes, merged along y with the next
+    /// one wherever they span the same z.
+    pub fn cuboids(&self) -> Vec<Cuboid> {
+        if self.shapes.is_empty() {
+            return vec![self.cuboid];
+        }
+
+        let mut cuboids = Vec::new();
+        for x in self.cuboid.begin.x..=self.cuboid.end.x {
+            let mut current: Option<Cuboid> = None;
+            for y in self.cuboid.begin.y..=self.cuboid.end.y {
+                let run = self.column(x, y);
+                match current.as_mut() {
+                    Some(c) if run == Some((c.begin.z, c.end.z)) => c.end.y = y,
+                    _ => {
+                        cuboids.extend(current.take());
+                        current = run.map(|(start, end)| {
+                            Cuboid::new((x, y, start).into(), (x, y, end).into())
+                        });
+                    }
+                }
+            }
+            cuboids.extend(current);
+        }
+
+        cuboids
+    }
+
+    pub fn intersects_plane(&self, z: i64) -> bool {
+        self.cuboid.intersects_plane(z)
+    }
+}
+
+impl FromStr for Region {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok((_, (on, solid))) = tuple((state_parser, solid_parser))(s) {
+            // a negative radius or height leaves the bounds inside out
+            let bounds = solid.bounding_box();
+            if bounds.begin.x > bounds.end.x || bounds.begin.z > bounds.end.z {
+                bail!("shape is empty: {}", s);
+            }
+
+            return Ok(Region::with_shape(0, solid, on));
+        }
+
+        let (_, (on, ranges)) = region_parser(s).map_err(|_| anyhow!("could not parse input"))?;
+
+        if ranges.len() != 3 {
+            bail!("invalid number of ranges: {}", s);
+        }
+
+        let begin = Point::from((ranges[0].0, ranges[1].0, ranges[2].0));
+        let end = Point::from((ranges[0].1, ranges[1].1, ranges[2].1));
+        let cuboid = Cuboid { begin, end };
+
+        Ok(Region {
+            cuboid,
+            on,
+            ..Region::default()
+        })
+    }
+}
+
+fn range_parser(input: &str) -> IResult<&str, (i64, i64)> {
+    preceded(
+        tuple((alt((tag("x"), tag("y"), tag("z"))), tag("="))),
+        separated_pair(complete::i64, tag(".."), complete::i64),
+    )(input)
+}
+
+fn state_parser(input: &str) -> IResult<&str, bool> {
+    let (input, state) = terminated(alt((tag("on"), tag("off"))), space1)(input)?;
+
+    let on = match state {
+        "on" => true,
+        "off" => false,
+        _ => unreachable!("this should not be possible"),
+    };
+
+    Ok((input, on))
+}
+
+fn region_parser(input: &str) -> IResult<&str, (bool, Vec<(i64, i64)>)> {
+    tuple((state_parser, separated_list1(tag(","), range_parser)))(input)
+}
+
+/// A single `name=value` setting of a shape
+fn setting_parser<'a>(name: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, i64> {
+    preceded(tuple((tag(name), tag("="))), complete::i64)
+}
+
+fn point_parser(input: &str) -> IResult<&str, Point> {
+    let (input, (x, _, y, _, z)) = tuple((
+        setting_parser("x"),
+        tag(","),
+        setting_parser("y"),
+        tag(","),
+        setting_parser("z"),
+    ))(input)?;
+
+    Ok((input, (x, y, z).into()))
+}
+
+/// `sphere x=0,y=0,z=0,r=5` or `cylinder x=0,y=0,z=0,r=5,h=10`, where the
+/// point is the center of the sphere or the middle of the cylinder's base
+fn solid_parser(input: &str) -> IResult<&str, Solid> {
+    alt((
+        map(
+            preceded(
+                terminated(tag("sphere"), space1),
+                separated_pair(point_parser, tag(","), setting_parser("r")),
+            ),
+            |(center, radius)| Sphere::new(center, radius).into(),
+        ),
+        map(
+            preceded(
+                terminated(tag("cylinder"), space1),
+                tuple((
+                    point_parser,
+                    tag(","),
+                    setting_parser("r"),
+                    tag(","),
+                    setting_parser("h"),
+                )),
+            ),
+            |(base, _, radius, _, height)| Cylinder::new(base, radius, height).into(),
+        ),
+    ))(input)
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Instructions {
+    regions: Vec<Region>,
+}
+
+impl TryFrom<Vec<String>> for Instructions {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<String>) -> Result<Self> {
+        let regions = value
+            .iter()
+            .enumerate()
+            .map(|(idx, s)| {
+                Region::from_str(s).map(|mut r| {
+                    r.index = idx;
+                    r
+                })
+            })
+            .collect::<Result<Vec<Region>>>()?;
+
+        Ok(Self { regions })
+    }
+}
+
+/// The file formats [`Reactor::export`] can write.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    /// One `begin_x,begin_y,begin_z,end_x,end_y,end_z` row per cuboid, with
+    /// both ends inclusive.
+    Csv,
+    /// An array of `{"begin": {"x", "y", "z"}, "end": {"x", "y", "z"}}`
+    /// objects, with both ends inclusive.
+    Json,
+}
+
+/// The ways `volume_by` can count the cubes that are on
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Strategy {
+    /// Keep every region along with a signed copy of its intersection with
+    /// everything before it, and add up the signed volumes
+    SignedIntersections,
+    /// Keep a set of disjoint cuboids that are on, splitting them against
+    /// every new region, and add up their volumes
+    Disjoint,
+    /// Switch every region in an [`Octree`] over their bounding cuboid
+    Octree,
+    /// Cut space into cells at every region boundary and switch whole cells.
+    /// There are up to `(2n)^3` cells for `n` regions, so this is only worth
+    /// it for a few regions, however large their coordinates are
+    Compressed,
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Self::SignedIntersections
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Reactor {
+    regions: Vec<Region>,
+    /// Disjoint cuboids that are on, as left by `apply`
+    lit: Vec<Cuboid>,
+    /// The backend `is_on` and `on_volume_within` are answered with
+    strategy: Strategy,
+    /// Built by `reboot` when the strategy is [`Strategy::Octree`]
+    octree: Option<Octree>,
+}
+
+impl Reactor {
+    /// The fewest signed regions `volume` hands to a thread at once, so short
+    /// lists aren't split up for nothing.
+    const MIN_CHUNK: usize = 1024;
+
+    /// A reactor that answers queries with `strategy`.
+    pub fn new(strategy: Strategy) -> Self {
+        Self {
+            strategy,
+            ..Self::default()
+        }
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Start over with `instructions`, building whatever the strategy needs to
+    /// answer queries up front.
+    pub fn reboot(&mut self, instructions: &Instructions) {
+        self.regions.clear();
+        self.regions.extend(
+            instructions
+                .regions
+                .iter()
+                .enumerate()
+                .map(|(index, r)| Region { index, ..r.clone() }),
+        );
+        self.lit.clear();
+        self.octree = None;
+
+        match self.strategy {
+            Strategy::Disjoint => {
+                for region in instructions.regions.iter() {
+                    self.switch(region);
+                }
+            }
+            Strategy::Octree => self.octree = Some(self.regions.iter().collect()),
+            Strategy::SignedIntersections | Strategy::Compressed => {}
+        }
+    }
+
+    /// Switch every region of `instructions`, in order, on top of whatever
+    /// was already on, keeping the cubes that are on as disjoint cuboids.
+    pub fn apply(&mut self, instructions: &Instructions) {
+        for region in instructions.regions.iter() {
+            self.step(region);
+        }
+    }
+
+    /// Like `apply`, but one region at a time, yielding the number of cubes
+    /// that are on after each. Regions after the last one taken from the
+    /// iterator are never switched, so it can be stopped early.
+    pub fn apply_iter<'a>(
+        &'a mut self,
+        instructions: &'a Instructions,
+    ) -> impl Iterator<Item = i64> + 'a {
+        let mut volume = self.lit_volume();
+        instructions.regions.iter().map(move |region| {
+            volume += self.step(region);
+            volume
+        })
+    }
+
+    /// Switch one more region, returning how much that changed the number of
+    /// cubes that are on.
+    fn step(&mut self, region: &Region) -> i64 {
+        // regions are looked up by index, so they carry on from the last one
+        let region = Region {
+            index: self.regions.len(),
+            ..region.clone()
+        };
+        self.regions.push(region.clone());
+
+        if let Some(octree) = self.octree.as_mut() {
+            if octree.bounds().fully_contains(&region.cuboid) {
+                octree.switch(&region);
+            } else {
+                // the region reaches past the tree, so it has to be rebuilt
+                self.octree = Some(self.regions.iter().collect());
+            }
+        }
+
+        self.switch(&region)
+    }
+
+    /// Whether the cube at `point` is on.
+    pub fn is_on(&self, point: &Point) -> bool {
+        match (self.strategy, &self.octree) {
+            (Strategy::Octree, Some(octree)) => octree.is_on(point),
+            (Strategy::Disjoint, _) => self.lit.iter().any(|c| c.contains(point)),
+            // the last region to touch the point decides
+            _ => match self
+                .regions
+                .iter()
+                .rev()
+                .find(|r| Shape::contains(*r, point))
+            {
+                Some(region) => region.on,
+                None => false,
+            },
+        }
+    }
+
+    /// The number of cubes within `cuboid` that are on. Regions that reach
+    /// outside of it are clipped rather than left out.
+    pub fn on_volume_within(&self, cuboid: &Cuboid) -> i64 {
+        match (self.strategy, &self.octree) {
+            (Strategy::Octree, Some(octree)) => octree.volume_within(cuboid),
+            (Strategy::Disjoint, _) => self
+                .lit
+                .iter()
+                .filter_map(|c| c.intersection(cuboid))
+                .map(|c| c.volume())
+                .sum(),
+            (strategy, _) => self.volume_by(strategy, &Some(*cuboid)),
+        }
+    }
+
+    /// The number of cubes left on by `apply`.
+    pub fn lit_volume(&self) -> i64 {
+        self.lit.iter().map(|c| c.volume()).sum()
+    }
+
+    /// The disjoint cuboids that are on, as left by `apply`, or by `reboot`
+    /// with [`Strategy::Disjoint`].
+    pub fn on_cuboids(&self) -> &[Cuboid] {
+        &self.lit
+    }
+
+    /// Write [`Reactor::on_cuboids`] to `writer`, so the core can be drawn or
+    /// compared somewhere else.
+    pub fn export<W: Write>(&self, writer: &mut W, format: Format) -> Result<()> {
+        match format {
+            Format::Csv => {
+                writeln!(writer, "begin_x,begin_y,begin_z,end_x,end_y,end_z")?;
+                for c in self.lit.iter() {
+                    writeln!(
+                        writer,
+                        "{},{},{},{},{},{}",
+                        c.begin.x, c.begin.y, c.begin.z, c.end.x, c.end.y, c.end.z
+                    )?;
+                }
+            }
+            Format::Json => {
+                serde_json::to_writer(&mut *writer, &self.lit)?;
+                writeln!(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn volume_by(&self, strategy: Strategy, limit: &Option<Cuboid>) -> i64 {
+        match strategy {
+            Strategy::SignedIntersections => self.volume(limit),
+            Strategy::Disjoint => {
+                let mut reactor = Self::default();
+                for region in self.regions_within(limit).iter() {
+                    reactor.switch(region);
+                }
+
+                reactor.lit_volume()
+            }
+            Strategy::Octree => self
+                .regions_within(limit)
+                .iter()
+                .collect::<Octree>()
+                .volume(),
+            Strategy::Compressed => {
+                Self::compressed_volume(&Self::cuboid_regions(self.regions_within(limit)))
+            }
+        }
+    }
+
+    /// The regions clipped to `limit`, leaving out any that miss it entirely.
+    fn regions_within(&self, limit: &Option<Cuboid>) -> Vec<Region> {
+        match limit {
+            Some(limit) => self.regions.iter().filter_map(|r| r.clip(limit)).collect(),
+            None => self.regions.clone(),
+        }
+    }
+
+    /// `regions` with their shapes broken up into cuboids, for the backends
+    /// that can only handle cuboids.
+    fn cuboid_regions(regions: Vec<Region>) -> Vec<Region> {
+        if regions.iter().all(|r| r.shapes.is_empty()) {
+            return regions;
+        }
+
+        regions
+            .iter()
+            .flat_map(|r| {
+                r.cuboids()
+                    .into_iter()
+                    .map(move |c| Region::new(r.index, c, r.on))
+            })
+            .collect()
+    }
+
+    fn compressed_volume(regions: &[Region]) -> i64 {
+        // every coordinate a cell starts on along one axis, in order. A region
+        // covers the cells from its begin up to just before its end + 1
+        let boundaries = |axis: fn(&Point) -> i64| {
+            let mut bounds = regions
+                .iter()
+                .flat_map(|r| [axis(&r.cuboid.begin), axis(&r.cuboid.end) + 1])
+                .collect::<Vec<_>>();
+            bounds.sort_unstable();
+            bounds.dedup();
+            bounds
+        };
+        let xs = boundaries(|p| p.x);
+        let ys = boundaries(|p| p.y);
+        let zs = boundaries(|p| p.z);
+
+        let cells = |bounds: &[i64], begin: i64, end: i64| {
+            let idx = |v| {
+                bounds
+                    .binary_search(&v)
+                    .expect("every region boundary is a cell boundary")
+            };
+            idx(begin)..idx(end + 1)
+        };
+
+        let mut on = vec![false; xs.len() * ys.len() * zs.len()];
+        for region in regions.iter() {
+            let c = &region.cuboid;
+            for x in cells(&xs, c.begin.x, c.end.x) {
+                for y in cells(&ys, c.begin.y, c.end.y) {
+                    for z in cells(&zs, c.begin.z, c.end.z) {
+                        on[(x * ys.len() + y) * zs.len() + z] = region.on;
+                    }
+                }
+            }
+        }
+
+        // the last boundary on each axis only ever ends a cell
+        let mut volume = 0;
+        for (x, x_cell) in xs.windows(2).enumerate() {
+            for (y, y_cell) in ys.windows(2).enumerate() {
+                for (z, z_cell) in zs.windows(2).enumerate() {
+                    if on[(x * ys.len() + y) * zs.len() + z] {
+                        volume += (x_cell[1] - x_cell[0])
+                            * (y_cell[1] - y_cell[0])
+                            * (z_cell[1] - z_cell[0]);
+                    }
+                }
+            }
+        }
+
+        volume
+    }
+
+    /// Switch `region` in the disjoint cuboids, returning how much that
+    /// changed the number of cubes that are on.
+    fn switch(&mut self, region: &Region) -> i64 {
+        if !region.shapes.is_empty() {
+            return region
+                .cuboids()
+                .into_iter()
+                .map(|c| self.switch(&Region::new(region.index, c, region.on)))
+                .sum();
+        }
+
+        let mut change = 0;
+        let mut lit = Vec::with_capacity(self.lit.len() + 1);
+        for cuboid in self.lit.iter() {
+            if let Some(overlap) = cuboid.intersection(&region.cuboid) {
+                change -= overlap.volume();
+                lit.extend(cuboid.subtract(&region.cuboid));
+            } else {
+                lit.push(*cuboid);
+            }
+        }
+
+        if region.on {
+            change += region.cuboid.volume();
+            lit.push(region.cuboid);
+        }
+
+        self.lit = lit;
+        change
+    }
+
+    /// The number of cubes that are on, only counting those within `limit`
+    /// if there is one.
+    pub fn volume(&self, limit: &Option<Cuboid>) -> i64 {
+        let regions = self.regions_within(limit);
+
+        let mut final_regions: Vec<Region> = Vec::with_capacity(regions.len() * 200);
+        let mut intersections = Vec::new();
+
+        for region in regions.iter() {
+            // every region so far cancels out its overlap with the new one.
+            // That's the bulk of the work once there are a lot of them, so
+            // the list is checked in chunks across threads
+            intersections.par_extend(
+                final_regions
+                    .par_iter()
+                    .with_min_len(Self::MIN_CHUNK)
+                    .filter_map(|f| f.intersection(region)),
+            );
+            final_regions.append(&mut intersections);
+
+            if region.on {
+                final_regions.push(region.clone());
+            }
+        }
+
+        final_regions
+            .par_iter()
+            .with_min_len(Self::MIN_CHUNK)
+            .map(|r| r.volume())
+            .sum()
+    }
+
+    /// Sigh. This was a trap. It felt a lot like the 2018 problem with the
+    /// fabric. But it's a different question being asked
+    pub fn compute_volume_of_on_cubes(&self, limit: &Option<Cuboid>) -> i64 {
+        // sort by Z values
+        let mut regions = Self::cuboid_regions(self.regions_within(limit));
+
+        regions.sort_by(|a, b| a.cuboid.begin.z.cmp(&b.cuboid.begin.z));
+        // sweep an x, y plane across the z values
+        let min_z = regions
+            .first()
+            .map(|r| r.cuboid.begin.z)
+            .unwrap_or_default();
+        let max_z = regions
+            .iter()
+            .map(|r| r.cuboid.end.z)
+            .max()
+            .unwrap_or_default();
+
+        (min_z..=max_z).fold(0_i64, |vol, z| {
+            let mut local_intersections = Vec::with_capacity(regions.len());
+            for region in regions.iter() {
+                // this is safe because the list will be sorted
+                if region.cuboid.begin.z > z {
+                    break;
+                }
+
+                if region.intersects_plane(z) {
+                    // we need to additionally track which region made this
+                    // particular intersection
+                    local_intersections.push((region.index, region.cuboid.rect_for_intersect()));
+                }
+            }
+
+            // don't bother with other calculations
+            if local_intersections.is_empty() {
+                return vol;
+            }
+
+            // now that we have all the overlapping regions, we need to figure
+            // out which regions correspond to lit areas by finding the actual
+            // region overlaps
+
+            // sort this so that they're in order from min_x to max_x
+            local_intersections.sort_by(|a, b| a.1.min_x.cmp(&b.1.min_x));
+            let local_area = self.reduce(&local_intersections);
+            // area is the area of all rectangles that intersected the plane
+            // minus the regions of overlaps
+
+            vol + local_area
+        })
+    }
+
+    fn reduce(&self, rects: &[(usize, Rectangle)]) -> i64 {
+        let mut sum = 0;
+
+        let mut unintersected = FxHashSet::from_iter(rects.iter());
+        let mut intersected = FxHashSet::default();
+
+        for (r1, r2) in rects.iter().tuple_combinations() {
+            if r1.1.intersects(&r2.1) {
+                unintersected.remove(r1);
+                unintersected.remove(r2);
+                intersected.insert(r1);
+                intersected.insert(r2);
+            }
+        }
+
+        sum += unintersected.iter().fold(0, |acc, (i, r)| {
+            if self.regions[*i].on {
+                acc + r.area()
+            } else {
+                acc
+            }
+        });
+
+        if intersected.is_empty() {
+            return sum;
+        }
+
+        let mut remaining = intersected
+            .into_iter()
+            .sorted_by(|a, b| a.1.min_x.cmp(&b.1.min_x))
+            .collect::<Vec<_>>();
+
+        // there was only a single intersection, special case
+        if remaining.len() == 2 {
+            remaining.sort_by(|a, b| b.0.cmp(&a.0));
+
+            let top = remaining[0];
+            let top_reg = &self.regions[top.0];
+
+            let bot = remaining[1];
+            let bot_reg = &self.regions[bot.0];
+
+            // if both are off
+            if !top_reg.on && !bot_reg.on {
+                return sum;
+            }
+
+            let inter_area = top.1.intersection(&bot.1).area();
+
+            // if both are on
+            if top_reg.on && bot_reg.on {
+                return sum + top.1.area() + bot.1.area() - inter_area;
+            }
+
+            // if the top is off, take the chunk out
+            if !top_reg.on && bot_reg.on {
+                return sum + bot.1.area() - inter_area;
+            }
+
+            return sum + top.1.area();
+        }
+
+        // for the remaining rectangles, we need to perform the same procedure
+        // as the cubes, but with one less dimension
+        let min_x = remaining.first().map(|(_, r)| r.min_x).unwrap_or_default();
+        let max_x = remaining
+            .iter()
+            .map(|(_, r)| r.max_x)
+            .max()
+            .unwrap_or_default();
+
+        sum += (min_x..=max_x)
+            .into_par_iter()
+            .map(|x| {
+                let mut local_lines = Vec::with_capacity(remaining.len());
+                for (idx, rect) in remaining.iter() {
+                    if rect.min_x > x {
+                        break;
+                    }
+
+                    if rect.intersects_x(x) {
+                        local_lines.push((*idx, rect.y_line()));
+                    }
+                }
+
+                if local_lines.is_empty() {
+                    return 0;
+                }
+
+                // let's be stupid
+
+                local_lines.sort_by(|a, b| a.0.cmp(&b.0));
+
+                let min_y = local_lines
+                    .iter()
+                    .map(|(_, line)| line.start)
+                    .min()
+                    .unwrap_or_default();
+                let max_y = local_lines
+                    .iter()
+                    .map(|(_, line)| line.end)
+                    .max()
+                    .unwrap_or_default();
+
+                let mut tracking = vec![false; (max_y - min_y) as usize + 1];
+
+                for (idx, line) in local_lines.iter() {
+                    let region = &self.regions[*idx];
+                    for y in line.start..=line.end {
+                        let v = region.on;
+                        tracking[(y - min_y) as usize] = v;
+                    }
+                }
+
+                tracking.iter().filter(|v| **v).count() as i64
+
+                // let overlaps = self.reduce_lines(&local_lines);
+
+                // let local_area = local_lines
+                //     .iter()
+                //     .fold(0, |acc, (idx, line)| {
+                //         if self.regions[*idx].on {
+                //             acc + line.length()
+                //         } else {
+                //             acc
+                //         }
+                //     });
+
+                // let overlap_area = overlaps.iter().fold(0, |acc, (_, line)| acc + line.length());
+
+                // tot + local_area - overlap_area
+            })
+            .sum::<i64>();
+
+        sum
+    }
+
+    // pub fn reduce_lines(&self, lines: &Vec<(usize, Line)>) -> Vec<(usize, Line)> {
+    //     let mut overlaps: Vec<(usize, Line)> = Vec::with_capacity(lines.len());
+
+    //     for (a, b) in lines.iter().tuple_combinations() {
+    //         if a.1.intersects(&b.1) {
+    //             overlaps.push((a.0.max(b.0), a.1.overlap(&b.1)));
+    //         }
+    //     }
+
+    //     if overlaps.len() < 2 {
+    //         return overlaps;
+    //     }
+
+    //     overlaps.sort_by(|a, b| {
+    //         b.1.start
+    //             .cmp(&a.1.start)
+    //             .then_with(|| b.1.length().cmp(&a.1.length()))
+    //     });
+
+    //     let mut unique: Vec<(usize, Line)> = Vec::with_capacity(overlaps.len());
+
+    //     while let Some(last) = overlaps.pop() {
+    //         if !unique.iter_mut().any(|(idx, line)| line.merge(&last.1)) {
+    //             unique.push(last);
+    //         }
+    //     }
+
+    //     unique
+    // }
+}
+
+#[derive(Debug, Clone)]
+pub struct Procedure {
+    instructions: Instructions,
+    reactor: Reactor,
+    limit: Cuboid,
+}
+
+impl TryFrom<Vec<String>> for Procedure {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<String>) -> Result<Self> {
+        let instructions = Instructions::try_from(value)?;
+        let reactor = Reactor::default();
+        let limit = Cuboid::new((-50, -50, -50).into(), (50, 50, 50).into());
+
+        Ok(Self {
+            instructions,
+            reactor,
+            limit,
+        })
+    }
+}
+
+impl Solver for Procedure {
+    const ID: &'static str = "reactor reboot";
+    const DAY: usize = 22;
+
+    type P1 = i64;
+    type P2 = i64;
+
+    fn part_one(&mut self) -> Self::P1 {
+        self.reactor.volume(&Some(self.limit))
+    }
+
+    fn part_two(&mut self) -> Self::P2 {
+        self.reactor.volume(&None)
+    }
+
+    // in this case, poor design decisions lead to the reboot happening
+    // separate from construction
+    fn instance() -> Self {
+        let mut inst = Self::try_from(Self::load_input()).expect("could not parse input");
+        inst.reactor.reboot(&inst.instructions);
+
+        inst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod cuboid {
+        use super::super::*;
+
+        #[test]
+        fn subtract() {
+            let a = Cuboid::new((0, 0, 0).into(), (4, 4, 4).into());
+
+            // a hole in the middle leaves a piece on every side
+            let hole = Cuboid::new((1, 1, 1).into(), (3, 3, 3).into());
+            let pieces = a.subtract(&hole);
+            assert_eq!(pieces.len(), 6);
+            assert_eq!(
+                pieces.iter().map(|c| c.volume()).sum::<i64>(),
+                a.volume() - hole.volume()
+            );
+            for (p1, p2) in pieces.iter().tuple_combinations() {
+                assert_eq!(p1.intersection(p2), None);
+            }
+
+            // a corner only leaves three
+            let corner = Cuboid::new((3, 3, 3).into(), (9, 9, 9).into());
+            let pieces = a.subtract(&corner);
+            assert_eq!(pieces.len(), 3);
+            assert_eq!(pieces.iter().map(|c| c.volume()).sum::<i64>(), 125 - 8);
+
+            let far = Cuboid::new((5, 0, 0).into(), (6, 4, 4).into());
+            assert_eq!(a.subtract(&far), vec![a]);
+            assert!(a.subtract(&a).is_empty());
+        }
+    }
+
+    mod region {
+        use super::super::*;
+
+        #[test]
+        fn from_str() {
+            Region::from_str("on x=-20..26,y=-36..17,z=-47..7").expect("could not parse region");
+
+            let sphere = Region::from_str("off sphere x=1,y=-2,z=3,r=5").expect("could not parse");
+            assert_eq!(
+                sphere,
+                Region::with_shape(0, Sphere::new((1, -2, 3).into(), 5), false)
+            );
+            assert_eq!(
+                sphere.cuboid,
+                Cuboid::new((-4, -7, -2).into(), (6, 3, 8).into())
+            );
+
+            let cylinder =
+                Region::from_str("on cylinder x=0,y=0,z=-3,r=2,h=4").expect("could not parse");
+            assert_eq!(
+                cylinder,
+                Region::with_shape(0, Cylinder::new((0, 0, -3).into(), 2, 4), true)
+            );
+            assert_eq!(cylinder.volume(), 13 * 4);
+
+            assert!(Region::from_str("on sphere x=0,y=0,z=0,r=-1").is_err());
+            assert!(Region::from_str("on cylinder x=0,y=0,z=0,r=1,h=0").is_err());
+            assert!(Region::from_str("on sphere x=0,y=0,r=1").is_err());
+        }
+
+        #[test]
+        fn cuboids() {
+            let region = Region::with_shape(0, Sphere::new((0, 0, 0).into(), 4), true)
+                .intersection(&Region::with_shape(
+                    0,
+                    Cylinder::new((2, 1, -9).into(), 3, 12),
+                    false,
+                ))
+                .expect("the shapes overlap");
+            let cuboids = region.cuboids();
+            assert_eq!(
+                cuboids.iter().map(|c| c.volume()).sum::<i64>(),
+                -region.volume()
+            );
+            for (a, b) in cuboids.iter().tuple_combinations() {
+                assert_eq!(a.intersection(b), None);
+            }
+
+            // the shapes' boxes overlap, but the shapes don't
+            let far = Region::with_shape(0, Sphere::new((9, 9, 9).into(), 4), true);
+            assert_eq!(
+                Region::with_shape(0, Sphere::new((0, 0, 0).into(), 4), true).intersection(&far),
+                None
+            );
+        }
+    }
+
+    mod reactor {
+        use aoc_helpers::util::test_input;
+
+        use super::super::*;
+
+        #[test]
+        fn solving() {
+            let input = test_input(
+                "
+                on x=-20..26,y=-36..17,z=-47..7
+                on x=-20..33,y=-21..23,z=-26..28
+                on x=-22..28,y=-29..23,z=-38..16
+                on x=-46..7,y=-6..46,z=-50..-1
+                on x=-49..1,y=-3..46,z=-24..28
+                on x=2..47,y=-22..22,z=-23..27
+                on x=-27..23,y=-28..26,z=-21..29
+                on x=-39..5,y=-6..47,z=-3..44
+                on x=-30..21,y=-8..43,z=-13..34
+                on x=-22..26,y=-27..20,z=-29..19
+                off x=-48..-32,y=26..41,z=-47..-37
+                on x=-12..35,y=6..50,z=-50..-2
+                off x=-48..-32,y=-32..-16,z=-15..-5
+                on x=-18..26,y=-33..15,z=-7..46
+                off x=-40..-22,y=-38..-28,z=23..41
+                on x=-16..35,y=-41..10,z=-47..6
+                off x=-32..-23,y=11..30,z=-14..3
+                on x=-49..-5,y=-3..45,z=-29..18
+                off x=18..30,y=-20..-8,z=-3..13
+                on x=-41..9,y=-7..43,z=-33..15
+                on x=-54112..-39298,y=-85059..-49293,z=-27449..7877
+                on x=967..23432,y=45373..81175,z=27513..53682
+                ",
+            );
+
+            let insts = Instructions::try_from(input).expect("could not parse input");
+
+            let limit = Cuboid {
+                begin: (-50, -50, -50).into(),
+                end: (50, 50, 50).into(),
+            };
+            let mut reactor = Reactor::default();
+            reactor.reboot(&insts);
+
+            assert_eq!(reactor.volume(&Some(limit)), 590784);
+            let volume = reactor.volume(&None);
+            for strategy in [Strategy::Disjoint, Strategy::Octree, Strategy::Compressed] {
+                assert_eq!(reactor.volume_by(strategy, &Some(limit)), 590784);
+                assert_eq!(reactor.volume_by(strategy, &None), volume);
+            }
+        }
+
+        #[test]
+        fn apply() {
+            let input = test_input(
+                "
+                on x=10..12,y=10..12,z=10..12
+                on x=11..13,y=11..13,z=11..13
+                off x=9..11,y=9..11,z=9..11
+                on x=10..10,y=10..10,z=10..10
+                ",
+            );
+            let insts = Instructions::try_from(input).expect("could not parse input");
+
+            let mut reactor = Reactor::default();
+            reactor.apply(&insts);
+            assert_eq!(reactor.lit_volume(), 39);
+
+            // applying the same steps again doesn't change anything
+            reactor.apply(&insts);
+            assert_eq!(reactor.lit_volume(), 39);
+
+            reactor.reboot(&insts);
+            assert_eq!(reactor.volume(&None), 39);
+            assert_eq!(reactor.volume_by(Strategy::Compressed, &None), 39);
+        }
+
+        #[test]
+        fn reboot_then_apply() {
+            let first = Instructions::try_from(test_input(
+                "
+                on x=10..12,y=10..12,z=10..12
+                on x=11..13,y=11..13,z=11..13
+                ",
+            ))
+            .expect("could not parse input");
+            let second = Instructions::try_from(test_input(
+                "
+                off x=9..11,y=9..11,z=9..11
+                on x=10..10,y=10..10,z=10..10
+                ",
+            ))
+            .expect("could not parse input");
+
+            let mut reactor = Reactor::default();
+            reactor.reboot(&first);
+            reactor.apply(&second);
+            assert_eq!(
+                reactor.regions.iter().map(|r| r.index).collect::<Vec<_>>(),
+                vec![0, 1, 2, 3]
+            );
+            assert_eq!(reactor.volume(&None), 39);
+            assert_eq!(reactor.compute_volume_of_on_cubes(&None), 39);
+        }
+
+        #[test]
+        fn apply_iter() {
+            let input = test_input(
+                "
+                on x=10..12,y=10..12,z=10..12
+                on x=11..13,y=11..13,z=11..13
+                off x=9..11,y=9..11,z=9..11
+                on x=10..10,y=10..10,z=10..10
+                ",
+            );
+            let insts = Instructions::try_from(input).expect("could not parse input");
+
+            let mut reactor = Reactor::default();
+            assert_eq!(
+                reactor.apply_iter(&insts).collect::<Vec<_>>(),
+                vec![27, 46, 38, 39]
+            );
+
+            // stopping early leaves the rest of the steps unapplied
+            let mut reactor = Reactor::new(Strategy::Octree);
+            reactor.reboot(&Instructions::default());
+            assert_eq!(reactor.apply_iter(&insts).position(|v| v > 40), Some(1));
+            assert_eq!(reactor.lit_volume(), 46);
+            assert_eq!(reactor.on_volume_within(&insts.regions[1].cuboid), 27);
+            assert!(reactor.is_on(&(11, 11, 11).into()));
+        }
+
+        #[test]
+        fn export() {
+            let input = test_input(
+                "
+                on x=10..12,y=10..12,z=10..12
+                off x=10..12,y=10..11,z=10..12
+                ",
+            );
+            let insts = Instructions::try_from(input).expect("could not parse input");
+            let mut reactor = Reactor::new(Strategy::Disjoint);
+            reactor.reboot(&insts);
+            assert_eq!(
+                reactor.on_cuboids(),
+                &[Cuboid::new((10, 12, 10).into(), (12, 12, 12).into())]
+            );
+
+            let mut out = Vec::new();
+            reactor
+                .export(&mut out, Format::Csv)
+                .expect("could not export");
+            assert_eq!(
+                String::from_utf8(out).expect("invalid utf8"),
+                "begin_x,begin_y,begin_z,end_x,end_y,end_z\n10,12,10,12,12,12\n"
+            );
+
+            let mut out = Vec::new();
+            reactor
+                .export(&mut out, Format::Json)
+                .expect("could not export");
+            assert_eq!(
+                String::from_utf8(out).expect("invalid utf8"),
+                concat!(
+                    r#"[{"begin":{"x":10,"y":12,"z":10},"#,
+                    r#""end":{"x":12,"y":12,"z":12}}]"#,
+                    "\n"
+                )
+            );
+
+            // nothing is on without the disjoint backend
+            let mut reactor = Reactor::default();
+            reactor.reboot(&insts);
+            assert!(reactor.on_cuboids().is_empty());
+        }
+
+        #[test]
+        fn straddling() {
+            let input = test_input(
+                "
+                on x=-60..-40,y=0..0,z=0..0
+                on x=0..0,y=45..55,z=-55..55
+                off x=-45..45,y=0..0,z=0..0
+                on x=60..70,y=60..70,z=60..70
+                ",
+            );
+            let insts = Instructions::try_from(input).expect("could not parse input");
+            let limit = Cuboid::new((-50, -50, -50).into(), (50, 50, 50).into());
+
+            // -50..-46 of the first, 45..50 by -50..50 of the second, nothing
+            // of the last
+            let expected = 5 + 6 * 101;
+            let mut reactor = Reactor::default();
+            reactor.reboot(&insts);
+            assert_eq!(reactor.volume(&Some(limit)), expected);
+            assert_eq!(reactor.compute_volume_of_on_cubes(&Some(limit)), expected);
+            for strategy in [Strategy::Disjoint, Strategy::Octree, Strategy::Compressed] {
+                assert_eq!(reactor.volume_by(strategy, &Some(limit)), expected);
+            }
+            assert_eq!(reactor.on_volume_within(&limit), expected);
+
+            assert_eq!(reactor.volume(&None), 21 - 6 + 11 * 111 + 11 * 11 * 11);
+        }
+
+        #[test]
+        fn shapes() {
+            let input = test_input(
+                "
+                on x=-10..10,y=-10..10,z=-10..10
+                off sphere x=0,y=0,z=0,r=8
+                on cylinder x=2,y=-3,z=-12,r=3,h=20
+                off x=-2..2,y=-20..20,z=-2..2
+                on sphere x=5,y=5,z=5,r=6
+                off cylinder x=-4,y=4,z=0,r=5,h=4
+                ",
+            );
+            let insts = Instructions::try_from(input).expect("could not parse input");
+            let limit = Cuboid::new((-6, -6, -6).into(), (6, 6, 6).into());
+
+            // the last region holding a cube decides, one cube at a time
+            let brute_force = |within: &Cuboid| {
+                let mut volume = 0;
+                for x in within.begin.x..=within.end.x {
+                    for y in within.begin.y..=within.end.y {
+                        for z in within.begin.z..=within.end.z {
+                            let p = (x, y, z).into();
+                            let last = insts.regions.iter().rev().find(|r| Shape::contains(*r, &p));
+                            volume += last.map(|r| r.on as i64).unwrap_or_default();
+                        }
+                    }
+                }
+                volume
+            };
+            let everything = Cuboid::new((-20, -20, -20).into(), (20, 20, 20).into());
+            let expected = brute_force(&everything);
+            let expected_within = brute_force(&limit);
+
+            let tree = insts.regions.iter().collect::<Octree>();
+            assert_eq!(tree.volume(), expected);
+            assert_eq!(tree.volume_within(&limit), expected_within);
+
+            let mut reactor = Reactor::default();
+            reactor.reboot(&insts);
+            assert_eq!(reactor.volume(&None), expected);
+            assert_eq!(reactor.volume(&Some(limit)), expected_within);
+            assert_eq!(reactor.compute_volume_of_on_cubes(&None), expected);
+            assert_eq!(
+                reactor.compute_volume_of_on_cubes(&Some(limit)),
+                expected_within
+            );
+            for strategy in [Strategy::Disjoint, Strategy::Octree, Strategy::Compressed] {
+                assert_eq!(reactor.volume_by(strategy, &None), expected);
+                assert_eq!(reactor.volume_by(strategy, &Some(limit)), expected_within);
+            }
+
+            for strategy in [
+                Strategy::SignedIntersections,
+                Strategy::Disjoint,
+                Strategy::Octree,
+                Strategy::Compressed,
+            ] {
+                let mut reactor = Reactor::new(strategy);
+                reactor.reboot(&insts);
+                assert_eq!(reactor.on_volume_within(&limit), expected_within);
+                for x in -12..=12 {
+                    for y in -12..=12 {
+                        let p = (x, y, x - y).into();
+                        assert_eq!(reactor.is_on(&p), tree.is_on(&p));
+                    }
+                }
+            }
+
+            let mut reactor = Reactor::default();
+            reactor.apply(&insts);
+            assert_eq!(reactor.lit_volume(), expected);
+        }
+
+        #[test]
+        fn queries() {
+            let input = test_input(
+                "
+                on x=10..12,y=10..12,z=10..12
+                on x=11..13,y=11..13,z=11..13
+                off x=9..11,y=9..11,z=9..11
+                on x=10..10,y=10..10,z=10..10
+                on x=45..60,y=0..1,z=0..0
+                ",
+            );
+            let insts = Instructions::try_from(input).expect("could not parse input");
+            let limit = Cuboid::new((-50, -50, -50).into(), (50, 50, 50).into());
+            let corner = Cuboid::new((11, 11, 11).into(), (11, 11, 13).into());
+
+            for strategy in [
+                Strategy::SignedIntersections,
+                Strategy::Disjoint,
+                Strategy::Octree,
+                Strategy::Compressed,
+            ] {
+                let mut reactor = Reactor::new(strategy);
+                reactor.reboot(&insts);
+
+                assert!(reactor.is_on(&(10, 10, 10).into()));
+                assert!(!reactor.is_on(&(11, 11, 11).into()));
+                assert!(reactor.is_on(&(13, 13, 13).into()));
+                assert!(reactor.is_on(&(60, 1, 0).into()));
+                assert!(!reactor.is_on(&(61, 1, 0).into()));
+
+                // the last region straddles the limit, so only part of it counts
+                assert_eq!(reactor.on_volume_within(&limit), 39 + 6 * 2);
+                assert_eq!(reactor.on_volume_within(&corner), 2);
+
+                // applying more steps keeps the queries up to date
+                reactor.apply(
+                    &Instructions::try_from(vec!["off x=0..100,y=1..1,z=0..0".to_string()])
+                        .expect("could not parse input"),
+                );
+                assert!(!reactor.is_on(&(60, 1, 0).into()));
+                assert_eq!(reactor.on_volume_within(&limit), 39 + 6);
+            }
+        }
+    }
+}
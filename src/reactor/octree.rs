@@ -0,0 +1,389 @@
+use std::iter::FromIterator;
+
+use super::{Cuboid, Point, Region, Shape};
+
+/// The cubes that are on within a bounding cuboid, as a tree where every
+/// branch splits its cuboid into eight. A branch is only split where a region
+/// that was switched has a boundary, so large uniform areas stay a single leaf
+/// no matter how many cubes they cover.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Octree {
+    bounds: Cuboid,
+    root: Node,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Node {
+    Leaf(bool),
+    /// The low octants end just before `mid` and the high ones start on it.
+    /// A split can leave some octants empty.
+    Branch {
+        mid: Point,
+        children: Vec<Node>,
+    },
+}
+
+impl Octree {
+    /// A tree covering `bounds`, with everything off.
+    pub fn new(bounds: Cuboid) -> Self {
+        Self {
+            bounds,
+            root: Node::Leaf(false),
+        }
+    }
+
+    pub fn bounds(&self) -> &Cuboid {
+        &self.bounds
+    }
+
+    /// Switch every cube in `cuboid` on or off. Anything outside the tree's
+    /// bounds is ignored.
+    pub fn set(&mut self, cuboid: &Cuboid, on: bool) {
+        if let Some(overlap) = self.bounds.intersection(cuboid) {
+            self.root.set(&self.bounds, &overlap, on);
+        }
+    }
+
+    /// Switch every cube in `shape` on or off. Anything outside the tree's
+    /// bounds is ignored.
+    pub fn set_shape<S: Shape + ?Sized>(&mut self, shape: &S, on: bool) {
+        self.root.set_shape(&self.bounds, shape, on);
+    }
+
+    /// Switch every cube of `region`, with any shapes it holds.
+    pub fn switch(&mut self, region: &Region) {
+        if region.shapes.is_empty() {
+            self.set(&region.cuboid, region.on);
+        } else {
+            self.set_shape(region, region.on);
+        }
+    }
+
+    /// The number of cubes that are on.
+    pub fn volume(&self) -> i64 {
+        self.root.volume(&self.bounds)
+    }
+
+    pub fn is_on(&self, point: &Point) -> bool {
+        if !self.bounds.contains(point) {
+            return false;
+        }
+
+        let mut node = &self.root;
+        loop {
+            match node {
+                Node::Leaf(on) => return *on,
+                Node::Branch { mid, children } => {
+                    let idx = (point.x >= mid.x) as usize
+                        | (((point.y >= mid.y) as usize) << 1)
+                        | (((point.z >= mid.z) as usize) << 2);
+                    node = &children[idx];
+                }
+            }
+        }
+    }
+
+    /// The number of cubes within `cuboid` that are on.
+    pub fn volume_within(&self, cuboid: &Cuboid) -> i64 {
+        self.bounds
+            .intersection(cuboid)
+            .map(|overlap| self.root.volume_within(&self.bounds, &overlap))
+            .unwrap_or_default()
+    }
+
+    /// The number of leaves, on or off.
+    pub fn leaves(&self) -> usize {
+        self.root.leaves()
+    }
+}
+
+/// A tree just big enough for every region, with them switched in order.
+impl<'a> FromIterator<&'a Region> for Octree {
+    fn from_iter<T: IntoIterator<Item = &'a Region>>(iter: T) -> Self {
+        let regions = iter.into_iter().collect::<Vec<_>>();
+        let bounds = regions
+            .iter()
+            .map(|r| r.cuboid)
+            .reduce(|a, b| {
+                Cuboid::new(
+                    (
+                        a.begin.x.min(b.begin.x),
+                        a.begin.y.min(b.begin.y),
+                        a.begin.z.min(b.begin.z),
+                    )
+                        .into(),
+                    (
+                        a.end.x.max(b.end.x),
+                        a.end.y.max(b.end.y),
+                        a.end.z.max(b.end.z),
+                    )
+                        .into(),
+                )
+            })
+            .unwrap_or_default();
+
+        let mut tree = Self::new(bounds);
+        for region in regions {
+            tree.switch(region);
+        }
+
+        tree
+    }
+}
+
+impl Node {
+    /// `region` has to be within `bounds`.
+    fn set(&mut self, bounds: &Cuboid, region: &Cuboid, on: bool) {
+        if region == bounds {
+            *self = Node::Leaf(on);
+            return;
+        }
+
+        if let Node::Leaf(cur) = *self {
+            if cur == on {
+                return;
+            }
+
+            *self = Node::Branch {
+                mid: split(bounds, region),
+                children: vec![Node::Leaf(cur); 8],
+            };
+        }
+
+        let mut collapse = false;
+        if let Node::Branch { mid, children } = self {
+            collapse = true;
+            for (idx, child) in children.iter_mut().enumerate() {
+                if let Some(child_bounds) = octant(bounds, mid, idx) {
+                    if let Some(overlap) = child_bounds.intersection(region) {
+                        child.set(&child_bounds, &overlap, on);
+                    }
+
+                    collapse &= *child == Node::Leaf(on);
+                }
+            }
+        }
+
+        if collapse {
+            *self = Node::Leaf(on);
+        }
+    }
+
+    /// Like `set`, but anything the shape only partly covers is split in
+    /// half along every axis until it's either covered or missed entirely.
+    fn set_shape<S: Shape + ?Sized>(&mut self, bounds: &Cuboid, shape: &S, on: bool) {
+        if !shape.intersects(bounds) {
+            return;
+        }
+
+        if shape.covers(bounds) {
+            *self = Node::Leaf(on);
+            return;
+        }
+
+        if let Node::Leaf(cur) = *self {
+            if cur == on {
+                return;
+            }
+
+            *self = Node::Branch {
+                mid: halve(bounds),
+                children: vec![Node::Leaf(cur); 8],
+            };
+        }
+
+        let mut collapse = false;
+        if let Node::Branch { mid, children } = self {
+            collapse = true;
+            for (idx, child) in children.iter_mut().enumerate() {
+                if let Some(child_bounds) = octant(bounds, mid, idx) {
+                    child.set_shape(&child_bounds, shape, on);
+                    collapse &= *child == Node::Leaf(on);
+                }
+            }
+        }
+
+        if collapse {
+            *self = Node::Leaf(on);
+        }
+    }
+
+    fn volume(&self, bounds: &Cuboid) -> i64 {
+        match self {
+            Node::Leaf(true) => bounds.volume(),
+            Node::Leaf(false) => 0,
+            Node::Branch { mid, children } => children
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, child)| octant(bounds, mid, idx).map(|b| child.volume(&b)))
+                .sum(),
+        }
+    }
+
+    /// `query` has to be within `bounds`.
+    fn volume_within(&self, bounds: &Cuboid, query: &Cuboid) -> i64 {
+        match self {
+            Node::Leaf(true) => query.volume(),
+            Node::Leaf(false) => 0,
+            Node::Branch { mid, children } => children
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, child)| {
+                    let child_bounds = octant(bounds, mid, idx)?;
+                    let overlap = child_bounds.intersection(query)?;
+                    Some(child.volume_within(&child_bounds, &overlap))
+                })
+                .sum(),
+        }
+    }
+
+    fn leaves(&self) -> usize {
+        match self {
+            Node::Leaf(_) => 1,
+            Node::Branch { children, .. } => children.iter().map(|c| c.leaves()).sum(),
+        }
+    }
+}
+
+/// Where to split `bounds` so one of the octants starts on a corner of
+/// `region`, or ends on one if `region` starts on the edge of `bounds`. An
+/// axis where `region` spans all of `bounds` isn't split at all, which leaves
+/// its low octants empty.
+fn split(bounds: &Cuboid, region: &Cuboid) -> Point {
+    let axis = |b_begin: i64, b_end: i64, r_begin: i64, r_end: i64| {
+        if r_begin > b_begin {
+            r_begin
+        } else if r_end < b_end {
+            r_end + 1
+        } else {
+            b_begin
+        }
+    };
+
+    Point {
+        x: axis(bounds.begin.x, bounds.end.x, region.begin.x, region.end.x),
+        y: axis(bounds.begin.y, bounds.end.y, region.begin.y, region.end.y),
+        z: axis(bounds.begin.z, bounds.end.z, region.begin.z, region.end.z),
+    }
+}
+
+/// The middle of `bounds`. A single cube is never partly covered, so there's
+/// always an axis at least two long to split.
+fn halve(bounds: &Cuboid) -> Point {
+    Point {
+        x: bounds.begin.x + (bounds.end.x - bounds.begin.x + 1) / 2,
+        y: bounds.begin.y + (bounds.end.y - bounds.begin.y + 1) / 2,
+        z: bounds.begin.z + (bounds.end.z - bounds.begin.z + 1) / 2,
+    }
+}
+
+/// The part of `bounds` in octant `idx` of a split at `mid`, where bits 0, 1
+/// and 2 pick the high side in x, y and z. `None` if that part is empty.
+fn octant(bounds: &Cuboid, mid: &Point, idx: usize) -> Option<Cuboid> {
+    let range = |high: bool, b_begin: i64, b_end: i64, mid: i64| {
+        if high {
+            (mid, b_end)
+        } else {
+            (b_begin, mid - 1)
+        }
+    };
+
+    let x = range(idx & 1 != 0, bounds.begin.x, bounds.end.x, mid.x);
+    let y = range(idx & 2 != 0, bounds.begin.y, bounds.end.y, mid.y);
+    let z = range(idx & 4 != 0, bounds.begin.z, bounds.end.z, mid.z);
+    if x.0 > x.1 || y.0 > y.1 || z.0 > z.1 {
+        return None;
+    }
+
+    Some(Cuboid::new((x.0, y.0, z.0).into(), (x.1, y.1, z.1).into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use aoc_helpers::util::test_input;
+
+    use super::super::{Instructions, Reactor, Strategy};
+    use super::*;
+
+    fn cuboid(begin: (i64, i64, i64), end: (i64, i64, i64)) -> Cuboid {
+        Cuboid::new(begin.into(), end.into())
+    }
+
+    #[test]
+    fn switching() {
+        let mut tree = Octree::new(cuboid((0, 0, 0), (9, 9, 9)));
+        assert_eq!(tree.volume(), 0);
+        assert_eq!(tree.leaves(), 1);
+
+        tree.set(&cuboid((2, 2, 2), (4, 4, 4)), true);
+        assert_eq!(tree.volume(), 27);
+        assert!(tree.is_on(&(2, 3, 4).into()));
+        assert!(!tree.is_on(&(5, 3, 4).into()));
+        assert!(!tree.is_on(&(1, 3, 4).into()));
+
+        // anything off the tree is ignored
+        tree.set(&cuboid((-5, -5, -5), (0, 0, 0)), true);
+        assert_eq!(tree.volume(), 28);
+        assert!(tree.is_on(&(0, 0, 0).into()));
+        assert!(!tree.is_on(&(-1, 0, 0).into()));
+
+        tree.set(&cuboid((3, 3, 3), (12, 3, 3)), false);
+        assert_eq!(tree.volume(), 26);
+        assert!(!tree.is_on(&(3, 3, 3).into()));
+
+        assert_eq!(tree.volume_within(&cuboid((0, 0, 0), (3, 3, 3))), 1 + 7);
+        assert_eq!(tree.volume_within(&cuboid((-9, -9, -9), (99, 99, 99))), 26);
+        assert_eq!(tree.volume_within(&cuboid((5, 5, 5), (9, 9, 9))), 0);
+
+        // switching everything merges the tree back into one leaf
+        tree.set(&cuboid((0, 0, 0), (9, 9, 9)), true);
+        assert_eq!(tree.volume(), 1000);
+        assert_eq!(tree.leaves(), 1);
+
+        // as does undoing the only change
+        let mut tree = Octree::new(cuboid((0, 0, 0), (9, 9, 9)));
+        tree.set(&cuboid((2, 0, 0), (9, 9, 9)), true);
+        assert_eq!(tree.volume(), 800);
+        tree.set(&cuboid((2, 0, 0), (9, 9, 9)), false);
+        assert_eq!(tree, Octree::new(cuboid((0, 0, 0), (9, 9, 9))));
+    }
+
+    #[test]
+    fn agrees_with_reactor() {
+        let input = test_input(
+            "
+            on x=10..12,y=10..12,z=10..12
+            on x=11..13,y=11..13,z=11..13
+            off x=9..11,y=9..11,z=9..11
+            on x=10..10,y=10..10,z=10..10
+            on x=-20..26,y=-36..17,z=-47..7
+            off x=-48..-32,y=-32..-16,z=-15..-5
+            on x=-54112..-39298,y=-85059..-49293,z=-27449..7877
+            off x=-50000..-40000,y=-60000..-50000,z=0..100
+            ",
+        );
+        let insts = Instructions::try_from(input).expect("could not parse input");
+        let tree = insts.regions.iter().collect::<Octree>();
+
+        // only the disjoint strategy keeps `lit` up to date through a reboot
+        let mut reactor = Reactor::new(Strategy::Disjoint);
+        reactor.apply(&insts);
+        assert_eq!(tree.volume(), reactor.lit_volume());
+
+        reactor.reboot(&insts);
+        assert_eq!(
+            reactor.volume_by(Strategy::Octree, &None),
+            reactor.volume(&None)
+        );
+
+        for point in [(10, 10, 10), (11, 11, 11), (13, 13, 13), (-40, -20, -10)] {
+            let point = point.into();
+            assert_eq!(
+                tree.is_on(&point),
+                reactor.lit.iter().any(|c| c.contains(&point))
+            );
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use super::{Cuboid, Point, Region};
+
+/// Something that can be switched on or off in a reactor, made of the cubes
+/// whose coordinates fall inside it.
+///
+/// Regions can hold a [`Solid`] as well as a cuboid. The signed intersection
+/// backend counts each piece with `intersection_volume`, and an
+/// [`Octree`](super::Octree) subdivides wherever a shape only partly covers a
+/// node, so both are exact. The backends that can only split cuboids break
+/// shapes up into one cuboid per run of columns instead.
+pub trait Shape {
+    /// The smallest cuboid holding every cube of the shape.
+    fn bounding_box(&self) -> Cuboid;
+
+    fn contains(&self, point: &Point) -> bool;
+
+    /// The cubes of the shape in the column along z through `x` and `y`, as
+    /// the first and last z. The shape has to be convex along z, so they are
+    /// always one run.
+    fn column(&self, x: i64, y: i64) -> Option<(i64, i64)>;
+
+    /// Whether any cube of `cuboid` is in the shape.
+    fn intersects(&self, cuboid: &Cuboid) -> bool;
+
+    /// The number of cubes of `cuboid` in the shape.
+    fn intersection_volume(&self, cuboid: &Cuboid) -> i64;
+
+    /// Whether every cube of `cuboid` is in the shape. Checking the corners is
+    /// enough for a convex shape, but anything else has to override this.
+    fn covers(&self, cuboid: &Cuboid) -> bool {
+        corners(cuboid).iter().all(|p| self.contains(p))
+    }
+}
+
+impl Shape for Cuboid {
+    fn bounding_box(&self) -> Cuboid {
+        *self
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        Cuboid::contains(self, point)
+    }
+
+    fn column(&self, x: i64, y: i64) -> Option<(i64, i64)> {
+        if x < self.begin.x || x > self.end.x || y < self.begin.y || y > self.end.y {
+            return None;
+        }
+
+        Some((self.begin.z, self.end.z))
+    }
+
+    fn intersects(&self, cuboid: &Cuboid) -> bool {
+        self.intersection(cuboid).is_some()
+    }
+
+    fn intersection_volume(&self, cuboid: &Cuboid) -> i64 {
+        self.intersection(cuboid)
+            .map(|c| c.volume())
+            .unwrap_or_default()
+    }
+
+    fn covers(&self, cuboid: &Cuboid) -> bool {
+        self.fully_contains(cuboid)
+    }
+}
+
+/// Every cube within `radius` of `center`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Sphere {
+    center: Point,
+    radius: i64,
+}
+
+impl Sphere {
+    pub fn new(center: Point, radius: i64) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl Shape for Sphere {
+    fn bounding_box(&self) -> Cuboid {
+        let c = self.center;
+        let r = self.radius;
+        Cuboid::new(
+            (c.x - r, c.y - r, c.z - r).into(),
+            (c.x + r, c.y + r, c.z + r).into(),
+        )
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        let (dx, dy, dz) = (
+            point.x - self.center.x,
+            point.y - self.center.y,
+            point.z - self.center.z,
+        );
+        dx * dx + dy * dy + dz * dz <= self.radius * self.radius
+    }
+
+    fn column(&self, x: i64, y: i64) -> Option<(i64, i64)> {
+        let (dx, dy) = (x - self.center.x, y - self.center.y);
+        let rest = self.radius * self.radius - dx * dx - dy * dy;
+        if rest < 0 {
+            return None;
+        }
+
+        let half = isqrt(rest);
+        Some((self.center.z - half, self.center.z + half))
+    }
+
+    fn intersects(&self, cuboid: &Cuboid) -> bool {
+        self.contains(&closest(cuboid, &self.center))
+    }
+
+    fn intersection_volume(&self, cuboid: &Cuboid) -> i64 {
+        let overlap = match self.bounding_box().intersection(cuboid) {
+            Some(overlap) => overlap,
+            None => return 0,
+        };
+
+        // the cubes in each column along z form one run
+        let mut volume = 0;
+        for x in overlap.begin.x..=overlap.end.x {
+            for y in overlap.begin.y..=overlap.end.y {
+                let (dx, dy) = (x - self.center.x, y - self.center.y);
+                let rest = self.radius * self.radius - dx * dx - dy * dy;
+                if rest >= 0 {
+                    let half = isqrt(rest);
+                    volume += run(
+                        self.center.z - half,
+                        self.center.z + half,
+                        overlap.begin.z,
+                        overlap.end.z,
+                    );
+                }
+            }
+        }
+
+        volume
+    }
+}
+
+/// Every cube within `radius` of a line parallel to the z axis through `base`,
+/// from `base` up through `height` layers.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Cylinder {
+    base: Point,
+    radius: i64,
+    height: i64,
+}
+
+impl Cylinder {
+    pub fn new(base: Point, radius: i64, height: i64) -> Self {
+        Self {
+            base,
+            radius,
+            height,
+        }
+    }
+}
+
+impl Shape for Cylinder {
+    fn bounding_box(&self) -> Cuboid {
+        let b = self.base;
+        let r = self.radius;
+        Cuboid::new(
+            (b.x - r, b.y - r, b.z).into(),
+            (b.x + r, b.y + r, b.z + self.height - 1).into(),
+        )
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        let (dx, dy) = (point.x - self.base.x, point.y - self.base.y);
+        dx * dx + dy * dy <= self.radius * self.radius
+            && point.z >= self.base.z
+            && point.z < self.base.z + self.height
+    }
+
+    fn column(&self, x: i64, y: i64) -> Option<(i64, i64)> {
+        let (dx, dy) = (x - self.base.x, y - self.base.y);
+        if self.height < 1 || dx * dx + dy * dy > self.radius * self.radius {
+            return None;
+        }
+
+        Some((self.base.z, self.base.z + self.height - 1))
+    }
+
+    fn intersects(&self, cuboid: &Cuboid) -> bool {
+        self.contains(&closest(cuboid, &self.base))
+    }
+
+    fn intersection_volume(&self, cuboid: &Cuboid) -> i64 {
+        let overlap = match self.bounding_box().intersection(cuboid) {
+            Some(overlap) => overlap,
+            None => return 0,
+        };
+
+        // every layer is the same disc, with one run along y for each x
+        let mut area = 0;
+        for x in overlap.begin.x..=overlap.end.x {
+            let dx = x - self.base.x;
+            let rest = self.radius * self.radius - dx * dx;
+            if rest >= 0 {
+                let half = isqrt(rest);
+                area += run(
+                    self.base.y - half,
+                    self.base.y + half,
+                    overlap.begin.y,
+                    overlap.end.y,
+                );
+            }
+        }
+
+        area * overlap.depth()
+    }
+}
+
+/// The shapes a [`Region`] can hold on top of its cuboid.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Solid {
+    Sphere(Sphere),
+    Cylinder(Cylinder),
+}
+
+impl From<Sphere> for Solid {
+    fn from(v: Sphere) -> Self {
+        Self::Sphere(v)
+    }
+}
+
+impl From<Cylinder> for Solid {
+    fn from(v: Cylinder) -> Self {
+        Self::Cylinder(v)
+    }
+}
+
+impl Solid {
+    fn shape(&self) -> &dyn Shape {
+        match self {
+            Self::Sphere(sphere) => sphere,
+            Self::Cylinder(cylinder) => cylinder,
+        }
+    }
+}
+
+impl Shape for Solid {
+    fn bounding_box(&self) -> Cuboid {
+        self.shape().bounding_box()
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        self.shape().contains(point)
+    }
+
+    fn column(&self, x: i64, y: i64) -> Option<(i64, i64)> {
+        self.shape().column(x, y)
+    }
+
+    fn intersects(&self, cuboid: &Cuboid) -> bool {
+        self.shape().intersects(cuboid)
+    }
+
+    fn intersection_volume(&self, cuboid: &Cuboid) -> i64 {
+        self.shape().intersection_volume(cuboid)
+    }
+
+    fn covers(&self, cuboid: &Cuboid) -> bool {
+        self.shape().covers(cuboid)
+    }
+}
+
+/// The cubes of the region's cuboid that are in every one of its shapes.
+impl Shape for Region {
+    fn bounding_box(&self) -> Cuboid {
+        self.cuboid
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        self.cuboid.contains(point) && self.shapes.iter().all(|s| s.contains(point))
+    }
+
+    fn column(&self, x: i64, y: i64) -> Option<(i64, i64)> {
+        let mut run = Shape::column(&self.cuboid, x, y)?;
+        for shape in self.shapes.iter() {
+            let (start, end) = shape.column(x, y)?;
+            run = (run.0.max(start), run.1.min(end));
+            if run.0 > run.1 {
+                return None;
+            }
+        }
+
+        Some(run)
+    }
+
+    fn intersects(&self, cuboid: &Cuboid) -> bool {
+        let overlap = match self.cuboid.intersection(cuboid) {
+            Some(overlap) => overlap,
+            None => return false,
+        };
+
+        match self.shapes.as_slice() {
+            [] => true,
+            [shape] => shape.intersects(&overlap),
+            // shapes can each reach the cuboid without meeting inside it
+            _ => self.intersection_volume(&overlap) > 0,
+        }
+    }
+
+    fn intersection_volume(&self, cuboid: &Cuboid) -> i64 {
+        let overlap = match self.cuboid.intersection(cuboid) {
+            Some(overlap) => overlap,
+            None => return 0,
+        };
+
+        match self.shapes.as_slice() {
+            [] => overlap.volume(),
+            [shape] => shape.intersection_volume(&overlap),
+            _ => {
+                let mut volume = 0;
+                for x in overlap.begin.x..=overlap.end.x {
+                    for y in overlap.begin.y..=overlap.end.y {
+                        if let Some((start, end)) = self.column(x, y) {
+                            volume += run(start, end, overlap.begin.z, overlap.end.z);
+                        }
+                    }
+                }
+
+                volume
+            }
+        }
+    }
+
+    fn covers(&self, cuboid: &Cuboid) -> bool {
+        self.cuboid.fully_contains(cuboid) && self.shapes.iter().all(|s| s.covers(cuboid))
+    }
+}
+
+/// The point of `cuboid` closest to `point`, on the edge if it's outside.
+fn closest(cuboid: &Cuboid, point: &Point) -> Point {
+    Point {
+        x: point.x.clamp(cuboid.begin.x, cuboid.end.x),
+        y: point.y.clamp(cuboid.begin.y, cuboid.end.y),
+        z: point.z.clamp(cuboid.begin.z, cuboid.end.z),
+    }
+}
+
+fn corners(cuboid: &Cuboid) -> [Point; 8] {
+    let (b, e) = (cuboid.begin, cuboid.end);
+    [
+        (b.x, b.y, b.z).into(),
+        (e.x, b.y, b.z).into(),
+        (b.x, e.y, b.z).into(),
+        (e.x, e.y, b.z).into(),
+        (b.x, b.y, e.z).into(),
+        (e.x, b.y, e.z).into(),
+        (b.x, e.y, e.z).into(),
+        (e.x, e.y, e.z).into(),
+    ]
+}
+
+/// The number of values in both `start..=end` and `min..=max`.
+fn run(start: i64, end: i64, min: i64, max: i64) -> i64 {
+    (end.min(max) - start.max(min) + 1).max(0)
+}
+
+/// The largest value whose square is at most `n`.
+fn isqrt(n: i64) -> i64 {
+    let mut root = (n as f64).sqrt() as i64;
+    while root * root > n {
+        root -= 1;
+    }
+    while (root + 1) * (root + 1) <= n {
+        root += 1;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Octree;
+    use super::*;
+
+    fn cuboid(begin: (i64, i64, i64), end: (i64, i64, i64)) -> Cuboid {
+        Cuboid::new(begin.into(), end.into())
+    }
+
+    /// Every cube of `within`, one at a time.
+    fn brute_force(shape: &dyn Shape, within: &Cuboid) -> i64 {
+        let mut volume = 0;
+        for x in within.begin.x..=within.end.x {
+            for y in within.begin.y..=within.end.y {
+                for z in within.begin.z..=within.end.z {
+                    if shape.contains(&(x, y, z).into()) {
+                        volume += 1;
+                    }
+                }
+            }
+        }
+        volume
+    }
+
+    #[test]
+    fn volumes() {
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Sphere::new((0, 0, 0).into(), 0)),
+            Box::new(Sphere::new((1, -2, 3).into(), 5)),
+            Box::new(Cylinder::new((-1, 2, -3).into(), 4, 7)),
+            Box::new(cuboid((-2, -3, -4), (2, 3, 4))),
+        ];
+        let probes = [
+            cuboid((-10, -10, -10), (10, 10, 10)),
+            cuboid((0, 0, 0), (3, 3, 3)),
+            cuboid((-4, 1, -6), (-1, 5, 0)),
+            cuboid((5, 5, 5), (9, 9, 9)),
+        ];
+
+        for shape in shapes.iter() {
+            let bounds = shape.bounding_box();
+            assert_eq!(
+                shape.intersection_volume(&bounds),
+                brute_force(shape.as_ref(), &bounds)
+            );
+
+            for probe in probes.iter() {
+                let volume = brute_force(shape.as_ref(), probe);
+                assert_eq!(shape.intersection_volume(probe), volume);
+                assert_eq!(shape.intersects(probe), volume > 0);
+                assert_eq!(shape.covers(probe), volume == probe.volume());
+
+                let mut columns = 0;
+                for x in probe.begin.x..=probe.end.x {
+                    for y in probe.begin.y..=probe.end.y {
+                        if let Some((start, end)) = shape.column(x, y) {
+                            columns += run(start, end, probe.begin.z, probe.end.z);
+                        }
+                    }
+                }
+                assert_eq!(columns, volume);
+            }
+        }
+
+        // the sphere of radius 1 is a cube and its six neighbors
+        assert_eq!(
+            Sphere::new((0, 0, 0).into(), 1).intersection_volume(&cuboid((-5, -5, -5), (5, 5, 5))),
+            7
+        );
+    }
+
+    #[test]
+    fn octree() {
+        let bounds = cuboid((-12, -12, -12), (12, 12, 12));
+        let sphere = Sphere::new((0, 0, 0).into(), 9);
+        let cylinder = Cylinder::new((3, 3, -20).into(), 3, 40);
+        let hole = cuboid((-4, -4, -4), (4, 4, 4));
+
+        let mut tree = Octree::new(bounds);
+        tree.set_shape(&sphere, true);
+        assert_eq!(tree.volume(), sphere.intersection_volume(&bounds));
+
+        tree.set_shape(&cylinder, false);
+        tree.set(&hole, false);
+        let small = Sphere::new((0, 0, 0).into(), 2);
+        tree.set_shape(&small, true);
+
+        // check every cube against switching them one at a time
+        let mut expected = 0;
+        for x in -12..=12 {
+            for y in -12..=12 {
+                for z in -12..=12 {
+                    let p: Point = (x, y, z).into();
+                    let on = small.contains(&p)
+                        || (sphere.contains(&p)
+                            && !cylinder.contains(&p)
+                            && !Shape::contains(&hole, &p));
+                    assert_eq!(tree.is_on(&p), on);
+                    expected += on as i64;
+                }
+            }
+        }
+        assert_eq!(tree.volume(), expected);
+
+        // switching the whole thing off leaves a single leaf again
+        tree.set_shape(&Sphere::new((0, 0, 0).into(), 30), false);
+        assert_eq!(tree.leaves(), 1);
+    }
+}
@@ -0,0 +1,11 @@
+/// A small xorshift generator for tests, so randomized cases are the same every
+/// run. Each call returns a value in `0..bound`. `seed` must not be 0.
+pub fn xorshift(seed: u64) -> impl FnMut(usize) -> usize {
+    let mut state = seed;
+    move |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as usize
+    }
+}
@@ -0,0 +1,141 @@
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::{AmbiguousMatch, Scanner, ScannerN};
+
+/// An index of every pairwise beacon distance across every scanner. The
+/// distance between two beacons does not depend on which scanner saw them, so
+/// two scanners that overlap will share at least `k * (k - 1) / 2` distances,
+/// where `k` is the number of beacons in the overlap. Grouping by distance once
+/// lets us find those scanner pairs, and which beacons correspond, without
+/// comparing every scanner with every other scanner beacon by beacon.
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintIndex {
+    /// The index each scanner reported in its header, by position.
+    ids: Vec<usize>,
+    /// Keyed by (lower scanner, higher scanner), holding the beacon pairs in
+    /// each scanner that are the same distance apart.
+    shared: FxHashMap<(usize, usize), Vec<SharedDistance>>,
+}
+
+/// The indices of a pair of beacons in one scanner alongside those of an
+/// equally distant pair in another.
+type SharedDistance = ([usize; 2], [usize; 2]);
+
+impl FingerprintIndex {
+    pub fn new<const D: usize>(scanners: &[ScannerN<D>]) -> Self {
+        let mut by_distance: FxHashMap<i64, Vec<(usize, [usize; 2])>> = FxHashMap::default();
+        for (s_idx, scanner) in scanners.iter().enumerate() {
+            for (a, b) in (0..scanner.beacons.len()).tuple_combinations() {
+                let dist = scanner.beacons[a].dist_squared(&scanner.beacons[b]);
+                by_distance.entry(dist).or_default().push((s_idx, [a, b]));
+            }
+        }
+
+        let mut shared: FxHashMap<(usize, usize), Vec<SharedDistance>> = FxHashMap::default();
+        for edges in by_distance.values() {
+            for (i, (s_a, pair_a)) in edges.iter().enumerate() {
+                // edges were pushed in scanner order, so s_a <= s_b
+                for (s_b, pair_b) in edges[i + 1..].iter().filter(|(s, _)| s != s_a) {
+                    shared
+                        .entry((*s_a, *s_b))
+                        .or_default()
+                        .push((*pair_a, *pair_b));
+                }
+            }
+        }
+
+        Self {
+            ids: scanners.iter().map(|s| s.index).collect(),
+            shared,
+        }
+    }
+
+    /// Returns, for every scanner, the scanners it shares enough distances
+    /// with to plausibly overlap, most shared distances first.
+    pub fn neighbors(&self, scanners: usize) -> Vec<Vec<usize>> {
+        let min_shared = Scanner::THRESHOLD * (Scanner::THRESHOLD - 1) / 2;
+        let mut out: Vec<Vec<(usize, usize)>> = vec![Vec::new(); scanners];
+        for ((a, b), pairs) in self.shared.iter() {
+            if pairs.len() >= min_shared {
+                out[*a].push((pairs.len(), *b));
+                out[*b].push((pairs.len(), *a));
+            }
+        }
+
+        out.into_iter()
+            .map(|mut n| {
+                n.sort_unstable_by(|l, r| r.cmp(l));
+                n.into_iter().map(|(_, idx)| idx).collect()
+            })
+            .collect()
+    }
+
+    /// Returns the (`from` beacon, `to` beacon) index pairs that appear to be
+    /// the same beacon, strongest evidence first, or an error if two beacons in
+    /// one scanner both look most like the same beacon in the other.
+    ///
+    /// Every shared distance votes for both ways of lining up its two beacon
+    /// pairs. A true correspondence collects a vote from every other beacon in
+    /// the overlap, while a false one only collects the odd stray vote.
+    pub fn correspondences(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> Result<Vec<(usize, usize)>, AmbiguousMatch> {
+        let (key, flip) = if from < to {
+            ((from, to), false)
+        } else {
+            ((to, from), true)
+        };
+
+        let pairs = match self.shared.get(&key) {
+            Some(pairs) => pairs,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut votes: FxHashMap<(usize, usize), usize> = FxHashMap::default();
+        for (low, high) in pairs {
+            for l in low {
+                for h in high {
+                    *votes.entry((*l, *h)).or_default() += 1;
+                }
+            }
+        }
+
+        // keep only the best candidate for each beacon in the lower scanner
+        let mut best: FxHashMap<usize, (usize, usize)> = FxHashMap::default();
+        for ((l, h), count) in votes {
+            if count < Scanner::THRESHOLD - 1 {
+                continue;
+            }
+
+            let e = best.entry(l).or_insert((count, h));
+            if (count, h) > *e {
+                *e = (count, h);
+            }
+        }
+
+        let mut found = best
+            .into_iter()
+            .map(|(l, (count, h))| (count, l, h))
+            .collect::<Vec<_>>();
+        found.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut seen: FxHashSet<usize> = FxHashSet::default();
+        for (_, _, h) in &found {
+            if !seen.insert(*h) {
+                return Err(AmbiguousMatch {
+                    scanner: self.ids[key.0],
+                    other: self.ids[key.1],
+                    beacon: *h,
+                });
+            }
+        }
+
+        Ok(found
+            .into_iter()
+            .map(|(_, l, h)| if flip { (h, l) } else { (l, h) })
+            .collect())
+    }
+}
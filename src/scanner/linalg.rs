@@ -0,0 +1,178 @@
+//! Just enough integer linear algebra to describe how a scanner is oriented.
+//! Every orientation a scanner can have is a rotation by some multiple of 90
+//! degrees about the axes, so every entry in these matrices is -1, 0 or 1.
+
+/// A `D` by `D` integer matrix in row-major order.
+pub type Matrix<const D: usize> = [[i64; D]; D];
+
+pub const IDENTITY: Matrix<3> = [[1, 0, 0], [0, 1, 0], [0, 0, 1]];
+
+/// The 24 proper rotations of a cube, with the identity first.
+pub const ROTATIONS: [Matrix<3>; 24] = rotations();
+
+/// The 4 proper rotations of a square, as successive quarter turns
+/// counterclockwise, starting with the identity.
+pub const ROTATIONS_2D: [Matrix<2>; 4] = [
+    [[1, 0], [0, 1]],
+    [[0, -1], [1, 0]],
+    [[-1, 0], [0, -1]],
+    [[0, 1], [-1, 0]],
+];
+
+/// Builds the set of rotations as every signed permutation matrix with a
+/// determinant of 1. Each permutation of the axes admits exactly four sign
+/// choices that preserve handedness, which is where the 6 * 4 = 24 comes from.
+const fn rotations() -> [Matrix<3>; 24] {
+    const PERMUTATIONS: [[usize; 3]; 6] = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+
+    let mut out = [[[0; 3]; 3]; 24];
+    let mut count = 0;
+    let mut p = 0;
+    while p < PERMUTATIONS.len() {
+        let perm = PERMUTATIONS[p];
+        // bit i of signs set means row i is negated
+        let mut signs = 0;
+        while signs < 8 {
+            let mut m = [[0; 3]; 3];
+            let mut row = 0;
+            while row < 3 {
+                m[row][perm[row]] = if signs & (1 << row) == 0 { 1 } else { -1 };
+                row += 1;
+            }
+
+            if determinant(&m) == 1 {
+                out[count] = m;
+                count += 1;
+            }
+
+            signs += 1;
+        }
+        p += 1;
+    }
+
+    out
+}
+
+pub const fn determinant(m: &Matrix<3>) -> i64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+pub fn multiply<const D: usize>(a: &Matrix<D>, b: &Matrix<D>) -> Matrix<D> {
+    let mut out = [[0; D]; D];
+    for (row, out_row) in out.iter_mut().enumerate() {
+        for (col, cell) in out_row.iter_mut().enumerate() {
+            *cell = (0..D).map(|k| a[row][k] * b[k][col]).sum();
+        }
+    }
+    out
+}
+
+pub fn transpose<const D: usize>(m: &Matrix<D>) -> Matrix<D> {
+    let mut out = [[0; D]; D];
+    for (row, out_row) in out.iter_mut().enumerate() {
+        for (col, cell) in out_row.iter_mut().enumerate() {
+            *cell = m[col][row];
+        }
+    }
+    out
+}
+
+pub fn apply<const D: usize>(m: &Matrix<D>, v: &[i64; D]) -> [i64; D] {
+    let mut out = [0; D];
+    for (row, cell) in m.iter().zip(out.iter_mut()) {
+        *cell = row.iter().zip(v.iter()).map(|(a, b)| a * b).sum();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashSet;
+
+    use super::*;
+
+    #[test]
+    fn identity_comes_first() {
+        assert_eq!(ROTATIONS[0], IDENTITY);
+    }
+
+    #[test]
+    fn rotations_are_proper() {
+        for m in &ROTATIONS {
+            assert_eq!(determinant(m), 1);
+            assert_eq!(multiply(m, &transpose(m)), IDENTITY);
+        }
+    }
+
+    #[test]
+    fn rotations_are_the_orientation_preserving_axis_permutations() {
+        let generated: FxHashSet<Matrix<3>> = ROTATIONS.iter().copied().collect();
+        assert_eq!(generated.len(), 24);
+
+        // independently, close the two quarter turns about x and about y
+        // under composition, which yields the full rotation group of the cube
+        let quarter_x = [[1, 0, 0], [0, 0, -1], [0, 1, 0]];
+        let quarter_y = [[0, 0, 1], [0, 1, 0], [-1, 0, 0]];
+        let mut group: FxHashSet<Matrix<3>> = FxHashSet::default();
+        group.insert(IDENTITY);
+        let mut frontier = vec![IDENTITY];
+        while let Some(m) = frontier.pop() {
+            for g in &[quarter_x, quarter_y] {
+                let next = multiply(g, &m);
+                if group.insert(next) {
+                    frontier.push(next);
+                }
+            }
+        }
+
+        assert_eq!(generated, group);
+    }
+
+    #[test]
+    fn rotations_form_a_group() {
+        let generated: FxHashSet<Matrix<3>> = ROTATIONS.iter().copied().collect();
+        for a in &ROTATIONS {
+            assert!(generated.contains(&transpose(a)));
+            for b in &ROTATIONS {
+                assert!(generated.contains(&multiply(a, b)));
+            }
+        }
+    }
+
+    #[test]
+    fn planar_rotations() {
+        // the signed 2x2 permutation matrices with a determinant of 1
+        let mut expected = Vec::new();
+        for swap in [false, true] {
+            for signs in 0..4 {
+                let a = if signs & 1 == 0 { 1 } else { -1 };
+                let b = if signs & 2 == 0 { 1 } else { -1 };
+                let m = if swap {
+                    [[0, a], [b, 0]]
+                } else {
+                    [[a, 0], [0, b]]
+                };
+                if m[0][0] * m[1][1] - m[0][1] * m[1][0] == 1 {
+                    expected.push(m);
+                }
+            }
+        }
+
+        let generated: FxHashSet<Matrix<2>> = ROTATIONS_2D.iter().copied().collect();
+        assert_eq!(generated, expected.into_iter().collect());
+
+        for (idx, m) in ROTATIONS_2D.iter().enumerate() {
+            assert_eq!(multiply(m, &transpose(m)), [[1, 0], [0, 1]]);
+            assert_eq!(multiply(&ROTATIONS_2D[1], m), ROTATIONS_2D[(idx + 1) % 4]);
+        }
+    }
+}
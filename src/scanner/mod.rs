@@ -0,0 +1,1206 @@
+use anyhow::{anyhow, bail, Result};
+use aoc_helpers::Solver;
+use itertools::Itertools;
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::{collections::VecDeque, convert::TryFrom, fmt, hash::Hash, io::Write, str::FromStr};
+
+pub use self::fingerprint::FingerprintIndex;
+pub use self::linalg::{Matrix, ROTATIONS, ROTATIONS_2D};
+
+mod fingerprint;
+pub mod linalg;
+
+/// A point seen by a scanner in `D` dimensions.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct BeaconN<const D: usize> {
+    coords: [i64; D],
+}
+
+pub type Beacon = BeaconN<3>;
+pub type Beacon2 = BeaconN<2>;
+
+/// Ties a dimension to the set of orientations a scanner can have in it.
+pub trait Rotations<const D: usize> {
+    const ALL: &'static [Matrix<D>];
+}
+
+impl Rotations<2> for BeaconN<2> {
+    const ALL: &'static [Matrix<2>] = &ROTATIONS_2D;
+}
+
+impl Rotations<3> for BeaconN<3> {
+    const ALL: &'static [Matrix<3>] = &ROTATIONS;
+}
+
+impl<const D: usize> BeaconN<D> {
+    pub fn dist_squared(&self, other: &Self) -> i64 {
+        self.coords
+            .iter()
+            .zip(other.coords.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum()
+    }
+
+    pub fn manhattan(&self, other: &Self) -> i64 {
+        self.coords
+            .iter()
+            .zip(other.coords.iter())
+            .map(|(a, b)| (a - b).abs())
+            .sum()
+    }
+
+    pub fn x(&self) -> i64 {
+        self.coords[0]
+    }
+
+    pub fn y(&self) -> i64 {
+        self.coords[1]
+    }
+
+    pub fn offset(&self, other: &Self) -> Self {
+        let mut coords = self.coords;
+        for (c, o) in coords.iter_mut().zip(other.coords.iter()) {
+            *c -= o;
+        }
+        coords.into()
+    }
+
+    pub fn translate(&mut self, by: &[i64; D]) {
+        for (c, b) in self.coords.iter_mut().zip(by.iter()) {
+            *c += b;
+        }
+    }
+}
+
+impl Beacon {
+    pub fn z(&self) -> i64 {
+        self.coords[2]
+    }
+}
+
+impl<const D: usize> BeaconN<D>
+where
+    Self: Rotations<D>,
+{
+    pub fn rotate(&mut self, idx: usize) {
+        self.coords = self.rotation(idx).coords;
+    }
+
+    pub fn rotation(&self, idx: usize) -> Self {
+        linalg::apply(&Self::ALL[idx], &self.coords).into()
+    }
+
+    pub fn rotations(&self) -> impl Iterator<Item = (usize, Self)> + '_ {
+        Self::ALL
+            .iter()
+            .enumerate()
+            .map(move |(idx, m)| (idx, linalg::apply(m, &self.coords).into()))
+    }
+}
+
+impl<const D: usize> Default for BeaconN<D> {
+    fn default() -> Self {
+        Self { coords: [0; D] }
+    }
+}
+
+impl<const D: usize> fmt::Display for BeaconN<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.coords.iter().join(", "))
+    }
+}
+
+impl<const D: usize> From<[i64; D]> for BeaconN<D> {
+    fn from(value: [i64; D]) -> Self {
+        Self { coords: value }
+    }
+}
+
+impl<const D: usize> FromStr for BeaconN<D> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut iter = s.split(',').map(i64::from_str);
+        let mut coords = [0; D];
+
+        for (idx, c) in coords.iter_mut().enumerate() {
+            *c = iter.next().ok_or_else(|| {
+                let axis = ["x", "y", "z"].get(idx).copied().unwrap_or("coordinate");
+                anyhow!("cannot make beacon, missing {}: {}", axis, s)
+            })??;
+        }
+
+        Ok(coords.into())
+    }
+}
+
+/// Pairs of beacons, one from each of two scanners, that are believed to be the
+/// same beacon.
+pub type Overlap<'a, const D: usize> = Vec<(&'a BeaconN<D>, &'a BeaconN<D>)>;
+
+/// More than one beacon in `scanner` looks like the same beacon in `other`, so
+/// the overlap between them can't be trusted.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct AmbiguousMatch {
+    pub scanner: usize,
+    pub other: usize,
+    /// The index of the contested beacon in `other`.
+    pub beacon: usize,
+}
+
+impl fmt::Display for AmbiguousMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "multiple beacons in scanner {} match beacon {} of scanner {}",
+            self.scanner, self.beacon, self.other
+        )
+    }
+}
+
+impl std::error::Error for AmbiguousMatch {}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Measurement {
+    dist: i64,
+    instance: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScannerN<const D: usize> {
+    index: usize,
+    beacons: Vec<BeaconN<D>>,
+    /// A mapping between a beacon and its distances to other beacons in the
+    /// scanner. So the idea is that the distances between any two beacons is
+    /// constant regardless of what scanner reads them. Hopefully this lets me
+    /// more quickly find the matching beacons. In this case, we're storing the
+    /// square of the distance to avoid issues with representing these as ints
+    /// instead of floats.
+    dist_map: Vec<FxHashSet<Measurement>>,
+    offset: Option<BeaconN<D>>,
+    rotation: Option<usize>,
+}
+
+pub type Scanner = ScannerN<3>;
+pub type Scanner2 = ScannerN<2>;
+
+impl<const D: usize> ScannerN<D> {
+    // So apparently, a threashold of 3 instead of 12 is good enough because of
+    // the dataset, but let's just meet in the middle for whatever reason
+    pub const THRESHOLD: usize = 6;
+
+    pub fn new(index: usize, beacons: Vec<BeaconN<D>>) -> Self {
+        let mut dist_map: Vec<FxHashSet<Measurement>> = vec![FxHashSet::default(); beacons.len()];
+
+        let mut dist_pre_map: Vec<FxHashMap<i64, usize>> =
+            vec![FxHashMap::default(); beacons.len()];
+
+        for comb in beacons.iter().enumerate().combinations(2) {
+            let a = comb[0];
+            let b = comb[1];
+
+            let dist = a.1.dist_squared(b.1);
+
+            let e = dist_pre_map[a.0].entry(dist).or_default();
+            *e += 1;
+
+            let e = dist_pre_map[b.0].entry(dist).or_default();
+            *e += 1;
+        }
+
+        for (idx, _) in beacons.iter().enumerate() {
+            for (k, v) in dist_pre_map[idx].iter() {
+                for i in 0..*v {
+                    dist_map[idx].insert(Measurement {
+                        dist: *k,
+                        instance: i,
+                    });
+                }
+            }
+        }
+
+        Self {
+            index,
+            beacons,
+            dist_map,
+            offset: None,
+            rotation: None,
+        }
+    }
+
+    /// The position and orientation of this scanner relative to the reference
+    /// scanner, if it has been resolved.
+    pub fn pose(&self) -> Option<Pose<D>> {
+        match (self.offset, self.rotation) {
+            (Some(position), Some(rotation)) => Some(Pose {
+                index: self.index,
+                position,
+                rotation,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns a vector of a mapping between the index of a beacon in this
+    /// scanner with the index of a beacon in the other scanner, or an error if
+    /// more than one beacon in this scanner matches the same beacon in the
+    /// other scanner, in which case we can't tell which match is the real one
+    pub fn intersection<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> Result<Option<Overlap<'a, D>>, AmbiguousMatch> {
+        let mut candidates = Vec::new();
+        let mut seen: FxHashSet<usize> = FxHashSet::default();
+
+        for (idx, dists) in self.dist_map.iter().enumerate() {
+            if let Some(found) = other.find_by_distances(dists) {
+                if !seen.insert(found) {
+                    return Err(AmbiguousMatch {
+                        scanner: self.index,
+                        other: other.index,
+                        beacon: found,
+                    });
+                }
+
+                candidates.push((&self.beacons[idx], &other.beacons[found]));
+            }
+
+            // we can stop after we find enough
+            if candidates.len() >= Self::THRESHOLD {
+                return Ok(Some(candidates));
+            }
+
+            if candidates.len() + (self.beacons.len() - idx - 1) < Self::THRESHOLD {
+                // we can't possibly satisfy this intersection, so break early
+                return Ok(None);
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn par_intersection<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> Result<Option<Overlap<'a, D>>, AmbiguousMatch> {
+        let found: Vec<_> = self
+            .dist_map
+            .par_iter()
+            .enumerate()
+            .filter_map(|(idx, dists)| other.find_by_distances(dists).map(|found| (idx, found)))
+            .collect();
+
+        let mut seen: FxHashSet<usize> = FxHashSet::default();
+        for (_, f) in &found {
+            if !seen.insert(*f) {
+                return Err(AmbiguousMatch {
+                    scanner: self.index,
+                    other: other.index,
+                    beacon: *f,
+                });
+            }
+        }
+
+        if found.len() < Self::THRESHOLD {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            found
+                .into_iter()
+                .map(|(idx, f)| (&self.beacons[idx], &other.beacons[f]))
+                .collect(),
+        ))
+    }
+
+    pub fn find_by_distances(&self, distances: &FxHashSet<Measurement>) -> Option<usize> {
+        for (idx, dists) in self.dist_map.iter().enumerate() {
+            if distances.intersection(dists).count() >= Self::THRESHOLD - 1 {
+                return Some(idx);
+            }
+        }
+
+        None
+    }
+
+    pub fn par_find_by_distances(&self, distances: &FxHashSet<Measurement>) -> Option<usize> {
+        self.dist_map
+            .par_iter()
+            .enumerate()
+            .find_any(|(_, dists)| distances.intersection(dists).count() >= Self::THRESHOLD - 1)
+            .map(|(idx, _)| idx)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&BeaconN<D>> {
+        self.beacons.get(index)
+    }
+}
+
+impl<const D: usize> ScannerN<D>
+where
+    BeaconN<D>: Rotations<D>,
+{
+    pub fn transform(&mut self, rot: usize, trans: &[i64; D]) {
+        self.beacons.iter_mut().for_each(|b| {
+            b.rotate(rot);
+            b.translate(trans);
+        });
+
+        self.offset = Some(BeaconN::from(*trans));
+        self.rotation = Some(rot);
+    }
+}
+
+impl<const D: usize> TryFrom<&[String]> for ScannerN<D> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[String]) -> Result<Self> {
+        let mut parts = value.iter();
+        let name_components = parts
+            .next()
+            .ok_or_else(|| anyhow!("missing scanner header"))?
+            .split_whitespace()
+            .collect::<Vec<&str>>();
+
+        if name_components.len() < 4 {
+            bail!("invalid scanner header: {}", value[0]);
+        }
+
+        let index = usize::from_str(name_components[2])?;
+
+        let beacons = parts
+            .map(|s| BeaconN::from_str(s))
+            .collect::<Result<Vec<BeaconN<D>>>>()?;
+
+        Ok(Self::new(index, beacons))
+    }
+}
+
+/// Where a scanner sits relative to the reference scanner (scanner 0).
+/// `rotation` indexes into the scanner's [`Rotations`] and maps the scanner's own readings
+/// into the reference orientation, after which `position` is added.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Pose<const D: usize> {
+    pub index: usize,
+    pub position: BeaconN<D>,
+    pub rotation: usize,
+}
+
+/// How a single scanner was placed during correlation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Resolution<const D: usize> {
+    pub scanner: usize,
+    /// The already resolved scanner this one was matched against. The
+    /// reference scanner is recorded as its own reference.
+    pub reference: usize,
+    /// The index into the [`Rotations`] applied to the scanner's readings.
+    pub rotation: usize,
+    /// The translation applied after the rotation, which is also the
+    /// scanner's position relative to the reference.
+    pub offset: BeaconN<D>,
+}
+
+/// The outcome of a [`MapperN::correlate`] call.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CorrelationReport<const D: usize> {
+    /// Every resolved scanner, in the order it was resolved, starting with
+    /// the reference.
+    pub resolved: Vec<Resolution<D>>,
+    /// The scanners that could not be connected to the reference.
+    pub unresolved: Vec<usize>,
+    /// The number of distinct beacons in the merged map.
+    pub beacons: usize,
+}
+
+/// Passed to the callback given to [`MapperN::correlate_with_progress`] every
+/// time another scanner is resolved.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Progress<const D: usize> {
+    pub latest: Resolution<D>,
+    /// How many scanners have been resolved so far, including `latest`.
+    pub resolved: usize,
+    /// How many scanners there are in total.
+    pub total: usize,
+}
+
+/// The file formats [`MapperN::export`] can write.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    /// One `kind,index,x,y,z` row per point, where `kind` is `scanner` or
+    /// `beacon` and `index` is only filled in for scanners.
+    Csv,
+    /// An ASCII PLY point cloud, with scanners in red and beacons in white.
+    Ply,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapperN<const D: usize> {
+    scanners: Vec<ScannerN<D>>,
+}
+
+pub type Mapper = MapperN<3>;
+pub type Mapper2 = MapperN<2>;
+
+impl<const D: usize> MapperN<D>
+where
+    BeaconN<D>: Rotations<D>,
+{
+    pub fn largest_distance(&self) -> Option<i64> {
+        self.scanners
+            .iter()
+            .combinations(2)
+            .map(|comb| {
+                comb[0]
+                    .offset
+                    .unwrap_or_default()
+                    .manhattan(&comb[1].offset.unwrap_or_default())
+            })
+            .max()
+    }
+
+    /// The poses of every resolved scanner, in input order. This is empty
+    /// until [`Mapper::correlate`] has been called.
+    pub fn scanner_poses(&self) -> Vec<Pose<D>> {
+        self.scanners.iter().filter_map(|s| s.pose()).collect()
+    }
+
+    /// Write the resolved scanner positions and the merged beacons to `writer`,
+    /// in a stable order. Only scanners with a known pose, and the beacons they
+    /// saw, are included, so this fails if nothing has been correlated yet.
+    pub fn export<W: Write>(&self, writer: &mut W, format: Format) -> Result<()> {
+        let poses = self.scanner_poses();
+        if poses.is_empty() {
+            bail!("no scanners have been correlated");
+        }
+
+        let mut beacons = self
+            .scanners
+            .iter()
+            .filter(|s| s.pose().is_some())
+            .flat_map(|s| s.beacons.iter().copied())
+            .collect::<FxHashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        beacons.sort_unstable_by_key(|b| b.coords);
+
+        let axes = &["x", "y", "z"][..D];
+        match format {
+            Format::Csv => {
+                writeln!(writer, "kind,index,{}", axes.join(","))?;
+                for pose in &poses {
+                    let coords = pose.position.coords.iter().join(",");
+                    writeln!(writer, "scanner,{},{}", pose.index, coords)?;
+                }
+                for b in &beacons {
+                    writeln!(writer, "beacon,,{}", b.coords.iter().join(","))?;
+                }
+            }
+            Format::Ply => {
+                // PLY viewers expect three dimensions, so planar maps sit at z = 0
+                let padded = |b: &BeaconN<D>| {
+                    let mut coords = [0; 3];
+                    coords[..D].copy_from_slice(&b.coords);
+                    coords.iter().join(" ")
+                };
+
+                writeln!(writer, "ply")?;
+                writeln!(writer, "format ascii 1.0")?;
+                writeln!(writer, "comment scanners are red, beacons are white")?;
+                writeln!(writer, "element vertex {}", poses.len() + beacons.len())?;
+                for prop in ["x", "y", "z"] {
+                    writeln!(writer, "property int {}", prop)?;
+                }
+                for prop in ["red", "green", "blue"] {
+                    writeln!(writer, "property uchar {}", prop)?;
+                }
+                writeln!(writer, "end_header")?;
+                for pose in &poses {
+                    writeln!(writer, "{} 255 0 0", padded(&pose.position))?;
+                }
+                for b in &beacons {
+                    writeln!(writer, "{} 255 255 255", padded(b))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the pose of every scanner reachable from scanner 0 and
+    /// collects the merged beacons, finding overlapping scanners through a
+    /// [`FingerprintIndex`] of their pairwise beacon distances.
+    ///
+    /// Fails if two scanners overlap in a way that doesn't pin down which
+    /// beacons are the same.
+    pub fn correlate(
+        &mut self,
+        beacons: &mut FxHashSet<BeaconN<D>>,
+    ) -> Result<CorrelationReport<D>, AmbiguousMatch> {
+        self.correlate_with_progress(beacons, |_| {})
+    }
+
+    /// The same as [`Mapper::correlate`], but calls `progress` every time
+    /// another scanner is resolved, the reference included.
+    pub fn correlate_with_progress<F>(
+        &mut self,
+        beacons: &mut FxHashSet<BeaconN<D>>,
+        mut progress: F,
+    ) -> Result<CorrelationReport<D>, AmbiguousMatch>
+    where
+        F: FnMut(&Progress<D>),
+    {
+        let mut report = CorrelationReport::default();
+        if self.scanners.is_empty() {
+            return Ok(report);
+        }
+
+        let index = FingerprintIndex::new(&self.scanners);
+        let neighbors = index.neighbors(self.scanners.len());
+
+        let total = self.scanners.len();
+        let mut record = |latest: Resolution<D>, report: &mut CorrelationReport<D>| {
+            report.resolved.push(latest);
+            progress(&Progress {
+                latest,
+                resolved: report.resolved.len(),
+                total,
+            });
+        };
+
+        // the reference is, by definition, unrotated at the origin
+        record(
+            self.resolve(0, 0, 0, BeaconN::default(), beacons),
+            &mut report,
+        );
+
+        let mut solved = vec![false; self.scanners.len()];
+        solved[0] = true;
+        let mut queue = VecDeque::from(vec![0]);
+
+        while let Some(r_idx) = queue.pop_front() {
+            for p_idx in &neighbors[r_idx] {
+                if solved[*p_idx] {
+                    continue;
+                }
+
+                let found = {
+                    let reference = &self.scanners[r_idx];
+                    let pending = &self.scanners[*p_idx];
+                    let intersection = index
+                        .correspondences(r_idx, *p_idx)?
+                        .into_iter()
+                        .map(|(r, p)| (&reference.beacons[r], &pending.beacons[p]))
+                        .collect::<Vec<_>>();
+
+                    if intersection.len() < ScannerN::<D>::THRESHOLD {
+                        None
+                    } else {
+                        self.find_offset(&intersection)
+                    }
+                };
+
+                if let Some((rot, offset)) = found {
+                    record(
+                        self.resolve(*p_idx, r_idx, rot, offset, beacons),
+                        &mut report,
+                    );
+                    solved[*p_idx] = true;
+                    queue.push_back(*p_idx);
+                }
+            }
+        }
+
+        Ok(self.finish(report, beacons))
+    }
+
+    /// The original approach to [`Mapper::correlate`], which compares
+    /// scanners beacon by beacon instead of using a [`FingerprintIndex`].
+    ///
+    /// Scanners are resolved breadth first from the reference: each newly
+    /// resolved scanner is checked once against every scanner still pending,
+    /// so no pair is ever compared twice.
+    pub fn correlate_pairwise(
+        &mut self,
+        beacons: &mut FxHashSet<BeaconN<D>>,
+    ) -> Result<CorrelationReport<D>, AmbiguousMatch> {
+        let mut report = CorrelationReport::default();
+        if self.scanners.is_empty() {
+            return Ok(report);
+        }
+
+        // the reference is, by definition, unrotated at the origin
+        let reference = self.resolve(0, 0, 0, BeaconN::default(), beacons);
+        report.resolved.push(reference);
+
+        let mut pending = (1..self.scanners.len()).collect::<Vec<_>>();
+        let mut queue = VecDeque::from(vec![0]);
+
+        while let Some(r_idx) = queue.pop_front() {
+            let mut still_pending = Vec::with_capacity(pending.len());
+            for p_idx in pending {
+                let found = match self.scanners[r_idx].par_intersection(&self.scanners[p_idx])? {
+                    Some(intersection) => self.find_offset(&intersection),
+                    None => None,
+                };
+
+                if let Some((rot, offset)) = found {
+                    let resolution = self.resolve(p_idx, r_idx, rot, offset, beacons);
+                    report.resolved.push(resolution);
+                    queue.push_back(p_idx);
+                } else {
+                    still_pending.push(p_idx);
+                }
+            }
+            pending = still_pending;
+        }
+
+        Ok(self.finish(report, beacons))
+    }
+
+    /// Moves the scanner at position `idx` into the reference frame and merges
+    /// its beacons, returning how it got there.
+    fn resolve(
+        &mut self,
+        idx: usize,
+        reference: usize,
+        rotation: usize,
+        offset: BeaconN<D>,
+        beacons: &mut FxHashSet<BeaconN<D>>,
+    ) -> Resolution<D> {
+        let reference = self.scanners[reference].index;
+        let s = &mut self.scanners[idx];
+        s.transform(rotation, &offset.coords);
+        beacons.extend(s.beacons.iter().copied());
+
+        Resolution {
+            scanner: s.index,
+            reference,
+            rotation,
+            offset,
+        }
+    }
+
+    fn finish(
+        &self,
+        mut report: CorrelationReport<D>,
+        beacons: &FxHashSet<BeaconN<D>>,
+    ) -> CorrelationReport<D> {
+        report.unresolved = self
+            .scanners
+            .iter()
+            .filter(|s| s.pose().is_none())
+            .map(|s| s.index)
+            .collect();
+        report.beacons = beacons.len();
+        report
+    }
+
+    fn find_offset(
+        &self,
+        intersection: &[(&BeaconN<D>, &BeaconN<D>)],
+    ) -> Option<(usize, BeaconN<D>)> {
+        for rot in 0..BeaconN::<D>::ALL.len() {
+            if let Some(offset) = self.check_rotation(rot, intersection) {
+                return Some((rot, offset));
+            }
+        }
+
+        None
+    }
+
+    fn check_rotation(
+        &self,
+        rot: usize,
+        intersection: &[(&BeaconN<D>, &BeaconN<D>)],
+    ) -> Option<BeaconN<D>> {
+        let mut prev: Option<BeaconN<D>> = None;
+        for (a, b) in intersection.iter().take(ScannerN::<D>::THRESHOLD) {
+            let delta = a.offset(&b.rotation(rot));
+            if let Some(p) = prev {
+                if delta != p {
+                    // this rotation is invalid
+                    return None;
+                }
+            } else {
+                prev = Some(delta);
+            }
+        }
+
+        prev
+    }
+}
+
+impl<const D: usize> TryFrom<Vec<String>> for MapperN<D> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<String>) -> Result<Self> {
+        let scanners = value
+            .split(|s| s.is_empty())
+            .map(ScannerN::try_from)
+            .collect::<Result<Vec<ScannerN<D>>>>()?;
+        Ok(Self { scanners })
+    }
+}
+
+impl Solver for Mapper {
+    const ID: &'static str = "beacon scanner";
+    const DAY: usize = 19;
+
+    type P1 = usize;
+    type P2 = i64;
+
+    fn part_one(&mut self) -> Self::P1 {
+        let mut beacons = FxHashSet::default();
+        self.correlate(&mut beacons)
+            .expect("could not correlate scanners");
+        beacons.len()
+    }
+
+    // in this case, we expect part_two to always be called after part 1,
+    // as it relies on a correlation, so we have to use the combined
+    // variant of the bench macro
+    fn part_two(&mut self) -> Self::P2 {
+        self.largest_distance()
+            .expect("could not find largest distance")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod scanner {
+        use aoc_helpers::util::test_input;
+
+        use super::super::*;
+
+        #[test]
+        fn construction() {
+            let input = test_input(
+                "
+                --- scanner 0 ---
+                -1,-1,1
+                -2,-2,2
+                -3,-3,3
+                -2,-3,1
+                5,6,-4
+                8,0,7
+                ",
+            );
+            Scanner::try_from(input.as_ref()).expect("could not parse scanner");
+        }
+
+        // every beacon in a point-symmetric cloud has the same distances to the
+        // rest of the cloud as its mirror image does
+        pub(super) fn symmetric(index: usize) -> Vec<String> {
+            test_input(&format!(
+                "
+                --- scanner {} ---
+                1,2,3
+                -1,-2,-3
+                5,-7,11
+                -5,7,-11
+                -13,17,4
+                13,-17,-4
+                20,3,-9
+                -20,-3,9
+                ",
+                index
+            ))
+        }
+
+        #[test]
+        fn ambiguous_intersection() {
+            let s = Scanner::try_from(symmetric(0).as_ref()).expect("could not parse scanner");
+            let expected = AmbiguousMatch {
+                scanner: 0,
+                other: 0,
+                beacon: 0,
+            };
+            assert_eq!(s.intersection(&s), Err(expected));
+            assert_eq!(s.par_intersection(&s), Err(expected));
+        }
+    }
+
+    mod mapping {
+        use aoc_helpers::util::test_input;
+
+        use super::super::*;
+
+        fn example() -> Vec<String> {
+            test_input(
+                "
+            --- scanner 0 ---
+            404,-588,-901
+            528,-643,409
+            -838,591,734
+            390,-675,-793
+            -537,-823,-458
+            -485,-357,347
+            -345,-311,381
+            -661,-816,-575
+            -876,649,763
+            -618,-824,-621
+            553,345,-567
+            474,580,667
+            -447,-329,318
+            -584,868,-557
+            544,-627,-890
+            564,392,-477
+            455,729,728
+            -892,524,684
+            -689,845,-530
+            423,-701,434
+            7,-33,-71
+            630,319,-379
+            443,580,662
+            -789,900,-551
+            459,-707,401
+
+            --- scanner 1 ---
+            686,422,578
+            605,423,415
+            515,917,-361
+            -336,658,858
+            95,138,22
+            -476,619,847
+            -340,-569,-846
+            567,-361,727
+            -460,603,-452
+            669,-402,600
+            729,430,532
+            -500,-761,534
+            -322,571,750
+            -466,-666,-811
+            -429,-592,574
+            -355,545,-477
+            703,-491,-529
+            -328,-685,520
+            413,935,-424
+            -391,539,-444
+            586,-435,557
+            -364,-763,-893
+            807,-499,-711
+            755,-354,-619
+            553,889,-390
+
+            --- scanner 2 ---
+            649,640,665
+            682,-795,504
+            -784,533,-524
+            -644,584,-595
+            -588,-843,648
+            -30,6,44
+            -674,560,763
+            500,723,-460
+            609,671,-379
+            -555,-800,653
+            -675,-892,-343
+            697,-426,-610
+            578,704,681
+            493,664,-388
+            -671,-858,530
+            -667,343,800
+            571,-461,-707
+            -138,-166,112
+            -889,563,-600
+            646,-828,498
+            640,759,510
+            -630,509,768
+            -681,-892,-333
+            673,-379,-804
+            -742,-814,-386
+            577,-820,562
+
+            --- scanner 3 ---
+            -589,542,597
+            605,-692,669
+            -500,565,-823
+            -660,373,557
+            -458,-679,-417
+            -488,449,543
+            -626,468,-788
+            338,-750,-386
+            528,-832,-391
+            562,-778,733
+            -938,-730,414
+            543,643,-506
+            -524,371,-870
+            407,773,750
+            -104,29,83
+            378,-903,-323
+            -778,-728,485
+            426,699,580
+            -438,-605,-362
+            -469,-447,-387
+            509,732,623
+            647,635,-688
+            -868,-804,481
+            614,-800,639
+            595,780,-596
+
+            --- scanner 4 ---
+            727,592,562
+            -293,-554,779
+            441,611,-461
+            -714,465,-776
+            -743,427,-804
+            -660,-479,-426
+            832,-632,460
+            927,-485,-438
+            408,393,-506
+            466,436,-512
+            110,16,151
+            -258,-428,682
+            -393,719,612
+            -211,-452,876
+            808,-476,-593
+            -575,615,604
+            -485,667,467
+            -680,325,-822
+            -627,-443,-432
+            872,-547,-609
+            833,512,582
+            807,604,487
+            839,-516,451
+            891,-625,532
+            -652,-548,-490
+            30,-46,-14
+            ",
+            )
+        }
+
+        #[test]
+        fn solution() {
+            let input = example();
+            let mut m = Mapper::try_from(input.clone()).expect("could not parse input");
+            let mut beacons = FxHashSet::default();
+            let report = m.correlate(&mut beacons).expect("could not correlate");
+            assert_eq!(beacons.len(), 79);
+            assert_eq!(report.beacons, 79);
+            assert_eq!(report.resolved.len(), 5);
+            assert!(report.unresolved.is_empty());
+            assert_eq!(m.largest_distance(), Some(3621));
+
+            let poses = m.scanner_poses();
+            assert_eq!(poses.len(), 5);
+            assert_eq!(poses[0].position, Beacon::default());
+            assert_eq!(poses[0].rotation, 0);
+            assert_eq!(poses[1].position, Beacon::from([68, -1246, -43]));
+            assert_eq!(poses[2].position, Beacon::from([1105, -1205, 1229]));
+            assert_eq!(poses[3].position, Beacon::from([-92, -2380, -20]));
+            assert_eq!(poses[4].position, Beacon::from([-20, -1133, 1061]));
+
+            // rotating and translating each scanner's raw readings by its pose
+            // must land every reading on a beacon in the merged map
+            let raw = Mapper::try_from(input).expect("could not parse input");
+            for pose in &poses {
+                for reading in &raw.scanners[pose.index].beacons {
+                    let mut b = reading.rotation(pose.rotation);
+                    b.translate(&pose.position.coords);
+                    assert!(beacons.contains(&b));
+                }
+            }
+        }
+
+        #[test]
+        fn resolution_order() {
+            let mut m = Mapper::try_from(example()).expect("could not parse input");
+            let mut updates = Vec::new();
+            let report = m
+                .correlate_with_progress(&mut FxHashSet::default(), |p| updates.push(*p))
+                .expect("could not correlate");
+
+            assert_eq!(
+                report.resolved[0],
+                Resolution {
+                    scanner: 0,
+                    reference: 0,
+                    rotation: 0,
+                    offset: Beacon::default(),
+                }
+            );
+
+            // everything is matched against something resolved before it, and
+            // the recorded transform is the pose the scanner ended up with
+            let poses = m.scanner_poses();
+            for (i, r) in report.resolved.iter().enumerate() {
+                assert!(report.resolved[..i.max(1)]
+                    .iter()
+                    .any(|prev| prev.scanner == r.reference));
+                let pose = poses.iter().find(|p| p.index == r.scanner).unwrap();
+                assert_eq!((pose.rotation, pose.position), (r.rotation, r.offset));
+            }
+
+            assert_eq!(updates.len(), 5);
+            for (i, update) in updates.iter().enumerate() {
+                assert_eq!(update.latest, report.resolved[i]);
+                assert_eq!(update.resolved, i + 1);
+                assert_eq!(update.total, 5);
+            }
+        }
+
+        #[test]
+        fn pairwise_agrees_with_fingerprints() {
+            let mut fingerprints = Mapper::try_from(example()).expect("could not parse input");
+            let mut expected = FxHashSet::default();
+            fingerprints
+                .correlate(&mut expected)
+                .expect("could not correlate");
+
+            let mut pairwise = Mapper::try_from(example()).expect("could not parse input");
+            let mut beacons = FxHashSet::default();
+            let report = pairwise
+                .correlate_pairwise(&mut beacons)
+                .expect("could not correlate");
+            assert_eq!(report.resolved.len(), 5);
+            assert_eq!(beacons, expected);
+            assert_eq!(pairwise.scanner_poses(), fingerprints.scanner_poses());
+        }
+
+        #[test]
+        fn disconnected_scanners_are_left_unresolved() {
+            let mut input = example();
+            input.extend(
+                ["", "--- scanner 5 ---", "1,2,3", "-40,7,900", "11,-300,5"]
+                    .iter()
+                    .map(|s| s.to_string()),
+            );
+
+            let m = Mapper::try_from(input).expect("could not parse input");
+            let mut fingerprints = m.clone();
+            let mut beacons = FxHashSet::default();
+            let report = fingerprints
+                .correlate(&mut beacons)
+                .expect("could not correlate");
+            assert_eq!(report.resolved.len(), 5);
+            assert_eq!(report.unresolved, vec![5]);
+            assert_eq!(beacons.len(), 79);
+            assert!(fingerprints.scanner_poses().iter().all(|p| p.index != 5));
+
+            let mut pairwise = m;
+            let report = pairwise
+                .correlate_pairwise(&mut FxHashSet::default())
+                .expect("could not correlate");
+            assert_eq!(report.resolved.len(), 5);
+            assert_eq!(report.unresolved, vec![5]);
+        }
+
+        #[test]
+        fn export() {
+            let mut m = Mapper::try_from(example()).expect("could not parse input");
+            let mut out = Vec::new();
+            assert!(m.export(&mut out, Format::Csv).is_err());
+
+            m.correlate(&mut FxHashSet::default())
+                .expect("could not correlate");
+
+            let mut out = Vec::new();
+            m.export(&mut out, Format::Csv).expect("could not export");
+            let csv = String::from_utf8(out).expect("invalid utf8");
+            let lines = csv.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), 1 + 5 + 79);
+            assert_eq!(lines[0], "kind,index,x,y,z");
+            assert_eq!(lines[1], "scanner,0,0,0,0");
+            assert_eq!(lines[2], "scanner,1,68,-1246,-43");
+            assert_eq!(lines[6], "beacon,,-892,524,684");
+            assert_eq!(lines[84], "beacon,,1994,-1805,1792");
+
+            let mut out = Vec::new();
+            m.export(&mut out, Format::Ply).expect("could not export");
+            let ply = String::from_utf8(out).expect("invalid utf8");
+            let (header, body) = ply.split_at(ply.find("end_header\n").unwrap() + 11);
+            assert!(header.starts_with("ply\nformat ascii 1.0\n"));
+            assert!(header.contains("element vertex 84\n"));
+            assert_eq!(body.lines().count(), 84);
+            assert_eq!(body.lines().next(), Some("0 0 0 255 0 0"));
+            assert_eq!(body.lines().last(), Some("1994 -1805 1792 255 255 255"));
+        }
+
+        #[test]
+        fn ambiguous_overlap() {
+            let mut input = super::scanner::symmetric(0);
+            input.push(String::new());
+            input.extend(super::scanner::symmetric(1));
+
+            let m = Mapper::try_from(input).expect("could not parse input");
+            let err = m
+                .clone()
+                .correlate(&mut FxHashSet::default())
+                .expect_err("should be ambiguous");
+            assert_eq!((err.scanner, err.other), (0, 1));
+            assert!(m
+                .clone()
+                .correlate_pairwise(&mut FxHashSet::default())
+                .is_err());
+        }
+    }
+
+    mod planar {
+        use crate::rng::xorshift;
+
+        use super::super::*;
+
+        #[test]
+        fn correlation() {
+            let mut rng = xorshift(0x1f83_d9ab_fb41_bd6b);
+            let mut next = |bound: usize| rng(bound) as i64;
+
+            let mut beacons = (0..80)
+                .map(|_| Beacon2::from([next(2801) - 1000, next(2301) - 1000]))
+                .collect::<FxHashSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            beacons.sort_unstable_by_key(|b| (b.x(), b.y()));
+
+            // each scanner's position, and the quarter turns applied to what
+            // it reports
+            let placements = [([0, 0], 0), ([800, 300], 1), ([1600, -200], 2)];
+            let sees = |pos: &[i64; 2], b: &Beacon2| {
+                let rel = b.offset(&Beacon2::from(*pos));
+                rel.x().abs() <= 1000 && rel.y().abs() <= 1000
+            };
+
+            let mut lines = Vec::new();
+            for (idx, (pos, rot)) in placements.iter().enumerate() {
+                if idx > 0 {
+                    lines.push(String::new());
+                }
+                lines.push(format!("--- scanner {} ---", idx));
+                for b in beacons.iter().filter(|b| sees(pos, b)) {
+                    let reading = b.offset(&Beacon2::from(*pos)).rotation(*rot);
+                    lines.push(reading.to_string().replace(' ', ""));
+                }
+            }
+
+            let mut m = Mapper2::try_from(lines).expect("could not parse input");
+            let mut merged = FxHashSet::default();
+            let report = m.correlate(&mut merged).expect("could not correlate");
+            assert!(report.unresolved.is_empty());
+
+            let poses = m.scanner_poses();
+            assert_eq!(poses.len(), placements.len());
+            for (pose, (pos, rot)) in poses.iter().zip(placements.iter()) {
+                assert_eq!(pose.position, Beacon2::from(*pos));
+                // undoing the turn applied to the readings
+                assert_eq!(pose.rotation, (4 - rot) % 4);
+            }
+
+            let expected = beacons
+                .iter()
+                .filter(|b| placements.iter().any(|(pos, _)| sees(pos, b)))
+                .copied()
+                .collect::<FxHashSet<_>>();
+            assert_eq!(merged, expected);
+
+            let mut out = Vec::new();
+            m.export(&mut out, Format::Csv).expect("could not export");
+            let csv = String::from_utf8(out).expect("invalid utf8");
+            assert!(csv.starts_with("kind,index,x,y\nscanner,0,0,0\nscanner,1,800,300\n"));
+
+            let mut out = Vec::new();
+            m.export(&mut out, Format::Ply).expect("could not export");
+            let ply = String::from_utf8(out).expect("invalid utf8");
+            assert!(ply.contains("end_header\n0 0 0 255 0 0\n800 300 0 255 0 0\n"));
+        }
+    }
+}
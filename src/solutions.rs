@@ -1,7 +1,6 @@
 #[cfg(test)]
 mod tests {
     use aoc_helpers::{Solution, Solver};
-    use itertools::Itertools;
 
     use crate::{
         alu::PrecompiledSolver,
@@ -117,30 +116,8 @@ mod tests {
     #[test]
     #[ignore]
     fn day_013() {
-        let expected = Solution::new(
-            731,
-            "
-0000 0  0  00  0  0  00  0000 0  0  00
-   0 0 0  0  0 0  0 0  0 0    0  0 0  0
-  0  00   0  0 0  0 0    000  0  0 0
- 0   0 0  0000 0  0 0    0    0  0 0
-0    0 0  0  0 0  0 0  0 0    0  0 0  0
-0000 0  0 0  0  00   00  0     00   00"
-                .to_string(),
-        );
-        let actual = Manual::solve();
-        assert_eq!(actual.part_one, expected.part_one);
-
-        // my editor cleaves trailing whitespace, so we need to do that for the
-        // actual solution
-        let stripped = actual
-            .part_two
-            .lines()
-            .map(|line| line.trim_end())
-            .join("\n")
-            .to_string();
-
-        assert_eq!(stripped, expected.part_two);
+        let expected = Solution::new(731, "ZKAUCFUC".to_string());
+        assert_eq!(Manual::solve(), expected);
     }
 
     #[test]
@@ -0,0 +1,614 @@
+use std::fmt;
+
+use itertools::Itertools;
+use rayon::prelude::*;
+
+use super::{Algorithm, Bound, Image, Pixel};
+
+const WORD_BITS: usize = 64;
+
+/// An image stored as one bitset per row over a fixed rectangle, along with the
+/// state of every pixel outside of that rectangle. Unlike [`Image`], this never
+/// has to hash a pixel: enhancing reads the rows around each output row
+/// straight out of their packed words.
+#[derive(Debug, Clone, Default)]
+pub struct BitImage {
+    /// The (row, col) of the top left pixel of the rectangle.
+    origin: Pixel,
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    /// Row-major, with column `c` of each row in bit `c % 64` of word `c / 64`.
+    words: Vec<u64>,
+    /// Whether every pixel outside of the rectangle is lit.
+    background: bool,
+}
+
+impl BitImage {
+    /// Makes a dark image covering `height` rows and `width` columns, with the
+    /// top left pixel at `origin`.
+    pub fn new(origin: Pixel, width: usize, height: usize) -> Self {
+        // this wastes a word when the width is a multiple of 64, but it means a
+        // row is never empty
+        let words_per_row = width / WORD_BITS + 1;
+        Self {
+            origin,
+            width,
+            height,
+            words_per_row,
+            words: vec![0; words_per_row * height],
+            background: false,
+        }
+    }
+
+    /// The rectangle this image stores explicitly. Every pixel outside of it
+    /// has the state given by [`BitImage::background`].
+    pub fn bounds(&self) -> Bound {
+        Bound {
+            min_row: self.origin.0,
+            max_row: self.origin.0 + self.height as i64 - 1,
+            min_col: self.origin.1,
+            max_col: self.origin.1 + self.width as i64 - 1,
+        }
+    }
+
+    pub fn background(&self) -> bool {
+        self.background
+    }
+
+    /// The number of lit pixels inside of [`BitImage::bounds`].
+    pub fn num_lit(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_lit(&self, pixel: &Pixel) -> bool {
+        match self.locate(pixel) {
+            Some((row, col)) => (self.row(row)[col / WORD_BITS] >> (col % WORD_BITS)) & 1 == 1,
+            None => self.background,
+        }
+    }
+
+    /// Sets the state of a pixel inside of [`BitImage::bounds`], returning
+    /// `false` and leaving the image unchanged for a pixel outside of it.
+    pub fn set(&mut self, pixel: &Pixel, lit: bool) -> bool {
+        match self.locate(pixel) {
+            Some((row, col)) => {
+                let word = &mut self.words[row * self.words_per_row + col / WORD_BITS];
+                let mask = 1 << (col % WORD_BITS);
+                if lit {
+                    *word |= mask;
+                } else {
+                    *word &= !mask;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn enhance(&self, algo: &Algorithm) -> Self {
+        // the window size is a constant in the inner loops, which is worth
+        // about a fifth of the time of a step
+        match algo.size() {
+            1 => self.enhance_sized::<1>(algo),
+            3 => self.enhance_sized::<3>(algo),
+            5 => self.enhance_sized::<5>(algo),
+            size => unreachable!("no {}x{} algorithms", size, size),
+        }
+    }
+
+    fn enhance_sized<const N: usize>(&self, algo: &Algorithm) -> Self {
+        let radius = N / 2;
+        let mut out = Self::new(
+            (self.origin.0 - radius as i64, self.origin.1 - radius as i64),
+            self.width + 2 * radius,
+            self.height + 2 * radius,
+        );
+        out.words
+            .par_chunks_mut(out.words_per_row)
+            .enumerate()
+            .for_each(|(r, out_row)| {
+                enhance_row(
+                    self.rows_around::<N>(r as i64),
+                    self.width,
+                    self.background,
+                    algo,
+                    out_row,
+                )
+            });
+
+        out.background = algo.next_background(self.background);
+        out
+    }
+
+    /// Two enhancement steps at once, without building the intermediate
+    /// image. The output is split into bands of rows, and each band only works
+    /// out the handful of intermediate rows it reads from, so the intermediate
+    /// step never exists as a whole.
+    pub fn enhance_twice_fused(&self, algo: &Algorithm) -> Self {
+        match algo.size() {
+            1 => self.enhance_twice_fused_sized::<1>(algo),
+            3 => self.enhance_twice_fused_sized::<3>(algo),
+            5 => self.enhance_twice_fused_sized::<5>(algo),
+            size => unreachable!("no {}x{} algorithms", size, size),
+        }
+    }
+
+    fn enhance_twice_fused_sized<const N: usize>(&self, algo: &Algorithm) -> Self {
+        const BAND: usize = 32;
+
+        let radius = N / 2;
+        let mut out = Self::new(
+            (
+                self.origin.0 - 2 * radius as i64,
+                self.origin.1 - 2 * radius as i64,
+            ),
+            self.width + 4 * radius,
+            self.height + 4 * radius,
+        );
+        let mid_width = self.width + 2 * radius;
+        let mid_height = self.height + 2 * radius;
+        let mid_words = mid_width / WORD_BITS + 1;
+        let mid_background = algo.next_background(self.background);
+        let words_per_row = out.words_per_row;
+
+        out.words
+            .par_chunks_mut(words_per_row * BAND)
+            .enumerate()
+            .for_each(|(band, out_rows)| {
+                let first = band * BAND;
+                let last = first + out_rows.len() / words_per_row;
+
+                // output row r reads intermediate rows r - N + 1..=r
+                let lo = first.saturating_sub(N - 1);
+                let hi = last.min(mid_height);
+                let mut mid = vec![0; (hi - lo) * mid_words];
+                for (i, mid_row) in mid.chunks_mut(mid_words).enumerate() {
+                    let row = (lo + i) as i64;
+                    enhance_row(
+                        self.rows_around::<N>(row),
+                        self.width,
+                        self.background,
+                        algo,
+                        mid_row,
+                    );
+                }
+
+                let mid_row_at = |row: i64| {
+                    if row < lo as i64 || row >= hi as i64 {
+                        None
+                    } else {
+                        let start = (row as usize - lo) * mid_words;
+                        Some(&mid[start..start + mid_words])
+                    }
+                };
+
+                for (k, out_row) in out_rows.chunks_mut(words_per_row).enumerate() {
+                    let r = (first + k) as i64;
+                    let mut rows = [None; N];
+                    for (i, row) in rows.iter_mut().enumerate() {
+                        *row = mid_row_at(r + 1 + i as i64 - N as i64);
+                    }
+                    enhance_row(rows, mid_width, mid_background, algo, out_row);
+                }
+            });
+
+        out.background = algo.next_background(mid_background);
+        out
+    }
+
+    pub fn enhance_times(&self, algo: &Algorithm, times: usize) -> Self {
+        let mut image = self.clone();
+        for _ in 0..times {
+            image = image.enhance(algo);
+        }
+        image
+    }
+
+    /// Work out what the pixels within `bounds` look like after `times`
+    /// enhancements, without enhancing the rest of the image.
+    ///
+    /// Every step only reads the radius of the algorithm past what it writes,
+    /// so it's enough to start from `bounds` grown by that much per step on
+    /// every side. Pixels near the edge of that crop come out wrong, since
+    /// they can't see what lies beyond it, but the damage only creeps inward
+    /// by the radius each step and never reaches `bounds` itself.
+    pub fn enhance_region(&self, algo: &Algorithm, bounds: &Bound, times: usize) -> Self {
+        self.crop(&bounds.grow((times * algo.radius()) as i64))
+            .enhance_times(algo, times)
+            .crop(bounds)
+    }
+
+    /// A copy of the pixels within `bounds`, which may extend past the bounds
+    /// of this image, in which case the extra pixels are background.
+    pub fn crop(&self, bounds: &Bound) -> Self {
+        let mut out = Self::new(
+            (bounds.min_row, bounds.min_col),
+            bounds.width(),
+            bounds.height(),
+        );
+        out.background = self.background;
+        for row in bounds.min_row..=bounds.max_row {
+            for col in bounds.min_col..=bounds.max_col {
+                if self.is_lit(&(row, col)) {
+                    out.set(&(row, col), true);
+                }
+            }
+        }
+        out
+    }
+
+    /// Converts back to a sparse [`Image`] of the lit pixels in the bounds.
+    pub fn to_image(&self) -> Image {
+        let bounds = self.bounds();
+        let mut out = Image {
+            pixels: (bounds.min_row..=bounds.max_row)
+                .flat_map(|row| (bounds.min_col..=bounds.max_col).map(move |col| (row, col)))
+                .filter(|p| self.is_lit(p))
+                .collect(),
+            // a sparse image treats the outside as lit on odd generations, which
+            // is the only way the background could have become lit
+            gen: self.background as usize,
+            ..Image::default()
+        };
+        out.recalc_bounds();
+        out
+    }
+
+    fn locate(&self, pixel: &Pixel) -> Option<(usize, usize)> {
+        let row = pixel.0 - self.origin.0;
+        let col = pixel.1 - self.origin.1;
+        if row < 0 || col < 0 || row as usize >= self.height || col as usize >= self.width {
+            None
+        } else {
+            Some((row as usize, col as usize))
+        }
+    }
+
+    fn row(&self, row: usize) -> &[u64] {
+        &self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    fn row_at(&self, row: i64) -> Option<&[u64]> {
+        if row < 0 || row as usize >= self.height {
+            None
+        } else {
+            Some(self.row(row as usize))
+        }
+    }
+
+    /// The input rows read by output row `row` of the next step with an `N` by
+    /// `N` window.
+    fn rows_around<const N: usize>(&self, row: i64) -> [Option<&[u64]>; N] {
+        // output pixel (r, c) is centered on input pixel (r - N / 2, c - N / 2),
+        // so its window covers input rows r - N + 1..=r and columns
+        // c - N + 1..=c
+        let mut rows = [None; N];
+        for (i, r) in rows.iter_mut().enumerate() {
+            *r = self.row_at(row + 1 + i as i64 - N as i64);
+        }
+        rows
+    }
+}
+
+/// Fills `out_row`, which is `width + N - 1` pixels wide, from the `N` input
+/// rows around it, each `width` pixels wide. A missing row, or a column past
+/// the end of a row, takes the state of the background.
+fn enhance_row<const N: usize>(
+    rows: [Option<&[u64]>; N],
+    width: usize,
+    background: bool,
+    algo: &Algorithm,
+    out_row: &mut [u64],
+) {
+    let bg = background as usize;
+    // repeats the `N` bits of a window row for every row of the window
+    let every_row = |bits: usize| (0..N).fold(0, |acc, _| (acc << N) | bits);
+    // every column of the window but the leftmost, which shifts out
+    let keep = every_row(((1 << N) - 1) & !1);
+
+    // the columns to the left of the input are background, and sit where a
+    // shift will move them into the window
+    let mut idx = if background {
+        every_row((1 << (N - 1)) - 1)
+    } else {
+        0
+    };
+    for (c, word) in (0..width + N - 1)
+        .step_by(WORD_BITS)
+        .zip(out_row.iter_mut())
+    {
+        // pull a word from each input row once, rather than once per pixel
+        let loaded = rows.map(|row| row.and_then(|r| r.get(c / WORD_BITS)).copied());
+        for bit in 0..WORD_BITS.min(width + N - 1 - c) {
+            let col = c + bit;
+            let mut incoming = 0;
+            for w in loaded.iter() {
+                incoming <<= N;
+                incoming |= match w {
+                    Some(w) if col < width => ((w >> bit) & 1) as usize,
+                    _ => bg,
+                };
+            }
+
+            idx = ((idx << 1) & keep) | incoming;
+            if algo.is_light(idx) {
+                *word |= 1 << bit;
+            }
+        }
+    }
+}
+
+/// Two images are equal when every pixel, inside or outside of their bounds,
+/// is in the same state, so the same picture stored over a larger rectangle is
+/// still equal.
+impl PartialEq for BitImage {
+    fn eq(&self, other: &Self) -> bool {
+        if self.background != other.background {
+            return false;
+        }
+
+        let (a, b) = (self.bounds(), other.bounds());
+        let combined = Bound::new(
+            a.min_row.min(b.min_row),
+            a.max_row.max(b.max_row),
+            a.min_col.min(b.min_col),
+            a.max_col.max(b.max_col),
+        );
+
+        (combined.min_row..=combined.max_row).all(|row| {
+            (combined.min_col..=combined.max_col)
+                .all(|col| self.is_lit(&(row, col)) == other.is_lit(&(row, col)))
+        })
+    }
+}
+
+impl Eq for BitImage {}
+
+impl fmt::Display for BitImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bounds = self.bounds();
+        let disp = (bounds.min_row..=bounds.max_row)
+            .map(|row| {
+                (bounds.min_col..=bounds.max_col)
+                    .map(|col| if self.is_lit(&(row, col)) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .join("\n");
+        write!(f, "{}", disp)
+    }
+}
+
+/// Sparse images only track lit pixels, so this assumes that everything outside
+/// of `image` is dark, as it is for any image that was just parsed.
+impl From<&Image> for BitImage {
+    fn from(image: &Image) -> Self {
+        if image.pixels.is_empty() {
+            return Self::default();
+        }
+
+        let bounds = image.bounds();
+        let mut out = Self::new(
+            (bounds.min_row, bounds.min_col),
+            bounds.width(),
+            bounds.height(),
+        );
+        for pixel in image.pixels.iter() {
+            out.set(pixel, true);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::TryFrom, str::FromStr};
+
+    use aoc_helpers::util::test_input;
+
+    use crate::rng::xorshift;
+
+    use super::super::Enhancer;
+    use super::*;
+
+    const ALGORITHM: &str = "..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#";
+
+    fn example(algorithm: &str) -> Enhancer {
+        let mut input = vec![algorithm.to_string(), String::new()];
+        input.extend(test_input(
+            "
+            #..#.
+            #....
+            ##..#
+            ..#..
+            ..###
+            ",
+        ));
+        Enhancer::try_from(input).expect("could not parse input")
+    }
+
+    #[test]
+    fn round_trip() {
+        let e = example(ALGORITHM);
+        let dense = BitImage::from(&e.image);
+        assert_eq!(dense.num_lit(), 10);
+        assert_eq!(dense.to_string(), e.image.to_string());
+        assert_eq!(dense.to_image().pixels, e.image.pixels);
+        assert!(dense.is_lit(&(0, 0)));
+        assert!(!dense.is_lit(&(0, 1)));
+        assert!(!dense.is_lit(&(-100, 100)));
+    }
+
+    #[test]
+    fn enhancing() {
+        let e = example(ALGORITHM);
+        let dense = BitImage::from(&e.image);
+        assert_eq!(dense.enhance_times(&e.algorithm, 2).num_lit(), 35);
+        assert_eq!(dense.enhance_times(&e.algorithm, 50).num_lit(), 3351);
+    }
+
+    #[test]
+    fn matches_sparse_with_flickering_background() {
+        // light up index 0 and darken index 511 so the infinite background
+        // flips on every step, like it does for the puzzle input
+        let mut algorithm = ALGORITHM.to_string();
+        algorithm.replace_range(0..1, "#");
+        algorithm.replace_range(511..512, ".");
+
+        let mut sparse = example(&algorithm);
+        let mut dense = BitImage::from(&sparse.image);
+        for gen in 1..=6 {
+            sparse.enhance();
+            dense = dense.enhance(&sparse.algorithm);
+            assert_eq!(dense.background(), gen % 2 == 1);
+
+            if gen % 2 == 0 {
+                assert_eq!(dense.num_lit(), sparse.image.num_lit());
+                assert_eq!(dense.to_image(), sparse.image);
+            }
+        }
+    }
+
+    #[test]
+    fn fused_matches_two_steps() {
+        let mut algorithm = ALGORITHM.to_string();
+        algorithm.replace_range(0..1, "#");
+        algorithm.replace_range(511..512, ".");
+
+        for algorithm in [ALGORITHM, &algorithm] {
+            let e = example(algorithm);
+            let mut stepped = BitImage::from(&e.image);
+            let mut fused = stepped.clone();
+            for _ in 0..5 {
+                stepped = stepped.enhance(&e.algorithm).enhance(&e.algorithm);
+                fused = fused.enhance_twice_fused(&e.algorithm);
+                assert_eq!(fused, stepped);
+            }
+        }
+
+        // tall enough to need several bands, and wide enough for several words
+        let e = example(ALGORITHM);
+        let mut image = BitImage::new((0, 0), 130, 100);
+        for i in 0..100 {
+            image.set(&(i, (i * 7) % 130), true);
+            image.set(&(i, (i * 13 + 5) % 130), true);
+        }
+        let stepped = image.enhance(&e.algorithm).enhance(&e.algorithm);
+        assert_eq!(image.enhance_twice_fused(&e.algorithm), stepped);
+    }
+
+    #[test]
+    fn equality_ignores_stored_bounds() {
+        let e = example(ALGORITHM);
+        let dense = BitImage::from(&e.image);
+        let padded = dense.crop(&dense.bounds().grow(70));
+        assert_eq!(padded, dense);
+        assert_eq!(padded.to_image(), e.image);
+
+        let mut changed = padded.clone();
+        changed.set(&(-60, 50), true);
+        assert_ne!(changed, dense);
+
+        // the same lit pixels on a different background are a different image
+        let mut algorithm = ALGORITHM.to_string();
+        algorithm.replace_range(0..1, "#");
+        algorithm.replace_range(511..512, ".");
+        let flipped = example(&algorithm);
+        let a = dense.enhance(&flipped.algorithm);
+        let mut b = a.crop(&a.bounds());
+        b.background = false;
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn regions() {
+        let mut algorithm = ALGORITHM.to_string();
+        algorithm.replace_range(0..1, "#");
+        algorithm.replace_range(511..512, ".");
+
+        for algorithm in [ALGORITHM, &algorithm] {
+            let e = example(algorithm);
+            for times in [1, 2, 5, 10] {
+                let full = e.enhance_dense(times);
+                for region in [
+                    Bound::new(1, 3, 1, 3),
+                    Bound::new(-4, 2, 3, 9),
+                    Bound::new(-30, -20, -30, -20),
+                    Bound::new(-12, 16, -12, 16),
+                ] {
+                    let zoomed = e.enhance_region(&region, times);
+                    assert_eq!(zoomed.bounds(), region);
+                    assert_eq!(zoomed, full.crop(&region));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn other_window_sizes() {
+        let mut next = xorshift(0x9b05_688c_2b3e_6c1f);
+
+        let mut image = Image::default();
+        for _ in 0..40 {
+            image.set_pixel(&(next(12) as i64, next(12) as i64));
+        }
+        image.recalc_bounds();
+
+        for size in [1, 5] {
+            let entries = 1 << (size * size);
+            for flicker in [false, true] {
+                // random, except that a flickering background flips on every
+                // step, which is all that a sparse image can follow
+                let algorithm = Algorithm::from_fn(size, |i| match i {
+                    0 => flicker,
+                    _ if i == entries - 1 => !flicker,
+                    _ => next(2) == 1,
+                })
+                .expect("invalid algorithm");
+
+                let mut sparse = image.clone();
+                let mut dense = BitImage::from(&image);
+                for gen in 1..=4 {
+                    sparse = sparse.enhance(&algorithm);
+                    dense = dense.enhance(&algorithm);
+                    assert_eq!(dense.background(), flicker && gen % 2 == 1);
+                    if !dense.background() {
+                        assert_eq!(dense.to_image(), sparse);
+                    }
+                }
+
+                let start = BitImage::from(&image);
+                let stepped = start.enhance(&algorithm).enhance(&algorithm);
+                assert_eq!(start.enhance_twice_fused(&algorithm), stepped);
+
+                let region = Bound::new(-3, 4, 2, 15);
+                assert_eq!(
+                    start.enhance_region(&algorithm, &region, 3),
+                    start.enhance_times(&algorithm, 3).crop(&region)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn wide_images_cross_word_boundaries() {
+        // a diagonal line more than two words wide, with an algorithm that
+        // keeps exactly the pixels whose center is lit
+        let algorithm = (0..512)
+            .map(|i| if i & 0b000_010_000 != 0 { '#' } else { '.' })
+            .collect::<String>();
+        let algorithm = Algorithm::from_str(&algorithm).expect("invalid algorithm");
+
+        let mut image = BitImage::new((0, 0), 150, 150);
+        for i in 0..150 {
+            image.set(&(i, i), true);
+        }
+        assert!(!image.set(&(150, 0), true));
+
+        let out = image.enhance_times(&algorithm, 3);
+        assert_eq!(out.num_lit(), 150);
+        assert!((0..150).all(|i| out.is_lit(&(i, i))));
+        assert_eq!(out.bounds().min_row, -3);
+        assert_eq!(out.bounds().max_col, 152);
+    }
+}
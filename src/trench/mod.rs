@@ -0,0 +1,810 @@
+use std::{convert::TryFrom, fmt, io::Write, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use aoc_helpers::Solver;
+use itertools::Itertools;
+use rayon::prelude::*;
+use rustc_hash::FxHashSet;
+
+pub use self::bitimage::BitImage;
+
+mod bitimage;
+
+/// The rule for one enhancement step: for every `size` by `size` window of the
+/// input, read as a binary number in [`neighbor_order`], whether the pixel at
+/// its center is lit in the output.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Algorithm {
+    size: usize,
+    lookup: Vec<bool>,
+    /// [`neighbor_order`] for `size`, so it isn't worked out for every pixel.
+    neighbors: Vec<Pixel>,
+}
+
+impl Algorithm {
+    /// The window size used by the puzzle.
+    pub const DEFAULT_SIZE: usize = 3;
+
+    /// The largest window size supported. There's an entry for every possible
+    /// window, so a 7x7 one would need 2^49 of them.
+    pub const MAX_SIZE: usize = 5;
+
+    /// Makes an algorithm for a `size` by `size` window from its `2^(size^2)`
+    /// entries.
+    pub fn new(size: usize, lookup: Vec<bool>) -> Result<Self> {
+        let entries = Self::entries(size)?;
+        if lookup.len() != entries {
+            bail!(
+                "A {}x{} algorithm needs {} entries, not {}",
+                size,
+                size,
+                entries,
+                lookup.len()
+            );
+        }
+
+        Ok(Self {
+            size,
+            lookup,
+            neighbors: neighbor_order(size).collect(),
+        })
+    }
+
+    /// Makes an algorithm for a `size` by `size` window by asking `rule`
+    /// about every possible window.
+    pub fn from_fn<F: FnMut(usize) -> bool>(size: usize, rule: F) -> Result<Self> {
+        let entries = Self::entries(size)?;
+        Self::new(size, (0..entries).map(rule).collect())
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// How far the window reaches from its center in each direction.
+    pub fn radius(&self) -> usize {
+        self.size / 2
+    }
+
+    pub fn is_light(&self, val: usize) -> bool {
+        self.lookup[val]
+    }
+
+    /// The state of the infinite background after a step, given its state
+    /// before it.
+    pub fn next_background(&self, background: bool) -> bool {
+        self.is_light(if background { self.lookup.len() - 1 } else { 0 })
+    }
+
+    fn entries(size: usize) -> Result<usize> {
+        // the window needs a center
+        if size % 2 != 1 || size > Self::MAX_SIZE {
+            bail!(
+                "Algorithm size must be odd and at most {}, not {}",
+                Self::MAX_SIZE,
+                size
+            );
+        }
+
+        Ok(1 << (size * size))
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    /// Parses one `#` or `.` per entry, with the size worked out from how many
+    /// there are.
+    fn from_str(s: &str) -> Result<Self> {
+        let vals: Vec<bool> = s.chars().map(|ch| ch == '#').collect();
+        let size = (1..=Self::MAX_SIZE)
+            .step_by(2)
+            .find(|size| 1 << (size * size) == vals.len())
+            .ok_or_else(|| anyhow!("Failed to parse algorithm"))?;
+        Self::new(size, vals)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Bound {
+    min_row: i64,
+    max_row: i64,
+    min_col: i64,
+    max_col: i64,
+}
+
+impl Bound {
+    pub fn new(min_row: i64, max_row: i64, min_col: i64, max_col: i64) -> Self {
+        Self {
+            min_row,
+            max_row,
+            min_col,
+            max_col,
+        }
+    }
+
+    /// This bound with `by` more rows and columns on every side.
+    pub fn grow(&self, by: i64) -> Self {
+        Self::new(
+            self.min_row - by,
+            self.max_row + by,
+            self.min_col - by,
+            self.max_col + by,
+        )
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max_col - self.min_col).abs() as usize + 1
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max_row - self.min_row).abs() as usize + 1
+    }
+
+    pub fn translate(&self, pixel: &Pixel) -> (usize, usize) {
+        (
+            (pixel.0 - self.min_row) as usize,
+            (pixel.1 - self.min_col) as usize,
+        )
+    }
+
+    pub fn size(&self) -> usize {
+        self.width() * self.height()
+    }
+
+    pub fn contains(&self, pixel: &Pixel) -> bool {
+        pixel.0 >= self.min_row
+            && pixel.0 <= self.max_row
+            && pixel.1 >= self.min_col
+            && pixel.1 <= self.max_col
+    }
+}
+
+/// The offset of each pixel in a 3x3 window from its center, from the most
+/// significant bit of the window's value to the least.
+pub const NEIGHBOR_ORDER: [(i64, i64); 9] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 0),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// Like [`NEIGHBOR_ORDER`], for a `size` by `size` window: row by row from the
+/// top left.
+pub fn neighbor_order(size: usize) -> impl Iterator<Item = Pixel> {
+    let radius = (size / 2) as i64;
+    (0..size * size).map(move |i| ((i / size) as i64 - radius, (i % size) as i64 - radius))
+}
+
+type Pixel = (i64, i64);
+
+#[derive(Debug, Clone, Default)]
+pub struct Image {
+    pixels: FxHashSet<Pixel>,
+    bounds: Bound,
+    gen: usize,
+}
+
+impl Image {
+    pub fn bounds(&self) -> &Bound {
+        &self.bounds
+    }
+
+    pub fn enhance(&self, algo: &Algorithm) -> Self {
+        let mut new_image = Self {
+            gen: self.gen + 1,
+            ..Self::default()
+        };
+
+        // In parallel, travel down each column, checking every potential pixel
+        // in the column. This is done to take advantage of the property that
+        // if you move a window "down" the value for the new window is the
+        // bottom rows of the old window plus the bottom row of the new
+        // window, and thus we can drastically cut down on the number of
+        // `set.contains` operations we need to perform.
+        let grown = self.bounds.grow(algo.radius() as i64);
+        let iter = (grown.min_col..=grown.max_col)
+            .into_par_iter()
+            .map(move |col| {
+                let mut cache: Option<usize> = None;
+                (grown.min_row..=grown.max_row).filter_map(move |row| {
+                    let pix = (row, col);
+                    let val = self.value_for_square(&pix, algo, &mut cache);
+
+                    if algo.is_light(val) {
+                        Some(pix)
+                    } else {
+                        None
+                    }
+                })
+            })
+            .flatten_iter();
+
+        new_image.pixels = FxHashSet::from_par_iter(iter);
+
+        new_image.recalc_bounds();
+        new_image
+    }
+
+    pub fn num_lit(&self) -> usize {
+        self.pixels.len()
+    }
+
+    pub fn is_lit(&self, pixel: &Pixel) -> bool {
+        self.pixels.contains(pixel)
+    }
+
+    /// The pixels lit in exactly one of the two images.
+    pub fn diff(&self, other: &Self) -> Self {
+        Self::from_pixels(self.pixels.symmetric_difference(&other.pixels).copied())
+    }
+
+    /// The pixels lit in both images.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::from_pixels(self.pixels.intersection(&other.pixels).copied())
+    }
+
+    /// The pixels lit in either image.
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_pixels(self.pixels.union(&other.pixels).copied())
+    }
+
+    /// An image of just the given lit pixels. Like a freshly parsed image,
+    /// everything outside of them is dark.
+    pub fn from_pixels<I: IntoIterator<Item = Pixel>>(pixels: I) -> Self {
+        let mut img = Self {
+            pixels: pixels.into_iter().collect(),
+            ..Self::default()
+        };
+        img.recalc_bounds();
+        img
+    }
+
+    pub fn value_for_square(
+        &self,
+        pix: &Pixel,
+        algo: &Algorithm,
+        cache: &mut Option<usize>,
+    ) -> usize {
+        let size = algo.size();
+        let bits = size * size;
+        let mut top = 0;
+        let mut start = 0_usize;
+
+        // Since we're moving "down" every column, we know that the bottom
+        // `size - 1` rows of the previous value will be the top rows of the new
+        // value therefore, no need to actually check all of those bits again.
+        // On the first time through this process, we have to calculate
+        // everything.
+        if let Some(val) = cache {
+            // cleave the top row
+            top = *val & ((1 << (bits - size)) - 1);
+
+            // shift a row's worth of positions
+            top <<= size;
+            start = bits - size;
+        }
+
+        let res = algo.neighbors[start..]
+            .iter()
+            .enumerate()
+            .fold(top, |acc, (i, (r, c))| {
+                let p: Pixel = (pix.0 + r, pix.1 + c);
+                // so, yeah. The situation with the algorithm for the examples
+                // not starting with a # vs the algorithm in the input starting
+                // with a #. It's not the cleanest, but still. The rationalle is
+                // that it's faster to check the bounds condition than the set
+                // contains operation.
+                if (algo.is_light(0) && self.gen % 2 == 1 && !self.bounds.contains(&p))
+                    || self.pixels.contains(&p)
+                {
+                    acc + (1 << (bits - 1 - start - i))
+                } else {
+                    acc
+                }
+            });
+
+        *cache = Some(res);
+        res
+    }
+
+    pub fn set_pixel(&mut self, pixel: &Pixel) {
+        self.pixels.insert(*pixel);
+    }
+
+    pub fn delete_pixel(&mut self, pixel: &Pixel) {
+        self.pixels.remove(pixel);
+    }
+
+    pub fn recalc_bounds(&mut self) {
+        // the same bounds as an image that was never given any pixels, so
+        // they can still be measured and grown
+        if self.pixels.is_empty() {
+            self.bounds = Bound::default();
+            return;
+        }
+
+        let mut min_row = i64::MAX;
+        let mut max_row = i64::MIN;
+        let mut min_col = i64::MAX;
+        let mut max_col = i64::MIN;
+
+        for p in self.pixels.iter() {
+            if p.0 < min_row {
+                min_row = p.0;
+            }
+
+            if p.1 < min_col {
+                min_col = p.1;
+            }
+
+            if p.0 > max_row {
+                max_row = p.0;
+            }
+
+            if p.1 > max_col {
+                max_col = p.1;
+            }
+        }
+
+        self.bounds = Bound {
+            min_row,
+            max_row,
+            min_col,
+            max_col,
+        };
+    }
+
+    /// Display the image with `lit` and `dark` in place of the usual `#` and
+    /// `.`.
+    pub fn display_with(&self, lit: char, dark: char) -> Glyphs<'_> {
+        Glyphs {
+            image: self,
+            lit,
+            dark,
+        }
+    }
+
+    /// Write the bounds of the image as a binary portable bitmap, with lit
+    /// pixels in black on white.
+    pub fn write_pbm<W: Write>(&self, writer: &mut W) -> Result<()> {
+        // in a bitmap, 1 is black
+        let (width, height, data) = self.packed_rows(true)?;
+        write!(writer, "P4\n{} {}\n", width, height)?;
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Write the bounds of the image as a black and white PNG, with lit pixels
+    /// in black on white.
+    #[cfg(feature = "image")]
+    pub fn write_png<W: Write>(&self, writer: &mut W) -> Result<()> {
+        // in grayscale, 0 is black
+        let (width, height, data) = self.packed_rows(false)?;
+        let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::One);
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(())
+    }
+
+    /// Pack the bounds of the image into rows of bits, most significant bit
+    /// first, where each row starts on a new byte. Lit pixels are set to `lit`
+    /// and everything else to its opposite. Returns the width, height and
+    /// packed data.
+    fn packed_rows(&self, lit: bool) -> Result<(usize, usize, Vec<u8>)> {
+        if self.pixels.is_empty() {
+            bail!("cannot write an image with no lit pixels");
+        }
+
+        let width = self.bounds.width();
+        let height = self.bounds.height();
+        let row_bytes = (width - 1) / 8 + 1;
+
+        let background = if lit { 0 } else { 0xff };
+        let mut data = vec![background; row_bytes * height];
+
+        for pix in self.pixels.iter() {
+            let (row, col) = self.bounds.translate(pix);
+            data[row * row_bytes + col / 8] ^= 0x80 >> (col % 8);
+        }
+
+        Ok((width, height, data))
+    }
+}
+
+/// Two images are equal when they light the same pixels, regardless of how they
+/// got there. The infinite background is left out: whether it is lit depends
+/// on the algorithm the image was enhanced with, which isn't kept, so the
+/// generation alone can't say.
+impl PartialEq for Image {
+    fn eq(&self, other: &Self) -> bool {
+        self.pixels == other.pixels
+    }
+}
+
+impl Eq for Image {}
+
+impl fmt::Display for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_with('#', '.').fmt(f)
+    }
+}
+
+/// An [`Image`] displayed with custom characters for lit and dark pixels, made
+/// by [`Image::display_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct Glyphs<'a> {
+    image: &'a Image,
+    lit: char,
+    dark: char,
+}
+
+impl fmt::Display for Glyphs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.image.pixels.is_empty() {
+            return Ok(());
+        }
+
+        let bound = self.image.bounds();
+        let mut output = vec![vec![self.dark; bound.width()]; bound.height()];
+        for pix in self.image.pixels.iter() {
+            let (row, col) = bound.translate(pix);
+            output[row][col] = self.lit;
+        }
+
+        let disp: String = output
+            .iter()
+            .map(|r| r.iter().collect::<String>())
+            .join("\n");
+        write!(f, "{}", disp)
+    }
+}
+
+impl From<&[String]> for Image {
+    fn from(value: &[String]) -> Self {
+        let pixels: FxHashSet<Pixel> = value
+            .iter()
+            .enumerate()
+            .map(move |(row, s)| {
+                s.chars().enumerate().filter_map(move |(col, ch)| match ch {
+                    '#' => Some((row as i64, col as i64)),
+                    _ => None,
+                })
+            })
+            .flatten()
+            .collect();
+
+        Self::from_pixels(pixels)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Enhancer {
+    pub algorithm: Algorithm,
+    pub image: Image,
+}
+
+impl Enhancer {
+    pub fn enhance(&mut self) {
+        self.image = self.image.enhance(&self.algorithm);
+    }
+
+    pub fn enhance_times(&mut self, times: usize) -> &Image {
+        for _ in 0..times {
+            self.enhance();
+        }
+        &self.image
+    }
+
+    /// Enhance a dense copy of the image `times` times, leaving this one as it
+    /// is.
+    pub fn enhance_dense(&self, times: usize) -> BitImage {
+        BitImage::from(&self.image).enhance_times(&self.algorithm, times)
+    }
+
+    /// Work out what the pixels within `bounds` look like after `times`
+    /// enhancements, without enhancing the rest of the image.
+    pub fn enhance_region(&self, bounds: &Bound, times: usize) -> BitImage {
+        BitImage::from(&self.image).enhance_region(&self.algorithm, bounds, times)
+    }
+
+    /// An endless iterator of `(generation, lit count)` for a dense copy of the
+    /// image as it is enhanced, starting with generation 0 as the image is now.
+    /// This one is left as it is. When the infinite background is lit, only
+    /// the pixels within the bounds of that generation are counted.
+    pub fn steps(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        std::iter::successors(Some(BitImage::from(&self.image)), move |img| {
+            Some(img.enhance(&self.algorithm))
+        })
+        .map(|img| img.num_lit())
+        .enumerate()
+    }
+}
+
+impl TryFrom<Vec<String>> for Enhancer {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<String>) -> Result<Self, Self::Error> {
+        let mut parts = value.split(|s| s.is_empty());
+        let algorithm = Algorithm::from_str(
+            parts
+                .next()
+                .and_then(|s| s.first())
+                .ok_or_else(|| anyhow!("Input too short"))?,
+        )?;
+
+        let image = Image::try_from(parts.next().ok_or_else(|| anyhow!("Input too short"))?)?;
+
+        Ok(Self { algorithm, image })
+    }
+}
+
+impl Solver for Enhancer {
+    const ID: &'static str = "trench map";
+    const DAY: usize = 20;
+
+    type P1 = usize;
+    type P2 = usize;
+
+    fn part_one(&mut self) -> Self::P1 {
+        self.enhance_dense(2).num_lit()
+    }
+
+    fn part_two(&mut self) -> Self::P2 {
+        self.enhance_dense(50).num_lit()
+    }
+
+    // the combined solve can pick up where part one left off
+    fn solve() -> aoc_helpers::Solution<Self::P1, Self::P2> {
+        let instance = Self::instance();
+        let two = instance.enhance_dense(2);
+        let fifty = two.enhance_times(&instance.algorithm, 48);
+        aoc_helpers::Solution::new(two.num_lit(), fifty.num_lit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod image {
+        use aoc_helpers::util::test_input;
+
+        use super::super::*;
+
+        #[test]
+        fn parsing() {
+            let input = test_input(
+                "
+                #..#.
+                #....
+                ##..#
+                ..#..
+                ..###
+                ",
+            );
+            let image = Image::from(input.as_ref());
+            assert_eq!(image.pixels.len(), 10);
+        }
+
+        #[test]
+        fn enhancing() {
+            let input = test_input("
+                ..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#
+
+                #..#.
+                #....
+                ##..#
+                ..#..
+                ..###
+                ");
+
+            let mut enhancer = Enhancer::try_from(input).expect("could not parse input");
+            let img = enhancer.enhance_times(2);
+            assert_eq!(img.num_lit(), 35);
+        }
+
+        #[test]
+        fn steps() {
+            let input = test_input("
+                ..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#
+
+                #..#.
+                #....
+                ##..#
+                ..#..
+                ..###
+                ");
+
+            let enhancer = Enhancer::try_from(input).expect("could not parse input");
+            let steps = enhancer.steps().take(3).collect::<Vec<_>>();
+            assert_eq!(steps, vec![(0, 10), (1, 24), (2, 35)]);
+            assert_eq!(enhancer.steps().nth(50), Some((50, 3351)));
+
+            // the enhancer itself is left alone
+            assert_eq!(enhancer.image.num_lit(), 10);
+        }
+
+        #[test]
+        fn comparison() {
+            let a = Image::from(
+                test_input(
+                    "
+                    ##.
+                    #..
+                    ",
+                )
+                .as_ref(),
+            );
+            let b = Image::from(
+                test_input(
+                    "
+                    .#.
+                    ##.
+                    ..#
+                    ",
+                )
+                .as_ref(),
+            );
+
+            assert_eq!(a.diff(&b), Image::from_pixels(vec![(0, 0), (1, 1), (2, 2)]));
+            assert_eq!(a.intersection(&b), Image::from_pixels(vec![(0, 1), (1, 0)]));
+            assert_eq!(a.union(&b).num_lit(), 5);
+            assert_eq!(a.union(&b).bounds(), &Bound::new(0, 2, 0, 2));
+
+            assert_eq!(a.diff(&a).num_lit(), 0);
+            assert_eq!(a.diff(&a).bounds(), &Bound::default());
+            assert_eq!(a.diff(&a).to_string(), "");
+            assert_eq!(a.union(&a), a);
+            assert_eq!(a.intersection(&a), a);
+            assert_ne!(a, b);
+
+            // images are compared by their pixels, however they were built
+            let mut c = Image::from_pixels(vec![(0, 0), (9, 9)]);
+            c.delete_pixel(&(9, 9));
+            c.set_pixel(&(0, 1));
+            c.set_pixel(&(1, 0));
+            assert_eq!(c, a);
+        }
+
+        #[test]
+        fn display_with() {
+            let img = Image::from_pixels(vec![(0, 0), (1, 2)]);
+            assert_eq!(img.to_string(), "#..\n..#");
+            assert_eq!(img.display_with('█', ' ').to_string(), "█  \n  █");
+        }
+
+        #[test]
+        fn write_pbm() {
+            // bounds start at the top left lit pixel, wherever that is
+            let img = Image::from_pixels(vec![(-1, -3), (0, 6)]);
+            let mut out = Vec::new();
+            img.write_pbm(&mut out).expect("could not write image");
+            let mut expected = b"P4\n10 2\n".to_vec();
+            expected.extend_from_slice(&[0b1000_0000, 0, 0, 0b0100_0000]);
+            assert_eq!(out, expected);
+
+            let mut out = Vec::new();
+            assert!(Image::default().write_pbm(&mut out).is_err());
+        }
+
+        #[cfg(feature = "image")]
+        #[test]
+        fn write_png() {
+            let img = Image::from_pixels(vec![(-1, -3), (0, 6)]);
+            let mut out = Vec::new();
+            img.write_png(&mut out).expect("could not write image");
+
+            let decoder = png::Decoder::new(out.as_slice());
+            let (info, mut reader) = decoder.read_info().expect("could not read image");
+            assert_eq!((info.width, info.height), (10, 2));
+            let mut data = vec![0; info.buffer_size()];
+            reader.next_frame(&mut data).expect("could not read image");
+            // the decoder expands each pixel to a byte, and lit pixels are black
+            let lit = data
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| **v == 0)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            assert_eq!(lit, vec![0, 19]);
+        }
+    }
+
+    mod algorithm {
+        use super::super::*;
+
+        #[test]
+        fn sizes() {
+            assert_eq!(Algorithm::from_str("#.").expect("invalid").size(), 1);
+            assert_eq!(
+                Algorithm::from_str(&".".repeat(512))
+                    .expect("invalid")
+                    .size(),
+                3
+            );
+            assert!(Algorithm::from_str(&".".repeat(511)).is_err());
+            assert!(Algorithm::from_str(&".".repeat(16)).is_err());
+
+            let dilate = Algorithm::from_fn(5, |w| w != 0).expect("invalid");
+            assert_eq!((dilate.size(), dilate.radius()), (5, 2));
+            assert!(!dilate.next_background(false));
+            assert!(dilate.next_background(true));
+
+            assert!(Algorithm::new(3, vec![false; 511]).is_err());
+            assert!(Algorithm::new(4, vec![false; 1 << 16]).is_err());
+            assert!(Algorithm::from_fn(7, |_| true).is_err());
+        }
+
+        #[test]
+        fn neighbors() {
+            assert_eq!(
+                neighbor_order(3).collect::<Vec<_>>(),
+                NEIGHBOR_ORDER.to_vec()
+            );
+            assert_eq!(neighbor_order(1).collect::<Vec<_>>(), vec![(0, 0)]);
+
+            let order = neighbor_order(5).collect::<Vec<_>>();
+            assert_eq!(order.len(), 25);
+            assert_eq!((order[0], order[12], order[24]), ((-2, -2), (0, 0), (2, 2)));
+        }
+
+        #[test]
+        fn life() {
+            // conway's game of life, as a 3x3 algorithm
+            let center = 0b000_010_000;
+            let life = Algorithm::from_fn(3, |w| {
+                let neighbors = (w & !center).count_ones();
+                neighbors == 3 || (w & center != 0 && neighbors == 2)
+            })
+            .expect("invalid algorithm");
+
+            let blinker = Image::from_pixels(vec![(0, -1), (0, 0), (0, 1)]);
+            let flipped = blinker.enhance(&life);
+            assert_eq!(flipped, Image::from_pixels(vec![(-1, 0), (0, 0), (1, 0)]));
+            assert_eq!(flipped.enhance(&life), blinker);
+
+            // a glider moves one down and one right every four generations
+            let glider = Image::from_pixels(vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+            let mut image = glider.clone();
+            for _ in 0..4 {
+                image = image.enhance(&life);
+            }
+            assert_eq!(
+                image,
+                Image::from_pixels(glider.pixels.iter().map(|(r, c)| (r + 1, c + 1)))
+            );
+            assert_eq!(
+                BitImage::from(&glider).enhance_times(&life, 4).to_image(),
+                image
+            );
+        }
+
+        #[test]
+        fn larger_windows() {
+            // lighting anything within reach of a lit pixel grows a square by
+            // the radius every step
+            let dot = Image::from_pixels(vec![(0, 0)]);
+            let wide = Algorithm::from_fn(5, |w| w != 0).expect("invalid algorithm");
+            let narrow = Algorithm::from_fn(3, |w| w != 0).expect("invalid algorithm");
+
+            let grown = dot.enhance(&wide).enhance(&wide);
+            assert_eq!(grown.num_lit(), 81);
+            assert_eq!(grown.bounds(), &Bound::new(-4, 4, -4, 4));
+
+            let mut stepped = dot.clone();
+            for _ in 0..4 {
+                stepped = stepped.enhance(&narrow);
+            }
+            assert_eq!(stepped, grown);
+            assert_eq!(
+                BitImage::from(&dot).enhance_times(&wide, 2).to_image(),
+                grown
+            );
+        }
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(feature = "image")]
+use std::convert::TryFrom;
+use std::{io::Write, thread, time::Duration};
+
+#[cfg(feature = "image")]
+use anyhow::bail;
+use anyhow::Result;
+
+/// Something that can be rendered as a sequence of text frames.
+pub trait Visualize {
+    /// Render the current state as a single frame
+    fn render_frame(&self) -> String;
+
+    /// Move to the next state, returning `false` if there is nothing further
+    /// to show
+    fn advance(&mut self) -> bool;
+}
+
+/// Collect the current frame and up to `limit` subsequent frames
+pub fn frames<V: Visualize>(subject: &mut V, limit: usize) -> Vec<String> {
+    let mut frames = vec![subject.render_frame()];
+
+    for _ in 0..limit {
+        if !subject.advance() {
+            break;
+        }
+        frames.push(subject.render_frame());
+    }
+
+    frames
+}
+
+/// Draw the current frame and up to `limit` subsequent frames to `out`,
+/// clearing the terminal before each one and pausing for `delay` between them.
+///
+/// Returns the number of frames drawn.
+pub fn animate<V, W>(subject: &mut V, limit: usize, delay: Duration, out: &mut W) -> Result<usize>
+where
+    V: Visualize,
+    W: Write,
+{
+    let mut drawn = 0;
+
+    loop {
+        // clear the screen and move the cursor to the top left
+        write!(out, "\x1b[2J\x1b[H{}", subject.render_frame())?;
+        out.flush()?;
+        drawn += 1;
+
+        if drawn > limit || !subject.advance() {
+            break;
+        }
+
+        thread::sleep(delay);
+    }
+
+    Ok(drawn)
+}
+
+/// Write the current frame and up to `limit` subsequent frames to `writer` as
+/// a looping animated GIF, showing each one for `delay`.
+///
+/// Every character of a frame is drawn as a `scale` by `scale` block in the
+/// color `palette` gives it, or in the first color of `palette` if it isn't
+/// listed. Frames are padded with the first color to the size of the largest
+/// one. Returns the number of frames written.
+#[cfg(feature = "image")]
+pub fn write_gif<V, W>(
+    subject: &mut V,
+    limit: usize,
+    delay: Duration,
+    scale: usize,
+    palette: &[(char, [u8; 3])],
+    writer: W,
+) -> Result<usize>
+where
+    V: Visualize,
+    W: Write,
+{
+    if palette.is_empty() || palette.len() > 256 {
+        bail!("palette must have 1 to 256 colors, not {}", palette.len());
+    }
+
+    let frames = frames(subject, limit);
+    let cols = frames
+        .iter()
+        .flat_map(|frame| frame.lines())
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let rows = frames
+        .iter()
+        .map(|frame| frame.lines().count())
+        .max()
+        .unwrap_or(0);
+
+    let width = u16::try_from(cols * scale)?;
+    let height = u16::try_from(rows * scale)?;
+    if width == 0 || height == 0 {
+        bail!("cannot write an animation of empty frames");
+    }
+
+    let colors: Vec<u8> = palette.iter().flat_map(|(_, rgb)| *rgb).collect();
+    let mut encoder = gif::Encoder::new(writer, width, height, &colors)?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    // the delay is in hundredths of a second
+    let delay = u16::try_from(delay.as_millis() / 10)?;
+    let row_len = width as usize;
+
+    for frame in frames.iter() {
+        let mut pixels = vec![0; row_len * height as usize];
+        for (row, line) in frame.lines().enumerate() {
+            for (col, ch) in line.chars().enumerate() {
+                let color = palette.iter().position(|(c, _)| *c == ch).unwrap_or(0) as u8;
+                for r in row * scale..(row + 1) * scale {
+                    let start = r * row_len + col * scale;
+                    pixels[start..start + scale].fill(color);
+                }
+            }
+        }
+
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, &pixels, None);
+        frame.delay = delay;
+        encoder.write_frame(&frame)?;
+    }
+
+    Ok(frames.len())
+}